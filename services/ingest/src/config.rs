@@ -6,6 +6,7 @@
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
 use std::time::Duration;
+use tracing::warn;
 
 /// Main configuration for the ingest service.
 #[derive(Debug, Clone, Deserialize)]
@@ -26,6 +27,14 @@ pub struct IngestConfig {
     /// Health check configuration
     #[serde(default)]
     pub health: HealthConfig,
+
+    /// Stream admission configuration
+    #[serde(default)]
+    pub streams: StreamsConfig,
+
+    /// Storage sampling configuration
+    #[serde(default)]
+    pub storage: StorageConfig,
 }
 
 /// RTSP stream connection configuration.
@@ -61,13 +70,64 @@ pub struct RtspConfig {
     #[serde(default = "default_reconnect_max_delay_ms")]
     pub reconnect_max_delay_ms: u64,
 
+    /// Seconds a connection must stay up before the consecutive-failure count resets
+    #[serde(default = "default_stable_connection_secs")]
+    pub stable_connection_secs: u64,
+
+    /// Window in seconds over which the reported current FPS is averaged
+    #[serde(default = "default_fps_window_secs")]
+    pub fps_window_secs: u64,
+
+    /// Seconds without a frame before a connected stream is treated as
+    /// stalled and reconnected (0 = disabled)
+    #[serde(default = "default_stall_timeout_secs")]
+    pub stall_timeout_secs: u64,
+
     /// RTSP transport protocol (tcp, udp, or udp-mcast)
     #[serde(default = "default_transport")]
     pub transport: String,
 
+    /// Video codec of the stream (h264, h265, vp8, vp9, or mjpeg)
+    #[serde(default = "default_codec")]
+    pub codec: String,
+
     /// Buffer size for RTSP stream in milliseconds
     #[serde(default = "default_buffer_ms")]
     pub buffer_ms: u32,
+
+    /// Which frame is dropped when the capture buffer is full. File sources
+    /// never drop; capture waits for the consumer instead.
+    #[serde(default)]
+    pub drop_policy: CaptureDropPolicy,
+
+    /// Replay speed for `file://` sources: 2.0 plays twice as fast as
+    /// recorded, 0 as fast as frames decode. Live streams ignore it.
+    #[serde(default = "default_playback_speed")]
+    pub playback_speed: f64,
+}
+
+/// Which frame the capture stage drops when the processor falls behind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureDropPolicy {
+    /// Drop the incoming frame, keeping the buffered ones
+    #[default]
+    DropNew,
+    /// Evict the oldest buffered frame so the newest always gets through
+    DropOld,
+}
+
+/// How frames are fitted to the processing target size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeMode {
+    /// Scale each axis independently, distorting the aspect ratio
+    #[default]
+    Stretch,
+    /// Fit the whole frame inside the target, padding the rest with `pad_color`
+    Letterbox,
+    /// Fill the target, cropping the overflow equally from both sides
+    CenterCrop,
 }
 
 /// Frame processing configuration.
@@ -85,6 +145,14 @@ pub struct ProcessingConfig {
     #[serde(default = "default_target_fps")]
     pub target_fps: f32,
 
+    /// How frames are fitted to the target size
+    #[serde(default)]
+    pub resize_mode: ResizeMode,
+
+    /// RGB fill for the bands added by `letterbox`
+    #[serde(default = "default_pad_color")]
+    pub pad_color: [u8; 3],
+
     /// Output pixel format
     #[serde(default = "default_pixel_format")]
     pub pixel_format: String,
@@ -100,14 +168,35 @@ pub struct ProcessingConfig {
     /// Whether to drop frames when queue is full
     #[serde(default = "default_drop_on_backpressure")]
     pub drop_on_backpressure: bool,
+
+    /// Maximum bytes of processed frames in flight to the inference client (0 = unlimited)
+    #[serde(default = "default_max_inflight_bytes")]
+    pub max_inflight_bytes: usize,
+
+    /// Run resize/convert on tokio's blocking thread pool instead of the async runtime
+    #[serde(default)]
+    pub use_blocking_pool: bool,
 }
 
 /// gRPC client configuration for inference service.
 #[derive(Debug, Clone, Deserialize)]
 pub struct GrpcConfig {
     /// Inference service endpoint
+    #[serde(default)]
     pub inference_endpoint: String,
 
+    /// Inference service replicas to balance across (overrides `inference_endpoint`)
+    #[serde(default)]
+    pub inference_endpoints: Vec<String>,
+
+    /// How requests are spread across inference endpoints
+    #[serde(default)]
+    pub load_balancing: LoadBalancing,
+
+    /// Seconds an endpoint is skipped after it fails
+    #[serde(default = "default_endpoint_cooldown_secs")]
+    pub endpoint_cooldown_secs: u64,
+
     /// Request timeout in seconds
     #[serde(default = "default_request_timeout")]
     pub request_timeout_secs: u64,
@@ -131,13 +220,55 @@ pub struct GrpcConfig {
     #[serde(default)]
     pub enable_compression: bool,
 
-    /// Batch size for frame submission
+    /// Maximum batch size; batches grow toward it while frames are queueing
     #[serde(default = "default_batch_size")]
     pub batch_size: usize,
 
-    /// Maximum time to wait for batch to fill in milliseconds
+    /// Maximum time a partially filled batch waits for more frames in milliseconds
     #[serde(default = "default_batch_timeout_ms")]
     pub batch_timeout_ms: u64,
+
+    /// Maximum size of an encoded request message in bytes
+    #[serde(default = "default_max_message_size")]
+    pub max_encoding_message_size: usize,
+
+    /// Maximum size of a decoded response message in bytes
+    #[serde(default = "default_max_message_size")]
+    pub max_decoding_message_size: usize,
+
+    /// Protocol used to probe inference service health
+    #[serde(default)]
+    pub health_check_protocol: HealthCheckProtocol,
+
+    /// Service name sent in `grpc.health.v1` checks (empty = whole server)
+    #[serde(default)]
+    pub health_check_service: String,
+
+    /// Consecutive failed probes before the service is reported unhealthy
+    #[serde(default = "default_health_failure_threshold")]
+    pub health_failure_threshold: u32,
+}
+
+/// How the inference service's health is probed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckProtocol {
+    /// The inference service's own `HealthCheck` RPC
+    #[default]
+    Inference,
+    /// The standard `grpc.health.v1.Health/Check` protocol
+    Grpc,
+}
+
+/// Client-side load balancing strategy across inference endpoints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalancing {
+    /// Cycle through healthy endpoints in order
+    #[default]
+    RoundRobin,
+    /// Pick the healthy endpoint with the fewest requests in flight
+    LeastInFlight,
 }
 
 /// Logging configuration.
@@ -156,6 +287,38 @@ pub struct LoggingConfig {
     pub include_location: bool,
 }
 
+/// Limits on how many RTSP streams run, and how fast they start.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamsConfig {
+    /// Maximum streams running at once; further streams wait for a free slot
+    #[serde(default = "default_max_concurrent_streams")]
+    pub max_concurrent_streams: usize,
+
+    /// Minimum delay between consecutive stream starts in milliseconds
+    #[serde(default = "default_start_stagger_ms")]
+    pub start_stagger_ms: u64,
+}
+
+/// Sampling of processed frames to the storage service.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageConfig {
+    /// Publish sampled frames to the storage service
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Kafka bootstrap servers (comma-separated)
+    #[serde(default = "default_storage_brokers")]
+    pub brokers: String,
+
+    /// Topic the storage service consumes trigger events from
+    #[serde(default = "default_storage_topic")]
+    pub topic: String,
+
+    /// Minimum capture time between sampled frames in seconds
+    #[serde(default = "default_storage_sample_interval_secs")]
+    pub sample_interval_secs: u64,
+}
+
 /// Health check configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct HealthConfig {
@@ -170,6 +333,22 @@ pub struct HealthConfig {
     /// Enable Prometheus metrics export
     #[serde(default)]
     pub enable_metrics: bool,
+
+    /// Backpressure drop rate (0.0-1.0) at which the instance is degraded
+    #[serde(default = "default_degraded_drop_rate")]
+    pub degraded_drop_rate: f64,
+
+    /// Backpressure drop rate (0.0-1.0) at which the instance is unhealthy
+    #[serde(default = "default_unhealthy_drop_rate")]
+    pub unhealthy_drop_rate: f64,
+
+    /// Delivered/target FPS ratio below which the instance is degraded
+    #[serde(default = "default_degraded_fps_ratio")]
+    pub degraded_fps_ratio: f64,
+
+    /// Delivered/target FPS ratio below which the instance is unhealthy
+    #[serde(default = "default_unhealthy_fps_ratio")]
+    pub unhealthy_fps_ratio: f64,
 }
 
 // Default value functions
@@ -185,12 +364,30 @@ fn default_reconnect_base_delay_ms() -> u64 {
 fn default_reconnect_max_delay_ms() -> u64 {
     30000
 }
+fn default_stable_connection_secs() -> u64 {
+    60
+}
+fn default_fps_window_secs() -> u64 {
+    5
+}
+fn default_stall_timeout_secs() -> u64 {
+    15
+}
 fn default_transport() -> String {
     "tcp".to_string()
 }
+/// Codecs the RTSP pipeline has a decode chain for
+pub const SUPPORTED_CODECS: [&str; 6] = ["h264", "h265", "hevc", "vp8", "vp9", "mjpeg"];
+
+fn default_codec() -> String {
+    "h264".to_string()
+}
 fn default_buffer_ms() -> u32 {
     200
 }
+fn default_playback_speed() -> f64 {
+    1.0
+}
 fn default_target_width() -> u32 {
     640
 }
@@ -200,6 +397,9 @@ fn default_target_height() -> u32 {
 fn default_target_fps() -> f32 {
     10.0
 }
+fn default_pad_color() -> [u8; 3] {
+    [114, 114, 114]
+}
 fn default_pixel_format() -> String {
     "RGB".to_string()
 }
@@ -212,6 +412,9 @@ fn default_num_workers() -> usize {
 fn default_drop_on_backpressure() -> bool {
     true
 }
+fn default_max_inflight_bytes() -> usize {
+    256 * 1024 * 1024
+}
 fn default_request_timeout() -> u64 {
     30
 }
@@ -227,6 +430,15 @@ fn default_batch_size() -> usize {
 fn default_batch_timeout_ms() -> u64 {
     100
 }
+fn default_max_message_size() -> usize {
+    64 * 1024 * 1024
+}
+fn default_endpoint_cooldown_secs() -> u64 {
+    10
+}
+fn default_health_failure_threshold() -> u32 {
+    3
+}
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -239,6 +451,33 @@ fn default_health_interval() -> u64 {
 fn default_health_port() -> u16 {
     8080
 }
+fn default_max_concurrent_streams() -> usize {
+    16
+}
+fn default_start_stagger_ms() -> u64 {
+    250
+}
+fn default_storage_brokers() -> String {
+    "localhost:9092".to_string()
+}
+fn default_storage_topic() -> String {
+    "nier.storage.triggers".to_string()
+}
+fn default_storage_sample_interval_secs() -> u64 {
+    5
+}
+fn default_degraded_drop_rate() -> f64 {
+    0.1
+}
+fn default_unhealthy_drop_rate() -> f64 {
+    0.5
+}
+fn default_degraded_fps_ratio() -> f64 {
+    0.8
+}
+fn default_unhealthy_fps_ratio() -> f64 {
+    0.25
+}
 
 impl Default for LoggingConfig {
     fn default() -> Self {
@@ -250,12 +489,50 @@ impl Default for LoggingConfig {
     }
 }
 
+impl Default for StreamsConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_streams: default_max_concurrent_streams(),
+            start_stagger_ms: default_start_stagger_ms(),
+        }
+    }
+}
+
+impl StreamsConfig {
+    /// Get the stream start stagger as Duration.
+    pub fn start_stagger(&self) -> Duration {
+        Duration::from_millis(self.start_stagger_ms)
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            brokers: default_storage_brokers(),
+            topic: default_storage_topic(),
+            sample_interval_secs: default_storage_sample_interval_secs(),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Get the storage sample interval as Duration.
+    pub fn sample_interval(&self) -> Duration {
+        Duration::from_secs(self.sample_interval_secs)
+    }
+}
+
 impl Default for HealthConfig {
     fn default() -> Self {
         Self {
             interval_secs: default_health_interval(),
             port: default_health_port(),
             enable_metrics: false,
+            degraded_drop_rate: default_degraded_drop_rate(),
+            unhealthy_drop_rate: default_unhealthy_drop_rate(),
+            degraded_fps_ratio: default_degraded_fps_ratio(),
+            unhealthy_fps_ratio: default_unhealthy_fps_ratio(),
         }
     }
 }
@@ -305,10 +582,27 @@ impl IngestConfig {
         if self.rtsp.url.is_empty() {
             return Err(ConfigValidationError::MissingField("rtsp.url".to_string()));
         }
-        if !self.rtsp.url.starts_with("rtsp://") && !self.rtsp.url.starts_with("rtsps://") {
+        if !self.rtsp.url.starts_with("rtsp://")
+            && !self.rtsp.url.starts_with("rtsps://")
+            && !self.rtsp.is_file_source()
+        {
             return Err(ConfigValidationError::InvalidValue {
                 field: "rtsp.url".to_string(),
-                message: "URL must start with rtsp:// or rtsps://".to_string(),
+                message: "URL must start with rtsp://, rtsps:// or file://".to_string(),
+            });
+        }
+
+        if !SUPPORTED_CODECS.contains(&self.rtsp.codec.as_str()) {
+            return Err(ConfigValidationError::InvalidValue {
+                field: "rtsp.codec".to_string(),
+                message: format!("Codec must be one of {}", SUPPORTED_CODECS.join(", ")),
+            });
+        }
+
+        if !self.rtsp.playback_speed.is_finite() || self.rtsp.playback_speed < 0.0 {
+            return Err(ConfigValidationError::InvalidValue {
+                field: "rtsp.playback_speed".to_string(),
+                message: "Playback speed must be 0 (unpaced) or positive".to_string(),
             });
         }
 
@@ -334,15 +628,43 @@ impl IngestConfig {
             });
         }
 
+        if self.streams.max_concurrent_streams == 0 {
+            return Err(ConfigValidationError::InvalidValue {
+                field: "streams.max_concurrent_streams".to_string(),
+                message: "Must allow at least one stream".to_string(),
+            });
+        }
+
+        if self.storage.enabled && self.storage.topic.is_empty() {
+            return Err(ConfigValidationError::MissingField("storage.topic".to_string()));
+        }
+
         // Validate gRPC config
-        if self.grpc.inference_endpoint.is_empty() {
+        if self.grpc.endpoints().is_empty() {
             return Err(ConfigValidationError::MissingField(
                 "grpc.inference_endpoint".to_string(),
             ));
         }
 
+        let estimated_batch_bytes = self.estimated_batch_bytes();
+        if estimated_batch_bytes > self.grpc.max_encoding_message_size {
+            warn!(
+                batch_size = self.grpc.batch_size,
+                estimated_batch_bytes = estimated_batch_bytes,
+                max_encoding_message_size = self.grpc.max_encoding_message_size,
+                "Full frame batches will exceed the gRPC max message size"
+            );
+        }
+
         Ok(())
     }
+
+    /// Approximate encoded size of a full batch of processed RGB frames.
+    pub fn estimated_batch_bytes(&self) -> usize {
+        let frame_bytes =
+            self.processing.target_width as usize * self.processing.target_height as usize * 3;
+        self.grpc.batch_size * frame_bytes
+    }
 }
 
 impl RtspConfig {
@@ -360,6 +682,37 @@ impl RtspConfig {
     pub fn reconnect_max_delay(&self) -> Duration {
         Duration::from_millis(self.reconnect_max_delay_ms)
     }
+
+    /// Get the stable-connection period as Duration.
+    pub fn stable_connection(&self) -> Duration {
+        Duration::from_secs(self.stable_connection_secs)
+    }
+
+    /// Get the current-FPS window as Duration.
+    pub fn fps_window(&self) -> Duration {
+        Duration::from_secs(self.fps_window_secs)
+    }
+
+    /// Get the stall timeout as Duration, or None if the watchdog is disabled.
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        (self.stall_timeout_secs > 0).then(|| Duration::from_secs(self.stall_timeout_secs))
+    }
+
+    /// Whether the source is a recorded file (`file://` URL) rather than a
+    /// live stream.
+    pub fn is_file_source(&self) -> bool {
+        self.url.starts_with("file://")
+    }
+}
+
+impl ProcessingConfig {
+    /// Size the capture pipeline can scale frames to before processing.
+    ///
+    /// Only a stretch can be done there; letterbox and center-crop need the
+    /// source aspect ratio, so those frames arrive at the source size.
+    pub fn pipeline_output_size(&self) -> Option<(u32, u32)> {
+        (self.resize_mode == ResizeMode::Stretch).then_some((self.target_width, self.target_height))
+    }
 }
 
 impl GrpcConfig {
@@ -377,6 +730,26 @@ impl GrpcConfig {
     pub fn batch_timeout(&self) -> Duration {
         Duration::from_millis(self.batch_timeout_ms)
     }
+
+    /// Get the unhealthy-endpoint cooldown as Duration.
+    pub fn endpoint_cooldown(&self) -> Duration {
+        Duration::from_secs(self.endpoint_cooldown_secs)
+    }
+
+    /// All configured inference endpoints, ignoring empty entries.
+    pub fn endpoints(&self) -> Vec<String> {
+        let endpoints = if self.inference_endpoints.is_empty() {
+            std::slice::from_ref(&self.inference_endpoint)
+        } else {
+            self.inference_endpoints.as_slice()
+        };
+
+        endpoints
+            .iter()
+            .filter(|e| !e.is_empty())
+            .cloned()
+            .collect()
+    }
 }
 
 /// Configuration validation errors.
@@ -404,20 +777,33 @@ mod tests {
                 max_reconnect_attempts: 5,
                 reconnect_base_delay_ms: 1000,
                 reconnect_max_delay_ms: 30000,
+                stable_connection_secs: 60,
+                fps_window_secs: 5,
+                stall_timeout_secs: 15,
                 transport: "tcp".to_string(),
+                codec: "h264".to_string(),
                 buffer_ms: 200,
+                drop_policy: CaptureDropPolicy::DropNew,
+                playback_speed: 1.0,
             },
             processing: ProcessingConfig {
                 target_width: 640,
                 target_height: 480,
                 target_fps: 10.0,
+                resize_mode: ResizeMode::Stretch,
+                pad_color: [114, 114, 114],
                 pixel_format: "RGB".to_string(),
                 queue_size: 100,
                 num_workers: 2,
                 drop_on_backpressure: true,
+                max_inflight_bytes: 256 * 1024 * 1024,
+                use_blocking_pool: false,
             },
             grpc: GrpcConfig {
                 inference_endpoint: "http://inference:50051".to_string(),
+                inference_endpoints: vec![],
+                load_balancing: LoadBalancing::RoundRobin,
+                endpoint_cooldown_secs: 10,
                 request_timeout_secs: 30,
                 connection_timeout_secs: 10,
                 max_concurrent_requests: 10,
@@ -426,9 +812,16 @@ mod tests {
                 enable_compression: false,
                 batch_size: 1,
                 batch_timeout_ms: 100,
+                max_encoding_message_size: 64 * 1024 * 1024,
+                max_decoding_message_size: 64 * 1024 * 1024,
+                health_check_protocol: HealthCheckProtocol::Inference,
+                health_check_service: String::new(),
+                health_failure_threshold: 3,
             },
             logging: LoggingConfig::default(),
             health: HealthConfig::default(),
+            streams: StreamsConfig::default(),
+            storage: StorageConfig::default(),
         }
     }
 
@@ -458,6 +851,47 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_file_source_and_playback_speed() {
+        let mut config = create_test_config();
+        config.rtsp.url = "file:///recordings/shift-a.mp4".to_string();
+        config.rtsp.playback_speed = 0.0;
+        assert!(config.validate().is_ok());
+
+        config.rtsp.playback_speed = -2.0;
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigValidationError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_codec() {
+        let mut config = create_test_config();
+        for codec in SUPPORTED_CODECS {
+            config.rtsp.codec = codec.to_string();
+            assert!(config.validate().is_ok(), "{codec} should be accepted");
+        }
+
+        config.rtsp.codec = "av1".to_string();
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigValidationError::InvalidValue { field, .. }) if field == "rtsp.codec"
+        ));
+    }
+
+    #[test]
+    fn test_pipeline_scales_only_stretched_frames() {
+        let mut config = create_test_config();
+        config.processing.resize_mode = ResizeMode::Stretch;
+        assert_eq!(config.processing.pipeline_output_size(), Some((640, 480)));
+
+        config.processing.resize_mode = ResizeMode::Letterbox;
+        assert_eq!(config.processing.pipeline_output_size(), None);
+        config.processing.resize_mode = ResizeMode::CenterCrop;
+        assert_eq!(config.processing.pipeline_output_size(), None);
+    }
+
     #[test]
     fn test_missing_device_id() {
         let mut config = create_test_config();
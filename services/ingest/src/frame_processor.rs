@@ -3,19 +3,22 @@
 //! This module handles decoding, resizing, format conversion, and
 //! frame rate control for camera frames before sending to inference.
 
-use crate::config::ProcessingConfig;
+use crate::config::{ProcessingConfig, ResizeMode};
+use crate::health::serialize_elapsed;
 use crate::rtsp_client::RawFrame;
 use bytes::Bytes;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_video as gst_video;
 use parking_lot::RwLock;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tracing::{debug, error, info, trace, warn};
+use uuid::Uuid;
 
 /// Errors that can occur during frame processing.
 #[derive(Debug, Error)]
@@ -35,6 +38,9 @@ pub enum ProcessingError {
     #[error("Queue full, frame dropped")]
     QueueFull,
 
+    #[error("In-flight byte budget exceeded, frame dropped")]
+    ByteBudgetExceeded,
+
     #[error("Processor shutdown")]
     Shutdown,
 }
@@ -67,6 +73,9 @@ pub struct ProcessedFrame {
     /// Frame sequence number
     pub sequence: u64,
 
+    /// Stream session; sequence numbers restart at 0 in each new session
+    pub session_id: String,
+
     /// Timestamp when original frame was captured
     pub captured_at: Instant,
 
@@ -75,19 +84,156 @@ pub struct ProcessedFrame {
 
     /// Processing latency in microseconds
     pub processing_latency_us: u64,
+
+    /// How the original frame was fitted to the processed size
+    pub transform: FrameTransform,
+}
+
+/// Geometry of the resize from an original frame to its processed frame.
+///
+/// An original pixel at `(x, y)` lands at `(x * scale_x + pad_x,
+/// y * scale_y + pad_y)`, so model coordinates map back with
+/// `((x - pad_x) / scale_x, (y - pad_y) / scale_y)`. Pads are negative when
+/// the frame was center-cropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTransform {
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub pad_x: i32,
+    pub pad_y: i32,
+}
+
+impl Default for FrameTransform {
+    fn default() -> Self {
+        Self {
+            scale_x: 1.0,
+            scale_y: 1.0,
+            pad_x: 0,
+            pad_y: 0,
+        }
+    }
+}
+
+impl FrameTransform {
+    /// Geometry for fitting a `src` sized frame into `dst` with `mode`.
+    fn fit(src: (u32, u32), dst: (u32, u32), mode: ResizeMode) -> Self {
+        let scale_x = dst.0 as f32 / src.0 as f32;
+        let scale_y = dst.1 as f32 / src.1 as f32;
+        let scale = match mode {
+            ResizeMode::Stretch => {
+                return Self {
+                    scale_x,
+                    scale_y,
+                    pad_x: 0,
+                    pad_y: 0,
+                }
+            }
+            ResizeMode::Letterbox => scale_x.min(scale_y),
+            ResizeMode::CenterCrop => scale_x.max(scale_y),
+        };
+
+        let scaled_width = (src.0 as f32 * scale).round() as i32;
+        let scaled_height = (src.1 as f32 * scale).round() as i32;
+        Self {
+            scale_x: scale,
+            scale_y: scale,
+            pad_x: (dst.0 as i32 - scaled_width) / 2,
+            pad_y: (dst.1 as i32 - scaled_height) / 2,
+        }
+    }
+}
+
+/// Output size and fit of the resize step.
+#[derive(Debug, Clone, Copy)]
+struct ResizeTarget {
+    width: u32,
+    height: u32,
+    mode: ResizeMode,
+    pad_color: [u8; 3],
 }
 
 /// Statistics for the frame processor.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct ProcessorStats {
     pub frames_processed: u64,
     pub frames_dropped_rate_limit: u64,
     pub frames_dropped_backpressure: u64,
+    pub frames_dropped_byte_budget: u64,
+    pub inflight_bytes: u64,
+    /// Number of gaps seen in the incoming sequence numbers
+    pub sequence_gaps: u64,
+    /// Total frames missing across all sequence gaps (never delivered by the stream)
+    pub frames_missing: u64,
+    /// Size of the largest sequence gap seen
+    pub largest_sequence_gap: u64,
     pub total_processing_time_us: u64,
     pub avg_processing_time_us: f64,
+    #[serde(rename = "secs_since_last_frame", serialize_with = "serialize_elapsed")]
     pub last_frame_at: Option<Instant>,
 }
 
+/// Tracks bytes of processed frames that are queued or batched but not yet
+/// submitted, bounding memory independently of the channel frame count.
+#[derive(Debug)]
+pub struct ByteBudget {
+    limit: usize,
+    used: AtomicUsize,
+    released: Notify,
+}
+
+impl ByteBudget {
+    /// Create a budget of `limit` bytes (0 = unlimited).
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    /// Reserve `bytes` if they fit within the budget.
+    ///
+    /// A frame larger than the whole budget is admitted when nothing else is
+    /// in flight so oversized frames cannot stall the pipeline forever.
+    pub fn try_acquire(&self, bytes: usize) -> bool {
+        if self.limit == 0 {
+            self.used.fetch_add(bytes, Ordering::SeqCst);
+            return true;
+        }
+
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used == 0 || used + bytes <= self.limit).then_some(used + bytes)
+            })
+            .is_ok()
+    }
+
+    /// Reserve `bytes`, waiting for earlier frames to be released if needed.
+    pub async fn acquire(&self, bytes: usize) {
+        loop {
+            let notified = self.released.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.try_acquire(bytes) {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Return `bytes` to the budget.
+    pub fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+        self.released.notify_waiters();
+    }
+
+    /// Bytes currently in flight.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+}
+
 /// Frame processor configuration for runtime adjustments.
 #[derive(Debug, Clone)]
 pub struct ProcessorSettings {
@@ -117,12 +263,16 @@ pub struct FrameProcessor {
     running: Arc<AtomicBool>,
     frame_counter: Arc<AtomicU64>,
     last_frame_time: Arc<RwLock<Option<Instant>>>,
+    last_sequence: Arc<RwLock<Option<u64>>>,
+    session_id: Arc<RwLock<String>>,
+    byte_budget: Arc<ByteBudget>,
 }
 
 impl FrameProcessor {
     /// Create a new frame processor.
     pub fn new(config: ProcessingConfig, device_id: String) -> Self {
         let settings = ProcessorSettings::from(&config);
+        let byte_budget = Arc::new(ByteBudget::new(config.max_inflight_bytes));
 
         Self {
             config,
@@ -132,12 +282,25 @@ impl FrameProcessor {
             running: Arc::new(AtomicBool::new(false)),
             frame_counter: Arc::new(AtomicU64::new(0)),
             last_frame_time: Arc::new(RwLock::new(None)),
+            last_sequence: Arc::new(RwLock::new(None)),
+            session_id: Arc::new(RwLock::new(Uuid::new_v4().to_string())),
+            byte_budget,
         }
     }
 
     /// Get current processor statistics.
     pub fn stats(&self) -> ProcessorStats {
-        self.stats.read().clone()
+        let mut stats = self.stats.read().clone();
+        stats.inflight_bytes = self.byte_budget.used() as u64;
+        stats
+    }
+
+    /// Get the in-flight byte budget shared with downstream consumers.
+    ///
+    /// Whoever finally consumes a processed frame must release its
+    /// `data.len()` bytes back to this budget.
+    pub fn byte_budget(&self) -> Arc<ByteBudget> {
+        self.byte_budget.clone()
     }
 
     /// Update processor settings at runtime.
@@ -157,68 +320,54 @@ impl FrameProcessor {
         self.running.load(Ordering::SeqCst)
     }
 
-    /// Start the frame processing pipeline.
+    /// Start the frame processing pipeline in the background.
     ///
     /// Returns a receiver for processed frames.
     pub fn start(
-        &self,
+        self: &Arc<Self>,
         input: mpsc::Receiver<RawFrame>,
     ) -> mpsc::Receiver<ProcessedFrame> {
         let (tx, rx) = mpsc::channel(self.config.queue_size);
 
-        self.running.store(true, Ordering::SeqCst);
-
-        // Spawn worker tasks
-        for worker_id in 0..self.config.num_workers {
-            self.spawn_worker(worker_id, input.clone(), tx.clone());
-        }
-
-        // Note: The input receiver is cloned for each worker. In practice,
-        // you'd want to use a proper work-stealing queue. For now, we'll
-        // use a single-consumer pattern in the main start method.
+        let processor = self.clone();
+        tokio::spawn(async move { processor.run(input, tx).await });
 
         rx
     }
 
-    /// Start with a single worker (simpler pattern).
+    /// Process frames with `num_workers` workers until the input closes or
+    /// the processor is stopped.
+    ///
+    /// Workers take frames from the shared input in turn, so throughput
+    /// scales with the worker count; output order across workers is not
+    /// preserved.
     pub async fn run(
-        &self,
-        mut input: mpsc::Receiver<RawFrame>,
+        self: &Arc<Self>,
+        input: mpsc::Receiver<RawFrame>,
         output: mpsc::Sender<ProcessedFrame>,
     ) {
         self.running.store(true, Ordering::SeqCst);
+        let num_workers = self.config.num_workers.max(1);
 
         info!(
             device_id = %self.device_id,
             target_width = self.config.target_width,
             target_height = self.config.target_height,
             target_fps = self.config.target_fps,
+            num_workers = num_workers,
             "Frame processor started"
         );
 
-        while self.running.load(Ordering::SeqCst) {
-            match input.recv().await {
-                Some(frame) => {
-                    if let Err(e) = self.process_and_send(frame, &output).await {
-                        match e {
-                            ProcessingError::QueueFull => {
-                                self.stats.write().frames_dropped_backpressure += 1;
-                            }
-                            ProcessingError::Shutdown => break,
-                            _ => {
-                                warn!(
-                                    device_id = %self.device_id,
-                                    error = %e,
-                                    "Frame processing error"
-                                );
-                            }
-                        }
-                    }
-                }
-                None => {
-                    info!(device_id = %self.device_id, "Input channel closed");
-                    break;
-                }
+        let input = Arc::new(tokio::sync::Mutex::new(input));
+        let workers: Vec<_> = (0..num_workers)
+            .map(|worker_id| self.spawn_worker(worker_id, input.clone(), output.clone()))
+            .collect();
+        // The output closes once the last worker exits
+        drop(output);
+
+        for worker in workers {
+            if let Err(e) = worker.await {
+                error!(device_id = %self.device_id, error = %e, "Frame processor worker failed");
             }
         }
 
@@ -226,49 +375,140 @@ impl FrameProcessor {
         info!(device_id = %self.device_id, "Frame processor stopped");
     }
 
-    /// Process a frame and send it to the output channel.
-    async fn process_and_send(
-        &self,
-        frame: RawFrame,
-        output: &mpsc::Sender<ProcessedFrame>,
-    ) -> Result<(), ProcessingError> {
-        let settings = self.settings.read().clone();
+    /// Track the frame's sequence and apply the frame rate limit.
+    ///
+    /// Returns false if the frame is dropped.
+    fn admit_frame(&self, frame: &RawFrame, settings: &ProcessorSettings) -> bool {
+        self.track_sequence(frame.sequence);
 
         // Frame rate limiting
-        if !self.should_process_frame(&settings) {
+        if !self.should_process_frame(settings) {
             self.stats.write().frames_dropped_rate_limit += 1;
             trace!(
                 device_id = %self.device_id,
                 sequence = frame.sequence,
                 "Frame dropped due to rate limiting"
             );
-            return Ok(());
+            return false;
         }
 
+        true
+    }
+
+    /// Process an admitted frame and send it to the output channel.
+    async fn send_processed(
+        &self,
+        frame: RawFrame,
+        settings: &ProcessorSettings,
+        output: &mpsc::Sender<ProcessedFrame>,
+    ) -> Result<(), ProcessingError> {
         // Process the frame
-        let processed = self.process_frame(frame, &settings)?;
+        let processed = if self.config.use_blocking_pool {
+            self.process_frame_blocking(frame, settings).await?
+        } else {
+            self.process_frame(frame, settings)?
+        };
+        let bytes = processed.data.len();
 
         // Send to output
         if settings.drop_on_backpressure {
+            if !self.byte_budget.try_acquire(bytes) {
+                return Err(ProcessingError::ByteBudgetExceeded);
+            }
             match output.try_send(processed) {
                 Ok(()) => {}
                 Err(mpsc::error::TrySendError::Full(_)) => {
+                    self.byte_budget.release(bytes);
                     return Err(ProcessingError::QueueFull);
                 }
                 Err(mpsc::error::TrySendError::Closed(_)) => {
+                    self.byte_budget.release(bytes);
                     return Err(ProcessingError::Shutdown);
                 }
             }
         } else {
-            output
-                .send(processed)
-                .await
-                .map_err(|_| ProcessingError::Shutdown)?;
+            self.byte_budget.acquire(bytes).await;
+            if output.send(processed).await.is_err() {
+                self.byte_budget.release(bytes);
+                return Err(ProcessingError::Shutdown);
+            }
         }
 
         Ok(())
     }
 
+    /// Count or log a frame that failed to process.
+    ///
+    /// Returns false once the output is closed and processing should stop.
+    fn record_error(&self, error: ProcessingError) -> bool {
+        match error {
+            ProcessingError::QueueFull => {
+                self.stats.write().frames_dropped_backpressure += 1;
+            }
+            ProcessingError::ByteBudgetExceeded => {
+                self.stats.write().frames_dropped_byte_budget += 1;
+            }
+            ProcessingError::Shutdown => return false,
+            _ => {
+                warn!(
+                    device_id = %self.device_id,
+                    error = %error,
+                    "Frame processing error"
+                );
+            }
+        }
+        true
+    }
+
+    /// Record gaps in the incoming sequence numbers.
+    ///
+    /// A sequence lower than the last one seen means the stream restarted
+    /// (sequence resets to 0 on reconnect); it is not counted as a gap and
+    /// starts a new stream session.
+    fn track_sequence(&self, sequence: u64) {
+        let mut last = self.last_sequence.write();
+
+        if let Some(prev) = *last {
+            if sequence > prev + 1 {
+                let gap = sequence - prev - 1;
+                let mut stats = self.stats.write();
+                stats.sequence_gaps += 1;
+                stats.frames_missing += gap;
+                stats.largest_sequence_gap = stats.largest_sequence_gap.max(gap);
+                debug!(
+                    device_id = %self.device_id,
+                    after = prev,
+                    missing = gap,
+                    "Sequence gap detected"
+                );
+            } else if sequence < prev {
+                let session_id = self.start_session();
+                debug!(
+                    device_id = %self.device_id,
+                    previous = prev,
+                    sequence = sequence,
+                    session_id = %session_id,
+                    "Sequence reset, stream restarted"
+                );
+            }
+        }
+
+        *last = Some(sequence);
+    }
+
+    /// Forget the last seen sequence, e.g. after an explicit reconnect.
+    pub fn reset_sequence(&self) {
+        *self.last_sequence.write() = None;
+        self.start_session();
+    }
+
+    /// Start a new stream session, returning its ID.
+    fn start_session(&self) -> String {
+        let session_id = Uuid::new_v4().to_string();
+        *self.session_id.write() = session_id.clone();
+        session_id
+    }
+
     /// Check if we should process this frame based on target FPS.
     fn should_process_frame(&self, settings: &ProcessorSettings) -> bool {
         let min_interval = Duration::from_secs_f32(1.0 / settings.target_fps);
@@ -293,16 +533,63 @@ impl FrameProcessor {
         let start = Instant::now();
 
         // Resize and convert if needed
-        let processed_data = self.resize_and_convert(
+        let resized = Self::resize_and_convert(
             &frame.data,
             frame.width,
             frame.height,
-            settings.target_width,
-            settings.target_height,
+            &self.resize_target(settings),
             &frame.format,
         )?;
 
-        let processing_time = start.elapsed();
+        self.finish_frame(frame, settings, resized, start.elapsed())
+    }
+
+    /// Process a single frame with resize/convert on the blocking thread pool.
+    ///
+    /// Keeps CPU-heavy pixel work off the async runtime so it stays
+    /// responsive under load.
+    async fn process_frame_blocking(
+        &self,
+        frame: RawFrame,
+        settings: &ProcessorSettings,
+    ) -> Result<ProcessedFrame, ProcessingError> {
+        let start = Instant::now();
+        let target = self.resize_target(settings);
+
+        let (frame, resized) = tokio::task::spawn_blocking(move || {
+            let resized = Self::resize_and_convert(
+                &frame.data,
+                frame.width,
+                frame.height,
+                &target,
+                &frame.format,
+            );
+            (frame, resized)
+        })
+        .await
+        .map_err(|e| ProcessingError::ProcessingFailed(e.to_string()))?;
+
+        self.finish_frame(frame, settings, resized?, start.elapsed())
+    }
+
+    /// Output size and fit for the current settings.
+    fn resize_target(&self, settings: &ProcessorSettings) -> ResizeTarget {
+        ResizeTarget {
+            width: settings.target_width,
+            height: settings.target_height,
+            mode: self.config.resize_mode,
+            pad_color: self.config.pad_color,
+        }
+    }
+
+    /// Wrap processed pixels into a `ProcessedFrame` and update stats.
+    fn finish_frame(
+        &self,
+        frame: RawFrame,
+        settings: &ProcessorSettings,
+        (processed_data, transform): (Vec<u8>, FrameTransform),
+        processing_time: Duration,
+    ) -> Result<ProcessedFrame, ProcessingError> {
         let processing_latency_us = processing_time.as_micros() as u64;
 
         // Generate frame ID
@@ -342,9 +629,11 @@ impl FrameProcessor {
             original_width: frame.width,
             original_height: frame.height,
             sequence: frame.sequence,
+            session_id: self.session_id.read().clone(),
             captured_at: frame.captured_at,
             processed_at: Instant::now(),
             processing_latency_us,
+            transform,
         })
     }
 
@@ -353,79 +642,130 @@ impl FrameProcessor {
     /// For production use, this would use GPU acceleration (CUDA, OpenCL)
     /// or optimized CPU libraries. This is a placeholder implementation.
     fn resize_and_convert(
-        &self,
         data: &[u8],
         src_width: u32,
         src_height: u32,
-        dst_width: u32,
-        dst_height: u32,
+        target: &ResizeTarget,
         src_format: &str,
-    ) -> Result<Vec<u8>, ProcessingError> {
+    ) -> Result<(Vec<u8>, FrameTransform), ProcessingError> {
+        let (dst_width, dst_height) = (target.width, target.height);
+
         // If no resize needed and format is already RGB, return as-is
         if src_width == dst_width && src_height == dst_height && src_format == "RGB" {
-            return Ok(data.to_vec());
+            return Ok((data.to_vec(), FrameTransform::default()));
         }
 
-        // Simple bilinear resize implementation
+        let src_size = (src_width * src_height * 3) as usize;
+        if src_size == 0 || data.len() < src_size {
+            return Err(ProcessingError::InvalidFormat(format!(
+                "{} bytes for a {}x{} RGB frame",
+                data.len(),
+                src_width,
+                src_height
+            )));
+        }
+
+        let transform = FrameTransform::fit(
+            (src_width, src_height),
+            (dst_width, dst_height),
+            target.mode,
+        );
+
+        // Bilinear resize
         // In production, use GPU-accelerated resize or libraries like image-rs
         let dst_size = (dst_width * dst_height * 3) as usize;
-        let mut output = vec![0u8; dst_size];
-
-        let x_ratio = src_width as f32 / dst_width as f32;
-        let y_ratio = src_height as f32 / dst_height as f32;
+        let mut output = target.pad_color.repeat(dst_size / 3);
+        let pixel =
+            |x: u32, y: u32, channel: u32| data[((y * src_width + x) * 3 + channel) as usize];
 
         for y in 0..dst_height {
+            // Rows outside the scaled frame keep the pad color
+            let content_y = (y as i32 - transform.pad_y) as f32;
+            if content_y < 0.0 || content_y / transform.scale_y >= src_height as f32 {
+                continue;
+            }
+            let (y0, y1, wy) = Self::bilinear_taps(content_y, transform.scale_y, src_height);
+
             for x in 0..dst_width {
-                let src_x = (x as f32 * x_ratio) as u32;
-                let src_y = (y as f32 * y_ratio) as u32;
+                let content_x = (x as i32 - transform.pad_x) as f32;
+                if content_x < 0.0 || content_x / transform.scale_x >= src_width as f32 {
+                    continue;
+                }
+                let (x0, x1, wx) = Self::bilinear_taps(content_x, transform.scale_x, src_width);
 
-                let src_idx = ((src_y * src_width + src_x) * 3) as usize;
                 let dst_idx = ((y * dst_width + x) * 3) as usize;
-
-                if src_idx + 2 < data.len() && dst_idx + 2 < output.len() {
-                    output[dst_idx] = data[src_idx];
-                    output[dst_idx + 1] = data[src_idx + 1];
-                    output[dst_idx + 2] = data[src_idx + 2];
+                for channel in 0..3 {
+                    let top = pixel(x0, y0, channel) as f32 * (1.0 - wx)
+                        + pixel(x1, y0, channel) as f32 * wx;
+                    let bottom = pixel(x0, y1, channel) as f32 * (1.0 - wx)
+                        + pixel(x1, y1, channel) as f32 * wx;
+                    output[dst_idx + channel as usize] =
+                        (top * (1.0 - wy) + bottom * wy).round() as u8;
                 }
             }
         }
 
-        Ok(output)
+        Ok((output, transform))
+    }
+
+    /// Source pixels either side of a destination coordinate, and the weight
+    /// of the second one.
+    ///
+    /// Samples at pixel centers; coordinates past the edge clamp to the
+    /// border pixel so nothing outside the frame is read.
+    fn bilinear_taps(dst: f32, scale: f32, src_len: u32) -> (u32, u32, f32) {
+        let last = (src_len - 1) as f32;
+        let src = ((dst + 0.5) / scale - 0.5).clamp(0.0, last);
+        let first = src.floor();
+        let second = (first + 1.0).min(last);
+        (first as u32, second as u32, src - first)
     }
 
     /// Spawn a worker task for processing frames.
     fn spawn_worker(
-        &self,
+        self: &Arc<Self>,
         worker_id: usize,
-        _input: mpsc::Receiver<RawFrame>,
-        _output: mpsc::Sender<ProcessedFrame>,
-    ) {
-        let device_id = self.device_id.clone();
-        let settings = self.settings.clone();
-        let stats = self.stats.clone();
-        let running = self.running.clone();
-        let last_frame_time = self.last_frame_time.clone();
+        input: Arc<tokio::sync::Mutex<mpsc::Receiver<RawFrame>>>,
+        output: mpsc::Sender<ProcessedFrame>,
+    ) -> tokio::task::JoinHandle<()> {
+        let processor = self.clone();
 
         tokio::spawn(async move {
             debug!(
-                device_id = %device_id,
+                device_id = %processor.device_id,
                 worker_id = worker_id,
                 "Frame processor worker started"
             );
 
-            // Worker implementation would go here
-            // For now, this is a placeholder showing the pattern
+            while processor.running.load(Ordering::SeqCst) {
+                // Admit frames under the input lock so sequence tracking and
+                // rate limiting see them in arrival order
+                let (frame, settings) = {
+                    let mut input = input.lock().await;
+                    let Some(frame) = input.recv().await else {
+                        debug!(device_id = %processor.device_id, "Input channel closed");
+                        break;
+                    };
+                    let settings = processor.settings.read().clone();
+                    if !processor.admit_frame(&frame, &settings) {
+                        continue;
+                    }
+                    (frame, settings)
+                };
 
-            while running.load(Ordering::SeqCst) {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                if let Err(e) = processor.send_processed(frame, &settings, &output).await {
+                    if !processor.record_error(e) {
+                        break;
+                    }
+                }
             }
 
             debug!(
-                device_id = %device_id,
+                device_id = %processor.device_id,
                 worker_id = worker_id,
                 "Frame processor worker stopped"
             );
-        });
+        })
     }
 
     /// Stop the processor.
@@ -496,10 +836,14 @@ mod tests {
             target_width: 320,
             target_height: 240,
             target_fps: 10.0,
+            resize_mode: ResizeMode::Stretch,
+            pad_color: [114, 114, 114],
             pixel_format: "RGB".to_string(),
             queue_size: 10,
             num_workers: 1,
             drop_on_backpressure: true,
+            max_inflight_bytes: 256 * 1024 * 1024,
+            use_blocking_pool: false,
         }
     }
 
@@ -538,6 +882,59 @@ mod tests {
         assert_eq!(processed.height, 240);
     }
 
+    #[tokio::test]
+    async fn test_blocking_pool_matches_inline_processing() {
+        let mut config = create_test_config();
+        config.use_blocking_pool = true;
+        let processor = Arc::new(FrameProcessor::new(config, "test-device".to_string()));
+        let settings = processor.settings.read().clone();
+
+        let mut frame = create_test_frame(640, 480);
+        for (i, pixel) in frame.data.iter_mut().enumerate() {
+            *pixel = (i % 251) as u8;
+        }
+        frame.sequence = 7;
+
+        let inline = processor.process_frame(frame.clone(), &settings).unwrap();
+        let blocking = processor
+            .process_frame_blocking(frame, &settings)
+            .await
+            .unwrap();
+
+        assert_eq!((blocking.width, blocking.height), (320, 240));
+        assert_eq!(blocking.sequence, 7);
+        assert_eq!(blocking.data, inline.data);
+        assert_eq!(processor.stats().frames_processed, 2);
+
+        // Workers route frames through the blocking pool when configured
+        let (tx, rx) = mpsc::channel(1);
+        let mut processed_rx = processor.start(rx);
+        tx.send(create_test_frame(640, 480)).await.unwrap();
+        assert_eq!(processed_rx.recv().await.unwrap().data.len(), 320 * 240 * 3);
+        assert_eq!(processor.stats().frames_processed, 3);
+    }
+
+    #[test]
+    fn test_sequence_reset_starts_new_session() {
+        let config = create_test_config();
+        let processor = FrameProcessor::new(config, "test-device".to_string());
+        let settings = processor.settings.read().clone();
+
+        let mut frame = create_test_frame(320, 240);
+        frame.sequence = 41;
+        processor.track_sequence(41);
+        let before = processor.process_frame(frame, &settings).unwrap();
+
+        // Reconnect restarts the sequence at 0
+        processor.track_sequence(0);
+        let after = processor
+            .process_frame(create_test_frame(320, 240), &settings)
+            .unwrap();
+
+        assert_ne!(before.session_id, after.session_id);
+        assert_eq!(processor.stats().sequence_gaps, 0);
+    }
+
     #[test]
     fn test_frame_buffer() {
         let mut buffer = FrameBuffer::new(3);
@@ -572,6 +969,73 @@ mod tests {
         assert!(stats.avg_processing_time_us > 0.0);
     }
 
+    #[test]
+    fn test_sequence_gap_detection() {
+        let config = create_test_config();
+        let processor = FrameProcessor::new(config, "test-device".to_string());
+
+        for sequence in [0, 1, 3, 4] {
+            processor.track_sequence(sequence);
+        }
+
+        let stats = processor.stats();
+        assert_eq!(stats.sequence_gaps, 1);
+        assert_eq!(stats.frames_missing, 1);
+        assert_eq!(stats.largest_sequence_gap, 1);
+
+        // Sequence restarting at 0 after a reconnect is not a gap
+        processor.track_sequence(0);
+        processor.track_sequence(1);
+        assert_eq!(processor.stats().sequence_gaps, 1);
+    }
+
+    #[tokio::test]
+    async fn test_byte_budget_trips_before_count_limit() {
+        let frame_bytes = 320 * 240 * 3;
+        let mut config = create_test_config();
+        config.target_fps = f32::MAX; // No rate limiting
+        config.queue_size = 10;
+        config.max_inflight_bytes = frame_bytes * 3;
+        let processor = Arc::new(FrameProcessor::new(config, "test-device".to_string()));
+
+        let (tx, rx) = mpsc::channel(10);
+        let mut processed_rx = processor.start(rx);
+        for i in 0..5 {
+            let mut frame = create_test_frame(640, 480);
+            frame.sequence = i;
+            tx.send(frame).await.unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while processor.stats().frames_dropped_byte_budget < 2 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // The last two frames find the budget spent, not the queue full
+        let stats = processor.stats();
+        assert_eq!(stats.frames_dropped_byte_budget, 2);
+        assert_eq!(stats.frames_dropped_backpressure, 0);
+        assert_eq!(stats.inflight_bytes, (frame_bytes * 3) as u64);
+
+        // Consuming a frame and releasing its bytes frees room for another
+        let frame = processed_rx.recv().await.unwrap();
+        assert_eq!(frame.sequence, 0);
+        processor.byte_budget().release(frame.data.len());
+
+        let mut frame = create_test_frame(640, 480);
+        frame.sequence = 5;
+        tx.send(frame).await.unwrap();
+        drop(tx);
+
+        let mut sequences = Vec::new();
+        while let Some(frame) = processed_rx.recv().await {
+            processor.byte_budget().release(frame.data.len());
+            sequences.push(frame.sequence);
+        }
+        assert_eq!(sequences, vec![1, 2, 5]);
+        assert_eq!(processor.stats().frames_dropped_byte_budget, 2);
+    }
+
     #[test]
     fn test_settings_update() {
         let config = create_test_config();
@@ -591,4 +1055,156 @@ mod tests {
         assert_eq!(current.target_height, 120);
         assert_eq!(current.target_fps, 5.0);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_workers_process_each_frame_exactly_once() {
+        let mut config = create_test_config();
+        config.num_workers = 4;
+        config.target_fps = f32::MAX; // No rate limiting
+        config.drop_on_backpressure = false;
+        let processor = Arc::new(FrameProcessor::new(config, "test-device".to_string()));
+
+        let (tx, rx) = mpsc::channel(64);
+        let mut processed_rx = processor.start(rx);
+
+        for sequence in 0..50 {
+            let mut frame = create_test_frame(64, 48);
+            frame.sequence = sequence;
+            tx.send(frame).await.unwrap();
+        }
+        drop(tx);
+
+        let mut sequences = Vec::new();
+        while let Some(frame) = processed_rx.recv().await {
+            processor.byte_budget().release(frame.data.len());
+            sequences.push(frame.sequence);
+        }
+        sequences.sort_unstable();
+
+        assert_eq!(sequences, (0..50).collect::<Vec<_>>());
+        assert_eq!(processor.stats().frames_processed, 50);
+        assert_eq!(processor.stats().sequence_gaps, 0);
+    }
+
+    #[test]
+    fn test_resize_modes_output_dimensions() {
+        for mode in [
+            ResizeMode::Stretch,
+            ResizeMode::Letterbox,
+            ResizeMode::CenterCrop,
+        ] {
+            let mut config = create_test_config();
+            config.resize_mode = mode;
+            let processor = FrameProcessor::new(config, "test-device".to_string());
+            let settings = processor.settings.read().clone();
+
+            let processed = processor
+                .process_frame(create_test_frame(1280, 720), &settings)
+                .unwrap();
+            assert_eq!((processed.width, processed.height), (320, 240));
+            assert_eq!(processed.data.len(), 320 * 240 * 3, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_letterbox_pads_wide_frame_to_square() {
+        let mut config = create_test_config();
+        config.target_width = 160;
+        config.target_height = 160;
+        config.resize_mode = ResizeMode::Letterbox;
+        config.pad_color = [0, 0, 0];
+        let processor = FrameProcessor::new(config, "test-device".to_string());
+        let settings = processor.settings.read().clone();
+
+        // 16:9 source scales to 160x90, leaving 35-row bands above and below
+        let processed = processor
+            .process_frame(create_test_frame(320, 180), &settings)
+            .unwrap();
+        assert_eq!(
+            processed.transform,
+            FrameTransform {
+                scale_x: 0.5,
+                scale_y: 0.5,
+                pad_x: 0,
+                pad_y: 35,
+            }
+        );
+
+        let row = |y: usize| &processed.data[y * 160 * 3..(y + 1) * 160 * 3];
+        for y in (0..35).chain(125..160) {
+            assert!(
+                row(y).iter().all(|&p| p == 0),
+                "row {} should be padding",
+                y
+            );
+        }
+        for y in 35..125 {
+            assert!(
+                row(y).iter().all(|&p| p == 128),
+                "row {} should be image",
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_center_crop_fills_target() {
+        let mut config = create_test_config();
+        config.target_width = 160;
+        config.target_height = 160;
+        config.resize_mode = ResizeMode::CenterCrop;
+        config.pad_color = [0, 0, 0];
+        let processor = FrameProcessor::new(config, "test-device".to_string());
+        let settings = processor.settings.read().clone();
+
+        let processed = processor
+            .process_frame(create_test_frame(320, 180), &settings)
+            .unwrap();
+
+        // Scaled to 284x160; 62 columns are cropped from each side
+        let transform = processed.transform;
+        assert!((transform.scale_x - 160.0 / 180.0).abs() < 1e-6);
+        assert_eq!((transform.pad_x, transform.pad_y), (-62, 0));
+        assert!(processed.data.iter().all(|&p| p == 128));
+    }
+
+    #[test]
+    fn test_bilinear_downscale_interpolates_gradient() {
+        // 4x2 gradient: +60 per column, +20 per row
+        let data: Vec<u8> = (0..2u8)
+            .flat_map(|y| (0..4u8).flat_map(move |x| [x * 60 + y * 20; 3]))
+            .collect();
+        let target = ResizeTarget {
+            width: 2,
+            height: 1,
+            mode: ResizeMode::Stretch,
+            pad_color: [0, 0, 0],
+        };
+
+        let (output, _) = FrameProcessor::resize_and_convert(&data, 4, 2, &target, "RGB").unwrap();
+
+        // Each output pixel averages a 2x2 block; nearest-neighbor would give 0 and 120
+        assert_eq!(output, vec![40, 40, 40, 160, 160, 160]);
+    }
+
+    #[test]
+    fn test_bilinear_upscale_stays_in_bounds() {
+        let data = vec![10, 10, 10, 250, 250, 250];
+        let target = ResizeTarget {
+            width: 8,
+            height: 3,
+            mode: ResizeMode::Stretch,
+            pad_color: [0, 0, 0],
+        };
+
+        let (output, _) = FrameProcessor::resize_and_convert(&data, 2, 1, &target, "RGB").unwrap();
+
+        assert_eq!(output.len(), 8 * 3 * 3);
+        // Edge pixels clamp to the border values
+        assert_eq!(&output[..3], &[10, 10, 10]);
+        assert_eq!(&output[7 * 3..8 * 3], &[250, 250, 250]);
+        // Interior pixels blend monotonically between them
+        let row: Vec<u8> = output[..8 * 3].iter().step_by(3).copied().collect();
+        assert!(row.windows(2).all(|w| w[0] <= w[1]), "{:?}", row);
+    }
 }
@@ -3,18 +3,23 @@
 //! This module handles communication with the inference service,
 //! including connection management, batching, and retry logic.
 
-use crate::config::GrpcConfig;
-use crate::frame_processor::ProcessedFrame;
+use crate::config::{GrpcConfig, HealthCheckProtocol, LoadBalancing};
+use crate::frame_processor::{ByteBudget, ProcessedFrame};
+use crate::health::serialize_elapsed;
+use crate::storage_event::StorageSampler;
 use async_trait::async_trait;
-use backoff::{backoff::Backoff, ExponentialBackoff};
 use bytes::Bytes;
+use nier_retry::{retry_with_backoff, BackoffPolicy};
 use parking_lot::RwLock;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::{mpsc, Semaphore};
 use tokio::time::timeout;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
 use tonic::transport::{Channel, Endpoint};
 use tonic::{Request, Status};
 use tracing::{debug, error, info, warn};
@@ -45,6 +50,10 @@ pub mod proto {
         pub original_height: u32,
         pub source_fps: f32,
         pub source_bitrate_kbps: u32,
+        pub scale_x: f32,
+        pub scale_y: f32,
+        pub pad_x: i32,
+        pub pad_y: i32,
     }
 
     #[derive(Clone, Debug)]
@@ -85,6 +94,33 @@ pub mod proto {
         pub healthy: bool,
         pub status: String,
     }
+
+    /// `grpc.health.v1` messages, encoded on the wire with prost
+    pub mod health {
+        /// Path of the standard `Health/Check` method
+        pub const CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct HealthCheckRequest {
+            #[prost(string, tag = "1")]
+            pub service: String,
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+        #[repr(i32)]
+        pub enum ServingStatus {
+            Unknown = 0,
+            Serving = 1,
+            NotServing = 2,
+            ServiceUnknown = 3,
+        }
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct HealthCheckResponse {
+            #[prost(enumeration = "ServingStatus", tag = "1")]
+            pub status: i32,
+        }
+    }
 }
 
 /// Errors that can occur during gRPC operations.
@@ -113,6 +149,9 @@ pub enum GrpcError {
 
     #[error("Max retries exceeded")]
     MaxRetriesExceeded,
+
+    #[error("Message of {size} bytes exceeds max encoding size of {limit} bytes")]
+    MessageTooLarge { size: usize, limit: usize },
 }
 
 impl From<Status> for GrpcError {
@@ -127,16 +166,22 @@ impl From<Status> for GrpcError {
 }
 
 /// Statistics for the gRPC client.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct ClientStats {
     pub frames_sent: u64,
     pub frames_accepted: u64,
     pub frames_rejected: u64,
     pub batches_sent: u64,
+    pub avg_batch_size: f64,
     pub total_latency_ms: u64,
     pub avg_latency_ms: f64,
     pub reconnect_count: u32,
+    #[serde(
+        rename = "secs_since_last_success",
+        serialize_with = "serialize_elapsed"
+    )]
     pub last_success_at: Option<Instant>,
+    #[serde(rename = "secs_since_last_error", serialize_with = "serialize_elapsed")]
     pub last_error_at: Option<Instant>,
 }
 
@@ -193,37 +238,210 @@ pub struct BatchResult {
     pub processing_ids: Vec<String>,
 }
 
+/// Client for one inference endpoint, with the configured message size
+/// limits enforced by its codec on every call.
+type InferenceChannel = tonic::client::Grpc<Channel>;
+
+/// A single inference replica and its connection health.
+struct EndpointSlot {
+    url: String,
+    channel: RwLock<Option<InferenceChannel>>,
+    in_flight: AtomicUsize,
+    unhealthy_until: RwLock<Option<Instant>>,
+}
+
+impl EndpointSlot {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.read().map_or(true, |until| now >= until)
+    }
+}
+
+/// Client-side load balancer over the configured inference endpoints.
+///
+/// Endpoints that fail are skipped for a cooldown period, after which they
+/// are tried again.
+pub struct EndpointPool {
+    slots: Vec<EndpointSlot>,
+    strategy: LoadBalancing,
+    cooldown: Duration,
+    next: AtomicUsize,
+}
+
+impl EndpointPool {
+    /// Create a pool over `urls` using the given strategy.
+    pub fn new(urls: Vec<String>, strategy: LoadBalancing, cooldown: Duration) -> Self {
+        Self {
+            slots: urls
+                .into_iter()
+                .map(|url| EndpointSlot {
+                    url,
+                    channel: RwLock::new(None),
+                    in_flight: AtomicUsize::new(0),
+                    unhealthy_until: RwLock::new(None),
+                })
+                .collect(),
+            strategy,
+            cooldown,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of endpoints in the pool.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether the pool has no endpoints.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// URL of the endpoint at `index`.
+    pub fn url(&self, index: usize) -> &str {
+        &self.slots[index].url
+    }
+
+    /// Choose a healthy endpoint, or None if every endpoint is cooling down.
+    pub fn pick(&self, now: Instant) -> Option<usize> {
+        let len = self.slots.len();
+        if len == 0 {
+            return None;
+        }
+
+        match self.strategy {
+            LoadBalancing::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..len)
+                    .map(|offset| (start + offset) % len)
+                    .find(|&i| self.slots[i].is_healthy(now))
+            }
+            LoadBalancing::LeastInFlight => (0..len)
+                .filter(|&i| self.slots[i].is_healthy(now))
+                .min_by_key(|&i| self.slots[i].in_flight.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Skip the endpoint at `index` until the cooldown elapses.
+    pub fn mark_unhealthy(&self, index: usize, now: Instant) {
+        *self.slots[index].unhealthy_until.write() = Some(now + self.cooldown);
+        *self.slots[index].channel.write() = None;
+    }
+
+    /// Clear any cooldown on the endpoint at `index`.
+    pub fn mark_healthy(&self, index: usize) {
+        *self.slots[index].unhealthy_until.write() = None;
+    }
+
+    /// Track a request in flight on the endpoint at `index` until the guard drops.
+    pub fn begin_request(&self, index: usize) -> InFlightGuard<'_> {
+        self.slots[index].in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard {
+            counter: &self.slots[index].in_flight,
+        }
+    }
+
+    fn channel(&self, index: usize) -> Option<InferenceChannel> {
+        self.slots[index].channel.read().clone()
+    }
+
+    fn set_channel(&self, index: usize, channel: Option<InferenceChannel>) {
+        *self.slots[index].channel.write() = channel;
+    }
+
+    fn has_channel(&self) -> bool {
+        self.slots.iter().any(|s| s.channel.read().is_some())
+    }
+}
+
+/// Decrements an endpoint's in-flight count when dropped.
+pub struct InFlightGuard<'a> {
+    counter: &'a AtomicUsize,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 /// gRPC client for the inference service.
 pub struct InferenceGrpcClient {
     config: GrpcConfig,
-    channel: Arc<RwLock<Option<Channel>>>,
+    endpoints: Arc<EndpointPool>,
     state: Arc<RwLock<ClientState>>,
     stats: Arc<RwLock<ClientStats>>,
     running: Arc<AtomicBool>,
     request_semaphore: Arc<Semaphore>,
+    health: HealthTracker,
 }
 
 impl InferenceGrpcClient {
     /// Create a new inference gRPC client.
     pub fn new(config: GrpcConfig) -> Self {
         let max_concurrent = config.max_concurrent_requests;
+        let health_failure_threshold = config.health_failure_threshold;
+        let endpoints = EndpointPool::new(
+            config.endpoints(),
+            config.load_balancing,
+            config.endpoint_cooldown(),
+        );
 
         Self {
             config,
-            channel: Arc::new(RwLock::new(None)),
+            endpoints: Arc::new(endpoints),
             state: Arc::new(RwLock::new(ClientState::Disconnected)),
             stats: Arc::new(RwLock::new(ClientStats::default())),
             running: Arc::new(AtomicBool::new(false)),
             request_semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            health: HealthTracker::new(health_failure_threshold),
         }
     }
 
     /// Connect to the inference service.
+    ///
+    /// Succeeds if at least one endpoint connects; the rest are marked
+    /// unhealthy and retried after the cooldown.
     pub async fn connect(&self) -> Result<(), GrpcError> {
         *self.state.write() = ClientState::Connecting;
         self.running.store(true, Ordering::SeqCst);
 
-        let endpoint = Endpoint::from_shared(self.config.inference_endpoint.clone())
+        let mut last_error =
+            GrpcError::ConnectionFailed("No inference endpoints configured".to_string());
+        let mut connected = 0usize;
+
+        for index in 0..self.endpoints.len() {
+            match self.connect_endpoint(index).await {
+                Ok(()) => connected += 1,
+                Err(e) => {
+                    warn!(
+                        endpoint = %self.endpoints.url(index),
+                        error = %e,
+                        "Failed to connect to inference endpoint"
+                    );
+                    self.endpoints.mark_unhealthy(index, Instant::now());
+                    last_error = e;
+                }
+            }
+        }
+
+        if connected == 0 {
+            return Err(last_error);
+        }
+
+        *self.state.write() = ClientState::Connected;
+
+        info!(
+            connected = connected,
+            endpoints = self.endpoints.len(),
+            "Connected to inference service"
+        );
+
+        Ok(())
+    }
+
+    /// Open a channel to a single endpoint.
+    async fn connect_endpoint(&self, index: usize) -> Result<(), GrpcError> {
+        let endpoint = Endpoint::from_shared(self.endpoints.url(index).to_string())
             .map_err(|e| GrpcError::ConnectionFailed(e.to_string()))?
             .connect_timeout(self.config.connection_timeout())
             .timeout(self.config.request_timeout());
@@ -235,80 +453,171 @@ impl InferenceGrpcClient {
             .connect()
             .await
             .map_err(|e| GrpcError::ConnectionFailed(e.to_string()))?;
+        let channel = tonic::client::Grpc::new(channel)
+            .max_encoding_message_size(self.config.max_encoding_message_size)
+            .max_decoding_message_size(self.config.max_decoding_message_size);
 
-        *self.channel.write() = Some(channel);
-        *self.state.write() = ClientState::Connected;
-
-        info!(
-            endpoint = %self.config.inference_endpoint,
-            "Connected to inference service"
-        );
+        self.endpoints.set_channel(index, Some(channel));
+        self.endpoints.mark_healthy(index);
 
         Ok(())
     }
 
     /// Connect with retry logic.
     pub async fn connect_with_retry(&self) -> Result<(), GrpcError> {
-        let mut backoff = ExponentialBackoff {
-            initial_interval: Duration::from_millis(500),
-            max_interval: Duration::from_secs(30),
-            max_elapsed_time: None,
-            ..Default::default()
-        };
-
-        let mut attempts = 0u32;
-
-        loop {
-            match self.connect().await {
-                Ok(()) => return Ok(()),
-                Err(e) => {
-                    attempts += 1;
-                    self.stats.write().reconnect_count = attempts;
-
-                    if let Some(delay) = backoff.next_backoff() {
-                        warn!(
-                            endpoint = %self.config.inference_endpoint,
-                            attempt = attempts,
-                            delay_ms = delay.as_millis(),
-                            error = %e,
-                            "Connection failed, retrying"
-                        );
-                        *self.state.write() = ClientState::Reconnecting;
-                        tokio::time::sleep(delay).await;
-                    } else {
-                        error!(
-                            endpoint = %self.config.inference_endpoint,
-                            attempts = attempts,
-                            "Max connection retries exceeded"
-                        );
-                        return Err(GrpcError::MaxRetriesExceeded);
-                    }
+        // Effectively retries forever, backing off to one attempt every 30s
+        let policy = BackoffPolicy::new(u32::MAX, Duration::from_millis(500))
+            .with_max_delay(Duration::from_secs(30));
+        let attempts = AtomicU32::new(0);
+
+        retry_with_backoff(
+            || async {
+                let result = self.connect().await;
+                if let Err(ref e) = result {
+                    let attempt = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+                    self.stats.write().reconnect_count = attempt;
+                    *self.state.write() = ClientState::Reconnecting;
+                    debug!(
+                        endpoints = ?self.config.endpoints(),
+                        attempt = attempt,
+                        error = %e,
+                        "Connection attempt failed"
+                    );
                 }
-            }
+                result
+            },
+            &policy,
+            |_| true,
+        )
+        .await
+        .map_err(|e| {
+            error!(
+                endpoints = ?self.config.endpoints(),
+                attempts = attempts.load(Ordering::Relaxed),
+                error = %e,
+                "Max connection retries exceeded"
+            );
+            GrpcError::MaxRetriesExceeded
+        })
+    }
+
+    /// Reject requests that would exceed the max encoding size before sending.
+    ///
+    /// The channel's codec enforces the same limit on the encoded message;
+    /// checking the frame bytes first avoids encoding a request that would
+    /// be refused anyway.
+    fn check_message_size(&self, size: usize) -> Result<(), GrpcError> {
+        let limit = self.config.max_encoding_message_size;
+        if size > limit {
+            return Err(GrpcError::MessageTooLarge { size, limit });
         }
+        Ok(())
     }
 
     /// Disconnect from the inference service.
     pub async fn disconnect(&self) {
         self.running.store(false, Ordering::SeqCst);
-        *self.channel.write() = None;
+        for index in 0..self.endpoints.len() {
+            self.endpoints.set_channel(index, None);
+        }
         *self.state.write() = ClientState::Disconnected;
         info!("Disconnected from inference service");
     }
 
-    /// Get the channel, reconnecting if necessary.
-    async fn get_channel(&self) -> Result<Channel, GrpcError> {
-        let channel = self.channel.read().clone();
+    /// Endpoint pool used for load balancing.
+    pub fn endpoints(&self) -> &EndpointPool {
+        &self.endpoints
+    }
+
+    /// Run one health probe with the configured protocol.
+    async fn probe_health(&self, device_id: &str) -> Result<bool, GrpcError> {
+        let (endpoint, channel) = self.get_channel().await?;
 
-        match channel {
-            Some(ch) => Ok(ch),
-            None => {
-                self.connect_with_retry().await?;
-                self.channel
-                    .read()
-                    .clone()
-                    .ok_or(GrpcError::ConnectionFailed("No channel".to_string()))
+        match self.config.health_check_protocol {
+            HealthCheckProtocol::Inference => {
+                let _request = proto::HealthCheckRequest {
+                    device_id: device_id.to_string(),
+                };
+
+                // Simulate health check
+                let response = proto::HealthCheckResponse {
+                    healthy: true,
+                    status: "OK".to_string(),
+                };
+
+                Ok(response.healthy)
             }
+            HealthCheckProtocol::Grpc => {
+                let service = self.config.health_check_service.clone();
+                let status = check_serving_status(channel, service).await.map_err(|e| {
+                    self.record_endpoint_error(endpoint, &e);
+                    e
+                })?;
+
+                // NOT_SERVING, SERVICE_UNKNOWN and UNKNOWN all fail the probe
+                if status != proto::health::ServingStatus::Serving {
+                    warn!(
+                        endpoint = %self.endpoints.url(endpoint),
+                        service = %self.config.health_check_service,
+                        status = ?status,
+                        "Inference service is not serving"
+                    );
+                }
+                Ok(status == proto::health::ServingStatus::Serving)
+            }
+        }
+    }
+
+    /// Pick a healthy endpoint and return its index and channel, reconnecting if necessary.
+    async fn get_channel(&self) -> Result<(usize, InferenceChannel), GrpcError> {
+        if !self.endpoints.has_channel() {
+            self.connect_with_retry().await?;
+        }
+
+        // Try each endpoint at most once per request
+        for _ in 0..self.endpoints.len() {
+            let index = self
+                .endpoints
+                .pick(Instant::now())
+                .ok_or(GrpcError::ServiceUnavailable)?;
+
+            if let Some(channel) = self.endpoints.channel(index) {
+                return Ok((index, channel));
+            }
+
+            // The endpoint's cooldown has elapsed since it last failed
+            match self.connect_endpoint(index).await {
+                Ok(()) => {
+                    if let Some(channel) = self.endpoints.channel(index) {
+                        return Ok((index, channel));
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        endpoint = %self.endpoints.url(index),
+                        error = %e,
+                        "Inference endpoint still unavailable"
+                    );
+                    self.endpoints.mark_unhealthy(index, Instant::now());
+                }
+            }
+        }
+
+        Err(GrpcError::ServiceUnavailable)
+    }
+
+    /// Mark the endpoint unhealthy if the error means the replica is unreachable.
+    fn record_endpoint_error(&self, index: usize, error: &GrpcError) {
+        if matches!(
+            error,
+            GrpcError::ServiceUnavailable | GrpcError::ConnectionFailed(_) | GrpcError::Timeout
+        ) {
+            warn!(
+                endpoint = %self.endpoints.url(index),
+                error = %error,
+                "Marking inference endpoint unhealthy"
+            );
+            self.endpoints.mark_unhealthy(index, Instant::now());
         }
     }
 
@@ -326,6 +635,10 @@ impl InferenceGrpcClient {
             metadata: Some(proto::FrameMetadata {
                 original_width: frame.original_width,
                 original_height: frame.original_height,
+                scale_x: frame.transform.scale_x,
+                scale_y: frame.transform.scale_y,
+                pad_x: frame.transform.pad_x,
+                pad_y: frame.transform.pad_y,
                 ..Default::default()
             }),
         }
@@ -350,9 +663,13 @@ impl InferenceClient for InferenceGrpcClient {
         let start = Instant::now();
         let frame_id = frame.frame_id.clone();
 
-        let _channel = self.get_channel().await?;
+        self.check_message_size(frame.data.len())?;
+
+        let (endpoint, _channel) = self.get_channel().await?;
+        let _in_flight = self.endpoints.begin_request(endpoint);
 
-        // In production, this would call the actual gRPC method
+        // In production, this would call the actual gRPC method on the
+        // endpoint's channel, which carries the configured message size limits
         // For now, we simulate the call
         let request = proto::SubmitFrameRequest {
             frame: Some(Self::frame_to_proto(&frame)),
@@ -368,13 +685,17 @@ impl InferenceClient for InferenceGrpcClient {
         );
 
         // Simulate gRPC call
-        // In production: let response = client.submit_frame(Request::new(request)).await?;
-        let response = proto::SubmitFrameResponse {
+        // In production: client.submit_frame(Request::new(request)).await.map_err(GrpcError::from)
+        let response: Result<_, GrpcError> = Ok(proto::SubmitFrameResponse {
             accepted: true,
             processing_id: format!("proc-{}", frame_id),
             estimated_processing_ms: 50,
             error_message: String::new(),
-        };
+        });
+        let response = response.map_err(|e| {
+            self.record_endpoint_error(endpoint, &e);
+            e
+        })?;
 
         let latency = start.elapsed().as_millis() as u64;
 
@@ -426,7 +747,10 @@ impl InferenceClient for InferenceGrpcClient {
         let start = Instant::now();
         let batch_size = frames.len();
 
-        let _channel = self.get_channel().await?;
+        self.check_message_size(frames.iter().map(|f| f.data.len()).sum())?;
+
+        let (endpoint, _channel) = self.get_channel().await?;
+        let _in_flight = self.endpoints.begin_request(endpoint);
 
         let request = proto::SubmitFrameBatchRequest {
             frames: frames.iter().map(Self::frame_to_proto).collect(),
@@ -440,14 +764,18 @@ impl InferenceClient for InferenceGrpcClient {
         );
 
         // Simulate gRPC call
-        let response = proto::SubmitFrameBatchResponse {
+        let response: Result<_, GrpcError> = Ok(proto::SubmitFrameBatchResponse {
             accepted_count: batch_size as u32,
             rejected_count: 0,
             processing_ids: frames
                 .iter()
                 .map(|f| format!("proc-{}", f.frame_id))
                 .collect(),
-        };
+        });
+        let response = response.map_err(|e| {
+            self.record_endpoint_error(endpoint, &e);
+            e
+        })?;
 
         let latency = start.elapsed().as_millis() as u64;
 
@@ -458,6 +786,8 @@ impl InferenceClient for InferenceGrpcClient {
             stats.frames_accepted += response.accepted_count as u64;
             stats.frames_rejected += response.rejected_count as u64;
             stats.batches_sent += 1;
+            stats.avg_batch_size +=
+                (batch_size as f64 - stats.avg_batch_size) / stats.batches_sent as f64;
             stats.total_latency_ms += latency;
             stats.avg_latency_ms = stats.total_latency_ms as f64 / stats.batches_sent as f64;
             stats.last_success_at = Some(Instant::now());
@@ -470,20 +800,18 @@ impl InferenceClient for InferenceGrpcClient {
         })
     }
 
+    /// Probe the inference service, reporting unhealthy only after
+    /// `health_failure_threshold` consecutive failed or erroring probes.
     async fn health_check(&self, device_id: &str) -> Result<bool, GrpcError> {
-        let _channel = self.get_channel().await?;
-
-        let request = proto::HealthCheckRequest {
-            device_id: device_id.to_string(),
-        };
-
-        // Simulate health check
-        let response = proto::HealthCheckResponse {
-            healthy: true,
-            status: "OK".to_string(),
+        let passed = match self.probe_health(device_id).await {
+            Ok(healthy) => healthy,
+            Err(e) => {
+                warn!(error = %e, "Inference health probe failed");
+                false
+            }
         };
 
-        Ok(response.healthy)
+        Ok(self.health.record(passed))
     }
 
     fn stats(&self) -> ClientStats {
@@ -495,12 +823,60 @@ impl InferenceClient for InferenceGrpcClient {
     }
 }
 
+/// Call `grpc.health.v1.Health/Check` for `service` on an endpoint's channel.
+async fn check_serving_status(
+    mut channel: InferenceChannel,
+    service: String,
+) -> Result<proto::health::ServingStatus, GrpcError> {
+    channel
+        .ready()
+        .await
+        .map_err(|e| GrpcError::ConnectionFailed(e.to_string()))?;
+
+    let response: tonic::Response<proto::health::HealthCheckResponse> = channel
+        .unary(
+            Request::new(proto::health::HealthCheckRequest { service }),
+            PathAndQuery::from_static(proto::health::CHECK_PATH),
+            ProstCodec::default(),
+        )
+        .await?;
+    Ok(response.into_inner().status())
+}
+
+/// Debounces health probes so transient failures don't flip the reported state.
+struct HealthTracker {
+    threshold: u32,
+    consecutive_failures: AtomicU32,
+}
+
+impl HealthTracker {
+    fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            consecutive_failures: AtomicU32::new(0),
+        }
+    }
+
+    /// Record a probe result and return whether the service counts as healthy.
+    fn record(&self, passed: bool) -> bool {
+        if passed {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+            return true;
+        }
+
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        failures < self.threshold
+    }
+}
+
 /// Batching layer for efficient frame submission.
 pub struct BatchingClient {
     inner: Arc<dyn InferenceClient>,
     config: GrpcConfig,
     batch_buffer: Arc<RwLock<Vec<ProcessedFrame>>>,
     running: Arc<AtomicBool>,
+    byte_budget: Option<Arc<ByteBudget>>,
+    storage: Option<StorageSampler>,
 }
 
 impl BatchingClient {
@@ -511,65 +887,112 @@ impl BatchingClient {
             config,
             batch_buffer: Arc::new(RwLock::new(Vec::new())),
             running: Arc::new(AtomicBool::new(false)),
+            byte_budget: None,
+            storage: None,
         }
     }
 
+    /// Release submitted frame bytes back to the processor's in-flight budget.
+    pub fn with_byte_budget(mut self, budget: Arc<ByteBudget>) -> Self {
+        self.byte_budget = Some(budget);
+        self
+    }
+
+    /// Sample submitted frames to the storage service.
+    pub fn with_storage_sampler(mut self, sampler: StorageSampler) -> Self {
+        self.storage = Some(sampler);
+        self
+    }
+
     /// Start the batching client with a receiver for frames.
+    ///
+    /// Batches adapt to load: a frame arriving while idle is sent immediately,
+    /// and the target batch size doubles (up to `batch_size`) while frames keep
+    /// queueing behind a full batch. `batch_timeout_ms` only bounds how long a
+    /// partially filled batch waits; a batch that times out shrinks the target.
     pub async fn run(&self, mut input: mpsc::Receiver<ProcessedFrame>) {
         self.running.store(true, Ordering::SeqCst);
 
         let batch_timeout = self.config.batch_timeout();
-        let batch_size = self.config.batch_size;
+        let mut sizer = AdaptiveBatchSize::new(self.config.batch_size);
 
         info!(
-            batch_size = batch_size,
+            max_batch_size = self.config.batch_size,
             timeout_ms = batch_timeout.as_millis(),
             "Batching client started"
         );
 
-        let mut batch: Vec<ProcessedFrame> = Vec::with_capacity(batch_size);
-        let mut last_flush = Instant::now();
+        let mut batch: Vec<ProcessedFrame> = Vec::with_capacity(self.config.batch_size);
+        // Frame taken while probing for backlog behind a full batch
+        let mut carry: Option<ProcessedFrame> = None;
+        let mut batch_started = Instant::now();
+        let mut closed = false;
+
+        while !closed && self.running.load(Ordering::SeqCst) {
+            if batch.is_empty() {
+                let first = match carry.take() {
+                    Some(frame) => frame,
+                    None => match timeout(batch_timeout, input.recv()).await {
+                        Ok(Some(frame)) => frame,
+                        Ok(None) => break,
+                        // Idle; re-check the running flag
+                        Err(_) => continue,
+                    },
+                };
+                batch.push(first);
+                batch_started = Instant::now();
+            }
 
-        loop {
-            let should_flush = batch.len() >= batch_size
-                || (!batch.is_empty() && last_flush.elapsed() >= batch_timeout);
+            // Take whatever is already queued, up to the current target
+            while batch.len() < sizer.target() {
+                match input.try_recv() {
+                    Ok(frame) => batch.push(frame),
+                    Err(_) => break,
+                }
+            }
 
-            if should_flush {
+            if batch.len() >= sizer.target() {
+                let backlog = match input.try_recv() {
+                    Ok(frame) => {
+                        carry = Some(frame);
+                        true
+                    }
+                    Err(_) => false,
+                };
                 self.flush_batch(&mut batch).await;
-                last_flush = Instant::now();
+                sizer.record_full(backlog);
+                continue;
             }
 
-            // Try to receive with timeout
-            match timeout(batch_timeout, input.recv()).await {
-                Ok(Some(frame)) => {
-                    batch.push(frame);
-                }
-                Ok(None) => {
-                    // Channel closed
-                    if !batch.is_empty() {
-                        self.flush_batch(&mut batch).await;
-                    }
-                    break;
-                }
+            // Under load but short of the target: wait for more frames, at
+            // most until the batch timeout
+            let remaining = batch_timeout.saturating_sub(batch_started.elapsed());
+            match timeout(remaining, input.recv()).await {
+                Ok(Some(frame)) => batch.push(frame),
+                Ok(None) => closed = true,
                 Err(_) => {
-                    // Timeout - flush if we have frames
-                    if !batch.is_empty() {
-                        self.flush_batch(&mut batch).await;
-                        last_flush = Instant::now();
-                    }
+                    let len = batch.len();
+                    self.flush_batch(&mut batch).await;
+                    sizer.record_timeout(len);
                 }
             }
-
-            if !self.running.load(Ordering::SeqCst) {
-                break;
-            }
         }
 
         // Flush remaining frames
+        batch.extend(carry.take());
         if !batch.is_empty() {
             self.flush_batch(&mut batch).await;
         }
 
+        // Frames still queued when stopped are dropped; return their bytes
+        // so the processor isn't left holding budget for frames never sent
+        input.close();
+        while let Ok(frame) = input.try_recv() {
+            if let Some(budget) = &self.byte_budget {
+                budget.release(frame.data.len());
+            }
+        }
+
         info!("Batching client stopped");
     }
 
@@ -581,8 +1004,23 @@ impl BatchingClient {
 
         let frames: Vec<ProcessedFrame> = batch.drain(..).collect();
         let count = frames.len();
+        let bytes: usize = frames.iter().map(|f| f.data.len()).sum();
+        let storage_events = self
+            .storage
+            .as_ref()
+            .map(|storage| storage.sample(&frames))
+            .unwrap_or_default();
 
-        match self.inner.submit_batch(frames, 0).await {
+        let result = self.inner.submit_batch(frames, 0).await;
+
+        if let Some(budget) = &self.byte_budget {
+            budget.release(bytes);
+        }
+        if let Some(storage) = &self.storage {
+            storage.publish(storage_events);
+        }
+
+        match result {
             Ok(result) => {
                 debug!(
                     accepted = result.accepted_count,
@@ -606,6 +1044,39 @@ impl BatchingClient {
     }
 }
 
+/// Target batch size that tracks load between 1 and the configured maximum.
+#[derive(Debug, Clone)]
+pub struct AdaptiveBatchSize {
+    target: usize,
+    max: usize,
+}
+
+impl AdaptiveBatchSize {
+    pub fn new(max: usize) -> Self {
+        Self {
+            target: 1,
+            max: max.max(1),
+        }
+    }
+
+    /// Number of frames to collect before sending.
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    /// A batch reached the target; grow if more frames were already waiting.
+    pub fn record_full(&mut self, backlog: bool) {
+        if backlog {
+            self.target = (self.target * 2).min(self.max);
+        }
+    }
+
+    /// A batch was sent on timeout with `len` frames; shrink toward it.
+    pub fn record_timeout(&mut self, len: usize) {
+        self.target = (self.target / 2).max(len).max(1);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -614,6 +1085,9 @@ mod tests {
     fn create_test_config() -> GrpcConfig {
         GrpcConfig {
             inference_endpoint: "http://localhost:50051".to_string(),
+            inference_endpoints: vec![],
+            load_balancing: LoadBalancing::RoundRobin,
+            endpoint_cooldown_secs: 10,
             request_timeout_secs: 30,
             connection_timeout_secs: 5,
             max_concurrent_requests: 10,
@@ -622,6 +1096,11 @@ mod tests {
             enable_compression: false,
             batch_size: 4,
             batch_timeout_ms: 100,
+            max_encoding_message_size: 64 * 1024 * 1024,
+            max_decoding_message_size: 64 * 1024 * 1024,
+            health_check_protocol: HealthCheckProtocol::Inference,
+            health_check_service: String::new(),
+            health_failure_threshold: 3,
         }
     }
 
@@ -636,9 +1115,11 @@ mod tests {
             original_width: 1280,
             original_height: 720,
             sequence: 1,
+            session_id: "test-session".to_string(),
             captured_at: Instant::now(),
             processed_at: Instant::now(),
             processing_latency_us: 1000,
+            transform: Default::default(),
         }
     }
 
@@ -649,6 +1130,77 @@ mod tests {
         assert_eq!(client.state(), ClientState::Disconnected);
     }
 
+    #[tokio::test]
+    async fn test_oversized_batch_is_rejected() {
+        let mut config = create_test_config();
+        config.max_encoding_message_size = 8 * 1024 * 1024;
+        config.max_decoding_message_size = 2 * 1024 * 1024;
+        let client = InferenceGrpcClient::new(config);
+
+        // Four 640x480 RGB frames (~3.7MB) fit, three times that does not
+        let frame_bytes = create_test_frame().data.len();
+        assert!(client.check_message_size(4 * frame_bytes).is_ok());
+
+        // Rejected before any connection is attempted
+        let frames = vec![create_test_frame(); 12];
+        let result = client.submit_batch(frames, 0).await;
+        assert!(matches!(
+            result,
+            Err(GrpcError::MessageTooLarge { size, limit })
+                if size == 12 * frame_bytes && limit == 8 * 1024 * 1024
+        ));
+        assert_eq!(client.state(), ClientState::Disconnected);
+        assert_eq!(client.stats().frames_sent, 0);
+    }
+
+    #[test]
+    fn test_round_robin_skips_unhealthy_endpoint() {
+        let pool = EndpointPool::new(
+            vec![
+                "http://inference-0:50051".to_string(),
+                "http://inference-1:50051".to_string(),
+                "http://inference-2:50051".to_string(),
+            ],
+            LoadBalancing::RoundRobin,
+            Duration::from_secs(10),
+        );
+        let now = Instant::now();
+
+        let picks: Vec<_> = (0..6).map(|_| pool.pick(now).unwrap()).collect();
+        assert_eq!(picks, vec![0, 1, 2, 0, 1, 2]);
+
+        pool.mark_unhealthy(1, now);
+        let picks: Vec<_> = (0..4).map(|_| pool.pick(now).unwrap()).collect();
+        assert!(!picks.contains(&1));
+        assert!(picks.contains(&0) && picks.contains(&2));
+
+        // The endpoint is tried again once its cooldown has passed
+        let later = now + Duration::from_secs(11);
+        assert!((0..3).any(|_| pool.pick(later) == Some(1)));
+    }
+
+    #[test]
+    fn test_least_in_flight_prefers_idle_endpoint() {
+        let pool = EndpointPool::new(
+            vec![
+                "http://inference-0:50051".to_string(),
+                "http://inference-1:50051".to_string(),
+            ],
+            LoadBalancing::LeastInFlight,
+            Duration::from_secs(10),
+        );
+        let now = Instant::now();
+
+        let guard = pool.begin_request(0);
+        assert_eq!(pool.pick(now), Some(1));
+        drop(guard);
+
+        pool.mark_unhealthy(1, now);
+        assert_eq!(pool.pick(now), Some(0));
+        pool.mark_unhealthy(0, now);
+        assert_eq!(pool.pick(now), None);
+    }
+
     #[test]
     fn test_frame_to_proto_conversion() {
         let frame = create_test_frame();
@@ -660,6 +1212,83 @@ mod tests {
         assert_eq!(proto.height, 480);
     }
 
+    /// Records the size of every submitted batch.
+    #[derive(Default)]
+    struct RecordingClient {
+        batches: parking_lot::Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl InferenceClient for RecordingClient {
+        async fn submit_frame(
+            &self,
+            _frame: ProcessedFrame,
+            _priority: u32,
+            _sync: bool,
+        ) -> Result<SubmitResult, GrpcError> {
+            // The batching client only submits batches
+            Err(GrpcError::RequestFailed("single-frame submit not expected".to_string()))
+        }
+
+        async fn submit_batch(
+            &self,
+            frames: Vec<ProcessedFrame>,
+            _priority: u32,
+        ) -> Result<BatchResult, GrpcError> {
+            self.batches.lock().push(frames.len());
+            Ok(BatchResult {
+                accepted_count: frames.len() as u32,
+                rejected_count: 0,
+                processing_ids: vec![],
+            })
+        }
+
+        async fn health_check(&self, _device_id: &str) -> Result<bool, GrpcError> {
+            Ok(true)
+        }
+
+        fn stats(&self) -> ClientStats {
+            ClientStats::default()
+        }
+
+        fn state(&self) -> ClientState {
+            ClientState::Connected
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adaptive_batching() {
+        let mut config = create_test_config();
+        config.batch_size = 4;
+        config.batch_timeout_ms = 5_000;
+
+        let inner = Arc::new(RecordingClient::default());
+        let batcher = Arc::new(BatchingClient::new(inner.clone(), config));
+        let (tx, rx) = mpsc::channel(64);
+        let handle = tokio::spawn({
+            let batcher = batcher.clone();
+            async move { batcher.run(rx).await }
+        });
+
+        // A lone frame is sent promptly rather than waiting out the timeout
+        tx.send(create_test_frame()).await.unwrap();
+        tokio::time::advance(Duration::from_millis(50)).await;
+        assert_eq!(*inner.batches.lock(), vec![1]);
+
+        // A sustained burst grows the batch to its maximum
+        for _ in 0..20 {
+            tx.send(create_test_frame()).await.unwrap();
+        }
+        drop(tx);
+        handle.await.unwrap();
+
+        // Ramps 1 -> 2 -> 4, then full batches until the tail drains
+        let batches = inner.batches.lock().clone();
+        assert_eq!(batches.iter().sum::<usize>(), 21);
+        assert_eq!(batches.iter().max(), Some(&4));
+        assert!(batches.iter().filter(|&&n| n == 4).count() >= 3);
+    }
+
     #[test]
     fn test_stats_default() {
         let config = create_test_config();
@@ -670,4 +1299,152 @@ mod tests {
         assert_eq!(stats.frames_accepted, 0);
         assert_eq!(stats.batches_sent, 0);
     }
+
+    #[test]
+    fn test_health_tracker_tolerates_transient_failures() {
+        let tracker = HealthTracker::new(3);
+
+        // A single failure under the threshold still reports healthy
+        assert!(tracker.record(false));
+        assert!(tracker.record(true));
+
+        assert!(tracker.record(false));
+        assert!(tracker.record(false));
+        assert!(!tracker.record(false));
+
+        // Recovers on the next passing probe
+        assert!(tracker.record(true));
+
+        // A threshold of 1 reports the first failure
+        assert!(!HealthTracker::new(1).record(false));
+    }
+
+    /// Holds every batch submission until the gate is opened.
+    struct GatedClient {
+        entered: tokio::sync::Notify,
+        gate: tokio::sync::Semaphore,
+    }
+
+    impl GatedClient {
+        fn new() -> Self {
+            Self {
+                entered: tokio::sync::Notify::new(),
+                gate: tokio::sync::Semaphore::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl InferenceClient for GatedClient {
+        async fn submit_frame(
+            &self,
+            _frame: ProcessedFrame,
+            _priority: u32,
+            _sync: bool,
+        ) -> Result<SubmitResult, GrpcError> {
+            Err(GrpcError::RequestFailed(
+                "batching client only submits batches".to_string(),
+            ))
+        }
+
+        async fn submit_batch(
+            &self,
+            frames: Vec<ProcessedFrame>,
+            _priority: u32,
+        ) -> Result<BatchResult, GrpcError> {
+            self.entered.notify_one();
+            let _permit = self.gate.acquire().await.unwrap();
+            Ok(BatchResult {
+                accepted_count: frames.len() as u32,
+                rejected_count: 0,
+                processing_ids: vec![],
+            })
+        }
+
+        async fn health_check(&self, _device_id: &str) -> Result<bool, GrpcError> {
+            Ok(true)
+        }
+
+        fn stats(&self) -> ClientStats {
+            ClientStats::default()
+        }
+
+        fn state(&self) -> ClientState {
+            ClientState::Connected
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_releases_budget_of_queued_frames() {
+        let budget = Arc::new(ByteBudget::new(0));
+        let inner = Arc::new(GatedClient::new());
+        let batcher = Arc::new(
+            BatchingClient::new(inner.clone(), create_test_config())
+                .with_byte_budget(budget.clone()),
+        );
+        let (tx, rx) = mpsc::channel(8);
+        let handle = tokio::spawn({
+            let batcher = batcher.clone();
+            async move { batcher.run(rx).await }
+        });
+
+        let send = |frame: ProcessedFrame| {
+            assert!(budget.try_acquire(frame.data.len()));
+            tx.try_send(frame).unwrap();
+        };
+
+        // The first frame is submitted and held there
+        send(create_test_frame());
+        inner.entered.notified().await;
+
+        // These queue up behind it, then the client is stopped
+        for _ in 0..3 {
+            send(create_test_frame());
+        }
+        batcher.stop();
+        inner.gate.add_permits(16);
+        handle.await.unwrap();
+
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_health_probe_calls_standard_health_service() {
+        use tokio_stream::wrappers::TcpListenerStream;
+        use tonic_health::ServingStatus;
+
+        let service_name = "nier.inference.InferenceService";
+        let (mut reporter, health_service) = tonic_health::server::health_reporter();
+        reporter
+            .set_service_status(service_name, ServingStatus::NotServing)
+            .await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(health_service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut config = create_test_config();
+        config.inference_endpoint = format!("http://{}", address);
+        config.health_check_protocol = HealthCheckProtocol::Grpc;
+        config.health_check_service = service_name.to_string();
+        let client = InferenceGrpcClient::new(config.clone());
+        client.connect().await.unwrap();
+
+        assert!(!client.probe_health("test-device").await.unwrap());
+
+        reporter
+            .set_service_status(service_name, ServingStatus::Serving)
+            .await;
+        assert!(client.probe_health("test-device").await.unwrap());
+
+        // A service the health server doesn't know never passes
+        config.health_check_service = "nier.unknown.Service".to_string();
+        let unknown = InferenceGrpcClient::new(config);
+        unknown.connect().await.unwrap();
+        assert!(!matches!(unknown.probe_health("test-device").await, Ok(true)));
+    }
 }
@@ -0,0 +1,341 @@
+//! Health state evaluation and the health/readiness HTTP endpoints.
+//!
+//! The health monitor samples stream counters every interval and derives a
+//! `HealthState` from the backpressure drop rate and the delivered frame rate,
+//! so an instance that is connected but shedding most of its frames is not
+//! reported as healthy. It also snapshots the stream, processor and gRPC
+//! client statistics served as JSON on `/stats`.
+
+use crate::config::HealthConfig;
+use crate::frame_processor::ProcessorStats;
+use crate::grpc_client::ClientStats;
+use crate::rtsp_client::StreamStats;
+use parking_lot::RwLock;
+use serde::{Serialize, Serializer};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info};
+
+/// Overall health of the ingest instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl HealthState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthState::Healthy => "healthy",
+            HealthState::Degraded => "degraded",
+            HealthState::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+/// Most recent health evaluation.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthState,
+    /// Fraction of received frames dropped due to backpressure, at the capture
+    /// queue or in the frame processor
+    pub drop_rate: f64,
+    /// Frames delivered to inference per second
+    pub delivered_fps: f64,
+    /// Configured target frames per second
+    pub target_fps: f64,
+}
+
+impl Default for HealthReport {
+    fn default() -> Self {
+        Self {
+            status: HealthState::Healthy,
+            drop_rate: 0.0,
+            delivered_fps: 0.0,
+            target_fps: 0.0,
+        }
+    }
+}
+
+/// Statistics snapshot served by `/stats`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsReport {
+    pub streams: Vec<StreamReport>,
+    /// Inference client statistics, once connected
+    pub grpc: Option<ClientStats>,
+}
+
+/// Statistics for one ingested stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamReport {
+    pub device_id: String,
+    pub rtsp: StreamStats,
+    pub processor: ProcessorStats,
+}
+
+/// Serialize an optional instant as seconds elapsed since it, or `null`.
+pub fn serialize_elapsed<S: Serializer>(
+    at: &Option<Instant>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    at.map(|at| at.elapsed().as_secs_f64())
+        .serialize(serializer)
+}
+
+/// Counter snapshot taken by the health monitor.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthSample {
+    pub frames_received: u64,
+    /// Frames dropped at the capture queue
+    pub frames_dropped: u64,
+    /// Frames the processor dropped for backpressure or its memory budget;
+    /// rate-limit drops are intended sampling and not counted
+    pub frames_dropped_processor: u64,
+    pub frames_sent: u64,
+    pub taken_at: Instant,
+}
+
+/// Evaluate health over the interval between two samples.
+pub fn evaluate(
+    previous: &HealthSample,
+    current: &HealthSample,
+    target_fps: f64,
+    config: &HealthConfig,
+) -> HealthReport {
+    let received = current
+        .frames_received
+        .saturating_sub(previous.frames_received);
+    let dropped = current
+        .frames_dropped
+        .saturating_sub(previous.frames_dropped)
+        + current
+            .frames_dropped_processor
+            .saturating_sub(previous.frames_dropped_processor);
+    let sent = current.frames_sent.saturating_sub(previous.frames_sent);
+    let elapsed = current
+        .taken_at
+        .saturating_duration_since(previous.taken_at)
+        .as_secs_f64();
+
+    let drop_rate = if received > 0 {
+        dropped as f64 / received as f64
+    } else {
+        0.0
+    };
+    let delivered_fps = if elapsed > 0.0 {
+        sent as f64 / elapsed
+    } else {
+        0.0
+    };
+
+    let drop_state = if drop_rate >= config.unhealthy_drop_rate {
+        HealthState::Unhealthy
+    } else if drop_rate >= config.degraded_drop_rate {
+        HealthState::Degraded
+    } else {
+        HealthState::Healthy
+    };
+
+    let fps_ratio = if target_fps > 0.0 {
+        delivered_fps / target_fps
+    } else {
+        1.0
+    };
+    let fps_state = if fps_ratio < config.unhealthy_fps_ratio {
+        HealthState::Unhealthy
+    } else if fps_ratio < config.degraded_fps_ratio {
+        HealthState::Degraded
+    } else {
+        HealthState::Healthy
+    };
+
+    HealthReport {
+        status: drop_state.max(fps_state),
+        drop_rate,
+        delivered_fps,
+        target_fps,
+    }
+}
+
+/// Build the status line and JSON body for a request path.
+fn route(path: &str, report: &HealthReport, stats: &StatsReport) -> (&'static str, String) {
+    match path {
+        "/health" => ("200 OK", r#"{"status":"alive"}"#.to_string()),
+        "/ready" => {
+            let status = if report.status == HealthState::Unhealthy {
+                "503 Service Unavailable"
+            } else {
+                "200 OK"
+            };
+            let body = serde_json::to_string(report).unwrap_or_default();
+            (status, body)
+        }
+        "/stats" => ("200 OK", serde_json::to_string(stats).unwrap_or_default()),
+        _ => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+    }
+}
+
+/// Serve `/health` (liveness), `/ready` (readiness with health state) and
+/// `/stats` (the latest statistics snapshot).
+///
+/// `/ready` returns 503 while the instance is unhealthy so orchestration can
+/// route traffic away from it; degraded instances stay ready.
+pub async fn serve(
+    port: u16,
+    report: Arc<RwLock<HealthReport>>,
+    stats: Arc<RwLock<StatsReport>>,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    info!(port = port, "Health endpoint listening");
+
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let report = report.clone();
+        let stats = stats.clone();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let n = match socket.read(&mut buf).await {
+                Ok(n) => n,
+                Err(e) => {
+                    debug!(error = %e, "Failed to read health request");
+                    return;
+                }
+            };
+
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            let (status, body) = route(path, &report.read(), &stats.read());
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn sample(received: u64, dropped: u64, sent: u64, taken_at: Instant) -> HealthSample {
+        HealthSample {
+            frames_received: received,
+            frames_dropped: dropped,
+            frames_dropped_processor: 0,
+            frames_sent: sent,
+            taken_at,
+        }
+    }
+
+    #[test]
+    fn test_high_drop_rate_is_degraded() {
+        let config = HealthConfig::default();
+        let start = Instant::now();
+        let previous = sample(0, 0, 0, start);
+
+        // 30 FPS in, a third dropped, still delivering the 10 FPS target
+        let current = sample(900, 300, 300, start + Duration::from_secs(30));
+        let report = evaluate(&previous, &current, 10.0, &config);
+
+        assert_eq!(report.status, HealthState::Degraded);
+        assert!((report.drop_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert!((report.delivered_fps - 10.0).abs() < 1e-9);
+
+        let (status, _) = route("/ready", &report, &StatsReport::default());
+        assert_eq!(status, "200 OK");
+    }
+
+    #[test]
+    fn test_processor_drops_count_toward_drop_rate() {
+        let config = HealthConfig::default();
+        let start = Instant::now();
+        let previous = sample(0, 0, 0, start);
+
+        // Nothing dropped at capture, but the processor sheds two thirds of
+        // the frames while the rest still meet the 10 FPS target
+        let current = HealthSample {
+            frames_dropped_processor: 600,
+            ..sample(900, 0, 300, start + Duration::from_secs(30))
+        };
+        let report = evaluate(&previous, &current, 10.0, &config);
+
+        assert_eq!(report.status, HealthState::Unhealthy);
+        assert!((report.drop_rate - 2.0 / 3.0).abs() < 1e-9);
+
+        // Both kinds of drop add up
+        let current = HealthSample {
+            frames_dropped_processor: 60,
+            ..sample(900, 60, 300, start + Duration::from_secs(30))
+        };
+        let report = evaluate(&previous, &current, 10.0, &config);
+        assert!((report.drop_rate - 120.0 / 900.0).abs() < 1e-9);
+        assert_eq!(report.status, HealthState::Degraded);
+    }
+
+    #[test]
+    fn test_stalled_output_is_unhealthy() {
+        let config = HealthConfig::default();
+        let start = Instant::now();
+        let previous = sample(0, 0, 0, start);
+        let current = sample(900, 0, 0, start + Duration::from_secs(30));
+
+        let report = evaluate(&previous, &current, 10.0, &config);
+
+        assert_eq!(report.status, HealthState::Unhealthy);
+        let (status, body) = route("/ready", &report, &StatsReport::default());
+        assert_eq!(status, "503 Service Unavailable");
+        assert!(body.contains(r#""status":"unhealthy""#));
+    }
+
+    #[test]
+    fn test_stats_report_json_shape() {
+        let stats = StatsReport {
+            streams: vec![StreamReport {
+                device_id: "camera-001".to_string(),
+                rtsp: StreamStats {
+                    frames_received: 900,
+                    frames_dropped: 12,
+                    last_frame_at: Some(Instant::now()),
+                    ..Default::default()
+                },
+                processor: ProcessorStats {
+                    frames_processed: 300,
+                    frames_dropped_rate_limit: 588,
+                    ..Default::default()
+                },
+            }],
+            grpc: Some(ClientStats {
+                frames_sent: 300,
+                frames_accepted: 298,
+                ..Default::default()
+            }),
+        };
+
+        let (status, body) = route("/stats", &HealthReport::default(), &stats);
+        assert_eq!(status, "200 OK");
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let stream = &json["streams"][0];
+        assert_eq!(stream["device_id"], "camera-001");
+        assert_eq!(stream["rtsp"]["frames_received"], 900);
+        assert_eq!(stream["rtsp"]["frames_dropped"], 12);
+        assert!(stream["rtsp"]["secs_since_last_frame"].as_f64().unwrap() < 60.0);
+        assert!(stream["rtsp"]["secs_since_stream_start"].is_null());
+        assert_eq!(stream["processor"]["frames_processed"], 300);
+        assert_eq!(stream["processor"]["frames_dropped_rate_limit"], 588);
+        assert!(stream["processor"]["secs_since_last_frame"].is_null());
+        assert_eq!(json["grpc"]["frames_sent"], 300);
+        assert_eq!(json["grpc"]["frames_accepted"], 298);
+        assert!(json["grpc"]["secs_since_last_error"].is_null());
+    }
+}
@@ -9,6 +9,9 @@
 //! RTSP Stream -> RtspClient -> FrameProcessor -> GrpcClient -> Inference Service
 //! ```
 //!
+//! With `storage.enabled`, frames submitted for inference are also sampled
+//! onto the storage service's trigger topic.
+//!
 //! # Configuration
 //!
 //! Configuration is loaded from:
@@ -20,12 +23,18 @@
 mod config;
 mod frame_processor;
 mod grpc_client;
+mod health;
 mod rtsp_client;
+mod storage_event;
+mod stream_gate;
 
 use config::IngestConfig;
 use frame_processor::{FrameProcessor, ProcessedFrame};
 use grpc_client::{BatchingClient, InferenceClient, InferenceGrpcClient};
-use rtsp_client::RtspClient;
+use health::{HealthReport, HealthSample, HealthState, StatsReport, StreamReport};
+use rtsp_client::{ConnectionState, RtspClient, RtspError};
+use storage_event::{KafkaStorageSink, StorageSampler};
+use stream_gate::StreamStartGate;
 
 use parking_lot::RwLock;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -41,15 +50,28 @@ struct AppState {
     running: Arc<AtomicBool>,
     rtsp_client: Option<Arc<RwLock<RtspClient>>>,
     grpc_client: Option<Arc<InferenceGrpcClient>>,
+    processor: Option<Arc<FrameProcessor>>,
+    health: Arc<RwLock<HealthReport>>,
+    stats: Arc<RwLock<StatsReport>>,
+    /// Admission for every stream this process starts
+    stream_gate: Arc<StreamStartGate>,
 }
 
 impl AppState {
     fn new(config: IngestConfig) -> Self {
+        let stream_gate = Arc::new(StreamStartGate::new(
+            config.streams.max_concurrent_streams,
+            config.streams.start_stagger(),
+        ));
         Self {
             config,
             running: Arc::new(AtomicBool::new(false)),
             rtsp_client: None,
             grpc_client: None,
+            processor: None,
+            health: Arc::new(RwLock::new(HealthReport::default())),
+            stats: Arc::new(RwLock::new(StatsReport::default())),
+            stream_gate,
         }
     }
 
@@ -144,7 +166,11 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
     let config = state.read().config.clone();
 
     // Create RTSP client
+    // Stretch in the pipeline so frames already arrive at the processing size
     let mut rtsp_client = RtspClient::new(config.rtsp.clone())?;
+    if let Some((width, height)) = config.processing.pipeline_output_size() {
+        rtsp_client = rtsp_client.with_output_size(width, height);
+    }
 
     // Create gRPC client
     let grpc_client = Arc::new(InferenceGrpcClient::new(config.grpc.clone()));
@@ -153,6 +179,10 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
     info!("Connecting to inference service...");
     grpc_client.connect_with_retry().await?;
 
+    // Wait for a stream slot; held until the pipeline stops
+    let stream_gate = state.read().stream_gate.clone();
+    let _stream_slot = stream_gate.acquire(&config.rtsp.device_id).await;
+
     // Start RTSP stream
     info!(
         url = %config.rtsp.url,
@@ -172,13 +202,22 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
     let (processed_tx, processed_rx) = mpsc::channel::<ProcessedFrame>(config.processing.queue_size);
 
     // Create frame processor
-    let processor = FrameProcessor::new(
+    let processor = Arc::new(FrameProcessor::new(
         config.processing.clone(),
         config.rtsp.device_id.clone(),
-    );
+    ));
+    state.write().processor = Some(processor.clone());
 
     // Create batching client
-    let batching_client = BatchingClient::new(grpc_client.clone(), config.grpc.clone());
+    let mut batching_client = BatchingClient::new(grpc_client.clone(), config.grpc.clone())
+        .with_byte_budget(processor.byte_budget());
+    if config.storage.enabled {
+        let sink = KafkaStorageSink::new(&config.storage)?;
+        batching_client = batching_client.with_storage_sampler(StorageSampler::new(
+            Arc::new(sink),
+            config.storage.sample_interval(),
+        ));
+    }
 
     // Spawn the frame processor task
     let processor_handle = tokio::spawn({
@@ -190,7 +229,7 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
     });
 
     // Spawn the batching client task
-    let client_handle = tokio::spawn({
+    let mut client_handle = tokio::spawn({
         let state = state.clone();
         async move {
             batching_client.run(processed_rx).await;
@@ -203,10 +242,22 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
         let state = state.clone();
         let grpc_client = grpc_client.clone();
         let device_id = config.rtsp.device_id.clone();
-        let interval = std::time::Duration::from_secs(config.health.interval_secs);
 
         async move {
-            run_health_monitor(state, grpc_client, device_id, interval).await;
+            run_health_monitor(state, grpc_client, device_id).await;
+        }
+    });
+
+    // Serve liveness/readiness/stats endpoints
+    let health_server_handle = tokio::spawn({
+        let report = state.read().health.clone();
+        let stats = state.read().stats.clone();
+        let port = config.health.port;
+
+        async move {
+            if let Err(e) = health::serve(port, report, stats).await {
+                error!(error = %e, "Health endpoint failed");
+            }
         }
     });
 
@@ -216,23 +267,40 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
         info!("Received shutdown signal");
     };
 
+    let mut stream_failed = false;
+    let mut input_finished = false;
     tokio::select! {
         _ = shutdown_signal => {
             info!("Initiating graceful shutdown...");
         }
+        // The frame channel closes when the stream ends for good
         _ = processor_handle => {
-            warn!("Processor task exited unexpectedly");
+            stream_failed = rtsp_state(&state) == Some(ConnectionState::Failed);
+            if stream_failed {
+                error!("RTSP stream failed and will not reconnect");
+            } else if config.rtsp.is_file_source() {
+                info!("Input file fully processed");
+                input_finished = true;
+            } else {
+                warn!("Processor task exited unexpectedly");
+            }
         }
-        _ = client_handle => {
+        _ = &mut client_handle => {
             warn!("Client task exited unexpectedly");
         }
     }
 
+    // Let the batching client send the frames the processor handed over
+    if input_finished {
+        let _ = client_handle.await;
+    }
+
     // Trigger shutdown
     state.write().shutdown();
 
     // Stop health monitor
     health_handle.abort();
+    health_server_handle.abort();
 
     // Stop RTSP client
     if let Some(rtsp) = &state.read().rtsp_client {
@@ -248,17 +316,35 @@ async fn run_pipeline(state: Arc<RwLock<AppState>>) -> anyhow::Result<()> {
     log_final_stats(&state.read());
 
     info!("Shutdown complete");
+    if stream_failed {
+        return Err(RtspError::MaxReconnectAttemptsExceeded.into());
+    }
     Ok(())
 }
 
+/// Connection state of the RTSP client, once one has been started.
+fn rtsp_state(state: &RwLock<AppState>) -> Option<ConnectionState> {
+    state.read().rtsp_client.as_ref().map(|client| client.read().state())
+}
+
 /// Run the health monitoring loop.
 async fn run_health_monitor(
     state: Arc<RwLock<AppState>>,
     grpc_client: Arc<InferenceGrpcClient>,
     device_id: String,
-    interval: std::time::Duration,
 ) {
-    let mut ticker = tokio::time::interval(interval);
+    let (health_config, target_fps, health, stats) = {
+        let state = state.read();
+        (
+            state.config.health.clone(),
+            state.config.processing.target_fps as f64,
+            state.health.clone(),
+            state.stats.clone(),
+        )
+    };
+    let mut ticker =
+        tokio::time::interval(std::time::Duration::from_secs(health_config.interval_secs));
+    let mut previous_sample: Option<HealthSample> = None;
 
     while state.read().is_running() {
         ticker.tick().await;
@@ -281,8 +367,10 @@ async fn run_health_monitor(
             info!(
                 frames_received = rtsp_stats.frames_received,
                 frames_dropped = rtsp_stats.frames_dropped,
+                frames_corrupt = rtsp_stats.frames_corrupt,
                 fps = format!("{:.2}", rtsp_stats.current_fps),
                 reconnects = rtsp_stats.reconnect_count,
+                stalls = rtsp_stats.stall_count,
                 "RTSP stream stats"
             );
         }
@@ -295,6 +383,60 @@ async fn run_health_monitor(
             avg_latency_ms = format!("{:.2}", grpc_stats.avg_latency_ms),
             "gRPC client stats"
         );
+
+        // Evaluate health over the last interval
+        let rtsp_stats = state.read().rtsp_client.as_ref().map(|r| r.read().stats());
+        let processor_stats = state.read().processor.as_ref().map(|p| p.stats());
+        let processor_dropped = processor_stats.as_ref().map_or(0, |s| {
+            s.frames_dropped_backpressure + s.frames_dropped_byte_budget
+        });
+
+        // Publish the snapshot served on /stats
+        *stats.write() = StatsReport {
+            streams: rtsp_stats
+                .clone()
+                .map(|rtsp| StreamReport {
+                    device_id: device_id.clone(),
+                    rtsp,
+                    processor: processor_stats.unwrap_or_default(),
+                })
+                .into_iter()
+                .collect(),
+            grpc: Some(grpc_stats.clone()),
+        };
+
+        let sample = HealthSample {
+            frames_received: rtsp_stats.as_ref().map_or(0, |s| s.frames_received),
+            frames_dropped: rtsp_stats.as_ref().map_or(0, |s| s.frames_dropped),
+            frames_dropped_processor: processor_dropped,
+            frames_sent: grpc_stats.frames_sent,
+            taken_at: std::time::Instant::now(),
+        };
+
+        if let Some(previous) = previous_sample.replace(sample) {
+            let report = health::evaluate(&previous, &sample, target_fps, &health_config);
+            let changed = health.read().status != report.status;
+
+            if report.status == HealthState::Healthy {
+                info!(
+                    status = report.status.as_str(),
+                    drop_rate = format!("{:.3}", report.drop_rate),
+                    delivered_fps = format!("{:.2}", report.delivered_fps),
+                    "Health state"
+                );
+            } else {
+                warn!(
+                    status = report.status.as_str(),
+                    changed = changed,
+                    drop_rate = format!("{:.3}", report.drop_rate),
+                    delivered_fps = format!("{:.2}", report.delivered_fps),
+                    target_fps = report.target_fps,
+                    "Ingest instance is not healthy"
+                );
+            }
+
+            *health.write() = report;
+        }
     }
 }
 
@@ -307,8 +449,10 @@ fn log_final_stats(state: &AppState) {
         info!(
             frames_received = stats.frames_received,
             frames_dropped = stats.frames_dropped,
+            frames_corrupt = stats.frames_corrupt,
             bytes_received = stats.bytes_received,
             reconnect_count = stats.reconnect_count,
+            stall_count = stats.stall_count,
             "RTSP final stats"
         );
     }
@@ -320,6 +464,7 @@ fn log_final_stats(state: &AppState) {
             frames_accepted = stats.frames_accepted,
             frames_rejected = stats.frames_rejected,
             batches_sent = stats.batches_sent,
+            avg_batch_size = format!("{:.2}", stats.avg_batch_size),
             avg_latency_ms = format!("{:.2}", stats.avg_latency_ms),
             "gRPC final stats"
         );
@@ -342,20 +487,33 @@ mod tests {
                 max_reconnect_attempts: 3,
                 reconnect_base_delay_ms: 1000,
                 reconnect_max_delay_ms: 30000,
+                stable_connection_secs: 60,
+                fps_window_secs: 5,
+                stall_timeout_secs: 15,
                 transport: "tcp".to_string(),
+                codec: "h264".to_string(),
                 buffer_ms: 200,
+                drop_policy: config::CaptureDropPolicy::DropNew,
+                playback_speed: 1.0,
             },
             processing: config::ProcessingConfig {
                 target_width: 640,
                 target_height: 480,
                 target_fps: 10.0,
+                resize_mode: config::ResizeMode::Stretch,
+                pad_color: [114, 114, 114],
                 pixel_format: "RGB".to_string(),
                 queue_size: 100,
                 num_workers: 2,
                 drop_on_backpressure: true,
+                max_inflight_bytes: 256 * 1024 * 1024,
+                use_blocking_pool: false,
             },
             grpc: config::GrpcConfig {
                 inference_endpoint: "http://localhost:50051".to_string(),
+                inference_endpoints: vec![],
+                load_balancing: config::LoadBalancing::RoundRobin,
+                endpoint_cooldown_secs: 10,
                 request_timeout_secs: 30,
                 connection_timeout_secs: 10,
                 max_concurrent_requests: 10,
@@ -364,9 +522,16 @@ mod tests {
                 enable_compression: false,
                 batch_size: 1,
                 batch_timeout_ms: 100,
+                max_encoding_message_size: 64 * 1024 * 1024,
+                max_decoding_message_size: 64 * 1024 * 1024,
+                health_check_protocol: config::HealthCheckProtocol::Inference,
+                health_check_service: String::new(),
+                health_failure_threshold: 3,
             },
             logging: config::LoggingConfig::default(),
             health: config::HealthConfig::default(),
+            streams: config::StreamsConfig::default(),
+            storage: config::StorageConfig::default(),
         };
 
         let state = AppState::new(config);
@@ -385,20 +550,33 @@ mod tests {
                 max_reconnect_attempts: 3,
                 reconnect_base_delay_ms: 1000,
                 reconnect_max_delay_ms: 30000,
+                stable_connection_secs: 60,
+                fps_window_secs: 5,
+                stall_timeout_secs: 15,
                 transport: "tcp".to_string(),
+                codec: "h264".to_string(),
                 buffer_ms: 200,
+                drop_policy: config::CaptureDropPolicy::DropNew,
+                playback_speed: 1.0,
             },
             processing: config::ProcessingConfig {
                 target_width: 640,
                 target_height: 480,
                 target_fps: 10.0,
+                resize_mode: config::ResizeMode::Stretch,
+                pad_color: [114, 114, 114],
                 pixel_format: "RGB".to_string(),
                 queue_size: 100,
                 num_workers: 2,
                 drop_on_backpressure: true,
+                max_inflight_bytes: 256 * 1024 * 1024,
+                use_blocking_pool: false,
             },
             grpc: config::GrpcConfig {
                 inference_endpoint: "http://localhost:50051".to_string(),
+                inference_endpoints: vec![],
+                load_balancing: config::LoadBalancing::RoundRobin,
+                endpoint_cooldown_secs: 10,
                 request_timeout_secs: 30,
                 connection_timeout_secs: 10,
                 max_concurrent_requests: 10,
@@ -407,9 +585,16 @@ mod tests {
                 enable_compression: false,
                 batch_size: 1,
                 batch_timeout_ms: 100,
+                max_encoding_message_size: 64 * 1024 * 1024,
+                max_decoding_message_size: 64 * 1024 * 1024,
+                health_check_protocol: config::HealthCheckProtocol::Inference,
+                health_check_service: String::new(),
+                health_failure_threshold: 3,
             },
             logging: config::LoggingConfig::default(),
             health: config::HealthConfig::default(),
+            streams: config::StreamsConfig::default(),
+            storage: config::StorageConfig::default(),
         };
 
         let state = AppState::new(config);
@@ -3,17 +3,20 @@
 //! This module handles connecting to RTSP streams from worker camera glasses,
 //! managing the GStreamer pipeline, and providing frames to the processing pipeline.
 
-use crate::config::RtspConfig;
-use backoff::{backoff::Backoff, ExponentialBackoff};
+use crate::config::{CaptureDropPolicy, RtspConfig};
+use crate::health::serialize_elapsed;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
-use parking_lot::RwLock;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use nier_retry::{retry_with_backoff, BackoffPolicy};
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tracing::{debug, error, info, warn};
 
 /// Errors that can occur during RTSP operations.
@@ -70,15 +73,28 @@ pub struct RawFrame {
 }
 
 /// Statistics for the RTSP stream.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct StreamStats {
     pub frames_received: u64,
     pub frames_dropped: u64,
+    /// Buffers skipped because they could not be mapped for reading
+    pub frames_corrupt: u64,
     pub bytes_received: u64,
     pub reconnect_count: u32,
+    pub consecutive_failures: u32,
+    /// Reconnects forced because a connected stream stopped delivering frames
+    pub stall_count: u32,
+    #[serde(rename = "secs_since_last_frame", serialize_with = "serialize_elapsed")]
     pub last_frame_at: Option<Instant>,
+    #[serde(
+        rename = "secs_since_stream_start",
+        serialize_with = "serialize_elapsed"
+    )]
     pub stream_start: Option<Instant>,
+    /// Frame rate over the last `fps_window_secs`
     pub current_fps: f64,
+    /// Frame rate averaged over the whole stream
+    pub average_fps: f64,
 }
 
 /// State of the RTSP connection.
@@ -91,15 +107,326 @@ pub enum ConnectionState {
     Failed,
 }
 
+/// Tracks consecutive connection failures against `max_reconnect_attempts`.
+///
+/// The count resets once a connection has stayed up for the stable period, so
+/// a camera that drops occasionally but recovers never exhausts its budget.
+/// A connection lost before then counts as a failure, so a flapping source
+/// that connects and immediately drops still runs out of attempts.
+#[derive(Debug, Clone)]
+pub struct ReconnectBudget {
+    max_attempts: u32,
+    stable_after: Duration,
+    consecutive_failures: u32,
+    connected_at: Option<Instant>,
+}
+
+impl ReconnectBudget {
+    /// Create a budget allowing `max_attempts` consecutive failures (0 = infinite).
+    pub fn new(max_attempts: u32, stable_after: Duration) -> Self {
+        Self {
+            max_attempts,
+            stable_after,
+            consecutive_failures: 0,
+            connected_at: None,
+        }
+    }
+
+    /// Record a successful connection.
+    pub fn on_connected(&mut self, now: Instant) {
+        self.connected_at = Some(now);
+    }
+
+    /// Record the loss of a connection; returns true once the budget is exhausted.
+    ///
+    /// A stable connection resets the count, a short-lived one is a failure.
+    pub fn on_disconnected(&mut self, now: Instant) -> bool {
+        match self.connected_at.take() {
+            Some(connected_at)
+                if now.saturating_duration_since(connected_at) >= self.stable_after =>
+            {
+                self.consecutive_failures = 0;
+                false
+            }
+            _ => self.record_failure(),
+        }
+    }
+
+    /// Record a failed connection attempt; returns true once the budget is exhausted.
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive_failures += 1;
+        self.max_attempts > 0 && self.consecutive_failures >= self.max_attempts
+    }
+
+    /// Number of failures since the last stable connection.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+}
+
+/// Frame arrival rate over a sliding time window.
+#[derive(Debug, Clone)]
+pub struct FpsWindow {
+    window: Duration,
+    arrivals: VecDeque<Instant>,
+    first_arrival: Option<Instant>,
+}
+
+impl FpsWindow {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            arrivals: VecDeque::new(),
+            first_arrival: None,
+        }
+    }
+
+    /// Record a frame arriving at `now`.
+    pub fn record(&mut self, now: Instant) {
+        self.first_arrival.get_or_insert(now);
+        self.arrivals.push_back(now);
+        self.evict(now);
+    }
+
+    /// Frames per second over the window ending at `now`.
+    ///
+    /// Until a full window has elapsed since the first frame, the rate is taken
+    /// over the time observed so far.
+    pub fn rate(&mut self, now: Instant) -> f64 {
+        self.evict(now);
+        let observed = match self.first_arrival {
+            Some(first) => now.saturating_duration_since(first).min(self.window),
+            None => return 0.0,
+        };
+        if observed.is_zero() {
+            return 0.0;
+        }
+        self.arrivals.len() as f64 / observed.as_secs_f64()
+    }
+
+    fn evict(&mut self, now: Instant) {
+        while let Some(&oldest) = self.arrivals.front() {
+            if now.saturating_duration_since(oldest) > self.window {
+                self.arrivals.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// Paces replay of a recorded file by frame presentation timestamps.
+///
+/// Each frame is released `pts offset / speed` after the first one, so a
+/// `speed` of 2.0 replays twice as fast as recorded. Anchoring to the first
+/// frame rather than the previous one keeps delays from accumulating drift.
+#[derive(Debug, Clone)]
+pub struct PlaybackPacer {
+    speed: f64,
+    /// PTS (ns) of the first frame and when it was released
+    origin: Option<(u64, Instant)>,
+}
+
+impl PlaybackPacer {
+    /// Create a pacer; a `speed` of 0 releases frames without waiting.
+    pub fn new(speed: f64) -> Self {
+        Self {
+            speed,
+            origin: None,
+        }
+    }
+
+    /// How long to hold a frame with `pts` (ns) that is ready at `now`.
+    ///
+    /// Frames without a PTS are released immediately; a PTS earlier than the
+    /// first frame's (e.g. the file looped) restarts pacing from that frame.
+    pub fn delay(&mut self, pts: Option<u64>, now: Instant) -> Duration {
+        let Some(pts) = pts else {
+            return Duration::ZERO;
+        };
+        if self.speed <= 0.0 {
+            return Duration::ZERO;
+        }
+        let (origin_pts, origin_at) = match self.origin {
+            Some((origin_pts, origin_at)) if pts >= origin_pts => (origin_pts, origin_at),
+            _ => {
+                self.origin = Some((pts, now));
+                return Duration::ZERO;
+            }
+        };
+        let offset = Duration::from_nanos(((pts - origin_pts) as f64 / self.speed) as u64);
+        (origin_at + offset).saturating_duration_since(now)
+    }
+}
+
+/// Bounded buffer between the appsink callback and the frame channel.
+///
+/// A channel sender cannot evict frames already queued, so captured frames
+/// wait here, where the drop policy decides which frame is lost when the
+/// buffer is full, and a forwarder task feeds them into the channel.
+pub struct FrameQueue {
+    frames: Mutex<VecDeque<RawFrame>>,
+    capacity: usize,
+    policy: CaptureDropPolicy,
+    available: Notify,
+    /// Signalled when a frame is taken, for `push_wait`
+    room: Condvar,
+    closed: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl FrameQueue {
+    pub fn new(capacity: usize, policy: CaptureDropPolicy) -> Self {
+        Self {
+            frames: Mutex::new(VecDeque::with_capacity(capacity.max(1))),
+            capacity: capacity.max(1),
+            policy,
+            available: Notify::new(),
+            room: Condvar::new(),
+            closed: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// Queue a frame, returning the frame dropped to make room, if any.
+    pub fn push(&self, frame: RawFrame) -> Option<RawFrame> {
+        let dropped = {
+            let mut frames = self.frames.lock();
+            if frames.len() < self.capacity {
+                frames.push_back(frame);
+                None
+            } else {
+                match self.policy {
+                    CaptureDropPolicy::DropNew => return Some(frame),
+                    CaptureDropPolicy::DropOld => {
+                        let oldest = frames.pop_front();
+                        frames.push_back(frame);
+                        oldest
+                    }
+                }
+            }
+        };
+        self.available.notify_one();
+        dropped
+    }
+
+    /// Queue a frame, waiting for room instead of dropping one.
+    ///
+    /// Blocks the calling thread; meant for the streaming thread of a file
+    /// source, where waiting throttles the decoder. Returns the frame if the
+    /// queue is closed before there is room.
+    pub fn push_wait(&self, frame: RawFrame) -> Result<(), RawFrame> {
+        {
+            let mut frames = self.frames.lock();
+            loop {
+                if self.is_closed() {
+                    return Err(frame);
+                }
+                if frames.len() < self.capacity {
+                    break;
+                }
+                self.room.wait(&mut frames);
+            }
+            frames.push_back(frame);
+        }
+        self.available.notify_one();
+        Ok(())
+    }
+
+    /// Wait for the oldest queued frame; `None` once closed, or once a
+    /// finished queue is empty.
+    pub async fn pop(&self) -> Option<RawFrame> {
+        loop {
+            let notified = self.available.notified();
+            if self.is_closed() {
+                return None;
+            }
+            if let Some(frame) = self.frames.lock().pop_front() {
+                self.room.notify_one();
+                return Some(frame);
+            }
+            if self.finished.load(Ordering::SeqCst) {
+                return None;
+            }
+            notified.await;
+        }
+    }
+
+    /// Mark the input as complete; frames already queued are still handed over.
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+        self.available.notify_waiters();
+    }
+
+    /// Stop accepting frames and wake the forwarder and any waiting push.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.available.notify_waiters();
+        // Under the lock, so a `push_wait` between its check and its wait
+        // cannot miss the wakeup
+        let _frames = self.frames.lock();
+        self.room.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+/// Unwrap a buffer mapping, or count the buffer as corrupt so the caller can
+/// skip it and keep the stream running.
+fn readable_or_skip<T, E: std::fmt::Display>(
+    mapped: Result<T, E>,
+    stats: &RwLock<StreamStats>,
+    device_id: &str,
+) -> Option<T> {
+    match mapped {
+        Ok(map) => Some(map),
+        Err(e) => {
+            stats.write().frames_corrupt += 1;
+            warn!(device_id = %device_id, error = %e, "Skipping unreadable buffer");
+            None
+        }
+    }
+}
+
+/// Whether a connected stream has gone `timeout` without a frame.
+///
+/// Time is measured from the later of the last frame and the stream start, so
+/// a fresh connection gets a full timeout before its first frame is due.
+fn is_stalled(stats: &StreamStats, now: Instant, timeout: Duration) -> bool {
+    let Some(last_activity) = stats.last_frame_at.max(stats.stream_start) else {
+        return false;
+    };
+    now.saturating_duration_since(last_activity) >= timeout
+}
+
+/// Quote a property value for a `gst::parse::launch` description, so paths
+/// with spaces, `!` or quotes stay a single value.
+fn quote_launch_value(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
 /// RTSP client for managing camera streams.
 pub struct RtspClient {
+    core: Arc<ClientCore>,
+}
+
+/// Connection state shared with the bus-monitoring and reconnect tasks.
+struct ClientCore {
     config: RtspConfig,
-    pipeline: Option<gst::Pipeline>,
+    pipeline: Mutex<Option<gst::Pipeline>>,
     state: Arc<RwLock<ConnectionState>>,
     running: Arc<AtomicBool>,
     frame_sequence: Arc<AtomicU64>,
     stats: Arc<RwLock<StreamStats>>,
-    frame_sender: Option<mpsc::Sender<RawFrame>>,
+    fps_window: Arc<Mutex<FpsWindow>>,
+    frame_queue: Mutex<Option<Arc<FrameQueue>>>,
+    reconnect_budget: Mutex<ReconnectBudget>,
+    /// Dimensions the pipeline scales frames to before the appsink; `None`
+    /// keeps the source size
+    output_size: Option<(u32, u32)>,
 }
 
 impl RtspClient {
@@ -108,129 +435,199 @@ impl RtspClient {
         // Initialize GStreamer
         gst::init().map_err(|e| RtspError::GstreamerInit(e.to_string()))?;
 
+        let reconnect_budget =
+            ReconnectBudget::new(config.max_reconnect_attempts, config.stable_connection());
+
+        let fps_window = FpsWindow::new(config.fps_window());
+
         Ok(Self {
-            config,
-            pipeline: None,
-            state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
-            running: Arc::new(AtomicBool::new(false)),
-            frame_sequence: Arc::new(AtomicU64::new(0)),
-            stats: Arc::new(RwLock::new(StreamStats::default())),
-            frame_sender: None,
+            core: Arc::new(ClientCore {
+                config,
+                pipeline: Mutex::new(None),
+                state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
+                running: Arc::new(AtomicBool::new(false)),
+                frame_sequence: Arc::new(AtomicU64::new(0)),
+                stats: Arc::new(RwLock::new(StreamStats::default())),
+                fps_window: Arc::new(Mutex::new(fps_window)),
+                frame_queue: Mutex::new(None),
+                output_size: None,
+                reconnect_budget: Mutex::new(reconnect_budget),
+            }),
         })
     }
 
+    /// Scale frames to `width`x`height` in the pipeline.
+    ///
+    /// Set this to the processing target size when stretching, so the
+    /// processor does not have to resize frames a second time. Without it
+    /// frames keep the source size.
+    pub fn with_output_size(mut self, width: u32, height: u32) -> Self {
+        Arc::get_mut(&mut self.core)
+            .expect("output size is set before the client is started")
+            .output_size = Some((width, height));
+        self
+    }
+
     /// Get the current connection state.
     pub fn state(&self) -> ConnectionState {
-        *self.state.read()
+        *self.core.state.read()
     }
 
     /// Get current stream statistics.
     pub fn stats(&self) -> StreamStats {
-        self.stats.read().clone()
+        let mut stats = self.core.stats.read().clone();
+        stats.current_fps = self.core.fps_window.lock().rate(Instant::now());
+        stats
     }
 
     /// Check if the client is running.
     pub fn is_running(&self) -> bool {
-        self.running.load(Ordering::SeqCst)
+        self.core.running.load(Ordering::SeqCst)
     }
 
     /// Start the RTSP stream and return a receiver for frames.
     pub async fn start(&mut self) -> Result<mpsc::Receiver<RawFrame>, RtspError> {
-        let (tx, rx) = mpsc::channel(self.config.buffer_ms as usize);
-        self.frame_sender = Some(tx);
-        self.running.store(true, Ordering::SeqCst);
+        // The queue holds the buffered frames; the channel only hands them over
+        let queue = Arc::new(FrameQueue::new(
+            self.core.config.buffer_ms as usize,
+            self.core.config.drop_policy,
+        ));
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                while let Some(frame) = queue.pop().await {
+                    if tx.send(frame).await.is_err() {
+                        break;
+                    }
+                }
+                // Receiver gone: make the appsink callback end the stream
+                queue.close();
+            }
+        });
+        *self.core.frame_queue.lock() = Some(queue);
+        self.core.running.store(true, Ordering::SeqCst);
 
         // Connect with retry logic
-        self.connect_with_retry().await?;
+        self.core.connect_with_retry().await?;
 
         // Start the frame extraction loop
-        self.start_frame_loop();
+        self.core.start_frame_loop();
+
+        // Watch for a stream that goes quiet without an error or EOS
+        if let Some(timeout) = self.core.config.stall_timeout() {
+            self.core.start_stall_watchdog(timeout);
+        }
 
         Ok(rx)
     }
 
     /// Stop the RTSP stream.
     pub async fn stop(&mut self) {
-        info!(device_id = %self.config.device_id, "Stopping RTSP client");
-        self.running.store(false, Ordering::SeqCst);
+        info!(device_id = %self.core.config.device_id, "Stopping RTSP client");
+        self.core.running.store(false, Ordering::SeqCst);
+
+        // Close the queue first: a file source's streaming thread may be
+        // waiting for room, and the pipeline cannot stop until it returns
+        let queue = self.core.frame_queue.lock().take();
+        if let Some(queue) = queue {
+            queue.close();
+        }
 
-        if let Some(pipeline) = self.pipeline.take() {
+        let pipeline = self.core.pipeline.lock().take();
+        if let Some(pipeline) = pipeline {
             let _ = pipeline.set_state(gst::State::Null);
         }
 
-        *self.state.write() = ConnectionState::Disconnected;
-        self.frame_sender = None;
+        *self.core.state.write() = ConnectionState::Disconnected;
     }
 
+    /// Reconnect to the stream after a disconnection.
+    pub async fn reconnect(&mut self) -> Result<(), RtspError> {
+        self.core.reconnect().await
+    }
+}
+
+impl ClientCore {
     /// Connect to the RTSP stream with exponential backoff retry.
-    async fn connect_with_retry(&mut self) -> Result<(), RtspError> {
-        let mut backoff = ExponentialBackoff {
-            initial_interval: self.config.reconnect_base_delay(),
-            max_interval: self.config.reconnect_max_delay(),
-            max_elapsed_time: None, // Retry forever unless max_reconnect_attempts is set
-            ..Default::default()
-        };
+    ///
+    /// Retries until connected, stopped, or the reconnect budget is spent; the
+    /// budget, not the backoff policy, decides when to give up.
+    async fn connect_with_retry(&self) -> Result<(), RtspError> {
+        let policy = BackoffPolicy::new(u32::MAX, self.config.reconnect_base_delay())
+            .with_max_delay(self.config.reconnect_max_delay());
+        let attempts = AtomicU32::new(0);
+
+        retry_with_backoff(
+            || async { self.try_connect(attempts.fetch_add(1, Ordering::Relaxed)) },
+            &policy,
+            |e| {
+                !matches!(
+                    e,
+                    RtspError::Disconnected | RtspError::MaxReconnectAttemptsExceeded
+                )
+            },
+        )
+        .await
+    }
 
-        let mut attempts = 0u32;
-        let max_attempts = self.config.max_reconnect_attempts;
+    /// One connection attempt for `connect_with_retry`, charging a failure to
+    /// the reconnect budget.
+    fn try_connect(&self, attempt: u32) -> Result<(), RtspError> {
+        if !self.running.load(Ordering::SeqCst) {
+            return Err(RtspError::Disconnected);
+        }
 
-        loop {
-            if !self.running.load(Ordering::SeqCst) {
-                return Err(RtspError::Disconnected);
+        {
+            // A reconnect stays Reconnecting throughout, which is what keeps
+            // `on_stream_lost` from starting a second one
+            let mut state = self.state.write();
+            if attempt > 0 || *state != ConnectionState::Reconnecting {
+                *state = if attempt == 0 {
+                    ConnectionState::Connecting
+                } else {
+                    ConnectionState::Reconnecting
+                };
             }
+        }
 
-            *self.state.write() = if attempts == 0 {
-                ConnectionState::Connecting
-            } else {
-                ConnectionState::Reconnecting
-            };
-
-            match self.create_and_start_pipeline() {
-                Ok(()) => {
-                    *self.state.write() = ConnectionState::Connected;
-                    info!(
+        match self.create_and_start_pipeline() {
+            Ok(()) => {
+                self.reconnect_budget.lock().on_connected(Instant::now());
+                *self.state.write() = ConnectionState::Connected;
+                info!(
+                    device_id = %self.config.device_id,
+                    url = %self.config.url,
+                    attempts = attempt + 1,
+                    "Connected to RTSP stream"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                // `reconnect_count` is charged once per reconnect by
+                // `on_stream_lost`; failed attempts show up here instead
+                let exhausted = self.reconnect_budget.lock().record_failure();
+                let consecutive_failures = self.reconnect_budget.lock().consecutive_failures();
+                self.stats.write().consecutive_failures = consecutive_failures;
+
+                if exhausted {
+                    *self.state.write() = ConnectionState::Failed;
+                    error!(
                         device_id = %self.config.device_id,
-                        url = %self.config.url,
-                        attempts = attempts,
-                        "Connected to RTSP stream"
+                        attempts = consecutive_failures,
+                        error = %e,
+                        "Max reconnection attempts exceeded"
                     );
-                    return Ok(());
-                }
-                Err(e) => {
-                    attempts += 1;
-                    self.stats.write().reconnect_count = attempts;
-
-                    if max_attempts > 0 && attempts >= max_attempts {
-                        *self.state.write() = ConnectionState::Failed;
-                        error!(
-                            device_id = %self.config.device_id,
-                            attempts = attempts,
-                            error = %e,
-                            "Max reconnection attempts exceeded"
-                        );
-                        return Err(RtspError::MaxReconnectAttemptsExceeded);
-                    }
-
-                    if let Some(delay) = backoff.next_backoff() {
-                        warn!(
-                            device_id = %self.config.device_id,
-                            attempt = attempts,
-                            delay_ms = delay.as_millis(),
-                            error = %e,
-                            "Connection failed, retrying"
-                        );
-                        tokio::time::sleep(delay).await;
-                    } else {
-                        backoff.reset();
-                    }
+                    return Err(RtspError::MaxReconnectAttemptsExceeded);
                 }
+                Err(e)
             }
         }
     }
 
     /// Create and start the GStreamer pipeline.
-    fn create_and_start_pipeline(&mut self) -> Result<(), RtspError> {
-        let pipeline_str = self.build_pipeline_string();
+    fn create_and_start_pipeline(&self) -> Result<(), RtspError> {
+        let pipeline_str = self.build_pipeline_string()?;
         debug!(pipeline = %pipeline_str, "Creating GStreamer pipeline");
 
         let pipeline = gst::parse::launch(&pipeline_str)
@@ -265,44 +662,86 @@ impl RtspClient {
             ));
         }
 
-        self.pipeline = Some(pipeline);
+        *self.pipeline.lock() = Some(pipeline);
         self.stats.write().stream_start = Some(Instant::now());
 
         Ok(())
     }
 
+    /// Depayload, parse and decode elements for the configured codec.
+    fn decode_chain(&self) -> Result<&'static str, RtspError> {
+        match self.config.codec.as_str() {
+            "h264" => Ok("rtph264depay ! h264parse ! avdec_h264"),
+            "h265" | "hevc" => Ok("rtph265depay ! h265parse ! avdec_h265"),
+            "vp8" => Ok("rtpvp8depay ! vp8dec"),
+            "vp9" => Ok("rtpvp9depay ! vp9dec"),
+            "mjpeg" => Ok("rtpjpegdepay ! jpegparse ! jpegdec"),
+            other => Err(RtspError::PipelineCreation(format!(
+                "Unsupported codec '{}' (expected h264, h265, vp8, vp9, or mjpeg)",
+                other
+            ))),
+        }
+    }
+
     /// Build the GStreamer pipeline string.
-    fn build_pipeline_string(&self) -> String {
+    fn build_pipeline_string(&self) -> Result<String, RtspError> {
+        if self.config.is_file_source() {
+            // Frames are paced in the appsink callback, which waits for room
+            // in the frame queue rather than dropping; with the sink not
+            // dropping either, a slow consumer pushes back on the decoder
+            return Ok(format!(
+                "filesrc location={path} ! decodebin ! {convert} \
+                 ! appsink name=sink emit-signals=true sync=false max-buffers=2 drop=false",
+                path = quote_launch_value(self.config.url.trim_start_matches("file://")),
+                convert = self.convert_chain(),
+            ));
+        }
+
         let transport = match self.config.transport.as_str() {
             "udp" => "0",
             "udp-mcast" => "1",
             _ => "2", // tcp
         };
 
-        format!(
+        Ok(format!(
             "rtspsrc location={url} protocols={transport} latency={latency} \
-             ! rtph264depay ! h264parse ! avdec_h264 \
-             ! videoconvert ! videoscale \
-             ! video/x-raw,format=RGB,width={width},height={height} \
+             ! {decode} ! {convert} \
              ! appsink name=sink emit-signals=true sync=false max-buffers=2 drop=true",
             url = self.config.url,
             transport = transport,
             latency = self.config.buffer_ms,
-            width = 640,  // Default, will be overridden by processor
-            height = 480, // Default, will be overridden by processor
-        )
+            decode = self.decode_chain()?,
+            convert = self.convert_chain(),
+        ))
+    }
+
+    /// Elements converting decoded frames to RGB, scaled to the output size
+    /// if one is set.
+    fn convert_chain(&self) -> String {
+        match self.output_size {
+            Some((width, height)) => format!(
+                "videoconvert ! videoscale ! video/x-raw,format=RGB,width={},height={}",
+                width, height
+            ),
+            None => "videoconvert ! video/x-raw,format=RGB".to_string(),
+        }
     }
 
     /// Configure the appsink with callbacks for frame handling.
     fn configure_appsink(&self, appsink: &gst_app::AppSink) -> Result<(), RtspError> {
-        let sender = self
-            .frame_sender
+        let queue = self
+            .frame_queue
+            .lock()
             .clone()
-            .ok_or_else(|| RtspError::FrameExtractionFailed("No frame sender".to_string()))?;
+            .ok_or_else(|| RtspError::FrameExtractionFailed("No frame queue".to_string()))?;
         let sequence = self.frame_sequence.clone();
         let stats = self.stats.clone();
+        let fps_window = self.fps_window.clone();
         let running = self.running.clone();
         let device_id = self.config.device_id.clone();
+        // A recording can wait for the consumer; a live stream cannot
+        let wait_for_room = self.config.is_file_source();
+        let mut pacer = wait_for_room.then(|| PlaybackPacer::new(self.config.playback_speed));
 
         appsink.set_callbacks(
             gst_app::AppSinkCallbacks::builder()
@@ -324,9 +763,23 @@ impl RtspClient {
                         .unwrap_or("RGB")
                         .to_string();
 
-                    // Map buffer to read data
-                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                    // Map buffer to read data; a bad buffer skips this frame
+                    // rather than tearing down the pipeline
+                    let Some(map) = readable_or_skip(buffer.map_readable(), &stats, &device_id)
+                    else {
+                        return Ok(gst::FlowSuccess::Ok);
+                    };
                     let data = map.as_slice().to_vec();
+                    let pts = buffer.pts().map(|t| t.nseconds());
+
+                    // Replay recorded files at the configured speed; holding
+                    // the streaming thread here throttles the decoder
+                    if let Some(pacer) = pacer.as_mut() {
+                        let delay = pacer.delay(pts, Instant::now());
+                        if !delay.is_zero() {
+                            std::thread::sleep(delay);
+                        }
+                    }
 
                     let seq = sequence.fetch_add(1, Ordering::SeqCst);
 
@@ -334,7 +787,7 @@ impl RtspClient {
                         data,
                         width: width as u32,
                         height: height as u32,
-                        pts: buffer.pts().map(|t| t.nseconds()),
+                        pts,
                         sequence: seq,
                         captured_at: Instant::now(),
                         format,
@@ -342,30 +795,43 @@ impl RtspClient {
 
                     // Update stats
                     {
+                        let now = Instant::now();
+                        let mut window = fps_window.lock();
+                        window.record(now);
+
                         let mut s = stats.write();
                         s.frames_received += 1;
                         s.bytes_received += frame.data.len() as u64;
-                        s.last_frame_at = Some(Instant::now());
+                        s.last_frame_at = Some(now);
+                        s.current_fps = window.rate(now);
 
-                        // Calculate FPS
+                        // Lifetime average
                         if let Some(start) = s.stream_start {
                             let elapsed = start.elapsed().as_secs_f64();
                             if elapsed > 0.0 {
-                                s.current_fps = s.frames_received as f64 / elapsed;
+                                s.average_fps = s.frames_received as f64 / elapsed;
                             }
                         }
                     }
 
-                    // Send frame to channel
-                    match sender.try_send(frame) {
-                        Ok(()) => Ok(gst::FlowSuccess::Ok),
-                        Err(mpsc::error::TrySendError::Full(_)) => {
-                            stats.write().frames_dropped += 1;
-                            debug!(device_id = %device_id, "Frame dropped due to backpressure");
-                            Ok(gst::FlowSuccess::Ok)
+                    // Queue the frame; a full queue drops one per the drop
+                    // policy, except for files, which wait for room
+                    if queue.is_closed() {
+                        return Err(gst::FlowError::Eos);
+                    }
+                    if wait_for_room {
+                        if queue.push_wait(frame).is_err() {
+                            return Err(gst::FlowError::Eos);
                         }
-                        Err(mpsc::error::TrySendError::Closed(_)) => Err(gst::FlowError::Eos),
+                    } else if let Some(dropped) = queue.push(frame) {
+                        stats.write().frames_dropped += 1;
+                        debug!(
+                            device_id = %device_id,
+                            sequence = dropped.sequence,
+                            "Frame dropped due to backpressure"
+                        );
                     }
+                    Ok(gst::FlowSuccess::Ok)
                 })
                 .build(),
         );
@@ -374,13 +840,13 @@ impl RtspClient {
     }
 
     /// Start the frame extraction loop with reconnection handling.
-    fn start_frame_loop(&self) {
-        let pipeline = match &self.pipeline {
-            Some(p) => p.clone(),
+    fn start_frame_loop(self: &Arc<Self>) {
+        let pipeline = match self.pipeline.lock().clone() {
+            Some(p) => p,
             None => return,
         };
 
-        let state = self.state.clone();
+        let core = self.clone();
         let running = self.running.clone();
         let device_id = self.config.device_id.clone();
 
@@ -395,6 +861,10 @@ impl RtspClient {
                 if !running.load(Ordering::SeqCst) {
                     break;
                 }
+                // A reconnect replaced the pipeline; its own task watches the new bus
+                if core.pipeline.lock().as_ref() != Some(&pipeline) {
+                    break;
+                }
 
                 // Poll for messages with timeout
                 if let Some(msg) = bus.timed_pop(gst::ClockTime::from_mseconds(100)) {
@@ -406,12 +876,18 @@ impl RtspClient {
                                 debug = ?err.debug(),
                                 "GStreamer pipeline error"
                             );
-                            *state.write() = ConnectionState::Disconnected;
+                            core.on_stream_lost();
+                            break;
+                        }
+                        // A recording has nothing left to reconnect to
+                        gst::MessageView::Eos(_) if core.config.is_file_source() => {
+                            info!(device_id = %device_id, "End of file");
+                            core.on_file_finished();
                             break;
                         }
                         gst::MessageView::Eos(_) => {
                             info!(device_id = %device_id, "End of stream");
-                            *state.write() = ConnectionState::Disconnected;
+                            core.on_stream_lost();
                             break;
                         }
                         gst::MessageView::StateChanged(s) => {
@@ -442,10 +918,117 @@ impl RtspClient {
         });
     }
 
+    /// Force a reconnect when a connected stream stops delivering frames.
+    ///
+    /// Only `Connected` streams are checked, so the watchdog stays quiet while
+    /// a connection or reconnection is in progress. Ends when the client stops.
+    fn start_stall_watchdog(self: &Arc<Self>, timeout: Duration) {
+        let core = self.clone();
+        let check_interval = (timeout / 4).max(Duration::from_millis(100));
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(check_interval);
+            while core.running.load(Ordering::SeqCst) {
+                ticker.tick().await;
+
+                if *core.state.read() != ConnectionState::Connected {
+                    continue;
+                }
+                if !is_stalled(&core.stats.read(), Instant::now(), timeout) {
+                    continue;
+                }
+
+                warn!(
+                    device_id = %core.config.device_id,
+                    timeout_secs = timeout.as_secs(),
+                    "No frames received within stall timeout, reconnecting"
+                );
+                core.stats.write().stall_count += 1;
+                core.on_stream_lost();
+            }
+        });
+    }
+
+    /// Reconnect in the background after the bus reported an error or EOS,
+    /// or the stall watchdog gave up on the stream.
+    ///
+    /// A stopped client just records the disconnect. The move to
+    /// `Reconnecting` is a compare-and-set, so when both the bus and the
+    /// watchdog report the same loss only one reconnect runs.
+    fn on_stream_lost(self: &Arc<Self>) {
+        if !self.running.load(Ordering::SeqCst) {
+            *self.state.write() = ConnectionState::Disconnected;
+            return;
+        }
+
+        {
+            let mut state = self.state.write();
+            if *state == ConnectionState::Reconnecting {
+                debug!(
+                    device_id = %self.config.device_id,
+                    "Stream loss reported while already reconnecting"
+                );
+                return;
+            }
+            *state = ConnectionState::Reconnecting;
+        }
+        self.stats.write().reconnect_count += 1;
+
+        let core = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = core.reconnect().await {
+                error!(
+                    device_id = %core.config.device_id,
+                    error = %e,
+                    "Automatic reconnection failed"
+                );
+                // No more attempts will be made: end the frame channel so
+                // the consumer sees the stream is gone
+                if matches!(e, RtspError::MaxReconnectAttemptsExceeded) {
+                    core.running.store(false, Ordering::SeqCst);
+                    if let Some(queue) = core.frame_queue.lock().as_ref() {
+                        queue.close();
+                    }
+                }
+            }
+        });
+    }
+
+    /// End the stream once a file source has been played to the end.
+    ///
+    /// Frames still queued are delivered before the frame channel closes.
+    fn on_file_finished(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        *self.state.write() = ConnectionState::Disconnected;
+        if let Some(queue) = self.frame_queue.lock().as_ref() {
+            queue.finish();
+        }
+    }
+
     /// Reconnect to the stream after a disconnection.
-    pub async fn reconnect(&mut self) -> Result<(), RtspError> {
+    async fn reconnect(self: &Arc<Self>) -> Result<(), RtspError> {
+        // A long-lived connection clears the consecutive-failure count, a
+        // short-lived one adds to it
+        let (exhausted, consecutive_failures) = {
+            let mut budget = self.reconnect_budget.lock();
+            let exhausted = budget.on_disconnected(Instant::now());
+            (exhausted, budget.consecutive_failures())
+        };
+        self.stats.write().consecutive_failures = consecutive_failures;
+
+        if exhausted {
+            *self.state.write() = ConnectionState::Failed;
+            error!(
+                device_id = %self.config.device_id,
+                attempts = consecutive_failures,
+                "Max reconnection attempts exceeded by short-lived connections"
+            );
+            return Err(RtspError::MaxReconnectAttemptsExceeded);
+        }
+
         // Stop existing pipeline
-        if let Some(pipeline) = self.pipeline.take() {
+        let pipeline = self.pipeline.lock().take();
+        if let Some(pipeline) = pipeline {
             let _ = pipeline.set_state(gst::State::Null);
         }
 
@@ -464,8 +1047,11 @@ impl RtspClient {
 
 impl Drop for RtspClient {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::SeqCst);
-        if let Some(pipeline) = self.pipeline.take() {
+        self.core.running.store(false, Ordering::SeqCst);
+        if let Some(queue) = self.core.frame_queue.lock().take() {
+            queue.close();
+        }
+        if let Some(pipeline) = self.core.pipeline.lock().take() {
             let _ = pipeline.set_state(gst::State::Null);
         }
     }
@@ -531,8 +1117,14 @@ mod tests {
             max_reconnect_attempts: 3,
             reconnect_base_delay_ms: 100,
             reconnect_max_delay_ms: 1000,
+            stable_connection_secs: 60,
+            fps_window_secs: 5,
+            stall_timeout_secs: 15,
             transport: "tcp".to_string(),
+            codec: "h264".to_string(),
             buffer_ms: 100,
+            drop_policy: CaptureDropPolicy::DropNew,
+            playback_speed: 1.0,
         }
     }
 
@@ -552,11 +1144,88 @@ mod tests {
         assert_eq!(stats.reconnect_count, 0);
     }
 
+    #[test]
+    fn test_reconnect_budget_resets_after_stable_connection() {
+        let mut budget = ReconnectBudget::new(4, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(!budget.record_failure());
+        assert!(!budget.record_failure());
+        budget.on_connected(start);
+
+        // A short-lived connection counts as another failure
+        assert!(!budget.on_disconnected(start + Duration::from_secs(5)));
+        assert_eq!(budget.consecutive_failures(), 3);
+
+        budget.on_connected(start + Duration::from_secs(10));
+        assert!(!budget.on_disconnected(start + Duration::from_secs(100)));
+        assert_eq!(budget.consecutive_failures(), 0);
+
+        assert!(!budget.record_failure());
+        assert!(!budget.record_failure());
+        assert!(!budget.record_failure());
+        assert!(budget.record_failure());
+    }
+
+    #[test]
+    fn test_flapping_connection_exhausts_budget() {
+        let mut budget = ReconnectBudget::new(3, Duration::from_secs(60));
+        let mut now = Instant::now();
+
+        // Every connection succeeds but drops within a second
+        let mut exhausted = false;
+        for _ in 0..3 {
+            budget.on_connected(now);
+            now += Duration::from_secs(1);
+            exhausted = budget.on_disconnected(now);
+        }
+
+        assert!(exhausted);
+        assert_eq!(budget.consecutive_failures(), 3);
+    }
+
+    #[test]
+    fn test_fps_window_drops_after_burst() {
+        let mut window = FpsWindow::new(Duration::from_secs(5));
+        let start = Instant::now();
+
+        // 10 seconds at 30 FPS
+        for i in 0..300 {
+            window.record(start + Duration::from_millis(i * 1000 / 30));
+        }
+        let streaming = window.rate(start + Duration::from_secs(10));
+        assert!((streaming - 30.0).abs() < 1.0, "got {}", streaming);
+
+        // Two seconds of silence: only the last 3 seconds of frames remain
+        let stalled = window.rate(start + Duration::from_secs(12));
+        assert!(stalled < 20.0 && stalled > 15.0, "got {}", stalled);
+
+        // A full window of silence reports zero
+        assert_eq!(window.rate(start + Duration::from_secs(16)), 0.0);
+    }
+
+    #[test]
+    fn test_unmappable_buffer_is_counted_and_skipped() {
+        gst::init().unwrap();
+        let stats = RwLock::new(StreamStats::default());
+
+        let buffer = gst::Buffer::from_slice(vec![1u8, 2, 3]);
+        let mapped = readable_or_skip(buffer.map_readable(), &stats, "test-device");
+        assert_eq!(mapped.map(|m| m.as_slice().to_vec()), Some(vec![1, 2, 3]));
+        assert_eq!(stats.read().frames_corrupt, 0);
+
+        // A mapping failure is counted rather than surfaced as a flow error
+        let failed: Result<gst::BufferMap<'_, gst::buffer::Readable>, _> =
+            Err(gst::glib::bool_error!("Failed to map buffer readable"));
+        assert!(readable_or_skip(failed, &stats, "test-device").is_none());
+        assert_eq!(stats.read().frames_corrupt, 1);
+    }
+
     #[test]
     fn test_pipeline_string_tcp() {
         let config = create_test_config();
         let client = RtspClient::new(config).unwrap();
-        let pipeline = client.build_pipeline_string();
+        let pipeline = client.core.build_pipeline_string().unwrap();
         assert!(pipeline.contains("protocols=2")); // TCP
         assert!(pipeline.contains("rtsp://test:554/stream"));
     }
@@ -566,7 +1235,475 @@ mod tests {
         let mut config = create_test_config();
         config.transport = "udp".to_string();
         let client = RtspClient::new(config).unwrap();
-        let pipeline = client.build_pipeline_string();
+        let pipeline = client.core.build_pipeline_string().unwrap();
         assert!(pipeline.contains("protocols=0")); // UDP
     }
+
+    #[test]
+    fn test_playback_speed_scales_frame_delay() {
+        let frame_interval_ns = 40_000_000; // 25 FPS
+        let start = Instant::now();
+
+        let mut realtime = PlaybackPacer::new(1.0);
+        assert_eq!(realtime.delay(Some(0), start), Duration::ZERO);
+        assert_eq!(
+            realtime.delay(Some(frame_interval_ns), start),
+            Duration::from_millis(40)
+        );
+
+        let mut double = PlaybackPacer::new(2.0);
+        assert_eq!(double.delay(Some(0), start), Duration::ZERO);
+        assert_eq!(
+            double.delay(Some(frame_interval_ns), start),
+            Duration::from_millis(20)
+        );
+
+        // Time already spent decoding counts towards the delay
+        let later = start + Duration::from_millis(30);
+        assert_eq!(
+            double.delay(Some(2 * frame_interval_ns), later),
+            Duration::from_millis(10)
+        );
+
+        let mut unpaced = PlaybackPacer::new(0.0);
+        assert!(unpaced.delay(Some(0), start).is_zero());
+        assert!(unpaced.delay(Some(frame_interval_ns), start).is_zero());
+    }
+
+    #[test]
+    fn test_pipeline_string_uses_output_size() {
+        let client = RtspClient::new(create_test_config())
+            .unwrap()
+            .with_output_size(1280, 720);
+        let pipeline = client.core.build_pipeline_string().unwrap();
+        assert!(pipeline.contains("width=1280,height=720"));
+        assert!(!pipeline.contains("width=640"));
+    }
+
+    #[test]
+    fn test_pipeline_string_file_source() {
+        let mut config = create_test_config();
+        config.url = "file:///recordings/shift-a.mp4".to_string();
+        let client = RtspClient::new(config).unwrap();
+        let pipeline = client.core.build_pipeline_string().unwrap();
+        assert!(pipeline.contains("filesrc location=\"/recordings/shift-a.mp4\""));
+        assert!(!pipeline.contains("rtspsrc"));
+    }
+
+    #[test]
+    fn test_pipeline_string_quotes_file_path() {
+        let mut config = create_test_config();
+        config.url = r#"file:///recordings/line 3 ! "night".mp4"#.to_string();
+        let client = RtspClient::new(config).unwrap();
+        let pipeline = client.core.build_pipeline_string().unwrap();
+        assert!(pipeline.contains(r#"filesrc location="/recordings/line 3 ! \"night\".mp4" ! "#));
+    }
+
+    #[test]
+    fn test_pipeline_string_per_codec() {
+        let cases: [(&str, &[&str]); 5] = [
+            ("h264", &["rtph264depay", "h264parse", "avdec_h264"]),
+            ("h265", &["rtph265depay", "h265parse", "avdec_h265"]),
+            ("vp8", &["rtpvp8depay", "vp8dec"]),
+            ("vp9", &["rtpvp9depay", "vp9dec"]),
+            ("mjpeg", &["rtpjpegdepay", "jpegparse", "jpegdec"]),
+        ];
+        for (codec, elements) in cases {
+            let mut config = create_test_config();
+            config.codec = codec.to_string();
+            let client = RtspClient::new(config).unwrap();
+            let pipeline = client.core.build_pipeline_string().unwrap();
+            for element in elements {
+                assert!(pipeline.contains(element), "{codec}: missing {element}");
+            }
+            if codec != "h264" {
+                assert!(
+                    !pipeline.contains("avdec_h264"),
+                    "{codec}: still decodes H.264"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pipeline_string_rejects_unknown_codec() {
+        let mut config = create_test_config();
+        config.codec = "av1".to_string();
+        let client = RtspClient::new(config).unwrap();
+        match client.core.build_pipeline_string() {
+            Err(RtspError::PipelineCreation(message)) => assert!(message.contains("av1")),
+            other => panic!("expected PipelineCreation error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_lost_while_reconnecting_is_ignored() {
+        let mut config = create_test_config();
+        // Every reconnect attempt fails fast without touching the network
+        config.codec = "unsupported".to_string();
+        let client = RtspClient::new(config).unwrap();
+        client.core.running.store(true, Ordering::SeqCst);
+        *client.core.state.write() = ConnectionState::Connected;
+
+        // The bus and the stall watchdog both report the same loss
+        client.core.on_stream_lost();
+        client.core.on_stream_lost();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while client.state() != ConnectionState::Failed && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // One reconnect, counted once, however many of its attempts failed
+        assert_eq!(client.state(), ConnectionState::Failed);
+        let stats = client.stats();
+        assert_eq!(stats.reconnect_count, 1);
+        assert_eq!(stats.consecutive_failures, 3);
+    }
+
+    #[tokio::test]
+    async fn test_bus_error_triggers_reconnect() {
+        let mut config = create_test_config();
+        // Every reconnect attempt fails fast without touching the network
+        config.codec = "unsupported".to_string();
+        let client = RtspClient::new(config).unwrap();
+        client.core.running.store(true, Ordering::SeqCst);
+
+        let pipeline = gst::Pipeline::new();
+        *client.core.pipeline.lock() = Some(pipeline.clone());
+        client.core.start_frame_loop();
+
+        let error = gst::message::Error::new(gst::CoreError::Failed, "injected failure");
+        pipeline.bus().unwrap().post(error).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while client.state() == ConnectionState::Disconnected && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert!(matches!(
+            client.state(),
+            ConnectionState::Connecting | ConnectionState::Reconnecting
+        ));
+        assert!(client.stats().reconnect_count >= 1);
+        client.core.running.store(false, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_stall_detected_after_timeout_without_frames() {
+        let timeout = Duration::from_secs(10);
+        let start = Instant::now();
+        let mut stats = StreamStats::default();
+
+        // Not connected yet
+        assert!(!is_stalled(&stats, start + timeout * 2, timeout));
+
+        // A new connection gets a full timeout before its first frame
+        stats.stream_start = Some(start);
+        assert!(!is_stalled(&stats, start + Duration::from_secs(9), timeout));
+        assert!(is_stalled(&stats, start + Duration::from_secs(10), timeout));
+
+        stats.last_frame_at = Some(start + Duration::from_secs(8));
+        assert!(!is_stalled(&stats, start + Duration::from_secs(17), timeout));
+        assert!(is_stalled(&stats, start + Duration::from_secs(18), timeout));
+
+        // A reconnect restarts the clock even though the last frame is old
+        stats.stream_start = Some(start + Duration::from_secs(30));
+        assert!(!is_stalled(&stats, start + Duration::from_secs(35), timeout));
+    }
+
+    fn frame(sequence: u64) -> RawFrame {
+        RawFrame {
+            data: vec![0; 12],
+            width: 2,
+            height: 2,
+            pts: None,
+            sequence,
+            captured_at: Instant::now(),
+            format: "RGB".to_string(),
+        }
+    }
+
+    async fn drain(queue: &FrameQueue) -> Vec<u64> {
+        queue.close();
+        let mut remaining = Vec::new();
+        while let Some(frame) = queue.frames.lock().pop_front() {
+            remaining.push(frame.sequence);
+        }
+        assert!(queue.pop().await.is_none());
+        remaining
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_decides_which_frame_survives() {
+        let keep_buffered = FrameQueue::new(2, CaptureDropPolicy::DropNew);
+        assert!(keep_buffered.push(frame(0)).is_none());
+        assert!(keep_buffered.push(frame(1)).is_none());
+        assert_eq!(keep_buffered.push(frame(2)).map(|f| f.sequence), Some(2));
+        assert_eq!(drain(&keep_buffered).await, vec![0, 1]);
+
+        let keep_newest = FrameQueue::new(2, CaptureDropPolicy::DropOld);
+        assert!(keep_newest.push(frame(0)).is_none());
+        assert!(keep_newest.push(frame(1)).is_none());
+        assert_eq!(keep_newest.push(frame(2)).map(|f| f.sequence), Some(0));
+        assert_eq!(drain(&keep_newest).await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_frame_queue_hands_frames_over_in_order() {
+        let queue = Arc::new(FrameQueue::new(4, CaptureDropPolicy::DropOld));
+        let consumer = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                let mut received = Vec::new();
+                while let Some(frame) = queue.pop().await {
+                    received.push(frame.sequence);
+                    if received.len() == 3 {
+                        break;
+                    }
+                }
+                received
+            }
+        });
+
+        for sequence in 0..3 {
+            queue.push(frame(sequence));
+        }
+
+        assert_eq!(consumer.await.unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_finished_queue_hands_over_remaining_frames() {
+        let queue = FrameQueue::new(4, CaptureDropPolicy::DropOld);
+        queue.push(frame(0));
+        queue.push(frame(1));
+        queue.finish();
+
+        assert_eq!(queue.pop().await.map(|f| f.sequence), Some(0));
+        assert_eq!(queue.pop().await.map(|f| f.sequence), Some(1));
+        assert!(queue.pop().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_file_source_eos_ends_stream() {
+        let mut config = create_test_config();
+        config.url = "file:///recordings/shift-a.mp4".to_string();
+        let client = RtspClient::new(config).unwrap();
+        let queue = Arc::new(FrameQueue::new(4, CaptureDropPolicy::DropOld));
+        queue.push(frame(0));
+        *client.core.frame_queue.lock() = Some(queue.clone());
+        client.core.running.store(true, Ordering::SeqCst);
+
+        let pipeline = gst::Pipeline::new();
+        *client.core.pipeline.lock() = Some(pipeline.clone());
+        client.core.start_frame_loop();
+        pipeline.bus().unwrap().post(gst::message::Eos::new()).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while client.is_running() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert!(!client.is_running());
+        assert_eq!(client.state(), ConnectionState::Disconnected);
+        assert_eq!(client.stats().reconnect_count, 0);
+        assert_eq!(queue.pop().await.map(|f| f.sequence), Some(0));
+        assert!(queue.pop().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_reconnects_close_frame_queue() {
+        let mut config = create_test_config();
+        config.max_reconnect_attempts = 1;
+        let client = RtspClient::new(config).unwrap();
+        let queue = Arc::new(FrameQueue::new(4, CaptureDropPolicy::DropOld));
+        *client.core.frame_queue.lock() = Some(queue.clone());
+        client.core.running.store(true, Ordering::SeqCst);
+
+        let pipeline = gst::Pipeline::new();
+        *client.core.pipeline.lock() = Some(pipeline.clone());
+        client.core.start_frame_loop();
+
+        let error = gst::message::Error::new(gst::CoreError::Failed, "injected failure");
+        pipeline.bus().unwrap().post(error).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !queue.is_closed() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert!(queue.is_closed());
+        assert!(!client.is_running());
+        assert_eq!(client.state(), ConnectionState::Failed);
+        assert!(queue.pop().await.is_none());
+    }
+
+    #[test]
+    fn test_push_wait_blocks_until_room_then_keeps_every_frame() {
+        let queue = Arc::new(FrameQueue::new(1, CaptureDropPolicy::DropNew));
+        queue.push_wait(frame(0)).unwrap();
+
+        let producer = std::thread::spawn({
+            let queue = queue.clone();
+            move || queue.push_wait(frame(1))
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        assert_eq!(runtime.block_on(queue.pop()).map(|f| f.sequence), Some(0));
+        producer.join().unwrap().unwrap();
+        assert_eq!(runtime.block_on(queue.pop()).map(|f| f.sequence), Some(1));
+
+        // Closing releases a waiting push without queueing its frame
+        queue.push_wait(frame(2)).unwrap();
+        let producer = std::thread::spawn({
+            let queue = queue.clone();
+            move || queue.push_wait(frame(3))
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        queue.close();
+        assert_eq!(producer.join().unwrap().map_err(|f| f.sequence), Err(3));
+    }
+
+    /// Encode a short MJPEG recording for file-source tests.
+    fn record_test_file(path: &std::path::Path, frames: u32) {
+        gst::init().unwrap();
+        let description = format!(
+            "videotestsrc num-buffers={} ! video/x-raw,width=64,height=48 ! jpegenc \
+             ! avimux ! filesink location={}",
+            frames,
+            quote_launch_value(path.to_str().unwrap())
+        );
+        let pipeline = gst::parse::launch(&description)
+            .unwrap()
+            .downcast::<gst::Pipeline>()
+            .unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let bus = pipeline.bus().unwrap();
+        let msg = bus
+            .timed_pop_filtered(
+                gst::ClockTime::from_seconds(10),
+                &[gst::MessageType::Eos, gst::MessageType::Error],
+            )
+            .unwrap();
+        assert!(matches!(msg.view(), gst::MessageView::Eos(_)));
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_file_replay_through_slow_consumer_drops_nothing() {
+        const FRAMES: u32 = 20;
+        let path = std::env::temp_dir().join(format!("nier-replay-{}.avi", std::process::id()));
+        record_test_file(&path, FRAMES);
+
+        let mut config = create_test_config();
+        config.url = format!("file://{}", path.display());
+        // Room for two frames, replayed as fast as they decode
+        config.buffer_ms = 2;
+        config.playback_speed = 0.0;
+        let mut client = RtspClient::new(config).unwrap();
+        let mut frames = client.start().await.unwrap();
+
+        let mut received = Vec::new();
+        while let Some(frame) =
+            tokio::time::timeout(Duration::from_secs(10), frames.recv()).await.unwrap()
+        {
+            received.push(frame.sequence);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(received, (0..FRAMES as u64).collect::<Vec<_>>());
+        assert_eq!(client.stats().frames_dropped, 0);
+        assert_eq!(client.stats().frames_received, FRAMES as u64);
+    }
+
+    /// Capture one synthetic `width`x`height` frame through the client's
+    /// conversion chain, as the appsink callback would receive it.
+    fn capture_test_frame(client: &RtspClient, width: u32, height: u32) -> RawFrame {
+        let description = format!(
+            "videotestsrc num-buffers=1 ! video/x-raw,width={},height={} ! {} ! appsink name=sink",
+            width,
+            height,
+            client.core.convert_chain()
+        );
+        let pipeline = gst::parse::launch(&description)
+            .unwrap()
+            .downcast::<gst::Pipeline>()
+            .unwrap();
+        let sink = pipeline
+            .by_name("sink")
+            .unwrap()
+            .downcast::<gst_app::AppSink>()
+            .unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let sample = sink.pull_sample().unwrap();
+        pipeline.set_state(gst::State::Null).unwrap();
+
+        let structure = sample.caps().unwrap().structure(0).unwrap();
+        let map = sample.buffer().unwrap().map_readable().unwrap();
+        RawFrame {
+            data: map.as_slice().to_vec(),
+            width: structure.get::<i32>("width").unwrap() as u32,
+            height: structure.get::<i32>("height").unwrap() as u32,
+            pts: None,
+            sequence: 0,
+            captured_at: Instant::now(),
+            format: structure.get::<&str>("format").unwrap().to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_letterbox_runs_on_source_sized_frames() {
+        use crate::config::{ProcessingConfig, ResizeMode};
+        use crate::frame_processor::FrameProcessor;
+
+        let processing = ProcessingConfig {
+            target_width: 160,
+            target_height: 160,
+            target_fps: 30.0,
+            resize_mode: ResizeMode::Letterbox,
+            pad_color: [114, 114, 114],
+            pixel_format: "RGB".to_string(),
+            queue_size: 4,
+            num_workers: 1,
+            drop_on_backpressure: false,
+            max_inflight_bytes: 0,
+            use_blocking_pool: false,
+        };
+
+        // Stretching is left to the pipeline; letterbox frames keep the source size
+        let mut stretch = processing.clone();
+        stretch.resize_mode = ResizeMode::Stretch;
+        let (width, height) = stretch.pipeline_output_size().unwrap();
+        let stretched = RtspClient::new(create_test_config())
+            .unwrap()
+            .with_output_size(width, height);
+        let frame = capture_test_frame(&stretched, 320, 180);
+        assert_eq!((frame.width, frame.height), (160, 160));
+
+        assert_eq!(processing.pipeline_output_size(), None);
+        let client = RtspClient::new(create_test_config()).unwrap();
+        let frame = capture_test_frame(&client, 320, 180);
+        assert_eq!((frame.width, frame.height), (320, 180));
+
+        let processor = Arc::new(FrameProcessor::new(processing, "test-device".to_string()));
+        let (raw_tx, raw_rx) = mpsc::channel(1);
+        let (processed_tx, mut processed_rx) = mpsc::channel(1);
+        raw_tx.send(frame).await.unwrap();
+        drop(raw_tx);
+        processor.run(raw_rx, processed_tx).await;
+
+        let processed = processed_rx.recv().await.unwrap();
+        assert_eq!((processed.original_width, processed.original_height), (320, 180));
+        assert_eq!((processed.width, processed.height), (160, 160));
+        assert_eq!(processed.transform.scale_x, 0.5);
+        assert_eq!(processed.transform.scale_y, 0.5);
+        assert_eq!((processed.transform.pad_x, processed.transform.pad_y), (0, 35));
+        // The band above the picture keeps the pad color
+        assert_eq!(&processed.data[..3], &[114, 114, 114]);
+        assert_eq!(processed.data.len(), 160 * 160 * 3);
+    }
 }
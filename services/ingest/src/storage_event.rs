@@ -0,0 +1,341 @@
+//! Conversion of processed frames into storage trigger events.
+//!
+//! The storage service consumes `StorageTriggerEvent` JSON messages. The
+//! types here mirror that wire format so frames can be handed to storage
+//! without each integrator mapping fields by hand. When enabled, the
+//! batching client samples frames it submits for inference onto a
+//! [`StorageSink`].
+
+use crate::config::StorageConfig;
+use crate::frame_processor::ProcessedFrame;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use nier_pipeline::{KafkaConfig, NierProducer, OutgoingMessage, TypedPayload};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Event type that triggered storage consideration.
+// Mirrors the storage service's wire format; ingest itself only samples
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerType {
+    /// Frame contains detections
+    Detection,
+    /// Periodic sample frame
+    Sample,
+    /// Debug/troubleshooting frame
+    Debug,
+    /// Manual trigger from operator
+    Manual,
+    /// Alert condition triggered
+    Alert,
+}
+
+/// A detection attached to a storage event.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    /// Detection type/class
+    pub detection_type: String,
+    /// Confidence score (0.0 - 1.0)
+    pub confidence: f32,
+    /// Bounding box [x, y, width, height] normalized 0-1
+    pub bbox: [f32; 4],
+    /// Additional detection metadata
+    pub attributes: serde_json::Value,
+}
+
+/// A frame submitted to the storage service.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageTriggerEvent {
+    pub event_id: Uuid,
+    pub device_id: String,
+    /// Wall-clock capture time
+    pub timestamp: DateTime<Utc>,
+    pub frame_number: u64,
+    /// Stream session the frame number belongs to
+    pub session_id: Option<String>,
+    /// Frame bytes, base64 encoded on the wire
+    #[serde(serialize_with = "serialize_base64")]
+    pub frame_data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Frame encoding; processed frames carry raw pixels in their pixel format
+    pub format: String,
+    pub detections: Vec<Detection>,
+    pub trigger_type: TriggerType,
+    pub metadata: serde_json::Value,
+}
+
+impl StorageTriggerEvent {
+    /// Build a storage event from a processed frame.
+    ///
+    /// The frame's sequence number and stream session become the frame
+    /// number and session, and its monotonic capture instant is converted
+    /// to wall-clock time.
+    pub fn from_processed_frame(
+        frame: &ProcessedFrame,
+        trigger_type: TriggerType,
+        detections: Vec<Detection>,
+    ) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            device_id: frame.device_id.clone(),
+            timestamp: capture_time(frame.captured_at, Utc::now()),
+            frame_number: frame.sequence,
+            session_id: Some(frame.session_id.clone()),
+            frame_data: frame.data.to_vec(),
+            width: frame.width,
+            height: frame.height,
+            format: frame.pixel_format.to_lowercase(),
+            detections,
+            trigger_type,
+            metadata: serde_json::json!({
+                "frame_id": frame.frame_id,
+                "original_width": frame.original_width,
+                "original_height": frame.original_height,
+                "scale_x": frame.transform.scale_x,
+                "scale_y": frame.transform.scale_y,
+                "pad_x": frame.transform.pad_x,
+                "pad_y": frame.transform.pad_y,
+            }),
+        }
+    }
+}
+
+/// Errors publishing storage events.
+#[derive(Error, Debug)]
+pub enum StorageSinkError {
+    #[error("Failed to create storage producer: {0}")]
+    Producer(String),
+
+    #[error("Failed to encode storage event: {0}")]
+    Encode(String),
+
+    #[error("Failed to publish storage event: {0}")]
+    Publish(String),
+}
+
+/// Destination for storage trigger events.
+#[async_trait]
+pub trait StorageSink: Send + Sync {
+    /// Publish one event to the storage service.
+    async fn publish(&self, event: StorageTriggerEvent) -> Result<(), StorageSinkError>;
+}
+
+/// Publishes storage events to the storage service's Kafka trigger topic.
+pub struct KafkaStorageSink {
+    producer: NierProducer,
+    topic: String,
+}
+
+impl KafkaStorageSink {
+    /// Create a sink for the configured brokers and topic.
+    pub fn new(config: &StorageConfig) -> Result<Self, StorageSinkError> {
+        let producer = NierProducer::new(KafkaConfig::new(config.brokers.clone()))
+            .map_err(|e| StorageSinkError::Producer(e.to_string()))?;
+
+        Ok(Self {
+            producer,
+            topic: config.topic.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl StorageSink for KafkaStorageSink {
+    async fn publish(&self, event: StorageTriggerEvent) -> Result<(), StorageSinkError> {
+        let payload =
+            TypedPayload::json(&event).map_err(|e| StorageSinkError::Encode(e.to_string()))?;
+        // Keyed by device so a device's frames stay ordered
+        let message =
+            OutgoingMessage::new_typed(&self.topic, payload).with_key(event.device_id.clone());
+
+        self.producer
+            .send(message)
+            .await
+            .map_err(|e| StorageSinkError::Publish(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Samples frames submitted for inference onto a storage sink.
+///
+/// At most one frame per interval of capture time is stored, as a
+/// `TriggerType::Sample` event without detections.
+pub struct StorageSampler {
+    sink: Arc<dyn StorageSink>,
+    interval: Duration,
+    last_sampled: Mutex<Option<Instant>>,
+}
+
+impl StorageSampler {
+    pub fn new(sink: Arc<dyn StorageSink>, interval: Duration) -> Self {
+        Self {
+            sink,
+            interval,
+            last_sampled: Mutex::new(None),
+        }
+    }
+
+    /// Storage events for the frames that are due for sampling.
+    pub fn sample(&self, frames: &[ProcessedFrame]) -> Vec<StorageTriggerEvent> {
+        let mut last_sampled = self.last_sampled.lock();
+
+        frames
+            .iter()
+            .filter(|frame| {
+                let due = last_sampled.map_or(true, |at| {
+                    frame.captured_at.saturating_duration_since(at) >= self.interval
+                });
+                if due {
+                    *last_sampled = Some(frame.captured_at);
+                }
+                due
+            })
+            .map(|frame| {
+                StorageTriggerEvent::from_processed_frame(frame, TriggerType::Sample, Vec::new())
+            })
+            .collect()
+    }
+
+    /// Publish events in the background so delivery never holds up inference.
+    pub fn publish(&self, events: Vec<StorageTriggerEvent>) {
+        if events.is_empty() {
+            return;
+        }
+
+        let sink = self.sink.clone();
+        tokio::spawn(async move {
+            for event in events {
+                let frame_number = event.frame_number;
+                if let Err(e) = sink.publish(event).await {
+                    warn!(frame_number, error = %e, "Failed to publish storage event");
+                }
+            }
+        });
+    }
+}
+
+/// Wall-clock time at which `captured_at` occurred, given the current time.
+fn capture_time(captured_at: Instant, now: DateTime<Utc>) -> DateTime<Utc> {
+    chrono::Duration::from_std(captured_at.elapsed())
+        .map(|age| now - age)
+        .unwrap_or(now)
+}
+
+fn serialize_base64<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    serializer.serialize_str(&STANDARD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use tokio::sync::mpsc;
+
+    fn create_test_frame() -> ProcessedFrame {
+        ProcessedFrame {
+            frame_id: "test-frame-1".to_string(),
+            device_id: "test-device".to_string(),
+            data: Bytes::from(vec![0u8; 640 * 480 * 3]),
+            width: 640,
+            height: 480,
+            pixel_format: "RGB24".to_string(),
+            original_width: 1280,
+            original_height: 720,
+            sequence: 42,
+            session_id: "session-1".to_string(),
+            captured_at: Instant::now(),
+            processed_at: Instant::now(),
+            processing_latency_us: 1000,
+            transform: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_from_processed_frame_preserves_identity() {
+        let frame = create_test_frame();
+        let detections = vec![Detection {
+            detection_type: "person".to_string(),
+            confidence: 0.9,
+            bbox: [0.1, 0.1, 0.2, 0.4],
+            attributes: serde_json::Value::Null,
+        }];
+
+        let event =
+            StorageTriggerEvent::from_processed_frame(&frame, TriggerType::Detection, detections);
+
+        assert_eq!(event.device_id, "test-device");
+        assert_eq!((event.width, event.height), (640, 480));
+        assert_eq!(event.frame_number, 42);
+        assert_eq!(event.session_id.as_deref(), Some("session-1"));
+        assert_eq!(event.format, "rgb24");
+        assert_eq!(event.detections.len(), 1);
+        assert_eq!(event.metadata["original_width"], 1280);
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["trigger_type"], "detection");
+        assert!(json["frame_data"].is_string());
+    }
+
+    /// Forwards published events to a channel.
+    struct ChannelSink(mpsc::UnboundedSender<StorageTriggerEvent>);
+
+    #[async_trait]
+    impl StorageSink for ChannelSink {
+        async fn publish(&self, event: StorageTriggerEvent) -> Result<(), StorageSinkError> {
+            self.0.send(event).unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampler_stores_one_frame_per_interval() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let sampler = StorageSampler::new(Arc::new(ChannelSink(tx)), Duration::from_secs(5));
+
+        let start = Instant::now();
+        let frames: Vec<_> = [0, 1, 6, 9, 11]
+            .into_iter()
+            .enumerate()
+            .map(|(sequence, secs)| ProcessedFrame {
+                sequence: sequence as u64,
+                captured_at: start + Duration::from_secs(secs),
+                ..create_test_frame()
+            })
+            .collect();
+
+        let events = sampler.sample(&frames[..3]);
+        assert_eq!(events.len(), 2);
+        // The interval carries over between batches
+        assert_eq!(sampler.sample(&frames[3..]).len(), 1);
+
+        sampler.publish(events);
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first.frame_number, 0);
+        assert_eq!(first.trigger_type, TriggerType::Sample);
+        assert!(first.detections.is_empty());
+        assert_eq!(rx.recv().await.unwrap().frame_number, 2);
+    }
+
+    #[test]
+    fn test_capture_time_accounts_for_frame_age() {
+        let now = Utc::now();
+        let captured_at = Instant::now() - Duration::from_secs(2);
+
+        let age = now - capture_time(captured_at, now);
+        assert!(age >= chrono::Duration::seconds(2));
+        assert!(age < chrono::Duration::seconds(3));
+    }
+}
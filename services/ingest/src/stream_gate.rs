@@ -0,0 +1,129 @@
+//! Admission control for starting RTSP streams.
+//!
+//! Every running stream holds a GStreamer pipeline and its sockets, so an
+//! instance configured with more streams than it can carry would run out of
+//! file descriptors. `StreamStartGate` caps how many streams run at once,
+//! queues the rest until a slot frees up, and spaces out stream starts so
+//! cameras are not all dialed in the same instant.
+
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Limits concurrent streams and staggers their starts.
+pub struct StreamStartGate {
+    slots: Arc<Semaphore>,
+    max_concurrent: usize,
+    stagger: Duration,
+    next_start: Mutex<Instant>,
+}
+
+/// A running stream's slot; dropping it lets a queued stream start.
+pub struct StreamSlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl StreamStartGate {
+    /// Create a gate allowing `max_concurrent` streams, started `stagger` apart.
+    pub fn new(max_concurrent: usize, stagger: Duration) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            stagger,
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until `device_id`'s stream may start.
+    ///
+    /// Waits for a free slot if the cap is reached, then for this stream's
+    /// turn in the start schedule. Hold the returned slot for as long as the
+    /// stream runs.
+    pub async fn acquire(&self, device_id: &str) -> StreamSlot {
+        let permit = match self.slots.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                warn!(
+                    device_id = %device_id,
+                    max_concurrent_streams = self.max_concurrent,
+                    "Concurrent stream cap reached, queueing stream start"
+                );
+                self.slots
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("stream gate semaphore is never closed")
+            }
+        };
+
+        let start_at = {
+            let mut next_start = self.next_start.lock().await;
+            let start_at = (*next_start).max(Instant::now());
+            *next_start = start_at + self.stagger;
+            start_at
+        };
+        tokio::time::sleep_until(start_at).await;
+
+        info!(device_id = %device_id, active = self.active(), "Stream slot acquired");
+        StreamSlot { _permit: permit }
+    }
+
+    /// Number of streams currently holding a slot.
+    pub fn active(&self) -> usize {
+        self.max_concurrent - self.slots.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex as SyncMutex;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cap_limits_initial_starts() {
+        let gate = Arc::new(StreamStartGate::new(2, Duration::ZERO));
+        let started = Arc::new(SyncMutex::new(Vec::new()));
+
+        let streams: Vec<_> = (0..5)
+            .map(|i| {
+                let gate = gate.clone();
+                let started = started.clone();
+                tokio::spawn(async move {
+                    let _slot = gate.acquire(&format!("camera-{}", i)).await;
+                    started.lock().push(i);
+                    // Streams run until the test ends
+                    std::future::pending::<()>().await;
+                })
+            })
+            .collect();
+
+        // The paused clock only advances once every task is blocked
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.lock().len(), 2);
+        assert_eq!(gate.active(), 2);
+
+        // Stopping a running stream lets a queued one start
+        let running = started.lock()[0];
+        streams[running].abort();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.lock().len(), 3);
+
+        for stream in streams {
+            stream.abort();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_starts_are_staggered() {
+        let gate = StreamStartGate::new(3, Duration::from_millis(40));
+        let begin = Instant::now();
+
+        let _first = gate.acquire("camera-1").await;
+        assert_eq!(begin.elapsed(), Duration::ZERO);
+        let _second = gate.acquire("camera-2").await;
+        assert_eq!(begin.elapsed(), Duration::from_millis(40));
+        let _third = gate.acquire("camera-3").await;
+        assert_eq!(begin.elapsed(), Duration::from_millis(80));
+    }
+}
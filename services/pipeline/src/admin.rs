@@ -0,0 +1,157 @@
+//! Kafka admin helpers for the Nier pipeline.
+//!
+//! This module provides topic provisioning for fresh environments where the
+//! broker has `auto.create.topics.enable` turned off.
+
+use crate::config::{ClientCreationError, KafkaConfig, TopicConfig};
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::types::RDKafkaErrorCode;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, info, instrument};
+
+/// Errors that can occur during admin operations
+#[derive(Error, Debug)]
+pub enum AdminError {
+    #[error("Failed to create admin client: {0}")]
+    CreationError(ClientCreationError),
+
+    #[error("Failed to create topic {topic}: {message}")]
+    TopicCreationError { topic: String, message: String },
+
+    #[error("Admin request failed: {0}")]
+    RequestError(String),
+}
+
+/// Specification for a topic to be created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicSpec {
+    /// Topic name
+    pub name: String,
+    /// Number of partitions
+    pub partitions: i32,
+    /// Replication factor
+    pub replication_factor: i32,
+}
+
+impl TopicSpec {
+    /// Build specs for all pipeline topics (frames, detections, alerts, critical alerts, dlq)
+    pub fn from_config(topics: &TopicConfig) -> Vec<Self> {
+        [
+            Some(&topics.frames),
+            Some(&topics.detections),
+            Some(&topics.alerts),
+            topics.critical_alerts.as_ref(),
+            Some(&topics.dead_letter_queue),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|name| Self {
+            name: name.clone(),
+            partitions: topics.partitions,
+            replication_factor: topics.replication_factor,
+        })
+        .collect()
+    }
+}
+
+/// Kafka admin client wrapper
+pub struct NierAdmin {
+    admin: AdminClient<DefaultClientContext>,
+    topics: TopicConfig,
+    timeout: Duration,
+}
+
+impl NierAdmin {
+    /// Create a new admin client with the given configuration
+    pub fn new(config: &KafkaConfig) -> Result<Self, AdminError> {
+        let admin: AdminClient<DefaultClientContext> = config
+            .build_admin_config()
+            .create()
+            .map_err(|e| AdminError::CreationError(e.into()))?;
+
+        Ok(Self {
+            admin,
+            topics: config.topics.clone(),
+            timeout: config.request_timeout(),
+        })
+    }
+
+    /// Create any configured pipeline topics that don't exist yet.
+    ///
+    /// Does nothing unless `topics.auto_create` is enabled. Topics that
+    /// already exist are left untouched.
+    #[instrument(skip(self))]
+    pub async fn ensure_topics(&self) -> Result<(), AdminError> {
+        if !self.topics.auto_create {
+            debug!("Topic auto-creation disabled, skipping");
+            return Ok(());
+        }
+
+        let specs = TopicSpec::from_config(&self.topics);
+        self.create_topics(&specs).await
+    }
+
+    /// Create the given topics, treating already-existing topics as success
+    pub async fn create_topics(&self, specs: &[TopicSpec]) -> Result<(), AdminError> {
+        let new_topics: Vec<NewTopic<'_>> = specs
+            .iter()
+            .map(|spec| {
+                NewTopic::new(
+                    &spec.name,
+                    spec.partitions,
+                    TopicReplication::Fixed(spec.replication_factor),
+                )
+            })
+            .collect();
+
+        let options = AdminOptions::new().operation_timeout(Some(self.timeout));
+
+        let results = self
+            .admin
+            .create_topics(&new_topics, &options)
+            .await
+            .map_err(|e| AdminError::RequestError(e.to_string()))?;
+
+        for result in results {
+            match result {
+                Ok(topic) => info!("Created topic {}", topic),
+                Err((topic, RDKafkaErrorCode::TopicAlreadyExists)) => {
+                    debug!("Topic {} already exists", topic)
+                }
+                Err((topic, code)) => {
+                    return Err(AdminError::TopicCreationError {
+                        topic,
+                        message: code.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_specs_from_config() {
+        let topics = TopicConfig {
+            auto_create: true,
+            partitions: 12,
+            replication_factor: 2,
+            ..Default::default()
+        };
+
+        let specs = TopicSpec::from_config(&topics);
+        let names: Vec<_> = specs.iter().map(|s| s.name.as_str()).collect();
+
+        assert_eq!(names, ["nier.frames", "nier.detections", "nier.alerts", "nier.dlq"]);
+        assert!(specs
+            .iter()
+            .all(|s| s.partitions == 12 && s.replication_factor == 2));
+    }
+}
@@ -3,7 +3,11 @@
 //! This module provides configuration structures and utilities for connecting
 //! to Kafka brokers with support for SSL/SASL authentication.
 
+use nier_retry::{retry_with_backoff, BackoffPolicy};
+use rdkafka::client::{Client, ClientContext};
 use rdkafka::config::ClientConfig;
+use rdkafka::error::KafkaError;
+use rdkafka::types::RDKafkaErrorCode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Duration;
@@ -22,6 +26,170 @@ pub enum ConfigError {
     LoadError(String),
 }
 
+/// Classified failure when creating or connecting a Kafka client
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ClientCreationError {
+    #[error("broker unreachable: {0}")]
+    BrokerUnreachable(String),
+
+    #[error("authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    #[error("invalid client configuration: {0}")]
+    ConfigError(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl ClientCreationError {
+    /// Classify an error from its librdkafka error code and message.
+    ///
+    /// The code is checked first; the message is used as a fallback because
+    /// librdkafka often reports connection problems as generic failures.
+    pub fn classify(code: Option<RDKafkaErrorCode>, message: impl Into<String>) -> Self {
+        let message = message.into();
+
+        match code {
+            Some(
+                RDKafkaErrorCode::BrokerTransportFailure
+                | RDKafkaErrorCode::Resolve
+                | RDKafkaErrorCode::AllBrokersDown
+                | RDKafkaErrorCode::BrokerNotAvailable
+                | RDKafkaErrorCode::NetworkException,
+            ) => return Self::BrokerUnreachable(message),
+            Some(
+                RDKafkaErrorCode::Authentication
+                | RDKafkaErrorCode::SaslAuthenticationFailed
+                | RDKafkaErrorCode::UnsupportedSaslMechanism
+                | RDKafkaErrorCode::IllegalSaslState
+                | RDKafkaErrorCode::SSL,
+            ) => return Self::AuthenticationFailed(message),
+            Some(RDKafkaErrorCode::InvalidArgument) => return Self::ConfigError(message),
+            _ => {}
+        }
+
+        let lower = message.to_lowercase();
+        if lower.contains("configuration property") || lower.contains("invalid value") {
+            Self::ConfigError(message)
+        } else if lower.contains("sasl")
+            || lower.contains("authentication")
+            || lower.contains("ssl handshake")
+        {
+            Self::AuthenticationFailed(message)
+        } else if lower.contains("failed to resolve")
+            || lower.contains("connection refused")
+            || lower.contains("all broker connections are down")
+        {
+            Self::BrokerUnreachable(message)
+        } else {
+            Self::Other(message)
+        }
+    }
+}
+
+impl From<KafkaError> for ClientCreationError {
+    fn from(err: KafkaError) -> Self {
+        match err {
+            KafkaError::ClientConfig(..) => Self::ConfigError(err.to_string()),
+            _ => Self::classify(err.rdkafka_error_code(), err.to_string()),
+        }
+    }
+}
+
+/// Check that the cluster answers a metadata request within `timeout`.
+///
+/// Creating a client does not contact any broker, so this is what surfaces an
+/// unreachable cluster or rejected credentials. A request that times out
+/// means no broker answered and is reported as unreachable. This blocks; the
+/// clients' `check_connectivity` runs it off the async runtime.
+pub fn probe_cluster<C: ClientContext>(
+    client: &Client<C>,
+    timeout: Duration,
+) -> Result<(), ClientCreationError> {
+    client
+        .fetch_metadata(None, timeout)
+        .map(|_| ())
+        .map_err(|e| probe_error(e, timeout))
+}
+
+/// Run `probe` on the blocking pool until it succeeds, backing off between
+/// attempts while the cluster is unreachable.
+///
+/// A broker that is still starting is worth waiting for; rejected credentials
+/// or bad configuration are returned at once.
+pub(crate) async fn wait_for_cluster<P>(
+    probe: P,
+    policy: &BackoffPolicy,
+) -> Result<(), ClientCreationError>
+where
+    P: Fn() -> Result<(), ClientCreationError> + Clone + Send + 'static,
+{
+    retry_with_backoff(
+        || {
+            let probe = probe.clone();
+            async move {
+                tokio::task::spawn_blocking(probe).await.unwrap_or_else(|e| {
+                    Err(ClientCreationError::Other(format!(
+                        "connectivity probe failed to run: {}",
+                        e
+                    )))
+                })
+            }
+        },
+        policy,
+        |e| matches!(e, ClientCreationError::BrokerUnreachable(_)),
+    )
+    .await
+}
+
+/// Classify a failed metadata probe
+fn probe_error(err: KafkaError, timeout: Duration) -> ClientCreationError {
+    if err.rdkafka_error_code() == Some(RDKafkaErrorCode::OperationTimedOut) {
+        return ClientCreationError::BrokerUnreachable(format!(
+            "no broker answered a metadata request within {:?}: {}",
+            timeout, err
+        ));
+    }
+    err.into()
+}
+
+/// librdkafka properties set from typed `KafkaConfig` fields
+const MANAGED_PROPERTIES: &[&str] = &[
+    "bootstrap.servers",
+    "client.id",
+    "security.protocol",
+    "ssl.ca.location",
+    "ssl.certificate.location",
+    "ssl.key.location",
+    "ssl.key.password",
+    "enable.ssl.certificate.verification",
+    "sasl.mechanism",
+    "sasl.username",
+    "sasl.password",
+    "sasl.kerberos.service.name",
+    "sasl.kerberos.keytab",
+    "sasl.kerberos.principal",
+    "retries",
+    "retry.backoff.ms",
+    "request.timeout.ms",
+    "message.timeout.ms",
+    "acks",
+    "enable.idempotence",
+    "batch.size",
+    "linger.ms",
+    "compression.type",
+    "max.in.flight.requests.per.connection",
+    "group.id",
+    "auto.offset.reset",
+    "enable.auto.commit",
+    "auto.commit.interval.ms",
+    "session.timeout.ms",
+    "heartbeat.interval.ms",
+    "max.poll.interval.ms",
+    "partition.assignment.strategy",
+];
+
 /// Security protocol for Kafka connections
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -53,6 +221,8 @@ pub enum SaslMechanism {
     ScramSha256,
     ScramSha512,
     OAuthBearer,
+    /// Kerberos; configured through `SaslConfig::kerberos`
+    Gssapi,
 }
 
 impl SaslMechanism {
@@ -62,6 +232,7 @@ impl SaslMechanism {
             SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
             SaslMechanism::ScramSha512 => "SCRAM-SHA-512",
             SaslMechanism::OAuthBearer => "OAUTHBEARER",
+            SaslMechanism::Gssapi => "GSSAPI",
         }
     }
 }
@@ -94,6 +265,20 @@ pub struct SaslConfig {
     pub password: Option<String>,
     /// OAuth bearer token (for OAuthBearer mechanism)
     pub oauth_token: Option<String>,
+    /// Kerberos settings (for Gssapi mechanism)
+    #[serde(default)]
+    pub kerberos: KerberosConfig,
+}
+
+/// Kerberos (SASL/GSSAPI) configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct KerberosConfig {
+    /// Kerberos principal name the brokers run as (librdkafka default: kafka)
+    pub service_name: Option<String>,
+    /// Path to the client keytab
+    pub keytab: Option<String>,
+    /// Client Kerberos principal
+    pub principal: Option<String>,
 }
 
 /// Retry and reliability configuration
@@ -108,7 +293,18 @@ pub struct ReliabilityConfig {
     /// Request timeout in milliseconds
     #[serde(default = "default_request_timeout_ms")]
     pub request_timeout_ms: u64,
+    /// Delivery timeout in milliseconds: how long a produced message may wait
+    /// for acknowledgement, retries included, before it fails as timed out
+    #[serde(default = "default_message_timeout_ms")]
+    pub message_timeout_ms: u64,
+    /// How long each `check_connectivity` attempt waits for a broker to
+    /// answer a metadata request (0 = skip the check)
+    #[serde(default = "default_connect_probe_timeout_ms")]
+    pub connect_probe_timeout_ms: u64,
     /// Enable idempotent producer
+    ///
+    /// Guarantees per-partition (and so per-key) ordering with retries and
+    /// multiple in-flight requests.
     #[serde(default = "default_true")]
     pub enable_idempotence: bool,
     /// Required acknowledgments: 0, 1, or -1 (all)
@@ -128,16 +324,36 @@ fn default_request_timeout_ms() -> u64 {
     30000
 }
 
+fn default_message_timeout_ms() -> u64 {
+    300000
+}
+
+fn default_connect_probe_timeout_ms() -> u64 {
+    10000
+}
+
 fn default_acks() -> String {
     "all".to_string()
 }
 
+impl ReliabilityConfig {
+    /// Backoff for retries the client performs itself (e.g. DLQ sends)
+    pub fn backoff_policy(&self) -> BackoffPolicy {
+        BackoffPolicy::new(
+            self.retries.saturating_add(1),
+            Duration::from_millis(self.retry_backoff_ms),
+        )
+    }
+}
+
 impl Default for ReliabilityConfig {
     fn default() -> Self {
         Self {
             retries: default_retries(),
             retry_backoff_ms: default_retry_backoff_ms(),
             request_timeout_ms: default_request_timeout_ms(),
+            message_timeout_ms: default_message_timeout_ms(),
+            connect_probe_timeout_ms: default_connect_probe_timeout_ms(),
             enable_idempotence: true,
             acks: default_acks(),
         }
@@ -157,8 +373,48 @@ pub struct ProducerConfig {
     #[serde(default = "default_compression")]
     pub compression_type: String,
     /// Maximum in-flight requests per connection
+    ///
+    /// Values above 1 only preserve per-key ordering across retries when
+    /// `reliability.enable_idempotence` is on; `KafkaConfig::validate`
+    /// rejects the unsafe combination.
     #[serde(default = "default_max_in_flight")]
     pub max_in_flight_requests: u32,
+    /// Maximum original payload bytes embedded in a dead-letter message
+    /// (0 = unlimited). Larger payloads are truncated and flagged.
+    #[serde(default = "default_max_dlq_payload_bytes")]
+    pub max_dlq_payload_bytes: usize,
+    /// Named overrides of these settings, e.g. a low-latency profile for
+    /// alerts. Each profile gets its own underlying producer, selected per
+    /// message with `OutgoingMessage::with_profile`.
+    #[serde(default)]
+    pub profiles: HashMap<String, ProducerProfile>,
+    /// Transactional ID for exactly-once production (None = not
+    /// transactional). Forces idempotence on and `acks` to all.
+    #[serde(default)]
+    pub transactional_id: Option<String>,
+}
+
+/// Producer settings that differ for one class of messages
+///
+/// Unset fields inherit the global `producer` and `reliability` settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProducerProfile {
+    /// Batch size in bytes
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+    /// Linger time in milliseconds
+    #[serde(default)]
+    pub linger_ms: Option<u64>,
+    /// Compression type: none, gzip, snappy, lz4, zstd
+    #[serde(default)]
+    pub compression_type: Option<String>,
+    /// Maximum in-flight requests per connection
+    #[serde(default)]
+    pub max_in_flight_requests: Option<u32>,
+    /// Required acknowledgments: 0, 1, or -1 (all). Anything other than all
+    /// turns idempotence off for this profile, which librdkafka requires.
+    #[serde(default)]
+    pub acks: Option<String>,
 }
 
 fn default_batch_size() -> usize {
@@ -177,6 +433,10 @@ fn default_max_in_flight() -> u32 {
     5
 }
 
+fn default_max_dlq_payload_bytes() -> usize {
+    256 * 1024
+}
+
 impl Default for ProducerConfig {
     fn default() -> Self {
         Self {
@@ -184,6 +444,9 @@ impl Default for ProducerConfig {
             linger_ms: default_linger_ms(),
             compression_type: default_compression(),
             max_in_flight_requests: default_max_in_flight(),
+            max_dlq_payload_bytes: default_max_dlq_payload_bytes(),
+            profiles: HashMap::new(),
+            transactional_id: None,
         }
     }
 }
@@ -214,6 +477,155 @@ pub struct ConsumerConfig {
     /// Maximum records to fetch per poll
     #[serde(default = "default_max_poll_records")]
     pub max_poll_records: u32,
+    /// Per-topic starting position for manual assignment mode
+    ///
+    /// Only used by `NierConsumer::assign_with_offset_resets`; group
+    /// subscriptions always use `auto_offset_reset`.
+    #[serde(default)]
+    pub topic_offset_resets: HashMap<String, OffsetReset>,
+    /// Maximum messages handed to the handler per second (None = unlimited).
+    ///
+    /// Useful when replaying from an old offset so a backfill does not
+    /// overwhelm downstream services.
+    #[serde(default)]
+    pub max_throughput_per_sec: Option<u32>,
+    /// How partitions are distributed across the consumer group
+    #[serde(default)]
+    pub assignment_strategy: AssignmentStrategy,
+    /// How header values that are not valid UTF-8 are handled
+    #[serde(default)]
+    pub header_decoding: HeaderDecoding,
+    /// Longest to wait for the final offset commit on shutdown, in milliseconds
+    #[serde(default = "default_shutdown_commit_timeout")]
+    pub shutdown_commit_timeout_ms: u64,
+    /// Fixed partition shard this consumer owns (None = every assigned partition)
+    #[serde(default)]
+    pub shard: Option<ShardSpec>,
+}
+
+impl ConsumerConfig {
+    /// Get the shutdown commit timeout as Duration
+    pub fn shutdown_commit_timeout(&self) -> Duration {
+        Duration::from_millis(self.shutdown_commit_timeout_ms)
+    }
+}
+
+/// Partition assignment strategy for consumer group rebalances
+///
+/// `Range` and `RoundRobin` are eager: every member revokes all of its
+/// partitions on each rebalance and processing pauses group-wide until the
+/// new assignment lands. `CooperativeSticky` rebalances incrementally, so
+/// only the partitions that actually move are revoked and the rest keep
+/// consuming. Switching a live group between eager and cooperative
+/// strategies requires a rolling restart through a mixed configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum AssignmentStrategy {
+    #[default]
+    #[serde(rename = "range")]
+    Range,
+    #[serde(rename = "roundrobin")]
+    RoundRobin,
+    #[serde(rename = "cooperative-sticky")]
+    CooperativeSticky,
+}
+
+impl AssignmentStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignmentStrategy::Range => "range",
+            AssignmentStrategy::RoundRobin => "roundrobin",
+            AssignmentStrategy::CooperativeSticky => "cooperative-sticky",
+        }
+    }
+
+    /// Whether rebalances only revoke the partitions that move
+    pub fn is_incremental(&self) -> bool {
+        matches!(self, AssignmentStrategy::CooperativeSticky)
+    }
+}
+
+/// Fixed shard of partitions owned by one consumer
+///
+/// Shard `shard_id` of `total_shards` owns every partition where
+/// `partition % total_shards == shard_id`, regardless of how the group
+/// assigns partitions. Assigned partitions outside the shard are paused, so
+/// give each shard its own consumer group: shards sharing a group would
+/// leave the partitions paused by one member unconsumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardSpec {
+    pub shard_id: u32,
+    pub total_shards: u32,
+}
+
+impl ShardSpec {
+    /// Whether this shard owns `partition`
+    pub fn owns(&self, partition: i32) -> bool {
+        self.total_shards > 0
+            && partition >= 0
+            && partition as u32 % self.total_shards == self.shard_id
+    }
+}
+
+/// Policy for decoding Kafka header values into strings
+///
+/// UTF-8 values are always kept as-is; the policy only decides what happens
+/// to binary values. Under `Base64` a header's string form no longer tells
+/// you whether it was binary, so producers and consumers of such headers
+/// must agree on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderDecoding {
+    /// Drop headers whose value is not valid UTF-8
+    #[default]
+    Utf8Only,
+    /// Replace invalid UTF-8 sequences with U+FFFD
+    Lossy,
+    /// Base64-encode (standard alphabet, padded) values that are not valid UTF-8
+    Base64,
+}
+
+impl HeaderDecoding {
+    /// Decode a header value, or `None` if the policy drops it
+    pub fn decode(&self, value: &[u8]) -> Option<String> {
+        if let Ok(v) = std::str::from_utf8(value) {
+            return Some(v.to_string());
+        }
+        match self {
+            HeaderDecoding::Utf8Only => None,
+            HeaderDecoding::Lossy => Some(String::from_utf8_lossy(value).into_owned()),
+            HeaderDecoding::Base64 => {
+                use base64::{engine::general_purpose::STANDARD, Engine};
+                Some(STANDARD.encode(value))
+            }
+        }
+    }
+}
+
+/// Starting position for partitions without a committed offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetReset {
+    #[default]
+    Earliest,
+    Latest,
+}
+
+impl OffsetReset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OffsetReset::Earliest => "earliest",
+            OffsetReset::Latest => "latest",
+        }
+    }
+
+    /// Parse an `auto.offset.reset` value, treating anything other than
+    /// latest/largest/end as earliest
+    pub fn from_auto_offset_reset(value: &str) -> Self {
+        match value {
+            "latest" | "largest" | "end" => OffsetReset::Latest,
+            _ => OffsetReset::Earliest,
+        }
+    }
 }
 
 fn default_auto_offset_reset() -> String {
@@ -240,6 +652,10 @@ fn default_max_poll_records() -> u32 {
     500
 }
 
+fn default_shutdown_commit_timeout() -> u64 {
+    5000
+}
+
 impl Default for ConsumerConfig {
     fn default() -> Self {
         Self {
@@ -251,6 +667,12 @@ impl Default for ConsumerConfig {
             heartbeat_interval_ms: default_heartbeat_interval(),
             max_poll_interval_ms: default_max_poll_interval(),
             max_poll_records: default_max_poll_records(),
+            topic_offset_resets: HashMap::new(),
+            max_throughput_per_sec: None,
+            assignment_strategy: AssignmentStrategy::default(),
+            header_decoding: HeaderDecoding::default(),
+            shutdown_commit_timeout_ms: default_shutdown_commit_timeout(),
+            shard: None,
         }
     }
 }
@@ -267,9 +689,21 @@ pub struct TopicConfig {
     /// Topic for safety alerts
     #[serde(default = "default_alerts_topic")]
     pub alerts: String,
+    /// High-priority topic for critical alerts (None = use `alerts`)
+    #[serde(default)]
+    pub critical_alerts: Option<String>,
     /// Dead letter queue topic
     #[serde(default = "default_dlq_topic")]
     pub dead_letter_queue: String,
+    /// Create missing topics on startup (for brokers with auto.create.topics disabled)
+    #[serde(default)]
+    pub auto_create: bool,
+    /// Number of partitions for auto-created topics
+    #[serde(default = "default_topic_partitions")]
+    pub partitions: i32,
+    /// Replication factor for auto-created topics
+    #[serde(default = "default_topic_replication_factor")]
+    pub replication_factor: i32,
 }
 
 fn default_frames_topic() -> String {
@@ -288,13 +722,55 @@ fn default_dlq_topic() -> String {
     "nier.dlq".to_string()
 }
 
+fn default_topic_partitions() -> i32 {
+    6
+}
+
+fn default_topic_replication_factor() -> i32 {
+    3
+}
+
 impl Default for TopicConfig {
     fn default() -> Self {
         Self {
             frames: default_frames_topic(),
             detections: default_detections_topic(),
             alerts: default_alerts_topic(),
+            critical_alerts: None,
             dead_letter_queue: default_dlq_topic(),
+            auto_create: false,
+            partitions: default_topic_partitions(),
+            replication_factor: default_topic_replication_factor(),
+        }
+    }
+}
+
+impl TopicConfig {
+    /// Topic that alerts of the given severity are routed to
+    pub fn alert_topic(&self, severity: AlertSeverity) -> &str {
+        match (severity, &self.critical_alerts) {
+            (AlertSeverity::Critical, Some(topic)) => topic,
+            _ => &self.alerts,
+        }
+    }
+}
+
+/// Alert severity, used for topic routing and the `severity` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl AlertSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertSeverity::Info => "info",
+            AlertSeverity::Warning => "warning",
+            AlertSeverity::Critical => "critical",
         }
     }
 }
@@ -437,6 +913,18 @@ impl KafkaConfig {
         if let Some(ref password) = self.sasl.password {
             config.set("sasl.password", password);
         }
+        if let SaslMechanism::Gssapi = self.sasl.mechanism {
+            let kerberos = &self.sasl.kerberos;
+            if let Some(ref service_name) = kerberos.service_name {
+                config.set("sasl.kerberos.service.name", service_name);
+            }
+            if let Some(ref keytab) = kerberos.keytab {
+                config.set("sasl.kerberos.keytab", keytab);
+            }
+            if let Some(ref principal) = kerberos.principal {
+                config.set("sasl.kerberos.principal", principal);
+            }
+        }
 
         // Extra properties
         for (key, value) in &self.extra_properties {
@@ -446,6 +934,19 @@ impl KafkaConfig {
         config
     }
 
+    /// Whether `key` is set by this config's typed fields.
+    ///
+    /// Extra properties with these keys are overridden or conflict with the
+    /// typed settings, so builders warn when they are set directly.
+    pub fn is_managed_property(key: &str) -> bool {
+        MANAGED_PROPERTIES.contains(&key)
+    }
+
+    /// Build an admin ClientConfig
+    pub fn build_admin_config(&self) -> ClientConfig {
+        self.build_base_config()
+    }
+
     /// Build a producer ClientConfig
     pub fn build_producer_config(&self) -> ClientConfig {
         let mut config = self.build_base_config();
@@ -454,6 +955,7 @@ impl KafkaConfig {
         config.set("retries", self.reliability.retries.to_string());
         config.set("retry.backoff.ms", self.reliability.retry_backoff_ms.to_string());
         config.set("request.timeout.ms", self.reliability.request_timeout_ms.to_string());
+        config.set("message.timeout.ms", self.reliability.message_timeout_ms.to_string());
         config.set("acks", &self.reliability.acks);
 
         if self.reliability.enable_idempotence {
@@ -469,9 +971,55 @@ impl KafkaConfig {
             self.producer.max_in_flight_requests.to_string(),
         );
 
+        // Transactions require idempotent delivery acknowledged by all replicas
+        if let Some(ref transactional_id) = self.producer.transactional_id {
+            config.set("transactional.id", transactional_id);
+            config.set("enable.idempotence", "true");
+            config.set("acks", "all");
+        }
+
         config
     }
 
+    /// This configuration with producer profile `name` applied, if it exists
+    pub fn with_producer_profile(&self, name: &str) -> Option<KafkaConfig> {
+        let profile = self.producer.profiles.get(name)?;
+        let mut config = self.clone();
+        config.producer.profiles.clear();
+        config.producer.transactional_id = None;
+
+        if let Some(batch_size) = profile.batch_size {
+            config.producer.batch_size = batch_size;
+        }
+        if let Some(linger_ms) = profile.linger_ms {
+            config.producer.linger_ms = linger_ms;
+        }
+        if let Some(ref compression) = profile.compression_type {
+            config.producer.compression_type = compression.clone();
+        }
+        if let Some(max_in_flight) = profile.max_in_flight_requests {
+            config.producer.max_in_flight_requests = max_in_flight;
+        }
+        if let Some(ref acks) = profile.acks {
+            config.reliability.acks = acks.clone();
+            if !matches!(acks.as_str(), "all" | "-1") {
+                config.reliability.enable_idempotence = false;
+            }
+        }
+
+        Some(config)
+    }
+
+    /// Build a ClientConfig for the producer used by `profile` (None = default)
+    ///
+    /// Unknown profiles get the default producer settings.
+    pub fn build_profile_producer_config(&self, profile: Option<&str>) -> ClientConfig {
+        match profile.and_then(|name| self.with_producer_profile(name)) {
+            Some(config) => config.build_producer_config(),
+            None => self.build_producer_config(),
+        }
+    }
+
     /// Build a consumer ClientConfig
     pub fn build_consumer_config(&self) -> ClientConfig {
         let mut config = self.build_base_config();
@@ -499,6 +1047,10 @@ impl KafkaConfig {
             "max.poll.interval.ms",
             self.consumer.max_poll_interval_ms.to_string(),
         );
+        config.set(
+            "partition.assignment.strategy",
+            self.consumer.assignment_strategy.as_str(),
+        );
 
         config
     }
@@ -508,6 +1060,42 @@ impl KafkaConfig {
         Duration::from_millis(self.reliability.request_timeout_ms)
     }
 
+    /// Get the producer delivery (message) timeout as Duration
+    pub fn message_timeout(&self) -> Duration {
+        Duration::from_millis(self.reliability.message_timeout_ms)
+    }
+
+    /// Get the per-attempt connectivity probe timeout, or None when the probe
+    /// is off
+    pub fn connect_probe_timeout(&self) -> Option<Duration> {
+        match self.reliability.connect_probe_timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// Reject producer settings that can reorder messages for the same key
+    fn validate_producer_ordering(&self, key: &str) -> Result<(), ConfigError> {
+        if self.producer.max_in_flight_requests > 1
+            && self.reliability.retries > 0
+            && !self.reliability.enable_idempotence
+        {
+            // A retried batch can land after a later one, reordering messages
+            // for the same key
+            return Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                message: format!(
+                    "idempotence must be enabled when retries > 0 and \
+                     max_in_flight_requests ({}) > 1, otherwise per-key ordering is not \
+                     guaranteed; enable idempotence (acks = all) or set \
+                     max_in_flight_requests = 1",
+                    self.producer.max_in_flight_requests
+                ),
+            });
+        }
+        Ok(())
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<(), ConfigError> {
         if self.bootstrap_servers.is_empty() {
@@ -522,10 +1110,13 @@ impl KafkaConfig {
             ));
         }
 
-        // Validate SASL config if using SASL
+        // Validate SASL config if using SASL; Kerberos authenticates by
+        // principal rather than username
         match self.security_protocol {
             SecurityProtocol::SaslPlaintext | SecurityProtocol::SaslSsl => {
-                if self.sasl.username.is_none() {
+                if self.sasl.username.is_none()
+                    && !matches!(self.sasl.mechanism, SaslMechanism::Gssapi)
+                {
                     return Err(ConfigError::MissingRequired(
                         "sasl.username (required for SASL)".to_string(),
                     ));
@@ -534,6 +1125,40 @@ impl KafkaConfig {
             _ => {}
         }
 
+        self.validate_producer_ordering("reliability.enable_idempotence")?;
+        for name in self.producer.profiles.keys() {
+            if let Some(profile) = self.with_producer_profile(name) {
+                profile.validate_producer_ordering(&format!("producer.profiles.{}", name))?;
+            }
+        }
+
+        if let Some(shard) = self.consumer.shard {
+            if shard.total_shards == 0 || shard.shard_id >= shard.total_shards {
+                return Err(ConfigError::InvalidValue {
+                    key: "consumer.shard".to_string(),
+                    message: format!(
+                        "shard_id ({}) must be less than total_shards ({})",
+                        shard.shard_id, shard.total_shards
+                    ),
+                });
+            }
+        }
+
+        if self.topics.auto_create {
+            if self.topics.partitions < 1 {
+                return Err(ConfigError::InvalidValue {
+                    key: "topics.partitions".to_string(),
+                    message: "must be at least 1".to_string(),
+                });
+            }
+            if self.topics.replication_factor < 1 {
+                return Err(ConfigError::InvalidValue {
+                    key: "topics.replication_factor".to_string(),
+                    message: "must be at least 1".to_string(),
+                });
+            }
+        }
+
         Ok(())
     }
 }
@@ -558,6 +1183,79 @@ mod tests {
         // Verify key settings are present
         assert!(producer_config.get("bootstrap.servers").is_some());
         assert!(producer_config.get("acks").is_some());
+        assert_eq!(producer_config.get("message.timeout.ms"), Some("300000"));
+        assert!(producer_config.get("transactional.id").is_none());
+    }
+
+    #[test]
+    fn test_transactional_producer_config_forces_acks_all() {
+        let mut config = KafkaConfig::new("localhost:9092");
+        config.reliability.acks = "1".to_string();
+        config.reliability.enable_idempotence = false;
+        config.producer.transactional_id = Some("alert-enricher-0".to_string());
+
+        let producer_config = config.build_producer_config();
+        assert_eq!(
+            producer_config.get("transactional.id"),
+            Some("alert-enricher-0")
+        );
+        assert_eq!(producer_config.get("enable.idempotence"), Some("true"));
+        assert_eq!(producer_config.get("acks"), Some("all"));
+    }
+
+    #[test]
+    fn test_gssapi_sets_kerberos_properties() {
+        let mut config = KafkaConfig::new("localhost:9092");
+        config.security_protocol = SecurityProtocol::SaslSsl;
+        config.sasl.mechanism = SaslMechanism::Gssapi;
+        config.sasl.kerberos = KerberosConfig {
+            service_name: Some("kafka".to_string()),
+            keytab: Some("/etc/security/nier.keytab".to_string()),
+            principal: Some("nier@EXAMPLE.COM".to_string()),
+        };
+        assert!(config.validate().is_ok());
+
+        let client_config = config.build_producer_config();
+        assert_eq!(client_config.get("sasl.mechanism"), Some("GSSAPI"));
+        assert_eq!(client_config.get("sasl.kerberos.service.name"), Some("kafka"));
+        assert_eq!(
+            client_config.get("sasl.kerberos.keytab"),
+            Some("/etc/security/nier.keytab")
+        );
+        assert_eq!(
+            client_config.get("sasl.kerberos.principal"),
+            Some("nier@EXAMPLE.COM")
+        );
+
+        // Kerberos settings are ignored for other mechanisms
+        config.sasl.mechanism = SaslMechanism::Plain;
+        let client_config = config.build_consumer_config();
+        assert_eq!(client_config.get("sasl.kerberos.keytab"), None);
+    }
+
+    #[test]
+    fn test_validate_rejects_ordering_unsafe_producer() {
+        let mut config = KafkaConfig::default();
+        config.producer.max_in_flight_requests = 5;
+        config.reliability.retries = 3;
+        config.reliability.enable_idempotence = false;
+
+        match config.validate() {
+            Err(ConfigError::InvalidValue { key, .. }) => {
+                assert_eq!(key, "reliability.enable_idempotence")
+            }
+            other => panic!("expected ordering error, got {:?}", other),
+        }
+
+        // Each safe variation passes
+        config.reliability.enable_idempotence = true;
+        assert!(config.validate().is_ok());
+        config.reliability.enable_idempotence = false;
+        config.producer.max_in_flight_requests = 1;
+        assert!(config.validate().is_ok());
+        config.producer.max_in_flight_requests = 5;
+        config.reliability.retries = 0;
+        assert!(config.validate().is_ok());
     }
 
     #[test]
@@ -569,4 +1267,153 @@ mod tests {
         assert!(consumer_config.get("bootstrap.servers").is_some());
         assert!(consumer_config.get("group.id").is_some());
     }
+
+    #[test]
+    fn test_assignment_strategy_sets_client_property() {
+        let mut config = KafkaConfig::new("localhost:9092");
+        assert_eq!(
+            config
+                .build_consumer_config()
+                .get("partition.assignment.strategy"),
+            Some("range")
+        );
+
+        for (strategy, expected) in [
+            (AssignmentStrategy::RoundRobin, "roundrobin"),
+            (AssignmentStrategy::CooperativeSticky, "cooperative-sticky"),
+        ] {
+            config.consumer.assignment_strategy = strategy;
+            assert_eq!(
+                config
+                    .build_consumer_config()
+                    .get("partition.assignment.strategy"),
+                Some(expected)
+            );
+        }
+
+        let parsed: AssignmentStrategy = serde_json::from_str("\"cooperative-sticky\"").unwrap();
+        assert_eq!(parsed, AssignmentStrategy::CooperativeSticky);
+        assert!(parsed.is_incremental());
+    }
+
+    #[test]
+    fn test_client_creation_error_from_code() {
+        assert!(matches!(
+            ClientCreationError::classify(Some(RDKafkaErrorCode::AllBrokersDown), "down"),
+            ClientCreationError::BrokerUnreachable(_)
+        ));
+        assert!(matches!(
+            ClientCreationError::classify(
+                Some(RDKafkaErrorCode::SaslAuthenticationFailed),
+                "bad credentials"
+            ),
+            ClientCreationError::AuthenticationFailed(_)
+        ));
+        assert!(matches!(
+            ClientCreationError::classify(Some(RDKafkaErrorCode::InvalidArgument), "bad arg"),
+            ClientCreationError::ConfigError(_)
+        ));
+    }
+
+    #[test]
+    fn test_probe_error_classification() {
+        let timeout = Duration::from_secs(10);
+        assert!(matches!(
+            probe_error(
+                KafkaError::MetadataFetch(RDKafkaErrorCode::OperationTimedOut),
+                timeout
+            ),
+            ClientCreationError::BrokerUnreachable(_)
+        ));
+        assert!(matches!(
+            probe_error(
+                KafkaError::MetadataFetch(RDKafkaErrorCode::SaslAuthenticationFailed),
+                timeout
+            ),
+            ClientCreationError::AuthenticationFailed(_)
+        ));
+        assert_eq!(
+            KafkaConfig::new("localhost:9092").connect_probe_timeout(),
+            Some(timeout)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_cluster_retries_only_unreachable_brokers() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+
+        let policy = BackoffPolicy::new(5, Duration::from_millis(100));
+
+        // The broker comes up on the third attempt
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let result = wait_for_cluster(
+            move || match counter.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(ClientCreationError::BrokerUnreachable("down".to_string())),
+                _ => Ok(()),
+            },
+            &policy,
+        )
+        .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Bad credentials will not fix themselves
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let result = wait_for_cluster(
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Err(ClientCreationError::AuthenticationFailed("denied".to_string()))
+            },
+            &policy,
+        )
+        .await;
+        assert!(matches!(result, Err(ClientCreationError::AuthenticationFailed(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // A cluster that never answers is reported once attempts run out
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let result = wait_for_cluster(
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Err(ClientCreationError::BrokerUnreachable("down".to_string()))
+            },
+            &policy,
+        )
+        .await;
+        assert!(matches!(result, Err(ClientCreationError::BrokerUnreachable(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_client_creation_error_from_message() {
+        assert!(matches!(
+            ClientCreationError::classify(
+                None,
+                "kafka:9092/bootstrap: Failed to resolve 'kafka:9092': Name or service not known"
+            ),
+            ClientCreationError::BrokerUnreachable(_)
+        ));
+        assert!(matches!(
+            ClientCreationError::classify(
+                None,
+                "SASL authentication error: Authentication failed during authentication due to invalid credentials"
+            ),
+            ClientCreationError::AuthenticationFailed(_)
+        ));
+        assert!(matches!(
+            ClientCreationError::classify(
+                None,
+                "No such configuration property: \"bootstrap.server\""
+            ),
+            ClientCreationError::ConfigError(_)
+        ));
+        assert!(matches!(
+            ClientCreationError::classify(None, "something else"),
+            ClientCreationError::Other(_)
+        ));
+    }
 }
@@ -3,15 +3,25 @@
 //! This module provides a high-level, type-safe interface for consuming messages
 //! from Kafka topics with support for protobuf deserialization and reliable processing.
 
-use crate::config::KafkaConfig;
-use crate::producer::{NierProducer, ProducerError};
+use crate::config::{
+    probe_cluster, wait_for_cluster, AssignmentStrategy, ClientCreationError, HeaderDecoding,
+    KafkaConfig, OffsetReset, ShardSpec, TopicConfig,
+};
+use crate::producer::{Format, NierProducer, ProducerError};
+use crate::transform::PayloadTransform;
+use futures::FutureExt;
 use prost::Message;
-use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::consumer::{
+    BaseConsumer, Consumer, ConsumerContext, ConsumerGroupMetadata, Rebalance, StreamConsumer,
+};
 use rdkafka::message::{Headers, Message as KafkaMessage};
-use rdkafka::TopicPartitionList;
+use rdkafka::{ClientContext, Offset, Timestamp, TopicPartitionList};
+use std::any::Any;
 use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tokio::sync::broadcast;
 use tracing::{debug, error, info, instrument, warn};
@@ -20,7 +30,7 @@ use tracing::{debug, error, info, instrument, warn};
 #[derive(Error, Debug)]
 pub enum ConsumerError {
     #[error("Failed to create consumer: {0}")]
-    CreationError(String),
+    CreationError(ClientCreationError),
 
     #[error("Failed to subscribe to topics: {0}")]
     SubscriptionError(String),
@@ -37,10 +47,44 @@ pub enum ConsumerError {
     #[error("Message processing error: {0}")]
     ProcessingError(String),
 
+    /// The handler panicked; the message is treated as failed and the
+    /// consumer keeps running
+    #[error("Message handler panicked: {0}")]
+    HandlerPanic(String),
+
+    #[error("Failed to transform payload: {0}")]
+    TransformError(String),
+
     #[error("Consumer shutdown")]
     Shutdown,
 }
 
+/// How far one assigned partition is behind its high watermark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionLag {
+    pub topic: String,
+    pub partition: i32,
+    /// Offset of the next message to consume (None = nothing consumed yet)
+    pub current_offset: Option<i64>,
+    /// Offset the next produced message will get
+    pub high_watermark: i64,
+    /// Messages not yet consumed. Without a position this is every message
+    /// still retained in the partition.
+    pub lag: i64,
+}
+
+impl PartitionLag {
+    /// Report this lag as the `nier.consumer.lag` gauge
+    pub fn record(&self) {
+        metrics::gauge!(
+            "nier.consumer.lag",
+            "topic" => self.topic.clone(),
+            "partition" => self.partition.to_string()
+        )
+        .set(self.lag as f64);
+    }
+}
+
 /// Metadata about a received message
 #[derive(Debug, Clone)]
 pub struct MessageMetadata {
@@ -54,10 +98,32 @@ pub struct MessageMetadata {
     pub key: Option<Vec<u8>>,
     /// Timestamp of the message
     pub timestamp: Option<i64>,
+    /// What `timestamp` records (None if the message has no timestamp)
+    pub timestamp_type: Option<TimestampType>,
     /// Message headers
     pub headers: HashMap<String, String>,
 }
 
+/// Kind of Kafka message timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampType {
+    /// Set by the producer when the message was created
+    CreateTime,
+    /// Set by the broker when it appended the message to the log
+    LogAppendTime,
+}
+
+impl TimestampType {
+    /// Split an rdkafka timestamp into millis and type
+    fn split(timestamp: Timestamp) -> (Option<i64>, Option<Self>) {
+        match timestamp {
+            Timestamp::NotAvailable => (None, None),
+            Timestamp::CreateTime(millis) => (Some(millis), Some(Self::CreateTime)),
+            Timestamp::LogAppendTime(millis) => (Some(millis), Some(Self::LogAppendTime)),
+        }
+    }
+}
+
 /// A received message with payload and metadata
 #[derive(Debug, Clone)]
 pub struct IncomingMessage {
@@ -80,6 +146,28 @@ impl IncomingMessage {
             .map_err(|e| ConsumerError::DeserializationError(e.to_string()))
     }
 
+    /// Deserialize the payload in the format named by its `content-type` header
+    ///
+    /// The counterpart of `NierProducer::send_typed`. Messages without the
+    /// header are decoded as protobuf, which producers sent before the header
+    /// existed.
+    pub fn decode_typed<T>(&self) -> Result<T, ConsumerError>
+    where
+        T: Message + Default + serde::de::DeserializeOwned,
+    {
+        let Some(content_type) = self.header("content-type") else {
+            return self.decode_proto();
+        };
+        match Format::from_content_type(content_type) {
+            Some(Format::Proto) => self.decode_proto(),
+            Some(Format::Json) => self.decode_json(),
+            None => Err(ConsumerError::DeserializationError(format!(
+                "Unsupported content-type: {}",
+                content_type
+            ))),
+        }
+    }
+
     /// Get the message key as a string
     pub fn key_str(&self) -> Option<String> {
         self.metadata
@@ -102,6 +190,42 @@ impl IncomingMessage {
     pub fn message_type(&self) -> Option<&str> {
         self.header("message-type")
     }
+
+    /// Get the alert severity header
+    pub fn severity(&self) -> Option<&str> {
+        self.header("severity")
+    }
+
+    /// Time elapsed since the message's Kafka timestamp
+    ///
+    /// Measured from CreateTime when the producer set it. On topics using
+    /// LogAppendTime this is the time since broker receipt, which leaves out
+    /// anything before the produce; check `timestamp_type` or use
+    /// `created_at` where that matters.
+    ///
+    /// Returns `None` if the message has no timestamp. Timestamps in the future
+    /// (producer clock skew) yield a zero age.
+    pub fn age(&self) -> Option<Duration> {
+        self.age_at(SystemTime::now())
+    }
+
+    /// When the producer created the message, if it carries a CreateTime
+    pub fn created_at(&self) -> Option<SystemTime> {
+        match self.metadata.timestamp_type? {
+            TimestampType::CreateTime => {
+                let millis = u64::try_from(self.metadata.timestamp?).ok()?;
+                Some(UNIX_EPOCH + Duration::from_millis(millis))
+            }
+            TimestampType::LogAppendTime => None,
+        }
+    }
+
+    /// Time elapsed between the message's Kafka timestamp and `now`
+    fn age_at(&self, now: SystemTime) -> Option<Duration> {
+        let millis = u64::try_from(self.metadata.timestamp?).ok()?;
+        let sent_at = UNIX_EPOCH + Duration::from_millis(millis);
+        Some(now.duration_since(sent_at).unwrap_or(Duration::ZERO))
+    }
 }
 
 /// Handler trait for processing messages
@@ -149,12 +273,57 @@ where
     }
 }
 
+/// Consumer context that pauses assigned partitions outside the shard
+///
+/// With cooperative rebalancing an assignment only lists the partitions
+/// being added, so the ones already held keep their paused state.
+pub struct ShardingContext {
+    shard: Option<ShardSpec>,
+}
+
+impl ClientContext for ShardingContext {}
+
+impl ConsumerContext for ShardingContext {
+    fn post_rebalance(&self, base_consumer: &BaseConsumer<Self>, rebalance: &Rebalance<'_>) {
+        let (Some(shard), Rebalance::Assign(assigned)) = (self.shard, rebalance) else {
+            return;
+        };
+
+        let foreign = partitions_outside_shard(assigned, shard);
+        if foreign.count() == 0 {
+            return;
+        }
+        info!(
+            "Shard {}/{}: pausing {} assigned partition(s) owned by other shards",
+            shard.shard_id,
+            shard.total_shards,
+            foreign.count()
+        );
+        if let Err(e) = base_consumer.pause(&foreign) {
+            error!("Failed to pause partitions outside shard: {}", e);
+        }
+    }
+}
+
+/// Partitions in `assigned` that `shard` does not own
+fn partitions_outside_shard(assigned: &TopicPartitionList, shard: ShardSpec) -> TopicPartitionList {
+    let mut foreign = TopicPartitionList::new();
+    for element in assigned.elements() {
+        if !shard.owns(element.partition()) {
+            foreign.add_partition(element.topic(), element.partition());
+        }
+    }
+    foreign
+}
+
 /// High-level Kafka consumer wrapper
 pub struct NierConsumer {
-    consumer: StreamConsumer,
+    consumer: Arc<StreamConsumer<ShardingContext>>,
     config: Arc<KafkaConfig>,
     shutdown_tx: broadcast::Sender<()>,
     dlq_producer: Option<Arc<NierProducer>>,
+    /// Applied to every received payload before it reaches the handler
+    payload_transform: Option<Arc<dyn PayloadTransform>>,
 }
 
 impl NierConsumer {
@@ -165,18 +334,37 @@ impl NierConsumer {
             config.bootstrap_servers, config.consumer.group_id
         );
 
+        if let Some(shard) = config.consumer.shard {
+            if shard.total_shards == 0 || shard.shard_id >= shard.total_shards {
+                return Err(ConsumerError::CreationError(
+                    ClientCreationError::ConfigError(format!(
+                        "shard_id ({}) must be less than total_shards ({})",
+                        shard.shard_id, shard.total_shards
+                    )),
+                ));
+            }
+            info!(
+                "Consuming shard {} of {} (partition % {} == {})",
+                shard.shard_id, shard.total_shards, shard.total_shards, shard.shard_id
+            );
+        }
+
         let consumer_config = config.build_consumer_config();
-        let consumer: StreamConsumer = consumer_config
-            .create()
-            .map_err(|e| ConsumerError::CreationError(e.to_string()))?;
+        let context = ShardingContext {
+            shard: config.consumer.shard,
+        };
+        let consumer: StreamConsumer<ShardingContext> = consumer_config
+            .create_with_context(context)
+            .map_err(|e| ConsumerError::CreationError(e.into()))?;
 
         let (shutdown_tx, _) = broadcast::channel(1);
 
         Ok(Self {
-            consumer,
+            consumer: Arc::new(consumer),
             config: Arc::new(config),
             shutdown_tx,
             dlq_producer: None,
+            payload_transform: None,
         })
     }
 
@@ -186,11 +374,39 @@ impl NierConsumer {
         self
     }
 
+    /// Decode payloads (e.g. decrypt) before handlers receive them
+    ///
+    /// Use the transform the producers encoded with. Messages that fail to
+    /// decode are reported to `on_error` and sent to the DLQ as received.
+    pub fn with_payload_transform(mut self, transform: Arc<dyn PayloadTransform>) -> Self {
+        self.payload_transform = Some(transform);
+        self
+    }
+
     /// Get the configuration
     pub fn config(&self) -> &KafkaConfig {
         &self.config
     }
 
+    /// Check that the cluster answers, retrying with backoff while no broker
+    /// is reachable
+    ///
+    /// Creating the consumer does not contact a broker, so call this at
+    /// startup to surface an unreachable cluster or rejected credentials.
+    /// Each attempt waits up to `reliability.connect_probe_timeout_ms`.
+    pub async fn check_connectivity(&self) -> Result<(), ConsumerError> {
+        let Some(timeout) = self.config.connect_probe_timeout() else {
+            return Ok(());
+        };
+        let consumer = self.consumer.clone();
+        wait_for_cluster(
+            move || probe_cluster(consumer.client(), timeout),
+            &self.config.reliability.backoff_policy(),
+        )
+        .await
+        .map_err(ConsumerError::CreationError)
+    }
+
     /// Subscribe to the specified topics
     pub fn subscribe(&self, topics: &[&str]) -> Result<(), ConsumerError> {
         info!("Subscribing to topics: {:?}", topics);
@@ -201,12 +417,7 @@ impl NierConsumer {
 
     /// Subscribe to all Nier pipeline topics
     pub fn subscribe_all(&self) -> Result<(), ConsumerError> {
-        let topics = [
-            self.config.topics.frames.as_str(),
-            self.config.topics.detections.as_str(),
-            self.config.topics.alerts.as_str(),
-        ];
-        self.subscribe(&topics)
+        self.subscribe(&pipeline_topics(&self.config.topics))
     }
 
     /// Subscribe to frame metadata topic
@@ -219,9 +430,86 @@ impl NierConsumer {
         self.subscribe(&[self.config.topics.detections.as_str()])
     }
 
-    /// Subscribe to alerts topic
+    /// Subscribe to alerts topic (and the critical alerts topic, if configured)
     pub fn subscribe_alerts(&self) -> Result<(), ConsumerError> {
-        self.subscribe(&[self.config.topics.alerts.as_str()])
+        self.subscribe(&self.alert_topics())
+    }
+
+    /// Alert topics for all severities
+    fn alert_topics(&self) -> Vec<&str> {
+        alert_topics(&self.config.topics)
+    }
+
+    /// Manually assign all partitions of `topics`, starting each topic at its
+    /// configured `topic_offset_resets` position.
+    ///
+    /// Partitions with a committed offset for the group resume from it; the
+    /// per-topic reset (falling back to `auto_offset_reset`) only applies to
+    /// partitions without one. Manual assignment bypasses group rebalancing.
+    pub fn assign_with_offset_resets(&self, topics: &[&str]) -> Result<(), ConsumerError> {
+        let timeout = self.config.request_timeout();
+
+        let mut partitions = Vec::new();
+        for topic in topics {
+            let metadata = self
+                .consumer
+                .fetch_metadata(Some(topic), timeout)
+                .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))?;
+
+            for t in metadata.topics() {
+                if let Some(err) = t.error() {
+                    return Err(ConsumerError::SubscriptionError(format!(
+                        "metadata error for topic {}: {:?}",
+                        t.name(),
+                        err
+                    )));
+                }
+                for p in t.partitions() {
+                    partitions.push((t.name().to_string(), p.id()));
+                }
+            }
+        }
+
+        let mut lookup = TopicPartitionList::new();
+        for (topic, partition) in &partitions {
+            lookup.add_partition(topic, *partition);
+        }
+        let committed: HashMap<(String, i32), i64> = self
+            .consumer
+            .committed_offsets(lookup, timeout)
+            .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))?
+            .elements()
+            .iter()
+            .filter_map(|e| match e.offset() {
+                Offset::Offset(offset) => Some(((e.topic().to_string(), e.partition()), offset)),
+                _ => None,
+            })
+            .collect();
+
+        let positions = resolve_start_offsets(
+            &partitions,
+            &committed,
+            &self.config.consumer.topic_offset_resets,
+            OffsetReset::from_auto_offset_reset(&self.config.consumer.auto_offset_reset),
+        );
+
+        let mut assignment = TopicPartitionList::new();
+        for (topic, partition, offset) in positions {
+            debug!("Assigning {}[{}] at {:?}", topic, partition, offset);
+            assignment
+                .add_partition_offset(&topic, partition, offset)
+                .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))?;
+        }
+
+        info!("Manually assigning topics: {:?}", topics);
+        self.consumer
+            .assign(&assignment)
+            .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))
+    }
+
+    /// Manually assign all Nier pipeline topics with per-topic offset resets
+    pub fn assign_all_with_offset_resets(&self) -> Result<(), ConsumerError> {
+        self.assign_with_offset_resets(&pipeline_topics(&self.config.topics))
     }
 
     /// Commit the current offsets synchronously
@@ -241,6 +529,58 @@ impl NierConsumer {
         }
     }
 
+    /// Commit past one handled message, leaving other partitions alone
+    ///
+    /// Committing the whole consumer state would also cover messages that
+    /// were delivered but failed, or are still being handled.
+    fn commit_handled(&self, metadata: &MessageMetadata) {
+        let handled = [(metadata.topic.as_str(), metadata.partition, metadata.offset)];
+        let result = commit_offsets(handled).and_then(|offsets| {
+            self.consumer
+                .commit(&offsets, rdkafka::consumer::CommitMode::Async)
+                .map_err(|e| ConsumerError::CommitError(e.to_string()))
+        });
+        if let Err(e) = result {
+            warn!(
+                "Failed to commit offset for {}[{}]@{}: {}",
+                metadata.topic, metadata.partition, metadata.offset, e
+            );
+        }
+    }
+
+    /// Commit past the last handled message of each partition on shutdown,
+    /// giving up after the configured timeout
+    ///
+    /// A synchronous commit blocks until the broker answers, so an
+    /// unreachable broker would otherwise hang shutdown.
+    async fn commit_on_shutdown(&self, handled: &HashMap<(String, i32), i64>) {
+        if handled.is_empty() {
+            return;
+        }
+        let offsets = match commit_offsets(
+            handled
+                .iter()
+                .map(|((topic, partition), offset)| (topic.as_str(), *partition, *offset)),
+        ) {
+            Ok(offsets) => offsets,
+            Err(e) => {
+                warn!("Failed to commit on shutdown: {}", e);
+                return;
+            }
+        };
+        let consumer = self.consumer.clone();
+        let commit = move || {
+            consumer
+                .commit(&offsets, rdkafka::consumer::CommitMode::Sync)
+                .map_err(|e| ConsumerError::CommitError(e.to_string()))
+        };
+
+        let timeout = self.config.consumer.shutdown_commit_timeout();
+        if let Err(e) = commit_with_timeout(commit, timeout).await {
+            warn!("Failed to commit on shutdown: {}", e);
+        }
+    }
+
     /// Get a shutdown receiver
     pub fn shutdown_receiver(&self) -> broadcast::Receiver<()> {
         self.shutdown_tx.subscribe()
@@ -261,6 +601,9 @@ impl NierConsumer {
         let mut shutdown_rx = self.shutdown_receiver();
         let stream = self.consumer.stream();
         tokio::pin!(stream);
+        let mut throttle = self.throttle();
+        // Last handled offset per partition, for the final commit
+        let mut handled_offsets = HashMap::new();
 
         info!("Starting message consumption loop");
 
@@ -273,7 +616,19 @@ impl NierConsumer {
                 message_result = stream.next() => {
                     match message_result {
                         Some(Ok(borrowed_message)) => {
-                            let incoming = self.convert_message(&borrowed_message);
+                            let incoming = self.convert(&borrowed_message);
+
+                            if let Some(age) = incoming.age() {
+                                let timestamp_type = match incoming.metadata.timestamp_type {
+                                    Some(TimestampType::LogAppendTime) => "log_append",
+                                    _ => "create",
+                                };
+                                metrics::histogram!(
+                                    "nier.consumer.message_age_seconds",
+                                    "timestamp_type" => timestamp_type
+                                )
+                                .record(age.as_secs_f64());
+                            }
 
                             debug!(
                                 "Received message from topic={}, partition={}, offset={}",
@@ -282,30 +637,24 @@ impl NierConsumer {
                                 incoming.metadata.offset
                             );
 
-                            match handler.handle(incoming.clone()).await {
-                                Ok(()) => {
-                                    if !self.config.consumer.enable_auto_commit {
-                                        self.commit_async();
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Message processing failed: {}", e);
-                                    handler.on_error(incoming.clone(), e).await;
-
-                                    // Send to DLQ if configured
-                                    if let Some(ref dlq) = self.dlq_producer {
-                                        if let Err(dlq_err) = dlq
-                                            .send_to_dlq(
-                                                &incoming.metadata.topic,
-                                                &incoming.payload,
-                                                "Processing failed",
-                                            )
-                                            .await
-                                        {
-                                            error!("Failed to send to DLQ: {}", dlq_err);
-                                        }
-                                    }
-                                }
+                            if let Some(ref mut throttle) = throttle {
+                                throttle.acquire().await;
+                            }
+
+                            let metadata = incoming.metadata.clone();
+                            let handled = dispatch(
+                                handler.as_ref(),
+                                incoming,
+                                self.payload_transform.as_deref(),
+                                self.dlq_producer.as_deref(),
+                            )
+                            .await;
+                            if handled && !self.config.consumer.enable_auto_commit {
+                                self.commit_handled(&metadata);
+                                handled_offsets.insert(
+                                    (metadata.topic, metadata.partition),
+                                    metadata.offset,
+                                );
                             }
                         }
                         Some(Err(e)) => {
@@ -322,9 +671,7 @@ impl NierConsumer {
 
         // Final commit before shutdown
         if !self.config.consumer.enable_auto_commit {
-            if let Err(e) = self.commit() {
-                warn!("Failed to commit on shutdown: {}", e);
-            }
+            self.commit_on_shutdown(&handled_offsets).await;
         }
 
         Ok(())
@@ -342,6 +689,7 @@ impl NierConsumer {
         let mut shutdown_rx = self.shutdown_receiver();
         let stream = self.consumer.stream();
         tokio::pin!(stream);
+        let mut throttle = self.throttle();
 
         loop {
             tokio::select! {
@@ -351,11 +699,22 @@ impl NierConsumer {
                 message_result = stream.next() => {
                     match message_result {
                         Some(Ok(borrowed_message)) => {
-                            let incoming = self.convert_message(&borrowed_message);
-                            if let Err(e) = callback(incoming).await {
+                            let incoming = self.convert(&borrowed_message);
+                            let metadata = incoming.metadata.clone();
+                            if let Some(ref mut throttle) = throttle {
+                                throttle.acquire().await;
+                            }
+                            let handling = async {
+                                let message = decode_payload(
+                                    self.payload_transform.as_deref(),
+                                    incoming,
+                                )?;
+                                callback(message).await
+                            };
+                            if let Err(e) = catch_handler_panic(handling).await {
                                 error!("Callback error: {}", e);
                             } else if !self.config.consumer.enable_auto_commit {
-                                self.commit_async();
+                                self.commit_handled(&metadata);
                             }
                         }
                         Some(Err(e)) => {
@@ -370,22 +729,179 @@ impl NierConsumer {
         Ok(())
     }
 
+    /// Fetch at most one message, waiting up to `timeout`.
+    ///
+    /// Returns `Ok(None)` if no message arrived in time. Offsets are not
+    /// committed; intended for tests and one-shot tools.
+    pub async fn poll_one(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<IncomingMessage>, ConsumerError> {
+        match tokio::time::timeout(timeout, self.consumer.recv()).await {
+            Ok(Ok(message)) => self.receive(&message).map(Some),
+            Ok(Err(e)) => Err(ConsumerError::PollError(e.to_string())),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Read the last `n` messages of every partition of `topic`.
+    ///
+    /// Seeks each partition to `high - n` (or its low watermark, if it holds
+    /// fewer than `n` messages) and collects until every partition reaches the
+    /// high watermark seen at the start or the request timeout elapses.
+    /// Replaces the consumer's current assignment and commits nothing; use a
+    /// dedicated consumer. Results are ordered by partition, then offset.
+    /// Messages whose payload fails to decode are logged and skipped.
+    pub async fn tail_messages(
+        &self,
+        topic: &str,
+        n: usize,
+    ) -> Result<Vec<IncomingMessage>, ConsumerError> {
+        let timeout = self.config.request_timeout();
+
+        let metadata = self
+            .consumer
+            .fetch_metadata(Some(topic), timeout)
+            .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))?;
+
+        let mut assignment = TopicPartitionList::new();
+        let mut pending: HashMap<i32, i64> = HashMap::new();
+        for t in metadata.topics() {
+            if let Some(err) = t.error() {
+                return Err(ConsumerError::SubscriptionError(format!(
+                    "metadata error for topic {}: {:?}",
+                    t.name(),
+                    err
+                )));
+            }
+            for p in t.partitions() {
+                let (low, high) = self
+                    .consumer
+                    .fetch_watermarks(topic, p.id(), timeout)
+                    .map_err(|e| ConsumerError::PollError(e.to_string()))?;
+
+                let start = tail_start_offset(low, high, n);
+                if start >= high {
+                    continue;
+                }
+                assignment
+                    .add_partition_offset(topic, p.id(), Offset::Offset(start))
+                    .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))?;
+                pending.insert(p.id(), high);
+            }
+        }
+
+        let mut messages = Vec::new();
+        if pending.is_empty() {
+            return Ok(messages);
+        }
+
+        self.consumer
+            .assign(&assignment)
+            .map_err(|e| ConsumerError::SubscriptionError(e.to_string()))?;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !pending.is_empty() {
+            let message = match tokio::time::timeout_at(deadline, self.consumer.recv()).await {
+                Ok(Ok(message)) => message,
+                Ok(Err(e)) => return Err(ConsumerError::PollError(e.to_string())),
+                Err(_) => {
+                    warn!(
+                        "Timed out tailing {}; {} partition(s) incomplete",
+                        topic,
+                        pending.len()
+                    );
+                    break;
+                }
+            };
+
+            if message.topic() != topic {
+                continue;
+            }
+            let Some(&high) = pending.get(&message.partition()) else {
+                continue;
+            };
+            if message.offset() < high {
+                // One undecodable payload shouldn't hide the rest of the tail
+                match self.receive(&message) {
+                    Ok(incoming) => messages.push(incoming),
+                    Err(e) => {
+                        warn!(
+                            "Skipping undecodable message at {}[{}]@{}: {}",
+                            topic,
+                            message.partition(),
+                            message.offset(),
+                            e
+                        );
+                        metrics::counter!("nier.consumer.tail_skipped").increment(1);
+                    }
+                }
+            }
+            if message.offset() >= high - 1 {
+                pending.remove(&message.partition());
+            }
+        }
+
+        messages.sort_by_key(|m| (m.metadata.partition, m.metadata.offset));
+        Ok(messages)
+    }
+
+    /// Rate limiter for handler invocations, if a maximum throughput is set
+    fn throttle(&self) -> Option<Throttle> {
+        self.config
+            .consumer
+            .max_throughput_per_sec
+            .filter(|&rate| rate > 0)
+            .map(|rate| {
+                info!("Throttling message delivery to {} per second", rate);
+                Throttle::new(rate)
+            })
+    }
+
+    /// Convert a message and decode its payload with the payload transform
+    fn receive<M: KafkaMessage>(&self, msg: &M) -> Result<IncomingMessage, ConsumerError> {
+        decode_payload(self.payload_transform.as_deref(), self.convert(msg))
+    }
+
+    /// Convert a Kafka message using this consumer's header decoding policy
+    fn convert<M: KafkaMessage>(&self, msg: &M) -> IncomingMessage {
+        Self::convert_message(msg, self.config.consumer.header_decoding)
+    }
+
     /// Convert a borrowed Kafka message to our IncomingMessage type
-    fn convert_message<M: KafkaMessage>(&self, msg: &M) -> IncomingMessage {
+    fn convert_message<M: KafkaMessage>(
+        msg: &M,
+        header_decoding: HeaderDecoding,
+    ) -> IncomingMessage {
         let payload = msg.payload().unwrap_or(&[]).to_vec();
         let key = msg.key().map(|k| k.to_vec());
 
         let mut headers = HashMap::new();
         if let Some(h) = msg.headers() {
             for header in h.iter() {
-                if let Some(value) = header.value {
-                    if let Ok(v) = String::from_utf8(value.to_vec()) {
+                let Some(value) = header.value else {
+                    continue;
+                };
+                match header_decoding.decode(value) {
+                    Some(v) => {
                         headers.insert(header.key.to_string(), v);
                     }
+                    None => {
+                        debug!(
+                            "Dropping non-UTF-8 header {} from {}/{}/{}",
+                            header.key,
+                            msg.topic(),
+                            msg.partition(),
+                            msg.offset()
+                        );
+                        metrics::counter!("nier.consumer.headers_dropped").increment(1);
+                    }
                 }
             }
         }
 
+        let (timestamp, timestamp_type) = TimestampType::split(msg.timestamp());
+
         IncomingMessage {
             payload,
             metadata: MessageMetadata {
@@ -393,7 +909,8 @@ impl NierConsumer {
                 partition: msg.partition(),
                 offset: msg.offset(),
                 key,
-                timestamp: msg.timestamp().to_millis(),
+                timestamp,
+                timestamp_type,
                 headers,
             },
         }
@@ -413,6 +930,38 @@ impl NierConsumer {
             .map_err(|e| ConsumerError::PollError(e.to_string()))
     }
 
+    /// Lag of every assigned partition behind its high watermark
+    ///
+    /// Queries the broker for each partition's watermarks, so this blocks for
+    /// up to the request timeout per partition; call it from a periodic task
+    /// (e.g. with `spawn_blocking`) and `record` the results.
+    pub fn lag(&self) -> Result<Vec<PartitionLag>, ConsumerError> {
+        let timeout = self.config.request_timeout();
+        let position = self.position()?;
+
+        position
+            .elements()
+            .iter()
+            .map(|elem| {
+                let watermarks = self
+                    .consumer
+                    .fetch_watermarks(elem.topic(), elem.partition(), timeout)
+                    .map_err(|e| ConsumerError::PollError(e.to_string()))?;
+                Ok(partition_lag(
+                    elem.topic(),
+                    elem.partition(),
+                    elem.offset(),
+                    watermarks,
+                ))
+            })
+            .collect()
+    }
+
+    /// Group metadata to pass to `NierProducer::send_offsets_to_transaction`
+    pub fn group_metadata(&self) -> Option<ConsumerGroupMetadata> {
+        self.consumer.group_metadata()
+    }
+
     /// Pause consumption for specific partitions
     pub fn pause(&self, partitions: &TopicPartitionList) -> Result<(), ConsumerError> {
         self.consumer
@@ -428,10 +977,226 @@ impl NierConsumer {
     }
 }
 
+/// Run `handler` on one message, handing failures to `on_error` and the DLQ
+///
+/// The payload is decoded with `transform` first. A panicking handler counts
+/// as a failed message rather than unwinding the consumer loop, so one bad
+/// message cannot stop consumption of every partition. The DLQ always gets
+/// the payload as it was received. Returns whether the message was handled
+/// successfully.
+async fn dispatch<H: MessageHandler + ?Sized>(
+    handler: &H,
+    incoming: IncomingMessage,
+    transform: Option<&dyn PayloadTransform>,
+    dlq: Option<&NierProducer>,
+) -> bool {
+    let (message, e) = match decode_payload(transform, incoming.clone()) {
+        Ok(decoded) => match catch_handler_panic(handler.handle(decoded.clone())).await {
+            Ok(()) => return true,
+            Err(e) => (decoded, e),
+        },
+        Err(e) => (incoming.clone(), e),
+    };
+
+    error!("Message processing failed: {}", e);
+    let reason = match e {
+        ConsumerError::HandlerPanic(_) => "Handler panicked",
+        ConsumerError::TransformError(_) => "Payload transform failed",
+        _ => "Processing failed",
+    };
+    handler.on_error(message, e).await;
+
+    // Send to DLQ if configured
+    if let Some(dlq) = dlq {
+        if let Err(dlq_err) = dlq
+            .send_to_dlq(&incoming.metadata.topic, &incoming.payload, reason)
+            .await
+        {
+            error!("Failed to send to DLQ: {}", dlq_err);
+        }
+    }
+
+    false
+}
+
+/// Decode a received message's payload with `transform`, if one is set
+fn decode_payload(
+    transform: Option<&dyn PayloadTransform>,
+    mut message: IncomingMessage,
+) -> Result<IncomingMessage, ConsumerError> {
+    if let Some(transform) = transform {
+        message.payload = transform
+            .decode(&message.metadata.topic, message.payload)
+            .map_err(|e| ConsumerError::TransformError(e.to_string()))?;
+    }
+    Ok(message)
+}
+
+/// Await a handler future, converting a panic into `ConsumerError::HandlerPanic`
+async fn catch_handler_panic<F>(handling: F) -> Result<(), ConsumerError>
+where
+    F: Future<Output = Result<(), ConsumerError>>,
+{
+    match AssertUnwindSafe(handling).catch_unwind().await {
+        Ok(result) => result,
+        Err(panic) => {
+            metrics::counter!("nier.consumer.handler_panics").increment(1);
+            Err(ConsumerError::HandlerPanic(panic_message(panic.as_ref())))
+        }
+    }
+}
+
+/// Text of a panic payload (`panic!` with a message, or `.unwrap()`)
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// Paces message delivery to a fixed maximum rate
+struct Throttle {
+    interval: Duration,
+    next: tokio::time::Instant,
+}
+
+impl Throttle {
+    fn new(per_sec: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_sec,
+            next: tokio::time::Instant::now(),
+        }
+    }
+
+    /// Wait until the next delivery slot
+    async fn acquire(&mut self) {
+        let now = tokio::time::Instant::now();
+        if self.next > now {
+            tokio::time::sleep_until(self.next).await;
+        }
+        // Idle time does not bank up a burst
+        self.next = self.next.max(now) + self.interval;
+    }
+}
+
+/// Alert topics for all severities
+fn alert_topics(topics: &TopicConfig) -> Vec<&str> {
+    let mut alerts = vec![topics.alerts.as_str()];
+    if let Some(ref critical) = topics.critical_alerts {
+        alerts.push(critical.as_str());
+    }
+    alerts
+}
+
+/// Every topic the pipeline consumes: frames, detections and all alert topics
+fn pipeline_topics(topics: &TopicConfig) -> Vec<&str> {
+    let mut all = vec![topics.frames.as_str(), topics.detections.as_str()];
+    all.extend(alert_topics(topics));
+    all
+}
+
+/// Resolve the starting offset for each (topic, partition) in manual assignment mode
+fn resolve_start_offsets(
+    partitions: &[(String, i32)],
+    committed: &HashMap<(String, i32), i64>,
+    resets: &HashMap<String, OffsetReset>,
+    default_reset: OffsetReset,
+) -> Vec<(String, i32, Offset)> {
+    partitions
+        .iter()
+        .map(|(topic, partition)| {
+            let offset = match committed.get(&(topic.clone(), *partition)) {
+                Some(&offset) => Offset::Offset(offset),
+                None => match resets.get(topic).copied().unwrap_or(default_reset) {
+                    OffsetReset::Earliest => Offset::Beginning,
+                    OffsetReset::Latest => Offset::End,
+                },
+            };
+            (topic.clone(), *partition, offset)
+        })
+        .collect()
+}
+
+/// First offset to read to get the last `n` messages of a partition with
+/// watermarks `low..high`, clamped to `low` for partitions with fewer messages
+fn tail_start_offset(low: i64, high: i64, n: usize) -> i64 {
+    high.saturating_sub(n as i64).max(low)
+}
+
+/// Lag of a partition consumed up to `position`, given its (low, high)
+/// watermarks
+fn partition_lag(
+    topic: &str,
+    partition: i32,
+    position: Offset,
+    watermarks: (i64, i64),
+) -> PartitionLag {
+    let (low, high) = watermarks;
+    let current_offset = match position {
+        Offset::Offset(offset) => Some(offset),
+        _ => None,
+    };
+    PartitionLag {
+        topic: topic.to_string(),
+        partition,
+        current_offset,
+        high_watermark: high,
+        lag: (high - current_offset.unwrap_or(low)).max(0),
+    }
+}
+
+/// Offsets to commit for handled messages, given as (topic, partition, offset)
+///
+/// Kafka commits the position of the next message to read, so each handled
+/// offset is committed as `offset + 1`.
+fn commit_offsets<'a>(
+    handled: impl IntoIterator<Item = (&'a str, i32, i64)>,
+) -> Result<TopicPartitionList, ConsumerError> {
+    let mut offsets = TopicPartitionList::new();
+    for (topic, partition, offset) in handled {
+        offsets
+            .add_partition_offset(topic, partition, Offset::Offset(offset + 1))
+            .map_err(|e| ConsumerError::CommitError(e.to_string()))?;
+    }
+    Ok(offsets)
+}
+
+/// Run a blocking commit on its own thread, waiting at most `timeout`
+///
+/// The thread is detached rather than joined, so a commit that never
+/// returns cannot keep the process from exiting.
+async fn commit_with_timeout<F>(commit: F, timeout: Duration) -> Result<(), ConsumerError>
+where
+    F: FnOnce() -> Result<(), ConsumerError> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::Builder::new()
+        .name("kafka-shutdown-commit".to_string())
+        .spawn(move || {
+            let _ = tx.send(commit());
+        })
+        .map_err(|e| ConsumerError::CommitError(e.to_string()))?;
+
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(ConsumerError::CommitError(
+            "commit thread exited without a result".to_string(),
+        )),
+        Err(_) => Err(ConsumerError::CommitError(format!(
+            "commit abandoned after {:?}",
+            timeout
+        ))),
+    }
+}
+
 /// Builder for creating consumers with custom settings
 pub struct ConsumerBuilder {
     config: KafkaConfig,
     dlq_producer: Option<Arc<NierProducer>>,
+    payload_transform: Option<Arc<dyn PayloadTransform>>,
 }
 
 impl ConsumerBuilder {
@@ -440,6 +1205,7 @@ impl ConsumerBuilder {
         Self {
             config: KafkaConfig::new(bootstrap_servers),
             dlq_producer: None,
+            payload_transform: None,
         }
     }
 
@@ -461,6 +1227,15 @@ impl ConsumerBuilder {
         self
     }
 
+    /// Set the starting position for a topic in manual assignment mode
+    pub fn topic_offset_reset(mut self, topic: impl Into<String>, reset: OffsetReset) -> Self {
+        self.config
+            .consumer
+            .topic_offset_resets
+            .insert(topic.into(), reset);
+        self
+    }
+
     /// Enable or disable auto commit
     pub fn enable_auto_commit(mut self, enable: bool) -> Self {
         self.config.consumer.enable_auto_commit = enable;
@@ -473,12 +1248,77 @@ impl ConsumerBuilder {
         self
     }
 
+    /// Decode payloads before handlers receive them
+    pub fn payload_transform(mut self, transform: Arc<dyn PayloadTransform>) -> Self {
+        self.payload_transform = Some(transform);
+        self
+    }
+
+    /// Set how long the final commit on shutdown may take
+    pub fn shutdown_commit_timeout(mut self, timeout: Duration) -> Self {
+        self.config.consumer.shutdown_commit_timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    /// Set how header values that are not valid UTF-8 are decoded
+    pub fn header_decoding(mut self, decoding: HeaderDecoding) -> Self {
+        self.config.consumer.header_decoding = decoding;
+        self
+    }
+
+    /// Limit messages handed to the handler per second
+    pub fn max_throughput_per_sec(mut self, per_sec: u32) -> Self {
+        self.config.consumer.max_throughput_per_sec = Some(per_sec);
+        self
+    }
+
+    /// Set how long each `check_connectivity` attempt waits for a broker to
+    /// answer (zero skips the check)
+    pub fn connect_probe_timeout(mut self, timeout: Duration) -> Self {
+        self.config.reliability.connect_probe_timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    /// Set the partition assignment strategy used on rebalance
+    pub fn assignment_strategy(mut self, strategy: AssignmentStrategy) -> Self {
+        self.config.consumer.assignment_strategy = strategy;
+        self
+    }
+
+    /// Only consume partitions where `partition % total_shards == shard_id`
+    ///
+    /// Other assigned partitions are paused. Use a distinct group ID per
+    /// shard so every partition has an owner.
+    pub fn shard(mut self, shard_id: u32, total_shards: u32) -> Self {
+        self.config.consumer.shard = Some(ShardSpec {
+            shard_id,
+            total_shards,
+        });
+        self
+    }
+
+    /// Set an arbitrary librdkafka property
+    pub fn extra_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        if KafkaConfig::is_managed_property(&key) {
+            warn!(
+                "Extra property {} is managed by the builder and may be overridden",
+                key
+            );
+        }
+        self.config.extra_properties.insert(key, value.into());
+        self
+    }
+
     /// Build the consumer
     pub fn build(self) -> Result<NierConsumer, ConsumerError> {
         let mut consumer = NierConsumer::new(self.config)?;
         if let Some(dlq) = self.dlq_producer {
             consumer = consumer.with_dlq_producer(dlq);
         }
+        if let Some(transform) = self.payload_transform {
+            consumer = consumer.with_payload_transform(transform);
+        }
         Ok(consumer)
     }
 }
@@ -489,6 +1329,8 @@ pub use async_trait::async_trait;
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::producer::{encode_payload, OutgoingMessage, TypedPayload};
+    use crate::transform::TransformError;
 
     #[test]
     fn test_incoming_message_headers() {
@@ -504,6 +1346,7 @@ mod tests {
                 offset: 100,
                 key: Some(b"key".to_vec()),
                 timestamp: Some(1234567890),
+                timestamp_type: Some(TimestampType::CreateTime),
                 headers,
             },
         };
@@ -512,4 +1355,573 @@ mod tests {
         assert_eq!(message.message_type(), Some("detection_event"));
         assert_eq!(message.key_str(), Some("key".to_string()));
     }
+
+    #[derive(Clone, PartialEq, prost::Message, serde::Serialize, serde::Deserialize)]
+    struct TypedEvent {
+        #[prost(string, tag = "1")]
+        id: String,
+    }
+
+    fn incoming(outgoing: OutgoingMessage) -> IncomingMessage {
+        IncomingMessage {
+            payload: outgoing.payload,
+            metadata: MessageMetadata {
+                topic: outgoing.topic,
+                partition: 0,
+                offset: 0,
+                key: None,
+                timestamp: None,
+                timestamp_type: None,
+                headers: outgoing.headers.into_iter().collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_decode_typed_follows_content_type() {
+        let event = TypedEvent {
+            id: "evt-1".to_string(),
+        };
+
+        let payloads = [
+            TypedPayload::proto(&event),
+            TypedPayload::json(&event).unwrap(),
+        ];
+        for payload in payloads {
+            let outgoing = OutgoingMessage::new_typed("nier.detections", payload);
+            let decoded: TypedEvent = incoming(outgoing).decode_typed().unwrap();
+            assert_eq!(decoded, event);
+        }
+
+        // No header: decoded as protobuf
+        let mut message = incoming(OutgoingMessage::new_proto("nier.detections", &event).unwrap());
+        message.metadata.headers.clear();
+        assert_eq!(message.decode_typed::<TypedEvent>().unwrap(), event);
+
+        message
+            .metadata
+            .headers
+            .insert("content-type".to_string(), "text/plain".to_string());
+        assert!(matches!(
+            message.decode_typed::<TypedEvent>(),
+            Err(ConsumerError::DeserializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_incoming_message_age() {
+        let mut message = IncomingMessage {
+            payload: vec![],
+            metadata: MessageMetadata {
+                topic: "test".to_string(),
+                partition: 0,
+                offset: 0,
+                key: None,
+                timestamp: Some(1_700_000_000_000),
+                timestamp_type: Some(TimestampType::CreateTime),
+                headers: HashMap::new(),
+            },
+        };
+
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_002_500);
+        assert_eq!(message.age_at(now), Some(Duration::from_millis(2_500)));
+
+        // Future timestamps are clamped to zero
+        let before = UNIX_EPOCH + Duration::from_millis(1_699_999_999_000);
+        assert_eq!(message.age_at(before), Some(Duration::ZERO));
+
+        message.metadata.timestamp = None;
+        assert_eq!(message.age_at(now), None);
+    }
+
+    #[test]
+    fn test_convert_message_captures_timestamp_type() {
+        use rdkafka::message::OwnedMessage;
+
+        let message = OwnedMessage::new(
+            Some(b"payload".to_vec()),
+            None,
+            "nier.detections".to_string(),
+            Timestamp::LogAppendTime(1_700_000_000_000),
+            0,
+            7,
+            None,
+        );
+
+        let incoming = NierConsumer::convert_message(&message, HeaderDecoding::default());
+
+        assert_eq!(incoming.metadata.timestamp, Some(1_700_000_000_000));
+        assert_eq!(
+            incoming.metadata.timestamp_type,
+            Some(TimestampType::LogAppendTime)
+        );
+        // Broker receipt time is not a creation time
+        assert_eq!(incoming.created_at(), None);
+
+        let created = OwnedMessage::new(
+            None,
+            None,
+            "nier.detections".to_string(),
+            Timestamp::CreateTime(1_700_000_000_000),
+            0,
+            8,
+            None,
+        );
+        let incoming = NierConsumer::convert_message(&created, HeaderDecoding::default());
+        assert_eq!(incoming.metadata.timestamp_type, Some(TimestampType::CreateTime));
+        assert_eq!(
+            incoming.created_at(),
+            Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_000))
+        );
+    }
+
+    #[test]
+    fn test_binary_header_follows_decoding_policy() {
+        use rdkafka::message::{Header, OwnedHeaders, OwnedMessage};
+
+        let binary: &[u8] = &[0xff, 0xfe, 0xfd];
+        let headers = OwnedHeaders::new()
+            .insert(Header {
+                key: "correlation-id",
+                value: Some("event-1"),
+            })
+            .insert(Header {
+                key: "trace-context",
+                value: Some(binary),
+            });
+        let message = OwnedMessage::new(
+            None,
+            None,
+            "nier.detections".to_string(),
+            Timestamp::NotAvailable,
+            0,
+            9,
+            Some(headers),
+        );
+
+        let incoming = NierConsumer::convert_message(&message, HeaderDecoding::Base64);
+        assert_eq!(incoming.header("trace-context"), Some("//79"));
+        assert_eq!(incoming.correlation_id(), Some("event-1"));
+
+        let incoming = NierConsumer::convert_message(&message, HeaderDecoding::Utf8Only);
+        assert_eq!(incoming.header("trace-context"), None);
+        assert_eq!(incoming.correlation_id(), Some("event-1"));
+
+        let incoming = NierConsumer::convert_message(&message, HeaderDecoding::Lossy);
+        assert_eq!(
+            incoming.header("trace-context"),
+            Some("\u{fffd}\u{fffd}\u{fffd}")
+        );
+    }
+
+    #[test]
+    fn test_resolve_start_offsets_per_topic() {
+        let partitions = vec![
+            ("nier.alerts".to_string(), 0),
+            ("nier.alerts".to_string(), 1),
+            ("nier.detections".to_string(), 0),
+            ("nier.frames".to_string(), 0),
+        ];
+
+        let mut resets = HashMap::new();
+        resets.insert("nier.alerts".to_string(), OffsetReset::Latest);
+        resets.insert("nier.detections".to_string(), OffsetReset::Earliest);
+
+        // alerts[1] already has a committed offset and must resume from it
+        let mut committed = HashMap::new();
+        committed.insert(("nier.alerts".to_string(), 1), 42);
+
+        let positions =
+            resolve_start_offsets(&partitions, &committed, &resets, OffsetReset::Latest);
+
+        assert_eq!(
+            positions,
+            vec![
+                ("nier.alerts".to_string(), 0, Offset::End),
+                ("nier.alerts".to_string(), 1, Offset::Offset(42)),
+                ("nier.detections".to_string(), 0, Offset::Beginning),
+                // Unconfigured topics fall back to the default reset
+                ("nier.frames".to_string(), 0, Offset::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pipeline_topics_include_critical_alerts() {
+        let mut topics = TopicConfig::default();
+        assert!(!pipeline_topics(&topics).contains(&"nier.alerts.critical"));
+
+        topics.critical_alerts = Some("nier.alerts.critical".to_string());
+        let all = pipeline_topics(&topics);
+        assert_eq!(all.len(), 4);
+        assert!(all.contains(&topics.frames.as_str()));
+        assert!(all.contains(&topics.detections.as_str()));
+        assert!(all.contains(&topics.alerts.as_str()));
+        assert!(all.contains(&"nier.alerts.critical"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttle_limits_rate() {
+        let mut throttle = Throttle::new(100);
+        let start = tokio::time::Instant::now();
+
+        // 21 deliveries at 100/s take exactly 20 intervals
+        for _ in 0..21 {
+            throttle.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+
+        // Idle time does not bank up a burst
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let idle_end = tokio::time::Instant::now();
+        throttle.acquire().await;
+        throttle.acquire().await;
+        assert_eq!(idle_end.elapsed(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_builder_sets_max_throughput() {
+        let builder = ConsumerBuilder::new("localhost:9092").max_throughput_per_sec(50);
+        assert_eq!(builder.config.consumer.max_throughput_per_sec, Some(50));
+    }
+
+    #[test]
+    fn test_builder_assignment_strategy_reaches_client_config() {
+        let builder = ConsumerBuilder::new("localhost:9092")
+            .assignment_strategy(AssignmentStrategy::CooperativeSticky);
+        let client_config = builder.config.build_consumer_config();
+        assert_eq!(
+            client_config.get("partition.assignment.strategy"),
+            Some("cooperative-sticky")
+        );
+    }
+
+    #[test]
+    fn test_tail_start_offset() {
+        assert_eq!(tail_start_offset(0, 100, 10), 90);
+        // Fewer than n messages retained: start at the low watermark
+        assert_eq!(tail_start_offset(95, 100, 10), 95);
+        assert_eq!(tail_start_offset(0, 4, 10), 0);
+        // Empty partition
+        assert_eq!(tail_start_offset(100, 100, 10), 100);
+    }
+
+    #[test]
+    fn test_partition_lag() {
+        let lag = partition_lag("nier.frames", 2, Offset::Offset(90), (10, 100));
+        assert_eq!(lag.current_offset, Some(90));
+        assert_eq!(lag.high_watermark, 100);
+        assert_eq!(lag.lag, 10);
+
+        // No position yet: everything retained is outstanding
+        let fresh = partition_lag("nier.frames", 2, Offset::Invalid, (10, 100));
+        assert_eq!(fresh.current_offset, None);
+        assert_eq!(fresh.lag, 90);
+
+        // Caught up
+        let caught_up = partition_lag("nier.frames", 2, Offset::Offset(100), (10, 100));
+        assert_eq!(caught_up.lag, 0);
+    }
+
+    #[test]
+    fn test_commit_covers_only_the_handled_message() {
+        let offsets = commit_offsets([("nier.detections", 3, 41)]).unwrap();
+
+        assert_eq!(offsets.count(), 1);
+        let committed = offsets.find_partition("nier.detections", 3).unwrap();
+        assert_eq!(committed.offset(), Offset::Offset(42));
+        assert!(offsets.find_partition("nier.detections", 0).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hung_shutdown_commit_is_abandoned() {
+        // A commit against an unreachable broker: blocks until released
+        let (release, blocked) = std::sync::mpsc::channel::<()>();
+        let hung_commit = move || {
+            let _ = blocked.recv();
+            Ok(())
+        };
+
+        let start = std::time::Instant::now();
+        let result = commit_with_timeout(hung_commit, Duration::from_millis(100)).await;
+
+        assert!(matches!(result, Err(ConsumerError::CommitError(_))));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        drop(release);
+
+        // Prompt commits still report their result
+        assert!(commit_with_timeout(|| Ok(()), Duration::from_secs(1))
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_builder_extra_property() {
+        let builder = ConsumerBuilder::new("localhost:9092")
+            .extra_property("fetch.min.bytes", "1024");
+
+        let client_config = builder.config.build_consumer_config();
+        assert_eq!(client_config.get("fetch.min.bytes"), Some("1024"));
+    }
+
+    #[cfg(feature = "integration-tests")]
+    #[tokio::test]
+    async fn test_poll_one_returns_none_on_empty_topic() {
+        use crate::admin::{NierAdmin, TopicSpec};
+
+        let bootstrap = std::env::var("KAFKA_BOOTSTRAP_SERVERS")
+            .unwrap_or_else(|_| "localhost:9092".to_string());
+        let topic = format!("nier.test.poll-one.{}", uuid::Uuid::new_v4());
+
+        let config = KafkaConfig::new(bootstrap);
+        NierAdmin::new(&config)
+            .unwrap()
+            .create_topics(&[TopicSpec {
+                name: topic.clone(),
+                partitions: 1,
+                replication_factor: 1,
+            }])
+            .await
+            .unwrap();
+
+        let consumer = ConsumerBuilder::new(config.bootstrap_servers.clone())
+            .group_id(format!("poll-one-{}", uuid::Uuid::new_v4()))
+            .build()
+            .unwrap();
+        consumer.subscribe(&[topic.as_str()]).unwrap();
+
+        let message = consumer.poll_one(Duration::from_secs(5)).await.unwrap();
+        assert!(message.is_none());
+    }
+
+    #[cfg(feature = "integration-tests")]
+    #[tokio::test]
+    async fn test_headers_round_trip_through_broker() {
+        use crate::admin::{NierAdmin, TopicSpec};
+        use crate::producer::{OutgoingMessage, ProducerBuilder};
+
+        let bootstrap = std::env::var("KAFKA_BOOTSTRAP_SERVERS")
+            .unwrap_or_else(|_| "localhost:9092".to_string());
+        let topic = format!("nier.test.headers.{}", uuid::Uuid::new_v4());
+
+        let config = KafkaConfig::new(bootstrap);
+        NierAdmin::new(&config)
+            .unwrap()
+            .create_topics(&[TopicSpec {
+                name: topic.clone(),
+                partitions: 1,
+                replication_factor: 1,
+            }])
+            .await
+            .unwrap();
+
+        let producer = ProducerBuilder::new(config.bootstrap_servers.clone())
+            .build()
+            .unwrap();
+        let message = OutgoingMessage::new_json(topic.as_str(), &serde_json::json!({}))
+            .unwrap()
+            .with_message_type("detection_event")
+            .with_correlation_id("corr-123");
+        producer.send(message).await.unwrap();
+
+        let consumer = ConsumerBuilder::new(config.bootstrap_servers.clone())
+            .group_id(format!("headers-{}", uuid::Uuid::new_v4()))
+            .auto_offset_reset("earliest")
+            .build()
+            .unwrap();
+        consumer.subscribe(&[topic.as_str()]).unwrap();
+
+        let received = consumer
+            .poll_one(Duration::from_secs(10))
+            .await
+            .unwrap()
+            .expect("message was delivered");
+        assert_eq!(received.message_type(), Some("detection_event"));
+        assert_eq!(received.correlation_id(), Some("corr-123"));
+    }
+
+    #[test]
+    fn test_shard_owns_its_partitions() {
+        let shard = ShardSpec {
+            shard_id: 1,
+            total_shards: 3,
+        };
+        let owned: Vec<i32> = (0..10).filter(|&p| shard.owns(p)).collect();
+        assert_eq!(owned, vec![1, 4, 7]);
+        assert!(!shard.owns(-1));
+
+        let mut assigned = TopicPartitionList::new();
+        for partition in 0..6 {
+            assigned.add_partition("nier.detections", partition);
+        }
+        let foreign: Vec<i32> = partitions_outside_shard(&assigned, shard)
+            .elements()
+            .iter()
+            .map(|e| e.partition())
+            .collect();
+        assert_eq!(foreign, vec![0, 2, 3, 5]);
+
+        // Every partition has exactly one owner across the shards
+        for partition in 0..12 {
+            let owners = (0..3)
+                .filter(|&shard_id| {
+                    ShardSpec {
+                        shard_id,
+                        total_shards: 3,
+                    }
+                    .owns(partition)
+                })
+                .count();
+            assert_eq!(owners, 1);
+        }
+    }
+
+    #[test]
+    fn test_build_does_not_contact_a_broker() {
+        // Nothing listens on port 1; creating the consumer must not wait for it
+        let started = std::time::Instant::now();
+        let consumer = ConsumerBuilder::new("127.0.0.1:1")
+            .group_id("nier-probe")
+            .connect_probe_timeout(Duration::from_secs(10))
+            .build();
+        assert!(consumer.is_ok());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_shard() {
+        let result = ConsumerBuilder::new("localhost:9092")
+            .group_id("nier-shard-3")
+            .shard(3, 3)
+            .build();
+        assert!(matches!(
+            result,
+            Err(ConsumerError::CreationError(ClientCreationError::ConfigError(_)))
+        ));
+    }
+
+    /// Handler that panics on one offset and records the offsets it handled
+    struct PanickyHandler {
+        panic_at: i64,
+        handled: std::sync::Mutex<Vec<i64>>,
+        failed: std::sync::Mutex<Vec<(i64, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MessageHandler for PanickyHandler {
+        async fn handle(&self, message: IncomingMessage) -> Result<(), ConsumerError> {
+            let offset = message.metadata.offset;
+            // Stands in for an `.unwrap()` on data the handler expected
+            let detections: Option<usize> = (offset != self.panic_at).then_some(1);
+            let _count = detections.unwrap();
+            self.handled.lock().unwrap().push(offset);
+            Ok(())
+        }
+
+        async fn on_error(&self, message: IncomingMessage, error: ConsumerError) {
+            self.failed
+                .lock()
+                .unwrap()
+                .push((message.metadata.offset, error.to_string()));
+        }
+    }
+
+    fn message_at(offset: i64) -> IncomingMessage {
+        IncomingMessage {
+            payload: vec![],
+            metadata: MessageMetadata {
+                topic: "nier.detections".to_string(),
+                partition: 0,
+                offset,
+                key: None,
+                timestamp: None,
+                timestamp_type: None,
+                headers: HashMap::new(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_panic_does_not_stop_later_messages() {
+        let handler = PanickyHandler {
+            panic_at: 2,
+            handled: Default::default(),
+            failed: Default::default(),
+        };
+
+        let mut results = Vec::new();
+        for offset in 1..=4 {
+            results.push(dispatch(&handler, message_at(offset), None, None).await);
+        }
+
+        assert_eq!(results, vec![true, false, true, true]);
+        assert_eq!(*handler.handled.lock().unwrap(), vec![1, 3, 4]);
+
+        let failed = handler.failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, 2);
+        assert!(failed[0].1.contains("panicked"));
+        assert!(failed[0].1.contains("`None` value"));
+    }
+
+    /// XORs payloads with a key, standing in for field-level encryption
+    struct XorCipher(u8);
+
+    impl PayloadTransform for XorCipher {
+        fn encode(&self, _topic: &str, payload: Vec<u8>) -> Result<Vec<u8>, TransformError> {
+            Ok(payload.into_iter().map(|b| b ^ self.0).collect())
+        }
+
+        fn decode(&self, topic: &str, payload: Vec<u8>) -> Result<Vec<u8>, TransformError> {
+            if payload.is_empty() {
+                return Err(TransformError::new(format!(
+                    "empty ciphertext on {}",
+                    topic
+                )));
+            }
+            self.encode(topic, payload)
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        payloads: std::sync::Mutex<Vec<Vec<u8>>>,
+        errors: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MessageHandler for RecordingHandler {
+        async fn handle(&self, message: IncomingMessage) -> Result<(), ConsumerError> {
+            self.payloads.lock().unwrap().push(message.payload);
+            Ok(())
+        }
+
+        async fn on_error(&self, _message: IncomingMessage, error: ConsumerError) {
+            self.errors.lock().unwrap().push(error.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payload_encrypted_on_produce_is_decrypted_for_handler() {
+        let cipher = XorCipher(0x5A);
+        let plaintext = br#"{"operator":"Jane Doe"}"#.to_vec();
+        let outgoing =
+            OutgoingMessage::new_json("nier.frames", &serde_json::json!({"operator": "Jane Doe"}))
+                .unwrap();
+        let sent = encode_payload(Some(&cipher), outgoing).unwrap();
+        assert_ne!(sent.payload, plaintext);
+
+        let mut received = message_at(1);
+        received.payload = sent.payload;
+        let handler = RecordingHandler::default();
+        assert!(dispatch(&handler, received, Some(&cipher), None).await);
+        assert_eq!(*handler.payloads.lock().unwrap(), vec![plaintext]);
+
+        // A payload that cannot be decoded never reaches the handler
+        assert!(!dispatch(&handler, message_at(2), Some(&cipher), None).await);
+        assert_eq!(handler.payloads.lock().unwrap().len(), 1);
+        let errors = handler.errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("empty ciphertext on nier.detections"));
+    }
 }
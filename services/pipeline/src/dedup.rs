@@ -0,0 +1,182 @@
+//! Alert deduplication for message handlers.
+//!
+//! Kafka delivers at least once, so a handler that raises an alert for a
+//! detection event can see the same event again after a rebalance or retry.
+//! `AlertDeduplicator` remembers which events already produced an alert so
+//! each logical violation is reported once.
+
+use crate::consumer::IncomingMessage;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Entries kept before expired ones are swept
+const MIN_PRUNE_THRESHOLD: usize = 1024;
+
+/// Suppresses repeat alerts for the same event within a TTL
+///
+/// Events are keyed by their `correlation-id` header, falling back to the
+/// message's topic, partition and offset when the header is missing.
+///
+/// ```rust,no_run
+/// # use nier_pipeline::{AlertDeduplicator, IncomingMessage};
+/// # fn handle(dedup: &AlertDeduplicator, message: &IncomingMessage) {
+/// if dedup.should_alert_for(message) {
+///     // send the alert
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AlertDeduplicator {
+    ttl: Duration,
+    state: Mutex<DedupState>,
+}
+
+#[derive(Debug)]
+struct DedupState {
+    /// Key -> when its alert was raised
+    seen: HashMap<String, Instant>,
+    prune_at: usize,
+}
+
+impl AlertDeduplicator {
+    /// Create a deduplicator that suppresses repeats for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Mutex::new(DedupState {
+                seen: HashMap::new(),
+                prune_at: MIN_PRUNE_THRESHOLD,
+            }),
+        }
+    }
+
+    /// Key used to deduplicate a message
+    pub fn key_for(message: &IncomingMessage) -> String {
+        match message.correlation_id() {
+            Some(id) => id.to_string(),
+            None => format!(
+                "{}/{}/{}",
+                message.metadata.topic, message.metadata.partition, message.metadata.offset
+            ),
+        }
+    }
+
+    /// Whether an alert should be raised for this message
+    pub fn should_alert_for(&self, message: &IncomingMessage) -> bool {
+        self.should_alert(&Self::key_for(message))
+    }
+
+    /// Whether an alert should be raised for this key
+    ///
+    /// Returns `true` the first time a key is seen, then `false` until the
+    /// TTL has passed.
+    pub fn should_alert(&self, key: &str) -> bool {
+        self.should_alert_at(key, Instant::now())
+    }
+
+    fn should_alert_at(&self, key: &str, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(&raised_at) = state.seen.get(key) {
+            if now.duration_since(raised_at) < self.ttl {
+                debug!(key = %key, "Suppressing duplicate alert");
+                metrics::counter!("nier.alerts.deduplicated").increment(1);
+                return false;
+            }
+        }
+
+        state.seen.insert(key.to_string(), now);
+
+        if state.seen.len() >= state.prune_at {
+            let ttl = self.ttl;
+            state
+                .seen
+                .retain(|_, raised_at| now.duration_since(*raised_at) < ttl);
+            state.prune_at = (state.seen.len() * 2).max(MIN_PRUNE_THRESHOLD);
+        }
+
+        true
+    }
+
+    /// Forget a key so the next delivery alerts again
+    ///
+    /// Call this when sending the alert failed, so a redelivery retries it.
+    pub fn forget(&self, key: &str) {
+        self.state.lock().unwrap().seen.remove(key);
+    }
+
+    /// Number of keys currently remembered
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().seen.len()
+    }
+
+    /// Whether no keys are remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consumer::MessageMetadata;
+
+    fn detection_message(correlation_id: Option<&str>, offset: i64) -> IncomingMessage {
+        let mut headers = HashMap::new();
+        if let Some(id) = correlation_id {
+            headers.insert("correlation-id".to_string(), id.to_string());
+        }
+        IncomingMessage {
+            payload: vec![],
+            metadata: MessageMetadata {
+                topic: "nier.detections".to_string(),
+                partition: 0,
+                offset,
+                key: None,
+                timestamp: None,
+                timestamp_type: None,
+                headers,
+            },
+        }
+    }
+
+    #[test]
+    fn test_same_correlation_id_alerts_once_within_ttl() {
+        let dedup = AlertDeduplicator::new(Duration::from_secs(60));
+        let mut alerts = 0;
+
+        // Redelivered after a rebalance: same event, new offset
+        for message in [
+            detection_message(Some("event-1"), 10),
+            detection_message(Some("event-1"), 42),
+        ] {
+            if dedup.should_alert_for(&message) {
+                alerts += 1;
+            }
+        }
+
+        assert_eq!(alerts, 1);
+        assert!(dedup.should_alert_for(&detection_message(Some("event-2"), 43)));
+    }
+
+    #[test]
+    fn test_alerts_again_after_ttl() {
+        let dedup = AlertDeduplicator::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(dedup.should_alert_at("event-1", start));
+        assert!(!dedup.should_alert_at("event-1", start + Duration::from_secs(59)));
+        assert!(dedup.should_alert_at("event-1", start + Duration::from_secs(60)));
+
+        dedup.forget("event-1");
+        assert!(dedup.is_empty());
+    }
+
+    #[test]
+    fn test_missing_correlation_id_keys_by_offset() {
+        let message = detection_message(None, 7);
+        assert_eq!(AlertDeduplicator::key_for(&message), "nier.detections/0/7");
+    }
+}
@@ -26,31 +26,43 @@
 //! }
 //! ```
 
+pub mod admin;
 pub mod config;
 pub mod consumer;
+pub mod dedup;
 pub mod producer;
+pub mod transform;
 
 // Re-export main types
+pub use admin::{AdminError, NierAdmin, TopicSpec};
 pub use config::{
-    ConfigError, ConsumerConfig, KafkaConfig, ProducerConfig, ReliabilityConfig,
-    SaslConfig, SaslMechanism, SecurityProtocol, SslConfig, TopicConfig,
+    AlertSeverity, AssignmentStrategy, ClientCreationError, ConfigError, ConsumerConfig,
+    HeaderDecoding, KafkaConfig, KerberosConfig, OffsetReset, ProducerConfig, ProducerProfile,
+    ReliabilityConfig, SaslConfig, SaslMechanism, SecurityProtocol, SslConfig, TopicConfig,
 };
 pub use consumer::{
     async_trait, ConsumerBuilder, ConsumerError, IncomingMessage, MessageHandler,
-    MessageMetadata, NierConsumer,
+    MessageMetadata, NierConsumer, PartitionLag, TimestampType,
 };
+pub use dedup::AlertDeduplicator;
+pub use nier_retry::{retry_with_backoff, BackoffPolicy};
 pub use producer::{
-    DeliveryResult, NierProducer, OutgoingMessage, ProducerBuilder, ProducerError,
+    CloseReport, DeliveryResult, Format, NierProducer, OutgoingMessage, ProducerBuilder,
+    ProducerError, TypedPayload,
 };
+pub use transform::{PayloadTransform, TransformError};
 
 /// Prelude module for convenient imports
 pub mod prelude {
-    pub use crate::config::KafkaConfig;
+    pub use crate::config::{AlertSeverity, KafkaConfig};
     pub use crate::consumer::{
         async_trait, ConsumerBuilder, ConsumerError, IncomingMessage, MessageHandler,
         NierConsumer,
     };
-    pub use crate::producer::{NierProducer, OutgoingMessage, ProducerBuilder, ProducerError};
+    pub use crate::dedup::AlertDeduplicator;
+    pub use crate::producer::{
+        Format, NierProducer, OutgoingMessage, ProducerBuilder, ProducerError, TypedPayload,
+    };
 }
 
 /// Protocol buffer generated types (when compiled with build.rs)
@@ -14,11 +14,16 @@ use tracing_subscriber::FmtSubscriber;
 /// Example detection event handler
 struct DetectionHandler {
     producer: Arc<NierProducer>,
+    /// One alert per detection event, even if it is redelivered
+    alerts: AlertDeduplicator,
 }
 
 impl DetectionHandler {
     fn new(producer: Arc<NierProducer>) -> Self {
-        Self { producer }
+        Self {
+            producer,
+            alerts: AlertDeduplicator::new(std::time::Duration::from_secs(300)),
+        }
     }
 }
 
@@ -35,6 +40,12 @@ impl MessageHandler for DetectionHandler {
             Some("detection_event") => {
                 info!("Received detection event, size={} bytes", message.payload.len());
 
+                // A redelivered event already raised its alert
+                if !self.alerts.should_alert_for(&message) {
+                    info!("Skipping alert for already handled detection event");
+                    return Ok(());
+                }
+
                 // In a real implementation, you would:
                 // 1. Deserialize the protobuf message
                 // 2. Process the detection (check for violations, aggregate stats, etc.)
@@ -43,7 +54,8 @@ impl MessageHandler for DetectionHandler {
                 // Example: Generate an alert if this was a PPE violation
                 // let event = message.decode_proto::<DetectionEvent>()?;
                 // if !event.ppe_violations.is_empty() {
-                //     self.producer.send_alert(&alert, alert_id).await?;
+                //     let severity = AlertSeverity::Critical;
+                //     self.producer.send_alert(TypedPayload::proto(&alert), alert_id, severity).await?;
                 // }
 
                 Ok(())
@@ -79,6 +91,7 @@ async fn run_producer(config: KafkaConfig) -> Result<()> {
     info!("Starting producer example");
 
     let producer = NierProducer::new(config)?;
+    producer.check_connectivity().await?;
 
     // Send a few example messages
     for i in 0..5 {
@@ -90,6 +103,8 @@ async fn run_producer(config: KafkaConfig) -> Result<()> {
                 ("message-type".to_string(), "detection_event".to_string()),
                 ("correlation-id".to_string(), format!("corr-{}", i)),
             ],
+            timestamp: None,
+            profile: None,
         };
 
         match producer.send(message).await {
@@ -105,8 +120,11 @@ async fn run_producer(config: KafkaConfig) -> Result<()> {
         }
     }
 
-    // Flush remaining messages
-    producer.flush(std::time::Duration::from_secs(5))?;
+    // Wait for remaining deliveries
+    let report = producer.close(std::time::Duration::from_secs(5)).await;
+    if !report.is_clean() {
+        error!("{} message(s) were not delivered", report.undelivered);
+    }
     info!("Producer finished");
 
     Ok(())
@@ -127,6 +145,7 @@ async fn run_consumer(config: KafkaConfig) -> Result<()> {
         .enable_auto_commit(false)
         .with_dlq_producer(producer.clone())
         .build()?;
+    consumer.check_connectivity().await?;
 
     // Subscribe to detection events
     consumer.subscribe_detections()?;
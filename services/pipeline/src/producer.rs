@@ -3,12 +3,23 @@
 //! This module provides a high-level, type-safe interface for producing messages
 //! to Kafka topics with support for protobuf serialization and reliable delivery.
 
-use crate::config::KafkaConfig;
+use crate::admin::{AdminError, NierAdmin};
+use crate::config::{
+    probe_cluster, wait_for_cluster, AlertSeverity, ClientCreationError, KafkaConfig,
+    ProducerProfile, TopicConfig,
+};
+use crate::transform::PayloadTransform;
+use nier_retry::retry_with_backoff;
 use prost::Message;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::consumer::ConsumerGroupMetadata;
 use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
 use rdkafka::util::Timeout;
+use rdkafka::TopicPartitionList;
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
@@ -17,7 +28,7 @@ use uuid::Uuid;
 #[derive(Error, Debug)]
 pub enum ProducerError {
     #[error("Failed to create producer: {0}")]
-    CreationError(String),
+    CreationError(ClientCreationError),
 
     #[error("Failed to serialize message: {0}")]
     SerializationError(String),
@@ -25,11 +36,44 @@ pub enum ProducerError {
     #[error("Failed to send message to topic {topic}: {message}")]
     SendError { topic: String, message: String },
 
+    /// The local producer queue stayed full for the whole timeout; nothing was
+    /// sent, so the message can be retried after backing off
+    #[error("Producer queue full while sending to topic {topic}")]
+    QueueFull { topic: String },
+
     #[error("Producer timeout after {0:?}")]
     Timeout(Duration),
 
+    #[error("Failed to transform payload for topic {topic}: {message}")]
+    TransformError { topic: String, message: String },
+
     #[error("Producer is not connected")]
     NotConnected,
+
+    /// A transactional call failed; `operation` names the call
+    #[error("Transaction {operation} failed: {message}")]
+    TransactionError {
+        operation: &'static str,
+        message: String,
+    },
+
+    /// The broker is too old for transactions (Kafka 0.11+ is required)
+    #[error("Broker does not support transactions: {0}")]
+    TransactionsUnsupported(String),
+
+    #[error("Producer is not transactional")]
+    NotTransactional,
+}
+
+impl ProducerError {
+    /// Whether sending again after backing off is safe and may succeed
+    ///
+    /// Only a full local queue qualifies: nothing was sent. A timed-out
+    /// message may already have been written, so resending it could
+    /// duplicate it.
+    pub fn is_retriable(&self) -> bool {
+        matches!(self, ProducerError::QueueFull { .. })
+    }
 }
 
 /// Result of a successful message delivery
@@ -45,6 +89,89 @@ pub struct DeliveryResult {
     pub key: Option<String>,
 }
 
+/// Outcome of an explicit `NierProducer::close`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseReport {
+    /// Messages still queued or awaiting a delivery report at the deadline
+    pub undelivered: usize,
+    /// Whether the flush gave up at the timeout
+    pub timed_out: bool,
+}
+
+impl CloseReport {
+    /// Whether every message was delivered
+    pub fn is_clean(&self) -> bool {
+        self.undelivered == 0 && !self.timed_out
+    }
+}
+
+/// Wire format of a message payload, advertised in the `content-type` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Proto,
+    Json,
+}
+
+impl Format {
+    /// MIME type written to the `content-type` header
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Proto => "application/x-protobuf",
+            Format::Json => "application/json",
+        }
+    }
+
+    /// Parse a `content-type` header value
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "application/x-protobuf" => Some(Format::Proto),
+            "application/json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A payload serialized in one of the supported wire formats
+///
+/// Each constructor asks only for what its format needs, so generated
+/// protobuf types (which do not implement `Serialize`) can be sent as proto
+/// and plain serde types as JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedPayload {
+    format: Format,
+    bytes: Vec<u8>,
+}
+
+impl TypedPayload {
+    /// Serialize a protobuf message
+    pub fn proto<M: Message>(message: &M) -> Self {
+        Self {
+            format: Format::Proto,
+            bytes: message.encode_to_vec(),
+        }
+    }
+
+    /// Serialize a value as JSON
+    pub fn json<T: serde::Serialize>(value: &T) -> Result<Self, ProducerError> {
+        let bytes = serde_json::to_vec(value)
+            .map_err(|e| ProducerError::SerializationError(e.to_string()))?;
+        Ok(Self {
+            format: Format::Json,
+            bytes,
+        })
+    }
+
+    /// Format the payload was serialized in
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Serialized bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 /// Message to be sent to Kafka
 #[derive(Debug, Clone)]
 pub struct OutgoingMessage {
@@ -56,18 +183,16 @@ pub struct OutgoingMessage {
     pub payload: Vec<u8>,
     /// Optional headers
     pub headers: Vec<(String, String)>,
+    /// Record timestamp in milliseconds since the epoch (None = send time)
+    pub timestamp: Option<i64>,
+    /// Producer profile to send with (None = default producer settings)
+    pub profile: Option<String>,
 }
 
 impl OutgoingMessage {
     /// Create a new outgoing message with a protobuf payload
     pub fn new_proto<M: Message>(topic: impl Into<String>, message: &M) -> Result<Self, ProducerError> {
-        let payload = message.encode_to_vec();
-        Ok(Self {
-            topic: topic.into(),
-            key: None,
-            payload,
-            headers: Vec::new(),
-        })
+        Ok(Self::new_typed(topic, TypedPayload::proto(message)))
     }
 
     /// Create a new outgoing message with a JSON payload
@@ -75,14 +200,21 @@ impl OutgoingMessage {
         topic: impl Into<String>,
         message: &T,
     ) -> Result<Self, ProducerError> {
-        let payload = serde_json::to_vec(message)
-            .map_err(|e| ProducerError::SerializationError(e.to_string()))?;
-        Ok(Self {
+        Ok(Self::new_typed(topic, TypedPayload::json(message)?))
+    }
+
+    /// Create a new outgoing message from a serialized payload, with a
+    /// `content-type` header naming its format
+    pub fn new_typed(topic: impl Into<String>, payload: TypedPayload) -> Self {
+        Self {
             topic: topic.into(),
             key: None,
-            payload,
+            payload: payload.bytes,
             headers: Vec::new(),
-        })
+            timestamp: None,
+            profile: None,
+        }
+        .with_content_type(payload.format)
     }
 
     /// Set the message key
@@ -97,6 +229,23 @@ impl OutgoingMessage {
         self
     }
 
+    /// Set the record timestamp (milliseconds since the epoch)
+    ///
+    /// Use the frame's capture time when backfilling or replaying, so
+    /// consumers see the original age and ordering rather than the send time.
+    pub fn with_timestamp(mut self, timestamp_ms: i64) -> Self {
+        self.timestamp = Some(timestamp_ms);
+        self
+    }
+
+    /// Send with the named producer profile from `producer.profiles`
+    ///
+    /// Messages naming an unknown profile are sent with the default settings.
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
     /// Add a correlation ID header
     pub fn with_correlation_id(self, id: impl Into<String>) -> Self {
         self.with_header("correlation-id", id)
@@ -106,17 +255,33 @@ impl OutgoingMessage {
     pub fn with_message_type(self, msg_type: impl Into<String>) -> Self {
         self.with_header("message-type", msg_type)
     }
+
+    fn with_content_type(self, format: Format) -> Self {
+        self.with_header("content-type", format.content_type())
+    }
 }
 
 /// High-level Kafka producer wrapper
 pub struct NierProducer {
     producer: FutureProducer,
+    /// One producer per configured profile, since librdkafka settings are
+    /// per client
+    profiles: HashMap<String, FutureProducer>,
     config: Arc<KafkaConfig>,
+    /// Applied to every payload just before it is sent
+    payload_transform: Option<Arc<dyn PayloadTransform>>,
     default_timeout: Duration,
+    /// `message.timeout.ms`, reported when a delivery times out
+    message_timeout: Duration,
+    /// Set by `close` so `Drop` does not flush a second time
+    closed: bool,
 }
 
 impl NierProducer {
     /// Create a new producer with the given configuration
+    ///
+    /// When `producer.transactional_id` is set the producer is transactional,
+    /// as with `new_transactional`.
     pub fn new(config: KafkaConfig) -> Result<Self, ProducerError> {
         info!(
             "Creating Kafka producer for {}",
@@ -126,22 +291,180 @@ impl NierProducer {
         let producer_config = config.build_producer_config();
         let producer: FutureProducer = producer_config
             .create()
-            .map_err(|e| ProducerError::CreationError(e.to_string()))?;
+            .map_err(|e| ProducerError::CreationError(e.into()))?;
+
+        let transactional = config.producer.transactional_id.is_some();
+        if transactional {
+            producer
+                .init_transactions(config.request_timeout())
+                .map_err(|e| transaction_error("init", e))?;
+            if !config.producer.profiles.is_empty() {
+                // Profile producers could not join the transaction
+                warn!("Producer profiles are ignored by transactional producers");
+            }
+        }
+
+        let mut profiles = HashMap::new();
+        for name in config.producer.profiles.keys().filter(|_| !transactional) {
+            debug!("Creating Kafka producer for profile {}", name);
+            let profile: FutureProducer = config
+                .build_profile_producer_config(Some(name))
+                .create()
+                .map_err(|e| ProducerError::CreationError(e.into()))?;
+            profiles.insert(name.clone(), profile);
+        }
 
         let default_timeout = config.request_timeout();
+        let message_timeout = config.message_timeout();
 
         Ok(Self {
             producer,
+            profiles,
             config: Arc::new(config),
+            payload_transform: None,
             default_timeout,
+            message_timeout,
+            closed: false,
         })
     }
 
+    /// Create a transactional producer for exactly-once read-process-write
+    ///
+    /// Sets `transactional.id` and turns idempotence on; `acks` is forced to
+    /// all whatever `reliability.acks` says. Producer profiles are ignored,
+    /// since every message must go through the one transactional client.
+    /// Fails with `ProducerError::TransactionsUnsupported` when the broker
+    /// cannot run transactions.
+    pub fn new_transactional(
+        mut config: KafkaConfig,
+        transactional_id: impl Into<String>,
+    ) -> Result<Self, ProducerError> {
+        config.producer.transactional_id = Some(transactional_id.into());
+        config.reliability.enable_idempotence = true;
+        config.reliability.acks = "all".to_string();
+        Self::new(config)
+    }
+
+    /// Start a transaction; messages sent until it is committed or aborted
+    /// belong to it
+    pub fn begin_transaction(&self) -> Result<(), ProducerError> {
+        self.ensure_transactional()?;
+        self.producer
+            .begin_transaction()
+            .map_err(|e| transaction_error("begin", e))
+    }
+
+    /// Commit consumed `offsets` as part of the current transaction
+    ///
+    /// `group` identifies the consumer group the offsets belong to; get it
+    /// from the consumer with `NierConsumer::group_metadata`. The offsets
+    /// are those of the next messages to consume (last processed + 1).
+    pub fn send_offsets_to_transaction(
+        &self,
+        offsets: &TopicPartitionList,
+        group: &ConsumerGroupMetadata,
+    ) -> Result<(), ProducerError> {
+        self.ensure_transactional()?;
+        self.producer
+            .send_offsets_to_transaction(offsets, group, self.default_timeout)
+            .map_err(|e| transaction_error("send_offsets", e))
+    }
+
+    /// Commit the current transaction, flushing its messages first
+    pub fn commit_transaction(&self) -> Result<(), ProducerError> {
+        self.ensure_transactional()?;
+        self.producer
+            .commit_transaction(self.default_timeout)
+            .map_err(|e| transaction_error("commit", e))
+    }
+
+    /// Abort the current transaction; its messages are never seen by
+    /// `read_committed` consumers
+    pub fn abort_transaction(&self) -> Result<(), ProducerError> {
+        self.ensure_transactional()?;
+        self.producer
+            .abort_transaction(self.default_timeout)
+            .map_err(|e| transaction_error("abort", e))
+    }
+
+    /// Whether this producer was created with a transactional ID
+    pub fn is_transactional(&self) -> bool {
+        self.config.producer.transactional_id.is_some()
+    }
+
+    fn ensure_transactional(&self) -> Result<(), ProducerError> {
+        if self.is_transactional() {
+            Ok(())
+        } else {
+            Err(ProducerError::NotTransactional)
+        }
+    }
+
+    /// Encode payloads (e.g. encrypt) just before they are sent
+    ///
+    /// Consumers need the same transform to decode them.
+    pub fn with_payload_transform(mut self, transform: Arc<dyn PayloadTransform>) -> Self {
+        self.payload_transform = Some(transform);
+        self
+    }
+
     /// Get the configuration
     pub fn config(&self) -> &KafkaConfig {
         &self.config
     }
 
+    /// Check that the cluster answers, retrying with backoff while no broker
+    /// is reachable
+    ///
+    /// Creating the producer does not contact a broker, so call this at
+    /// startup to surface an unreachable cluster or rejected credentials.
+    /// Each attempt waits up to `reliability.connect_probe_timeout_ms`.
+    pub async fn check_connectivity(&self) -> Result<(), ProducerError> {
+        let Some(timeout) = self.config.connect_probe_timeout() else {
+            return Ok(());
+        };
+        let producer = self.producer.clone();
+        wait_for_cluster(
+            move || probe_cluster(producer.client(), timeout),
+            &self.config.reliability.backoff_policy(),
+        )
+        .await
+        .map_err(ProducerError::CreationError)
+    }
+
+    /// Profile a message will be sent with (None = default producer)
+    pub fn profile_for<'a>(&self, message: &'a OutgoingMessage) -> Option<&'a str> {
+        let name = message.profile.as_deref()?;
+        if self.profiles.contains_key(name) {
+            Some(name)
+        } else {
+            warn!("Unknown producer profile {}, using default settings", name);
+            None
+        }
+    }
+
+    /// Underlying producer for a message's profile
+    fn producer_for(&self, message: &OutgoingMessage) -> &FutureProducer {
+        self.profile_for(message)
+            .and_then(|name| self.profiles.get(name))
+            .unwrap_or(&self.producer)
+    }
+
+    /// Every underlying producer, default first
+    fn all_producers(&self) -> Vec<FutureProducer> {
+        std::iter::once(&self.producer)
+            .chain(self.profiles.values())
+            .cloned()
+            .collect()
+    }
+
+    /// Create the configured pipeline topics if they are missing.
+    ///
+    /// No-op unless `topics.auto_create` is enabled.
+    pub async fn ensure_topics(&self) -> Result<(), AdminError> {
+        NierAdmin::new(&self.config)?.ensure_topics().await
+    }
+
     /// Send a message and wait for delivery confirmation
     #[instrument(skip(self, message), fields(topic = %message.topic, key = ?message.key))]
     pub async fn send(&self, message: OutgoingMessage) -> Result<DeliveryResult, ProducerError> {
@@ -155,14 +478,10 @@ impl NierProducer {
         message: OutgoingMessage,
         timeout: Duration,
     ) -> Result<DeliveryResult, ProducerError> {
+        let message = encode_payload(self.payload_transform.as_deref(), message)?;
         let topic = message.topic.clone();
         let key = message.key.clone();
-
-        let mut record = FutureRecord::to(&topic).payload(&message.payload);
-
-        if let Some(ref k) = key {
-            record = record.key(k);
-        }
+        let record = build_record(&message);
 
         debug!(
             "Sending message to topic {} (size: {} bytes)",
@@ -171,13 +490,10 @@ impl NierProducer {
         );
 
         let delivery_result = self
-            .producer
+            .producer_for(&message)
             .send(record, Timeout::After(timeout))
             .await
-            .map_err(|(e, _)| ProducerError::SendError {
-                topic: topic.clone(),
-                message: e.to_string(),
-            })?;
+            .map_err(|(e, _)| send_error(&topic, e, self.message_timeout))?;
 
         let result = DeliveryResult {
             topic,
@@ -205,62 +521,66 @@ impl NierProducer {
         futures::future::join_all(futures).await
     }
 
+    /// Send a serialized payload, with a `content-type` header naming its format
+    pub async fn send_typed(
+        &self,
+        topic: &str,
+        payload: TypedPayload,
+    ) -> Result<DeliveryResult, ProducerError> {
+        self.send(OutgoingMessage::new_typed(topic, payload)).await
+    }
+
     /// Send a detection event to the detections topic
-    pub async fn send_detection_event<M: Message>(
+    ///
+    /// Build the payload with `TypedPayload::proto` or `TypedPayload::json`.
+    pub async fn send_detection_event(
         &self,
-        event: &M,
+        event: TypedPayload,
         event_id: impl Into<String>,
     ) -> Result<DeliveryResult, ProducerError> {
-        let event_id = event_id.into();
-        let message = OutgoingMessage::new_proto(&self.config.topics.detections, event)?
-            .with_key(&event_id)
-            .with_message_type("detection_event")
-            .with_correlation_id(&event_id);
-
+        let message = detection_event_message(&self.config.topics, event, &event_id.into());
         self.send(message).await
     }
 
     /// Send frame metadata to the frames topic
-    pub async fn send_frame_metadata<M: Message>(
+    pub async fn send_frame_metadata(
         &self,
-        metadata: &M,
+        metadata: TypedPayload,
         frame_id: impl Into<String>,
     ) -> Result<DeliveryResult, ProducerError> {
-        let frame_id = frame_id.into();
-        let message = OutgoingMessage::new_proto(&self.config.topics.frames, metadata)?
-            .with_key(&frame_id)
-            .with_message_type("frame_metadata");
-
+        let message = frame_metadata_message(&self.config.topics, metadata, &frame_id.into());
         self.send(message).await
     }
 
-    /// Send an alert to the alerts topic
-    pub async fn send_alert<M: Message>(
+    /// Send an alert, routed to the topic for its severity
+    pub async fn send_alert(
         &self,
-        alert: &M,
+        alert: TypedPayload,
         alert_id: impl Into<String>,
+        severity: AlertSeverity,
     ) -> Result<DeliveryResult, ProducerError> {
-        let alert_id = alert_id.into();
-        let message = OutgoingMessage::new_proto(&self.config.topics.alerts, alert)?
-            .with_key(&alert_id)
-            .with_message_type("alert");
-
+        let message = alert_message(&self.config.topics, alert, &alert_id.into(), severity);
         self.send(message).await
     }
 
     /// Send a message to the dead letter queue
+    ///
+    /// A full queue is retried with backoff per the reliability settings, so
+    /// a briefly congested producer does not lose the failed message. A
+    /// delivery timeout is returned as is: the record may already be in the
+    /// DLQ, and each retry would hold up the caller for `message.timeout.ms`.
     pub async fn send_to_dlq(
         &self,
         original_topic: &str,
         original_message: &[u8],
         error: &str,
     ) -> Result<DeliveryResult, ProducerError> {
-        let dlq_message = serde_json::json!({
-            "original_topic": original_topic,
-            "original_message_base64": base64_encode(original_message),
-            "error": error,
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-        });
+        let dlq_message = dlq_envelope(
+            original_topic,
+            original_message,
+            error,
+            self.config.producer.max_dlq_payload_bytes,
+        );
 
         let message = OutgoingMessage::new_json(&self.config.topics.dead_letter_queue, &dlq_message)?
             .with_key(Uuid::new_v4().to_string())
@@ -268,91 +588,229 @@ impl NierProducer {
             .with_header("original-topic", original_topic)
             .with_header("error-reason", error);
 
-        self.send(message).await
+        retry_with_backoff(
+            || self.send(message.clone()),
+            &self.config.reliability.backoff_policy(),
+            ProducerError::is_retriable,
+        )
+        .await
     }
 
-    /// Flush all pending messages
+    /// Flush all pending messages, across every profile
     pub fn flush(&self, timeout: Duration) -> Result<(), ProducerError> {
-        self.producer.flush(Timeout::After(timeout)).map_err(|_| {
-            ProducerError::Timeout(timeout)
-        })
+        if self.all_producers().flush_within(timeout) {
+            Ok(())
+        } else {
+            Err(ProducerError::Timeout(timeout))
+        }
     }
 
-    /// Get the number of messages in the producer queue
+    /// Get the number of messages in the producer queues
     pub fn queue_len(&self) -> usize {
-        self.producer.in_flight_count()
+        self.all_producers().pending()
     }
-}
 
-impl Drop for NierProducer {
-    fn drop(&mut self) {
-        info!("Shutting down Kafka producer");
-        if let Err(e) = self.flush(Duration::from_secs(5)) {
-            warn!("Failed to flush producer on shutdown: {}", e);
+    /// Shut down the producer, waiting up to `timeout` for every queued
+    /// message to be delivered.
+    ///
+    /// Unlike the best-effort flush in `Drop`, this runs the blocking flush
+    /// off the async runtime and reports how many messages were left
+    /// undelivered, so callers can surface data loss on shutdown.
+    pub async fn close(mut self, timeout: Duration) -> CloseReport {
+        self.closed = true;
+        info!("Closing Kafka producer");
+
+        let producers = self.all_producers();
+        let report = tokio::task::spawn_blocking(move || drain_queue(&producers, timeout))
+            .await
+            .unwrap_or_else(|_| CloseReport {
+                undelivered: self.queue_len(),
+                timed_out: true,
+            });
+
+        if report.is_clean() {
+            info!("Kafka producer closed with all messages delivered");
+        } else {
+            warn!(
+                "Kafka producer closed with {} undelivered message(s) after {:?}",
+                report.undelivered, timeout
+            );
+            metrics::counter!("nier.producer.undelivered_on_close")
+                .increment(report.undelivered as u64);
         }
+
+        report
     }
 }
 
-/// Simple base64 encoding helper
-fn base64_encode(data: &[u8]) -> String {
-    use std::io::Write;
-    let mut buf = Vec::new();
-    {
-        let mut encoder = Base64Encoder::new(&mut buf);
-        encoder.write_all(data).ok();
+/// Map a transactional API failure, singling out brokers without support
+fn transaction_error(operation: &'static str, error: KafkaError) -> ProducerError {
+    match error.rdkafka_error_code() {
+        Some(RDKafkaErrorCode::UnsupportedVersion | RDKafkaErrorCode::UnsupportedFeature) => {
+            ProducerError::TransactionsUnsupported(error.to_string())
+        }
+        _ => ProducerError::TransactionError {
+            operation,
+            message: error.to_string(),
+        },
     }
-    String::from_utf8(buf).unwrap_or_default()
 }
 
-/// Simple base64 encoder
-struct Base64Encoder<W: std::io::Write> {
-    writer: W,
+/// Queue that can be flushed on shutdown
+trait FlushQueue {
+    /// Wait up to `timeout` for outstanding deliveries; false on timeout
+    fn flush_within(&self, timeout: Duration) -> bool;
+
+    /// Messages queued or awaiting a delivery report
+    fn pending(&self) -> usize;
 }
 
-impl<W: std::io::Write> Base64Encoder<W> {
-    fn new(writer: W) -> Self {
-        Self { writer }
+impl FlushQueue for FutureProducer {
+    fn flush_within(&self, timeout: Duration) -> bool {
+        self.flush(Timeout::After(timeout)).is_ok()
+    }
+
+    fn pending(&self) -> usize {
+        self.in_flight_count().max(0) as usize
     }
 }
 
-impl<W: std::io::Write> std::io::Write for Base64Encoder<W> {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+impl FlushQueue for Vec<FutureProducer> {
+    /// Flush each producer in turn, all within the one `timeout`
+    fn flush_within(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        self.iter().fold(true, |flushed, producer| {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            producer.flush_within(remaining) && flushed
+        })
+    }
 
-        for chunk in buf.chunks(3) {
-            let b0 = chunk[0] as usize;
-            let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
-            let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
+    fn pending(&self) -> usize {
+        self.iter().map(FlushQueue::pending).sum()
+    }
+}
 
-            let combined = (b0 << 16) | (b1 << 8) | b2;
+/// Flush `queue` and report what was left behind
+fn drain_queue<Q: FlushQueue>(queue: &Q, timeout: Duration) -> CloseReport {
+    let flushed = queue.flush_within(timeout);
+    CloseReport {
+        undelivered: queue.pending(),
+        timed_out: !flushed,
+    }
+}
 
-            self.writer.write_all(&[ALPHABET[(combined >> 18) & 0x3F]])?;
-            self.writer.write_all(&[ALPHABET[(combined >> 12) & 0x3F]])?;
+impl Drop for NierProducer {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        info!("Shutting down Kafka producer");
+        if let Err(e) = self.flush(Duration::from_secs(5)) {
+            warn!("Failed to flush producer on shutdown: {}", e);
+        }
+    }
+}
 
-            if chunk.len() > 1 {
-                self.writer.write_all(&[ALPHABET[(combined >> 6) & 0x3F]])?;
-            } else {
-                self.writer.write_all(b"=")?;
-            }
+/// Detection event message, keyed and tagged for the detections topic
+fn detection_event_message(
+    topics: &TopicConfig,
+    event: TypedPayload,
+    event_id: &str,
+) -> OutgoingMessage {
+    OutgoingMessage::new_typed(&topics.detections, event)
+        .with_key(event_id)
+        .with_message_type("detection_event")
+        .with_correlation_id(event_id)
+}
 
-            if chunk.len() > 2 {
-                self.writer.write_all(&[ALPHABET[combined & 0x3F]])?;
-            } else {
-                self.writer.write_all(b"=")?;
-            }
-        }
+/// Frame metadata message, keyed and tagged for the frames topic
+fn frame_metadata_message(
+    topics: &TopicConfig,
+    metadata: TypedPayload,
+    frame_id: &str,
+) -> OutgoingMessage {
+    OutgoingMessage::new_typed(&topics.frames, metadata)
+        .with_key(frame_id)
+        .with_message_type("frame_metadata")
+}
+
+/// Alert message, routed to the topic for its severity
+fn alert_message(
+    topics: &TopicConfig,
+    alert: TypedPayload,
+    alert_id: &str,
+    severity: AlertSeverity,
+) -> OutgoingMessage {
+    OutgoingMessage::new_typed(topics.alert_topic(severity), alert)
+        .with_key(alert_id)
+        .with_message_type("alert")
+        .with_header("severity", severity.as_str())
+}
 
-        Ok(buf.len())
+/// Map an rdkafka send error, separating a full local queue and a delivery
+/// timeout (the message may still have been written) from other failures.
+///
+/// A delivery timeout is reported with `message_timeout`, the producer's
+/// `message.timeout.ms`, not the time allowed for enqueueing.
+fn send_error(topic: &str, error: KafkaError, message_timeout: Duration) -> ProducerError {
+    match error.rdkafka_error_code() {
+        Some(RDKafkaErrorCode::QueueFull) => ProducerError::QueueFull {
+            topic: topic.to_string(),
+        },
+        Some(RDKafkaErrorCode::MessageTimedOut) => ProducerError::Timeout(message_timeout),
+        _ => ProducerError::SendError {
+            topic: topic.to_string(),
+            message: error.to_string(),
+        },
     }
+}
 
-    fn flush(&mut self) -> std::io::Result<()> {
-        self.writer.flush()
+/// Build the dead-letter envelope for a failed message.
+///
+/// Originals larger than `max_payload_bytes` (0 = unlimited) are truncated so
+/// the DLQ message itself stays under the broker's `message.max.bytes`; the
+/// envelope is flagged `truncated` and carries the original size and SHA-256
+/// so the full payload can still be matched against its source.
+fn dlq_envelope(
+    original_topic: &str,
+    original_message: &[u8],
+    error: &str,
+    max_payload_bytes: usize,
+) -> serde_json::Value {
+    let truncated = max_payload_bytes > 0 && original_message.len() > max_payload_bytes;
+
+    let mut envelope = serde_json::json!({
+        "original_topic": original_topic,
+        "error": error,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "original_size_bytes": original_message.len(),
+        "truncated": truncated,
+    });
+
+    if truncated {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(original_message);
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        envelope["original_message_base64"] =
+            base64_encode(&original_message[..max_payload_bytes]).into();
+        envelope["original_sha256"] = hex.into();
+    } else {
+        envelope["original_message_base64"] = base64_encode(original_message).into();
     }
+
+    envelope
+}
+
+/// Base64-encode (standard alphabet, padded) a payload for the DLQ envelope
+fn base64_encode(data: &[u8]) -> String {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    STANDARD.encode(data)
 }
 
 /// Builder for creating producers with custom settings
 pub struct ProducerBuilder {
     config: KafkaConfig,
+    payload_transform: Option<Arc<dyn PayloadTransform>>,
 }
 
 impl ProducerBuilder {
@@ -360,6 +818,7 @@ impl ProducerBuilder {
     pub fn new(bootstrap_servers: impl Into<String>) -> Self {
         Self {
             config: KafkaConfig::new(bootstrap_servers),
+            payload_transform: None,
         }
     }
 
@@ -393,10 +852,88 @@ impl ProducerBuilder {
         self
     }
 
+    /// Set how long each `check_connectivity` attempt waits for a broker to
+    /// answer (zero skips the check)
+    pub fn connect_probe_timeout(mut self, timeout: Duration) -> Self {
+        self.config.reliability.connect_probe_timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    /// Add a named producer profile, selected with `OutgoingMessage::with_profile`
+    pub fn profile(mut self, name: impl Into<String>, profile: ProducerProfile) -> Self {
+        self.config.producer.profiles.insert(name.into(), profile);
+        self
+    }
+
+    /// Set an arbitrary librdkafka property
+    pub fn extra_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        if KafkaConfig::is_managed_property(&key) {
+            warn!(
+                "Extra property {} is managed by the builder and may be overridden",
+                key
+            );
+        }
+        self.config.extra_properties.insert(key, value.into());
+        self
+    }
+
+    /// Encode payloads just before they are sent
+    pub fn payload_transform(mut self, transform: Arc<dyn PayloadTransform>) -> Self {
+        self.payload_transform = Some(transform);
+        self
+    }
+
     /// Build the producer
     pub fn build(self) -> Result<NierProducer, ProducerError> {
-        NierProducer::new(self.config)
+        let mut producer = NierProducer::new(self.config)?;
+        if let Some(transform) = self.payload_transform {
+            producer = producer.with_payload_transform(transform);
+        }
+        Ok(producer)
+    }
+}
+
+/// Encode a message's payload with `transform`, if one is set
+pub(crate) fn encode_payload(
+    transform: Option<&dyn PayloadTransform>,
+    mut message: OutgoingMessage,
+) -> Result<OutgoingMessage, ProducerError> {
+    if let Some(transform) = transform {
+        message.payload = transform
+            .encode(&message.topic, message.payload)
+            .map_err(|e| ProducerError::TransformError {
+                topic: message.topic.clone(),
+                message: e.to_string(),
+            })?;
     }
+    Ok(message)
+}
+
+/// Build the librdkafka record for a message
+fn build_record(message: &OutgoingMessage) -> FutureRecord<'_, String, Vec<u8>> {
+    let mut record = FutureRecord::to(&message.topic).payload(&message.payload);
+
+    if let Some(ref k) = message.key {
+        record = record.key(k);
+    }
+    if let Some(timestamp) = message.timestamp {
+        record = record.timestamp(timestamp);
+    }
+    if !message.headers.is_empty() {
+        let headers = message
+            .headers
+            .iter()
+            .fold(OwnedHeaders::new(), |headers, (key, value)| {
+                headers.insert(Header {
+                    key,
+                    value: Some(value),
+                })
+            });
+        record = record.headers(headers);
+    }
+
+    record
 }
 
 #[cfg(test)]
@@ -410,6 +947,8 @@ mod tests {
             key: None,
             payload: vec![1, 2, 3],
             headers: vec![],
+            timestamp: None,
+            profile: None,
         }
         .with_key("my-key")
         .with_header("header1", "value1")
@@ -419,10 +958,364 @@ mod tests {
         assert_eq!(message.headers.len(), 2);
     }
 
+    #[test]
+    fn test_explicit_timestamp_is_attached_to_record() {
+        let captured_at_ms = 1_700_000_000_000;
+        let message = OutgoingMessage::new_json("nier.frames", &serde_json::json!({}))
+            .unwrap()
+            .with_key("glasses-001")
+            .with_timestamp(captured_at_ms);
+
+        let record = build_record(&message);
+        assert_eq!(record.timestamp, Some(captured_at_ms));
+        assert_eq!(record.key, Some(&"glasses-001".to_string()));
+
+        // Without one, librdkafka stamps the send time
+        let message = OutgoingMessage::new_json("nier.frames", &serde_json::json!({})).unwrap();
+        assert_eq!(build_record(&message).timestamp, None);
+    }
+
+    #[test]
+    fn test_headers_are_attached_to_record() {
+        use rdkafka::message::Headers;
+
+        let message = OutgoingMessage::new_json("nier.detections", &serde_json::json!({}))
+            .unwrap()
+            .with_message_type("detection_event")
+            .with_correlation_id("corr-123")
+            .with_header("severity", "critical");
+
+        let record = build_record(&message);
+        let headers = record.headers.as_ref().expect("record has headers");
+        let on_record: Vec<(String, String)> = headers
+            .iter()
+            .map(|h| {
+                let value = h.value.map(|v| String::from_utf8_lossy(v).into_owned());
+                (h.key.to_string(), value.unwrap_or_default())
+            })
+            .collect();
+        assert_eq!(on_record, message.headers);
+        assert!(on_record.contains(&("message-type".to_string(), "detection_event".to_string())));
+        assert!(on_record.contains(&("correlation-id".to_string(), "corr-123".to_string())));
+
+        // Messages without headers send none
+        let bare = OutgoingMessage {
+            topic: "nier.frames".to_string(),
+            key: None,
+            payload: vec![],
+            headers: vec![],
+            timestamp: None,
+            profile: None,
+        };
+        assert!(build_record(&bare).headers.is_none());
+    }
+
     #[test]
     fn test_base64_encode() {
-        let data = b"Hello, World!";
-        let encoded = base64_encode(data);
-        assert!(!encoded.is_empty());
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let cases: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+        ];
+        for (data, expected) in cases {
+            let encoded = base64_encode(data);
+            assert_eq!(encoded, expected);
+            assert_eq!(STANDARD.decode(&encoded).unwrap(), data);
+        }
+
+        let binary: Vec<u8> = (0..=255u8).rev().collect();
+        for len in [1, 2, 3, 4, 256] {
+            let encoded = base64_encode(&binary[..len]);
+            assert_eq!(STANDARD.decode(&encoded).unwrap(), &binary[..len]);
+        }
+    }
+
+    #[test]
+    fn test_transaction_error_flags_unsupported_brokers() {
+        let unsupported = KafkaError::Global(RDKafkaErrorCode::UnsupportedVersion);
+        assert!(matches!(
+            transaction_error("init", unsupported),
+            ProducerError::TransactionsUnsupported(_)
+        ));
+
+        let fenced = KafkaError::Global(RDKafkaErrorCode::ProducerFenced);
+        assert!(matches!(
+            transaction_error("commit", fenced),
+            ProducerError::TransactionError {
+                operation: "commit",
+                ..
+            }
+        ));
+    }
+
+    /// Queue whose flush delivers only `deliverable` of its messages
+    struct MockQueue {
+        queued: std::sync::atomic::AtomicUsize,
+        deliverable: usize,
+    }
+
+    impl FlushQueue for MockQueue {
+        fn flush_within(&self, _timeout: Duration) -> bool {
+            use std::sync::atomic::Ordering;
+            let queued = self.queued.load(Ordering::SeqCst);
+            let remaining = queued.saturating_sub(self.deliverable);
+            self.queued.store(remaining, Ordering::SeqCst);
+            remaining == 0
+        }
+
+        fn pending(&self) -> usize {
+            self.queued.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn test_drain_queue_reports_undelivered_on_timeout() {
+        let stuck = MockQueue {
+            queued: 5.into(),
+            deliverable: 2,
+        };
+        let report = drain_queue(&stuck, Duration::from_millis(10));
+        assert_eq!(
+            report,
+            CloseReport {
+                undelivered: 3,
+                timed_out: true,
+            }
+        );
+        assert!(!report.is_clean());
+
+        let healthy = MockQueue {
+            queued: 5.into(),
+            deliverable: 5,
+        };
+        assert!(drain_queue(&healthy, Duration::from_millis(10)).is_clean());
+    }
+
+    #[test]
+    fn test_dlq_envelope_truncates_oversized_payload() {
+        let original = vec![0xABu8; 1024];
+        let envelope = dlq_envelope("nier.frames", &original, "decode failed", 300);
+
+        assert_eq!(envelope["truncated"], true);
+        assert_eq!(envelope["original_size_bytes"], 1024);
+        assert_eq!(
+            envelope["original_message_base64"],
+            base64_encode(&original[..300])
+        );
+        let sha = envelope["original_sha256"].as_str().unwrap();
+        assert_eq!(sha.len(), 64);
+
+        let small = dlq_envelope("nier.frames", b"ok", "decode failed", 300);
+        assert_eq!(small["truncated"], false);
+        assert_eq!(small["original_message_base64"], base64_encode(b"ok"));
+        assert!(small.get("original_sha256").is_none());
+
+        let unlimited = dlq_envelope("nier.frames", &original, "decode failed", 0);
+        assert_eq!(unlimited["truncated"], false);
+    }
+
+    #[test]
+    fn test_builder_extra_property() {
+        let builder = ProducerBuilder::new("localhost:9092")
+            .extra_property("message.max.bytes", "2000000");
+
+        let client_config = builder.config.build_producer_config();
+        assert_eq!(client_config.get("message.max.bytes"), Some("2000000"));
+    }
+
+    #[test]
+    fn test_alert_routing_by_severity() {
+        let topics = TopicConfig {
+            critical_alerts: Some("nier.alerts.critical".to_string()),
+            ..Default::default()
+        };
+        let alert = TypedPayload::proto(&"hard hat missing".to_string());
+        let route = |alert_id: &str, severity: AlertSeverity| {
+            alert_message(&topics, alert.clone(), alert_id, severity)
+        };
+
+        let critical = route("a-1", AlertSeverity::Critical);
+        assert_eq!(critical.topic, "nier.alerts.critical");
+        assert!(critical
+            .headers
+            .contains(&("severity".to_string(), "critical".to_string())));
+
+        let warning = route("a-2", AlertSeverity::Warning);
+        assert_eq!(warning.topic, "nier.alerts");
+        assert!(warning
+            .headers
+            .contains(&("severity".to_string(), "warning".to_string())));
+    }
+
+    #[test]
+    fn test_send_error_classification() {
+        let timeout = KafkaConfig::new("localhost:9092").message_timeout();
+        assert_eq!(timeout, Duration::from_secs(300));
+
+        let queue_full = KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull);
+        assert!(matches!(
+            send_error("nier.detections", queue_full, timeout),
+            ProducerError::QueueFull { topic } if topic == "nier.detections"
+        ));
+
+        let timed_out = KafkaError::MessageProduction(RDKafkaErrorCode::MessageTimedOut);
+        assert!(matches!(
+            send_error("nier.detections", timed_out, timeout),
+            ProducerError::Timeout(t) if t == timeout
+        ));
+
+        let too_large = KafkaError::MessageProduction(RDKafkaErrorCode::MessageSizeTooLarge);
+        assert!(matches!(
+            send_error("nier.detections", too_large.clone(), timeout),
+            ProducerError::SendError { .. }
+        ));
+
+        // Only congestion is worth retrying; an oversized message stays oversized
+        // and a timed-out one may already have been delivered
+        let queue_full = KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull);
+        assert!(send_error("nier.detections", queue_full, timeout).is_retriable());
+        assert!(!send_error("nier.detections", too_large, timeout).is_retriable());
+        let timed_out = KafkaError::MessageProduction(RDKafkaErrorCode::MessageTimedOut);
+        assert!(!send_error("nier.detections", timed_out, timeout).is_retriable());
+    }
+
+    #[derive(serde::Serialize)]
+    struct TestEvent {
+        id: String,
+    }
+
+    fn content_type(message: &OutgoingMessage) -> Option<&str> {
+        message
+            .headers
+            .iter()
+            .find(|(k, _)| k == "content-type")
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_typed_message_formats() {
+        let id = "evt-1".to_string();
+
+        let proto = OutgoingMessage::new_typed("nier.detections", TypedPayload::proto(&id));
+        assert_eq!(content_type(&proto), Some("application/x-protobuf"));
+        assert_eq!(String::decode(proto.payload.as_slice()).unwrap(), id);
+
+        // Plain serde types need not implement `Message` to be sent as JSON
+        let event = TestEvent { id };
+        let payload = TypedPayload::json(&event).unwrap();
+        let json = OutgoingMessage::new_typed("nier.detections", payload);
+        assert_eq!(content_type(&json), Some("application/json"));
+        assert_eq!(json.payload, br#"{"id":"evt-1"}"#);
+
+        assert_eq!(
+            Format::from_content_type(content_type(&json).unwrap()),
+            Some(Format::Json)
+        );
+    }
+
+    #[test]
+    fn test_typed_helpers_keep_format_and_headers() {
+        let topics = TopicConfig::default();
+        let event = TestEvent {
+            id: "evt-1".to_string(),
+        };
+
+        let payload = TypedPayload::json(&event).unwrap();
+        let message = detection_event_message(&topics, payload, "evt-1");
+        assert_eq!(message.topic, topics.detections);
+        assert_eq!(content_type(&message), Some("application/json"));
+        assert_eq!(message.key.as_deref(), Some("evt-1"));
+        assert!(message
+            .headers
+            .contains(&("correlation-id".to_string(), "evt-1".to_string())));
+
+        let payload = TypedPayload::proto(&"frame-1".to_string());
+        let message = frame_metadata_message(&topics, payload, "frame-1");
+        assert_eq!(message.topic, topics.frames);
+        assert_eq!(content_type(&message), Some("application/x-protobuf"));
+        assert!(message
+            .headers
+            .contains(&("message-type".to_string(), "frame_metadata".to_string())));
+    }
+
+    #[cfg(feature = "proto")]
+    #[test]
+    fn test_generated_detection_event_is_sent_as_proto() {
+        use crate::proto::DetectionEvent;
+
+        let event = DetectionEvent {
+            event_id: "evt-1".to_string(),
+            frame_id: "frame-1".to_string(),
+            device_id: "glasses-001".to_string(),
+            timestamp: Some(prost_types::Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 0,
+            }),
+            model_id: "ppe-detector".to_string(),
+            ..Default::default()
+        };
+
+        let topics = TopicConfig::default();
+        let message = detection_event_message(&topics, TypedPayload::proto(&event), "evt-1");
+        assert_eq!(message.topic, topics.detections);
+        assert_eq!(content_type(&message), Some("application/x-protobuf"));
+        assert_eq!(message.key.as_deref(), Some("evt-1"));
+        assert_eq!(DetectionEvent::decode(message.payload.as_slice()).unwrap(), event);
+    }
+
+    #[test]
+    fn test_build_does_not_contact_a_broker() {
+        // Nothing listens on port 1; creating the producer must not wait for it
+        let started = Instant::now();
+        let producer = ProducerBuilder::new("127.0.0.1:1")
+            .connect_probe_timeout(Duration::from_secs(10))
+            .build();
+        assert!(producer.is_ok());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_profile_message_uses_profile_producer_config() {
+        let producer = ProducerBuilder::new("localhost:9092")
+            .linger_ms(20)
+            .compression("zstd")
+            .profile(
+                "low_latency",
+                ProducerProfile {
+                    linger_ms: Some(0),
+                    acks: Some("1".to_string()),
+                    max_in_flight_requests: Some(1),
+                    ..Default::default()
+                },
+            )
+            .build()
+            .unwrap();
+
+        let alert = OutgoingMessage::new_json("nier.alerts", &serde_json::json!({}))
+            .unwrap()
+            .with_profile("low_latency");
+        let profile = producer.profile_for(&alert);
+        assert_eq!(profile, Some("low_latency"));
+
+        let client_config = producer.config().build_profile_producer_config(profile);
+        assert_eq!(client_config.get("linger.ms"), Some("0"));
+        assert_eq!(client_config.get("acks"), Some("1"));
+        assert_eq!(client_config.get("enable.idempotence"), None);
+        // Settings the profile leaves unset are inherited
+        assert_eq!(client_config.get("compression.type"), Some("zstd"));
+
+        let frame = OutgoingMessage::new_json("nier.frames", &serde_json::json!({})).unwrap();
+        assert_eq!(producer.profile_for(&frame), None);
+        let client_config = producer.config().build_profile_producer_config(None);
+        assert_eq!(client_config.get("linger.ms"), Some("20"));
+        assert_eq!(client_config.get("acks"), Some("all"));
+
+        // Unknown profiles fall back to the default producer
+        let unknown = frame.with_profile("bulk");
+        assert_eq!(producer.profile_for(&unknown), None);
     }
 }
@@ -0,0 +1,32 @@
+//! Payload transforms applied at the edges of the pipeline.
+//!
+//! A `PayloadTransform` rewrites message payloads on their way to and from
+//! Kafka — for example field-level encryption of PII in frame metadata, or
+//! compression of large payloads. The producer applies `encode` just before a
+//! message is sent and the consumer applies `decode` before the handler sees
+//! it, so handlers and callers only ever deal with plain payloads.
+
+use thiserror::Error;
+
+/// Error returned by a payload transform
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct TransformError(pub String);
+
+impl TransformError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+/// Reversible rewrite of message payloads
+///
+/// `decode(encode(p))` must return `p`. Both directions see the topic so one
+/// transform can treat topics differently (e.g. only encrypt frame metadata).
+pub trait PayloadTransform: Send + Sync {
+    /// Transform a payload before it is produced to `topic`
+    fn encode(&self, topic: &str, payload: Vec<u8>) -> Result<Vec<u8>, TransformError>;
+
+    /// Undo `encode` on a payload consumed from `topic`
+    fn decode(&self, topic: &str, payload: Vec<u8>) -> Result<Vec<u8>, TransformError>;
+}
@@ -0,0 +1,222 @@
+//! Retries with exponential backoff.
+//!
+//! Shared by the Nier services. Transient failures (a dropped database
+//! connection, a full producer queue, an S3 hiccup, an inference server that
+//! is still starting) are worth retrying after a short wait; permanent ones
+//! are not. `retry_with_backoff` runs an operation until it succeeds, returns a
+//! non-retriable error, or runs out of attempts, sleeping an exponentially
+//! growing, jittered delay between attempts.
+
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tracing::warn;
+
+/// How often and how long to wait between attempts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackoffPolicy {
+    /// Total attempts, including the first; 0 is treated as 1
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    /// Upper bound for any single delay
+    pub max_delay: Duration,
+    /// Factor the delay grows by after each retry
+    pub multiplier: f64,
+    /// Fraction of each delay that is randomized (0.0 - 1.0), so clients
+    /// failing together do not retry in lockstep
+    pub jitter: f64,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Policy with `max_attempts` attempts and defaults otherwise
+    pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_delay,
+            ..Default::default()
+        }
+    }
+
+    /// Set the delay cap
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Set the jitter fraction
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delay before retry number `retry` (0-based), before jitter
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.min(32) as i32);
+        let delay = self.initial_delay.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+    }
+
+    /// Delay before retry number `retry`, with up to `jitter` of it removed
+    fn jittered_delay_for(&self, retry: u32) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        self.delay_for(retry)
+            .mul_f64(1.0 - jitter * random_fraction())
+    }
+}
+
+/// Run `op` until it succeeds, fails with an error `is_retriable` rejects,
+/// or `policy.max_attempts` attempts have been made
+///
+/// The last error is returned when giving up.
+///
+/// ```rust,no_run
+/// # use nier_retry::{retry_with_backoff, BackoffPolicy};
+/// # async fn fetch() -> Result<String, std::io::Error> { Ok(String::new()) }
+/// # async fn run() {
+/// let policy = BackoffPolicy::default();
+/// let result = retry_with_backoff(
+///     fetch,
+///     &policy,
+///     |e| e.kind() != std::io::ErrorKind::InvalidInput,
+/// )
+/// .await;
+/// # }
+/// ```
+pub async fn retry_with_backoff<T, E, F, Fut, P>(
+    mut op: F,
+    policy: &BackoffPolicy,
+    is_retriable: P,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < max_attempts && is_retriable(&e) => {
+                let delay = policy.jittered_delay_for(attempt - 1);
+                warn!(
+                    attempt = attempt,
+                    max_attempts = max_attempts,
+                    delay_ms = delay.as_millis() as u64,
+                    error = %e,
+                    "Operation failed, retrying"
+                );
+                metrics::counter!("nier.retry.attempts").increment(1);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Uniform value in [0, 1) from the std hasher's random keys
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_attempts: u32) -> BackoffPolicy {
+        BackoffPolicy::new(max_attempts, Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_succeeds_after_retries() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<&str, String> = retry_with_backoff(
+            || async {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err("connection reset".to_string())
+                } else {
+                    Ok("done")
+                }
+            },
+            &fast_policy(5),
+            |_| true,
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), String> = retry_with_backoff(
+            || async {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                Err(format!("failure {}", attempt))
+            },
+            &fast_policy(3),
+            |_| true,
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err(), "failure 3");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_non_retriable_error_is_returned_immediately() {
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<(), String> = retry_with_backoff(
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err("invalid message".to_string())
+            },
+            &fast_policy(5),
+            |e| !e.starts_with("invalid"),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_delays_grow_and_are_capped() {
+        let policy = BackoffPolicy::new(10, Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(500))
+            .with_jitter(0.5);
+
+        assert_eq!(policy.delay_for(0), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(1), Duration::from_millis(200));
+        assert_eq!(policy.delay_for(3), Duration::from_millis(500));
+
+        for retry in 0..5 {
+            let delay = policy.jittered_delay_for(retry);
+            assert!(delay <= policy.delay_for(retry));
+            assert!(delay >= policy.delay_for(retry) / 2);
+        }
+    }
+}
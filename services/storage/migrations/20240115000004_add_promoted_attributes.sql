@@ -0,0 +1,10 @@
+-- Promote selected detection attributes to a frame-level indexed column
+-- The full attributes blob is still kept per detection in the detections table
+
+ALTER TABLE frames
+    ADD COLUMN IF NOT EXISTS promoted_attributes JSONB NOT NULL DEFAULT '{}';
+
+-- GIN index for containment queries (e.g. promoted_attributes @> '{"worker_posture": ["fallen"]}')
+CREATE INDEX IF NOT EXISTS idx_frames_promoted_attributes ON frames USING gin (promoted_attributes);
+
+COMMENT ON COLUMN frames.promoted_attributes IS 'Configured detection attribute keys mapped to the distinct values seen in the frame';
@@ -0,0 +1,12 @@
+-- Track frames whose S3 object has been removed by retention while the
+-- metadata row is kept for search and reporting
+
+ALTER TABLE frames
+    ADD COLUMN IF NOT EXISTS archived BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+
+-- Find frames still holding an S3 object, oldest first
+CREATE INDEX IF NOT EXISTS idx_frames_unarchived_timestamp ON frames (timestamp) WHERE archived = FALSE;
+
+COMMENT ON COLUMN frames.archived IS 'True once the S3 object has been deleted by retention; s3_key is kept for audit';
+COMMENT ON COLUMN frames.archived_at IS 'When the S3 object was deleted by retention';
@@ -0,0 +1,25 @@
+-- Audit log of every frame-selection decision (store or skip)
+-- Written only when decision_log is enabled with the postgres sink
+
+CREATE TABLE IF NOT EXISTS frame_selection_decisions (
+    id BIGSERIAL PRIMARY KEY,
+
+    -- Event the decision was made for
+    event_id UUID NOT NULL,
+    device_id VARCHAR(255) NOT NULL,
+    trigger_type VARCHAR(64) NOT NULL,
+
+    -- Decision outcome: 'store' or 'skip'
+    decision VARCHAR(16) NOT NULL,
+    reason TEXT NOT NULL,
+
+    -- Frame capture time and decision time
+    frame_timestamp TIMESTAMPTZ NOT NULL,
+    decided_at TIMESTAMPTZ NOT NULL
+);
+
+-- Audit queries by device and time range
+CREATE INDEX IF NOT EXISTS idx_frame_selection_decisions_device_decided_at ON frame_selection_decisions (device_id, decided_at DESC);
+
+-- Look up the decision for a specific event
+CREATE INDEX IF NOT EXISTS idx_frame_selection_decisions_event_id ON frame_selection_decisions (event_id);
@@ -0,0 +1,17 @@
+-- Frames may be downscaled before upload (s3.storage_max_dimension); keep the
+-- resolution the frame arrived at alongside the stored width/height
+
+ALTER TABLE frames
+    ADD COLUMN IF NOT EXISTS original_width INTEGER,
+    ADD COLUMN IF NOT EXISTS original_height INTEGER;
+
+UPDATE frames
+SET original_width = width, original_height = height
+WHERE original_width IS NULL;
+
+ALTER TABLE frames
+    ALTER COLUMN original_width SET NOT NULL,
+    ALTER COLUMN original_height SET NOT NULL;
+
+COMMENT ON COLUMN frames.original_width IS 'Width of the frame as received, before any storage downscale';
+COMMENT ON COLUMN frames.original_height IS 'Height of the frame as received, before any storage downscale';
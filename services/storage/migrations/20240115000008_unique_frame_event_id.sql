@@ -0,0 +1,14 @@
+-- One metadata row per storage event, so reprocessing an event updates its
+-- row instead of adding a duplicate
+
+-- Keep the earliest row for events that were indexed more than once
+DELETE FROM frames f
+USING frames keep
+WHERE f.event_id = keep.event_id
+  AND (f.created_at, f.id) > (keep.created_at, keep.id);
+
+-- The unique constraint's index replaces the plain event_id index
+DROP INDEX IF EXISTS idx_frames_event_id;
+
+ALTER TABLE frames
+    ADD CONSTRAINT frames_event_id_key UNIQUE (event_id);
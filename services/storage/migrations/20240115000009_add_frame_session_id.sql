@@ -0,0 +1,11 @@
+-- Stream session a frame belongs to. Frame numbers restart at 0 when a
+-- device's stream reconnects, so (device_id, session_id, frame_number)
+-- identifies a frame where (device_id, frame_number) alone does not
+
+ALTER TABLE frames
+    ADD COLUMN IF NOT EXISTS session_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_frames_device_session_frame
+    ON frames (device_id, session_id, frame_number);
+
+COMMENT ON COLUMN frames.session_id IS 'Stream session ID; a new session starts when the frame sequence resets';
@@ -0,0 +1,4 @@
+-- Look up a frame's journey by the trace ID carried in its event metadata
+
+CREATE INDEX IF NOT EXISTS idx_frames_trace_id
+    ON frames ((metadata->>'trace_id'));
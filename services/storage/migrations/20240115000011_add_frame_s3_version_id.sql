@@ -0,0 +1,8 @@
+-- S3 version of the stored object. On versioned buckets a re-uploaded frame
+-- keeps its key, so the version ID is needed to retrieve the copy the frame
+-- row was indexed with
+
+ALTER TABLE frames
+    ADD COLUMN IF NOT EXISTS s3_version_id TEXT;
+
+COMMENT ON COLUMN frames.s3_version_id IS 'S3 object version ID; NULL on unversioned buckets';
@@ -0,0 +1,11 @@
+-- Factory shift a frame was captured in, derived from its timestamp and the
+-- configured shift schedule. NULL when no shifts are configured
+
+ALTER TABLE frames
+    ADD COLUMN IF NOT EXISTS shift TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_frames_shift_timestamp
+    ON frames (shift, timestamp DESC)
+    WHERE shift IS NOT NULL;
+
+COMMENT ON COLUMN frames.shift IS 'Shift name from s3.shifts, e.g. day/swing/night';
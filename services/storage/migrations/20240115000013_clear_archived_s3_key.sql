@@ -0,0 +1,9 @@
+-- Archived frames no longer have an S3 object, so their key is cleared
+-- rather than left pointing at a deleted object
+
+ALTER TABLE frames ALTER COLUMN s3_key DROP NOT NULL;
+
+UPDATE frames SET s3_key = NULL WHERE archived = TRUE AND s3_key IS NOT NULL;
+
+COMMENT ON COLUMN frames.s3_key IS 'S3 object key where the frame is stored; NULL once archived';
+COMMENT ON COLUMN frames.archived IS 'True once the S3 object has been deleted by retention';
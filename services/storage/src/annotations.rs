@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Typed view of a detection's `attributes`
+///
+/// Known annotation schemas (pose keypoints, segmentation mask references)
+/// are parsed into structs; every other field is kept untouched in `extra`
+/// so nothing inference attached is lost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetectionAttributes {
+    /// Pose keypoints, in normalized image coordinates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypoints: Option<Vec<Keypoint>>,
+    /// Reference to a segmentation mask stored outside the detection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask_ref: Option<MaskRef>,
+    /// Attributes without a known schema
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A single pose keypoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keypoint {
+    /// Joint name (e.g. `left_wrist`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// X coordinate normalized 0-1
+    pub x: f32,
+    /// Y coordinate normalized 0-1
+    pub y: f32,
+    /// Keypoint confidence (0.0 - 1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+/// Pointer to a segmentation mask
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaskRef {
+    /// Where the mask is stored (e.g. `s3://bucket/masks/...`)
+    pub uri: String,
+    /// Mask encoding (e.g. `rle`, `png`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Mask width in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Mask height in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl DetectionAttributes {
+    /// Parse stored attributes
+    ///
+    /// A known key whose value does not match its schema is kept in `extra`
+    /// as-is rather than failing the whole detection.
+    pub fn from_value(value: &Value) -> Self {
+        let Value::Object(fields) = value else {
+            return Self::default();
+        };
+
+        let mut extra = fields.clone();
+        let keypoints = take_typed(&mut extra, "keypoints");
+        let mask_ref = take_typed(&mut extra, "mask_ref");
+
+        Self {
+            keypoints,
+            mask_ref,
+            extra,
+        }
+    }
+}
+
+/// Remove `key` from `fields` if it parses as `T`
+fn take_typed<T: serde::de::DeserializeOwned>(
+    fields: &mut Map<String, Value>,
+    key: &str,
+) -> Option<T> {
+    let parsed = serde_json::from_value(fields.get(key)?.clone()).ok()?;
+    fields.remove(key);
+    Some(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_schemas_are_typed_and_unknown_fields_kept() {
+        let value = serde_json::json!({
+            "keypoints": [
+                { "name": "left_wrist", "x": 0.25, "y": 0.5, "confidence": 0.75 },
+                { "x": 0.375, "y": 0.625 }
+            ],
+            "mask_ref": { "uri": "s3://nier-masks/frame-1.rle", "format": "rle" },
+            "tracking_id": 7
+        });
+
+        let attributes = DetectionAttributes::from_value(&value);
+
+        let keypoints = attributes.keypoints.as_ref().unwrap();
+        assert_eq!(keypoints.len(), 2);
+        assert_eq!(keypoints[0].name.as_deref(), Some("left_wrist"));
+        assert_eq!(keypoints[1].confidence, None);
+        assert_eq!(
+            attributes.mask_ref.as_ref().unwrap().uri,
+            "s3://nier-masks/frame-1.rle"
+        );
+        assert_eq!(attributes.extra["tracking_id"], 7);
+
+        // Serializes back to the stored shape
+        assert_eq!(serde_json::to_value(&attributes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_malformed_known_field_is_preserved() {
+        let value = serde_json::json!({ "keypoints": "not-a-list" });
+
+        let attributes = DetectionAttributes::from_value(&value);
+
+        assert_eq!(attributes.keypoints, None);
+        assert_eq!(attributes.extra["keypoints"], "not-a-list");
+        assert_eq!(
+            DetectionAttributes::from_value(&Value::Null),
+            DetectionAttributes::default()
+        );
+    }
+}
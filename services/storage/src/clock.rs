@@ -0,0 +1,50 @@
+//! Time sources for time-dependent selection logic.
+//!
+//! Frame-age checks, hourly and daily caps read the time through a `Clock`
+//! so tests can drive them with a `MockClock` instead of sleeping.
+
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current wall-clock time
+    fn now_utc(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock that only moves when told to
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Clock stopped at `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Move the clock forward by `by`
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += chrono::Duration::from_std(by).expect("duration out of range");
+    }
+}
+
+impl Clock for MockClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
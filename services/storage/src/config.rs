@@ -1,4 +1,6 @@
-use serde::Deserialize;
+use crate::kafka_consumer::TriggerType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 
 /// Main configuration for the storage service
@@ -16,6 +18,12 @@ pub struct Config {
     pub frame_selection: FrameSelectionConfig,
     /// API configuration
     pub api: ApiConfig,
+    /// Retention configuration
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Frame-selection decision log configuration
+    #[serde(default)]
+    pub decision_log: DecisionLogConfig,
 }
 
 /// Service-level configuration
@@ -64,6 +72,10 @@ pub struct KafkaConfig {
     /// Max poll interval in milliseconds
     #[serde(default = "default_max_poll_interval_ms")]
     pub max_poll_interval_ms: u32,
+    /// Drop in a device's frame number that starts a new stream session, for
+    /// events that arrive without a `session_id`
+    #[serde(default = "default_session_reset_threshold")]
+    pub session_reset_threshold: u64,
 }
 
 /// S3 storage configuration
@@ -85,12 +97,106 @@ pub struct S3Config {
     /// Upload concurrency limit
     #[serde(default = "default_upload_concurrency")]
     pub upload_concurrency: usize,
+    /// Idle connections kept open per S3 host for reuse across uploads
+    /// (None = `upload_concurrency`). Not a cap: busy connections are not
+    /// counted, so concurrency is bounded by `upload_concurrency` alone.
+    #[serde(default)]
+    pub max_idle_connections: Option<usize>,
     /// Multipart upload threshold in bytes (5MB default)
     #[serde(default = "default_multipart_threshold")]
     pub multipart_threshold_bytes: usize,
     /// Part size for multipart uploads in bytes (5MB default)
     #[serde(default = "default_part_size")]
     pub part_size_bytes: usize,
+    /// Parts of a single multipart upload sent at once
+    #[serde(default = "default_multipart_concurrency")]
+    pub multipart_concurrency: usize,
+    /// Event metadata keys promoted to S3 object tags (max 10 tags per object)
+    #[serde(default)]
+    pub tag_metadata_keys: Vec<String>,
+    /// Namespace prepended to every object key (e.g. a tenant ID), so tenants
+    /// sharing a bucket can be isolated by IAM prefix policies
+    #[serde(default)]
+    pub key_prefix: String,
+    /// Downscale frames so neither side exceeds this many pixels before
+    /// upload, preserving aspect ratio (None = store as received)
+    #[serde(default)]
+    pub storage_max_dimension: Option<u32>,
+    /// Upload with `If-None-Match: *` so an object that already exists under
+    /// the frame's key is left untouched and reported as already stored
+    #[serde(default)]
+    pub conditional_put: bool,
+    /// The bucket has versioning enabled, so deletes remove every version
+    /// by id; needs `s3:ListBucketVersions` and `s3:DeleteObjectVersion`
+    #[serde(default)]
+    pub versioned_bucket: bool,
+    /// IANA timezone shift boundaries and, with shifts configured, key dates
+    /// are evaluated in (e.g. `America/Chicago`)
+    #[serde(default = "default_partition_timezone")]
+    pub partition_timezone: String,
+    /// Factory shifts; when set, keys are partitioned by shift after the date
+    #[serde(default)]
+    pub shifts: Vec<ShiftConfig>,
+    /// Attempts per frame upload, including the first
+    #[serde(default = "default_upload_max_attempts")]
+    pub upload_max_attempts: u32,
+    /// Delay before the first upload retry, doubled for each further attempt
+    #[serde(default = "default_upload_retry_base_delay_ms")]
+    pub upload_retry_base_delay_ms: u64,
+    /// Sub-second digits of the capture time in object filenames
+    #[serde(default)]
+    pub key_timestamp_precision: KeyTimestampPrecision,
+    /// Encoding frames are stored in; frames are re-encoded before upload
+    /// unless already in it
+    #[serde(default)]
+    pub output_format: OutputFormat,
+}
+
+/// Encoding of stored frames
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Store frames in the encoding they arrived in
+    #[default]
+    Passthrough,
+    /// Lossless WebP: smaller than PNG, but usually larger than the JPEG it
+    /// replaces
+    Webp,
+    /// Lossy AVIF at a fixed encoder speed and quality
+    Avif,
+    /// Lossy JPEG at the encoder's default quality; any alpha channel is
+    /// dropped
+    Jpeg,
+}
+
+/// Sub-second precision of the timestamp in frame filenames
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyTimestampPrecision {
+    /// `HHMMSSmmm`, as keys were written before microsecond precision
+    Millis,
+    /// `HHMMSSuuuuuu`, which keeps high-FPS bursts in capture order
+    #[default]
+    Micros,
+}
+
+impl KeyTimestampPrecision {
+    /// chrono format string for the filename timestamp
+    pub fn time_format(self) -> &'static str {
+        match self {
+            KeyTimestampPrecision::Millis => "%H%M%S%3f",
+            KeyTimestampPrecision::Micros => "%H%M%S%6f",
+        }
+    }
+}
+
+/// A factory shift, running from `start` until the next shift starts
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShiftConfig {
+    /// Shift label used in keys and metadata (e.g. `day`, `night`)
+    pub name: String,
+    /// Local start time, `HH:MM`
+    pub start: String,
 }
 
 /// Database configuration
@@ -113,10 +219,29 @@ pub struct DatabaseConfig {
     /// Run migrations on startup
     #[serde(default = "default_run_migrations")]
     pub run_migrations: bool,
+    /// Detection attribute keys promoted to the indexed `promoted_attributes` column
+    #[serde(default)]
+    pub indexed_attribute_keys: Vec<String>,
+    /// Retries for operations that fail with a connection-level error
+    #[serde(default = "default_db_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further attempt
+    #[serde(default = "default_db_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+    /// Distinct detection types kept in a frame's `detection_types` summary
+    #[serde(default = "default_max_detection_types")]
+    pub max_detection_types: usize,
+    /// Longest detection type name kept, in characters; longer names are truncated
+    #[serde(default = "default_max_detection_type_len")]
+    pub max_detection_type_len: usize,
+    /// Decimal places detection confidences are rounded to on index
+    /// (None = store raw values)
+    #[serde(default)]
+    pub confidence_precision: Option<u32>,
 }
 
 /// Frame selection configuration
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FrameSelectionConfig {
     /// Store frames with detections
     #[serde(default = "default_true")]
@@ -127,9 +252,35 @@ pub struct FrameSelectionConfig {
     /// Sample rate: store 1 frame every N frames when no detections
     #[serde(default = "default_sample_rate")]
     pub sample_rate: u32,
+    /// Sample by time instead: store at most 1 frame per device every N
+    /// seconds of capture time (None = count-based `sample_rate`)
+    #[serde(default)]
+    pub sample_interval_secs: Option<u64>,
     /// Store frames marked for debug
     #[serde(default = "default_true")]
     pub store_debug: bool,
+    /// Store 1 debug frame every N per device
+    #[serde(default = "default_debug_sample_rate")]
+    pub debug_sample_rate: u32,
+    /// Maximum debug frames stored per device per hour (None = uncapped)
+    #[serde(default)]
+    pub max_debug_frames_per_hour: Option<u64>,
+    /// Store detection frames without detections when inference failed on
+    /// them (flagged by `metadata.inference_error`)
+    #[serde(default)]
+    pub store_inference_errors: bool,
+    /// Store 1 of every N consecutive detection frames per device; the first
+    /// frame of each burst is always stored (1 = store every frame)
+    #[serde(default = "default_detection_decimation")]
+    pub detection_decimation: u32,
+    /// Gap between detection frames, in milliseconds, that ends a burst
+    #[serde(default = "default_detection_burst_gap_ms")]
+    pub detection_burst_gap_ms: u64,
+    /// After storing a detection of a type, skip further frames from the same
+    /// device whose detections are all of types stored within this many
+    /// seconds (0 = no cooldown)
+    #[serde(default)]
+    pub detection_cooldown_secs: u64,
     /// Minimum confidence threshold for storing detection frames
     #[serde(default = "default_min_confidence")]
     pub min_confidence: f32,
@@ -139,6 +290,123 @@ pub struct FrameSelectionConfig {
     /// Maximum frame age in seconds (reject frames older than this)
     #[serde(default = "default_max_frame_age_secs")]
     pub max_frame_age_secs: u64,
+    /// Smallest inline frame, in bytes, worth storing; smaller frames are
+    /// treated as failed upstream encodes and skipped
+    #[serde(default = "default_min_frame_bytes")]
+    pub min_frame_bytes: usize,
+    /// Confidence profiles keyed by the producing model version
+    #[serde(default)]
+    pub confidence_profiles: HashMap<String, ConfidenceProfile>,
+    /// Only store frames from these devices (empty = all). Entries ending in
+    /// `*` match by prefix.
+    #[serde(default)]
+    pub device_allowlist: Vec<String>,
+    /// Never store frames from these devices. Entries ending in `*` match by
+    /// prefix. Takes precedence over the allowlist.
+    #[serde(default)]
+    pub device_denylist: Vec<String>,
+    /// Maximum frames stored per device per local day, by trigger type.
+    /// Trigger types without an entry are never capped.
+    #[serde(default)]
+    pub daily_quotas: HashMap<TriggerType, u64>,
+    /// Per-device settings that replace the global ones for that device
+    #[serde(default, skip_serializing)]
+    pub device_overrides: HashMap<String, DeviceSelectionOverride>,
+}
+
+/// Frame selection settings overridden for a single device
+///
+/// Unset fields fall back to the global frame selection settings.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DeviceSelectionOverride {
+    /// Store frames with detections
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_detections: Option<bool>,
+    /// Store periodic sample frames (even without detections)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_samples: Option<bool>,
+    /// Sample rate: store 1 frame every N frames when no detections
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Minimum confidence threshold for storing detection frames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f32>,
+    /// Detection types to store (empty = all)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_types: Option<Vec<String>>,
+}
+
+/// Confidence thresholds calibrated for a specific model version
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfidenceProfile {
+    /// Minimum confidence for storing detection frames from this model
+    pub min_confidence: f32,
+}
+
+impl FrameSelectionConfig {
+    /// Minimum confidence for detections produced by `model_version`
+    ///
+    /// Falls back to the global `min_confidence` when no profile matches.
+    pub fn min_confidence_for(&self, model_version: Option<&str>) -> f32 {
+        model_version
+            .and_then(|v| self.confidence_profiles.get(v))
+            .map(|p| p.min_confidence)
+            .unwrap_or(self.min_confidence)
+    }
+
+    /// Reason to skip every frame from `device_id` under the device
+    /// allow/denylists, if any
+    pub fn device_skip_reason(&self, device_id: &str) -> Option<String> {
+        if let Some(pattern) = find_device_pattern(&self.device_denylist, device_id) {
+            return Some(format!(
+                "Device {} is denylisted (matched {})",
+                device_id, pattern
+            ));
+        }
+
+        if !self.device_allowlist.is_empty()
+            && find_device_pattern(&self.device_allowlist, device_id).is_none()
+        {
+            return Some(format!("Device {} is not in the allowlist", device_id));
+        }
+
+        None
+    }
+
+    /// Settings in force for `device_id`: the global settings with the
+    /// device's override, if any, applied on top
+    pub fn for_device(&self, device_id: &str) -> Option<FrameSelectionConfig> {
+        let device = self.device_overrides.get(device_id)?;
+        let mut config = self.clone();
+        config.device_overrides = HashMap::new();
+        if let Some(enabled) = device.store_detections {
+            config.store_detections = enabled;
+        }
+        if let Some(enabled) = device.store_samples {
+            config.store_samples = enabled;
+        }
+        if let Some(rate) = device.sample_rate {
+            config.sample_rate = rate;
+        }
+        if let Some(confidence) = device.min_confidence {
+            config.min_confidence = confidence;
+        }
+        if let Some(types) = &device.detection_types {
+            config.detection_types = types.clone();
+        }
+        Some(config)
+    }
+}
+
+/// First pattern matching `device_id`; a trailing `*` matches by prefix
+fn find_device_pattern<'a>(patterns: &'a [String], device_id: &str) -> Option<&'a str> {
+    patterns
+        .iter()
+        .find(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => device_id.starts_with(prefix),
+            None => device_id == pattern.as_str(),
+        })
+        .map(String::as_str)
 }
 
 /// API configuration for presigned URL endpoint
@@ -153,9 +421,73 @@ pub struct ApiConfig {
     /// Enable CORS
     #[serde(default = "default_true")]
     pub cors_enabled: bool,
-    /// Allowed CORS origins
+    /// Allowed CORS origins (exact, or `https://*.example.com` for subdomains)
     #[serde(default)]
     pub cors_origins: Vec<String>,
+    /// Bearer token required by destructive endpoints (unset = disabled)
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Most frames a single ZIP export may contain
+    #[serde(default = "default_zip_export_max_frames")]
+    pub zip_export_max_frames: usize,
+}
+
+/// Retention policy configuration
+///
+/// S3 objects and metadata rows have separate cutoffs so searchable metadata
+/// can outlive the (more expensive) stored frames.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionConfig {
+    /// Enable the background retention task
+    #[serde(default)]
+    pub enabled: bool,
+    /// Delete S3 objects older than this many days, keeping metadata (None = keep forever)
+    #[serde(default)]
+    pub s3_retention_days: Option<u32>,
+    /// Delete metadata rows older than this many days (None = keep forever)
+    #[serde(default)]
+    pub metadata_retention_days: Option<u32>,
+    /// Maximum frames archived per batch
+    #[serde(default = "default_retention_batch_size")]
+    pub batch_size: i64,
+    /// Interval between retention runs in seconds
+    #[serde(default = "default_retention_interval_secs")]
+    pub interval_secs: u64,
+    /// Check that indexed S3 objects still exist and tombstone rows whose
+    /// objects were removed out-of-band
+    #[serde(default)]
+    pub verify_objects: bool,
+    /// Maximum S3 existence checks per second during verification (0 = unlimited)
+    #[serde(default = "default_verify_rate_per_sec")]
+    pub verify_rate_per_sec: u32,
+}
+
+/// Destination for frame-selection decision records
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionLogSink {
+    /// Publish JSON records to a Kafka topic
+    #[default]
+    Kafka,
+    /// Insert rows into the `frame_selection_decisions` table
+    Postgres,
+}
+
+/// Decision log configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct DecisionLogConfig {
+    /// Record every store/skip decision
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where to write decision records
+    #[serde(default)]
+    pub sink: DecisionLogSink,
+    /// Kafka topic for the kafka sink
+    #[serde(default = "default_decision_log_topic")]
+    pub topic: String,
+    /// Records buffered ahead of the sink; further records are dropped
+    #[serde(default = "default_decision_log_queue_size")]
+    pub queue_size: usize,
 }
 
 // Default value functions
@@ -195,6 +527,10 @@ fn default_max_poll_interval_ms() -> u32 {
     300000
 }
 
+fn default_session_reset_threshold() -> u64 {
+    crate::sessions::DEFAULT_SESSION_RESET_THRESHOLD
+}
+
 fn default_region() -> String {
     "us-east-1".to_string()
 }
@@ -203,6 +539,10 @@ fn default_presigned_url_expiry_secs() -> u64 {
     3600
 }
 
+fn default_partition_timezone() -> String {
+    "UTC".to_string()
+}
+
 fn default_upload_concurrency() -> usize {
     10
 }
@@ -215,6 +555,18 @@ fn default_part_size() -> usize {
     5 * 1024 * 1024 // 5MB
 }
 
+fn default_multipart_concurrency() -> usize {
+    4
+}
+
+fn default_upload_max_attempts() -> u32 {
+    3
+}
+
+fn default_upload_retry_base_delay_ms() -> u64 {
+    200
+}
+
 fn default_max_connections() -> u32 {
     10
 }
@@ -235,10 +587,42 @@ fn default_run_migrations() -> bool {
     true
 }
 
+fn default_db_max_retries() -> u32 {
+    3
+}
+
+fn default_db_retry_base_delay_ms() -> u64 {
+    200
+}
+
+fn default_max_detection_types() -> usize {
+    32
+}
+
+fn default_max_detection_type_len() -> usize {
+    64
+}
+
 fn default_true() -> bool {
     true
 }
 
+fn default_debug_sample_rate() -> u32 {
+    1
+}
+
+fn default_detection_decimation() -> u32 {
+    1
+}
+
+fn default_detection_burst_gap_ms() -> u64 {
+    1000
+}
+
+fn default_min_frame_bytes() -> usize {
+    1
+}
+
 fn default_sample_rate() -> u32 {
     30 // Store 1 frame per second at 30fps
 }
@@ -259,6 +643,30 @@ fn default_api_port() -> u16 {
     8080
 }
 
+fn default_zip_export_max_frames() -> usize {
+    1000
+}
+
+fn default_decision_log_topic() -> String {
+    "nier.storage.decisions".to_string()
+}
+
+fn default_decision_log_queue_size() -> usize {
+    1024
+}
+
+fn default_retention_batch_size() -> i64 {
+    500
+}
+
+fn default_retention_interval_secs() -> u64 {
+    3600
+}
+
+fn default_verify_rate_per_sec() -> u32 {
+    50
+}
+
 impl Config {
     /// Load configuration from environment and config files
     pub fn load() -> anyhow::Result<Self> {
@@ -319,6 +727,31 @@ impl Default for ServiceConfig {
     }
 }
 
+impl Default for DecisionLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sink: DecisionLogSink::default(),
+            topic: default_decision_log_topic(),
+            queue_size: default_decision_log_queue_size(),
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            s3_retention_days: None,
+            metadata_retention_days: None,
+            batch_size: default_retention_batch_size(),
+            interval_secs: default_retention_interval_secs(),
+            verify_objects: false,
+            verify_rate_per_sec: default_verify_rate_per_sec(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
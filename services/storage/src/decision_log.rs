@@ -0,0 +1,315 @@
+use crate::config::KafkaConfig;
+use crate::frame_selector::StorageDecision;
+use crate::kafka_consumer::{base_client_config, StorageTriggerEvent, TriggerType};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Outcome of a frame-selection decision
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    Store,
+    Skip,
+}
+
+impl DecisionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionOutcome::Store => "store",
+            DecisionOutcome::Skip => "skip",
+        }
+    }
+}
+
+/// Auditable record of a single store/skip decision
+#[derive(Debug, Clone, Serialize)]
+pub struct DecisionRecord {
+    /// Event the decision was made for
+    pub event_id: Uuid,
+    /// Device that produced the frame
+    pub device_id: String,
+    /// Trigger type of the event
+    pub trigger_type: TriggerType,
+    /// Whether the frame was stored
+    pub decision: DecisionOutcome,
+    /// Reason reported by the frame selector
+    pub reason: String,
+    /// Frame capture timestamp
+    pub frame_timestamp: DateTime<Utc>,
+    /// When the decision was made
+    pub decided_at: DateTime<Utc>,
+}
+
+impl DecisionRecord {
+    /// Build a record for `decision` on `event`
+    pub fn new(event: &StorageTriggerEvent, decision: &StorageDecision) -> Self {
+        let (outcome, reason) = match decision {
+            StorageDecision::Store { reason } => (DecisionOutcome::Store, reason),
+            StorageDecision::Skip { reason } => (DecisionOutcome::Skip, reason),
+        };
+
+        Self {
+            event_id: event.event_id,
+            device_id: event.device_id.clone(),
+            trigger_type: event.trigger_type.clone(),
+            decision: outcome,
+            reason: reason.clone(),
+            frame_timestamp: event.timestamp,
+            decided_at: Utc::now(),
+        }
+    }
+}
+
+/// Destination for decision records
+#[async_trait]
+pub trait DecisionSink: Send + Sync {
+    /// Write a single decision record
+    async fn record(&self, record: &DecisionRecord) -> Result<()>;
+}
+
+/// Writes decision records to a sink from a background task
+///
+/// Records are queued on a bounded channel so a slow sink never stalls
+/// message processing. When the queue is full the record is dropped and
+/// counted in `storage.decision_log.dropped`.
+#[derive(Clone)]
+pub struct DecisionLog {
+    tx: mpsc::Sender<DecisionRecord>,
+}
+
+impl DecisionLog {
+    /// Start writing to `sink`, buffering up to `queue_size` records
+    pub fn spawn(sink: Arc<dyn DecisionSink>, queue_size: usize) -> Self {
+        let (tx, mut rx) = mpsc::channel::<DecisionRecord>(queue_size.max(1));
+
+        tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                if let Err(e) = sink.record(&record).await {
+                    warn!(
+                        event_id = %record.event_id,
+                        error = %e,
+                        "Failed to write decision log record"
+                    );
+                    metrics::counter!("storage.decision_log.errors").increment(1);
+                }
+            }
+        });
+
+        Self { tx }
+    }
+
+    /// Queue a record without waiting for the sink
+    pub fn submit(&self, record: DecisionRecord) {
+        if self.tx.try_send(record).is_err() {
+            metrics::counter!("storage.decision_log.dropped").increment(1);
+        }
+    }
+}
+
+/// Publishes decision records as JSON to a Kafka topic, keyed by device
+pub struct KafkaDecisionSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaDecisionSink {
+    /// Create a Kafka sink using the service's Kafka connection settings
+    pub fn new(config: &KafkaConfig, topic: impl Into<String>) -> Result<Self> {
+        let producer: FutureProducer = base_client_config(config)
+            .create()
+            .context("Failed to create decision log producer")?;
+
+        Ok(Self {
+            producer,
+            topic: topic.into(),
+        })
+    }
+}
+
+#[async_trait]
+impl DecisionSink for KafkaDecisionSink {
+    async fn record(&self, record: &DecisionRecord) -> Result<()> {
+        let payload =
+            serde_json::to_vec(record).context("Failed to serialize decision record")?;
+
+        self.producer
+            .send(
+                FutureRecord::to(&self.topic)
+                    .key(&record.device_id)
+                    .payload(&payload),
+                Duration::from_secs(5),
+            )
+            .await
+            .map_err(|(e, _)| anyhow!("Failed to publish decision record: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Inserts decision records into the `frame_selection_decisions` table
+pub struct PostgresDecisionSink {
+    pool: PgPool,
+}
+
+impl PostgresDecisionSink {
+    /// Create a Postgres sink on an existing pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DecisionSink for PostgresDecisionSink {
+    async fn record(&self, record: &DecisionRecord) -> Result<()> {
+        let trigger_type = serde_json::to_value(&record.trigger_type)?
+            .as_str()
+            .unwrap_or("unknown")
+            .to_string();
+
+        sqlx::query(
+            r#"
+            INSERT INTO frame_selection_decisions (
+                event_id, device_id, trigger_type, decision, reason,
+                frame_timestamp, decided_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(record.event_id)
+        .bind(&record.device_id)
+        .bind(trigger_type)
+        .bind(record.decision.as_str())
+        .bind(&record.reason)
+        .bind(record.frame_timestamp)
+        .bind(record.decided_at)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert decision record")?;
+
+        Ok(())
+    }
+}
+
+/// A decision read back from the `frame_selection_decisions` table
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StoredDecision {
+    pub event_id: Uuid,
+    pub device_id: String,
+    pub trigger_type: String,
+    /// `store` or `skip`
+    pub decision: String,
+    pub reason: String,
+    pub frame_timestamp: DateTime<Utc>,
+    pub decided_at: DateTime<Utc>,
+}
+
+/// Load every recorded decision for an event, oldest first
+pub async fn decisions_for_event(pool: &PgPool, event_id: Uuid) -> Result<Vec<StoredDecision>> {
+    sqlx::query_as::<_, StoredDecision>(
+        r#"
+        SELECT event_id, device_id, trigger_type, decision, reason,
+               frame_timestamp, decided_at
+        FROM frame_selection_decisions
+        WHERE event_id = $1
+        ORDER BY decided_at ASC
+        "#,
+    )
+    .bind(event_id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to load decision records")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kafka_consumer::FrameLocation;
+    use chrono::TimeZone;
+
+    fn test_event() -> StorageTriggerEvent {
+        StorageTriggerEvent {
+            event_id: Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap(),
+            device_id: "glasses-001".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap(),
+            frame_number: 1,
+            session_id: None,
+            frame_data: vec![],
+            width: 1920,
+            height: 1080,
+            format: "jpeg".to_string(),
+            detections: vec![],
+            trigger_type: TriggerType::Sample,
+            metadata: serde_json::Value::Null,
+            frame_location: FrameLocation::Inline,
+        }
+    }
+
+    #[test]
+    fn test_decision_record_structure() {
+        let event = test_event();
+        let decision = StorageDecision::Skip {
+            reason: "Not sampled (rate: 1 per 100 frames)".to_string(),
+        };
+
+        let record = DecisionRecord::new(&event, &decision);
+        let json = serde_json::to_value(&record).unwrap();
+
+        assert_eq!(json["event_id"], "550e8400-e29b-41d4-a716-446655440000");
+        assert_eq!(json["device_id"], "glasses-001");
+        assert_eq!(json["trigger_type"], "sample");
+        assert_eq!(json["decision"], "skip");
+        assert_eq!(json["reason"], "Not sampled (rate: 1 per 100 frames)");
+        assert_eq!(json["frame_timestamp"], "2024-01-15T10:30:00Z");
+        assert!(json["decided_at"].is_string());
+    }
+
+    /// Sink that waits for a permit before each record, then keeps it
+    struct SlowSink {
+        gate: tokio::sync::Semaphore,
+        records: std::sync::Mutex<Vec<DecisionRecord>>,
+    }
+
+    #[async_trait]
+    impl DecisionSink for SlowSink {
+        async fn record(&self, record: &DecisionRecord) -> Result<()> {
+            self.gate.acquire().await?.forget();
+            self.records.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_sink_does_not_block_submit() {
+        let sink = Arc::new(SlowSink {
+            gate: tokio::sync::Semaphore::new(0),
+            records: std::sync::Mutex::new(Vec::new()),
+        });
+        let log = DecisionLog::spawn(sink.clone(), 2);
+        let decision = StorageDecision::Store {
+            reason: "Detection trigger".to_string(),
+        };
+
+        // The sink is stuck, yet submitting never waits; overflow is dropped
+        for _ in 0..10 {
+            log.submit(DecisionRecord::new(&test_event(), &decision));
+        }
+
+        sink.gate.add_permits(10);
+        drop(log);
+        while Arc::strong_count(&sink) > 1 {
+            tokio::task::yield_now().await;
+        }
+
+        // At most the one record in the sink plus the two queued behind it
+        let written = sink.records.lock().unwrap().len();
+        assert!((1..=3).contains(&written));
+    }
+}
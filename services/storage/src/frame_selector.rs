@@ -1,11 +1,12 @@
-use crate::config::FrameSelectionConfig;
-use crate::kafka_consumer::{StorageTriggerEvent, TriggerType};
-use chrono::Utc;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{ConfidenceProfile, FrameSelectionConfig};
+use crate::kafka_consumer::{FrameLocation, StorageTriggerEvent, TriggerType};
+use chrono::{DateTime, DurationRound, Local, NaiveDate, Utc};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::RwLock;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 /// Decision on whether to store a frame
 #[derive(Debug, Clone)]
@@ -16,35 +17,397 @@ pub enum StorageDecision {
     Skip { reason: String },
 }
 
+/// A frame-selection policy
+///
+/// Implement this to plug custom selection (e.g. importance scoring) into a
+/// `FrameSelector` alongside or instead of the built-in rules.
+pub trait SelectionStrategy: Send + Sync {
+    /// Decide whether to store the frame
+    fn decide(&self, event: &StorageTriggerEvent) -> StorageDecision;
+}
+
+/// How a `FrameSelector` combines the decisions of its strategies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChainMode {
+    /// Store if any strategy stores; strategies after the first Store are skipped
+    #[default]
+    FirstStore,
+    /// Store only if every strategy stores; stops at the first Skip
+    Unanimous,
+}
+
 /// Frame selector that decides which frames to store
 ///
+/// Runs a chain of selection strategies, starting with the built-in
+/// `DefaultStrategy`, and combines their decisions according to `ChainMode`.
+/// Frames the chain stores then count against the daily quotas.
+pub struct FrameSelector {
+    default: Arc<DefaultStrategy>,
+    strategies: Vec<Arc<dyn SelectionStrategy>>,
+    mode: ChainMode,
+    quotas: DailyQuotas,
+    reloads: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+impl FrameSelector {
+    /// Create a new frame selector with the given configuration
+    pub fn new(config: FrameSelectionConfig) -> Self {
+        Self::new_with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Create a frame selector that reads the time from `clock`
+    pub fn new_with_clock(config: FrameSelectionConfig, clock: Arc<dyn Clock>) -> Self {
+        let quotas = DailyQuotas::new(config.daily_quotas.clone());
+        let default = Arc::new(DefaultStrategy::new_with_clock(config, clock.clone()));
+
+        Self {
+            strategies: vec![default.clone() as Arc<dyn SelectionStrategy>],
+            default,
+            mode: ChainMode::default(),
+            quotas,
+            reloads: AtomicU64::new(0),
+            clock,
+        }
+    }
+
+    /// Append a strategy to the chain
+    pub fn with_strategy(mut self, strategy: Arc<dyn SelectionStrategy>) -> Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    /// Set how strategy decisions are combined
+    pub fn with_chain_mode(mut self, mode: ChainMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Determine if a frame should be stored
+    ///
+    /// The device allow/denylists, the empty-frame guard and an exhausted
+    /// daily quota skip the frame before any strategy runs, so such frames
+    /// don't use up sampling slots or start cooldowns. A frame counts
+    /// against its quota once the chain decides to store it.
+    pub fn should_store(&self, event: &StorageTriggerEvent) -> StorageDecision {
+        let config = self.default.config();
+        if let Some(reason) = config.device_skip_reason(&event.device_id) {
+            return StorageDecision::Skip { reason };
+        }
+
+        if let Some(reason) = empty_frame_reason(event, config.min_frame_bytes) {
+            metrics::counter!("storage.frames.empty").increment(1);
+            return StorageDecision::Skip { reason };
+        }
+
+        let today = self.clock.now_utc().with_timezone(&Local).date_naive();
+        if let Err(limit) = self
+            .quotas
+            .check(&event.device_id, &event.trigger_type, today)
+        {
+            return quota_exceeded(event, limit);
+        }
+
+        match self.run_chain(event) {
+            StorageDecision::Store { reason } => {
+                // A concurrent frame from the same device may have taken
+                // the last slot since the check
+                match self
+                    .quotas
+                    .try_consume(&event.device_id, &event.trigger_type, today)
+                {
+                    Ok(()) => StorageDecision::Store { reason },
+                    Err(limit) => quota_exceeded(event, limit),
+                }
+            }
+            skip => skip,
+        }
+    }
+
+    /// Replace the built-in strategy's selection settings and the daily
+    /// quota limits
+    ///
+    /// Called on SIGHUP with the re-read configuration. Sampling counters,
+    /// burst state and the frames already counted against today's quotas
+    /// carry over.
+    pub fn reload(&self, config: FrameSelectionConfig) {
+        self.quotas.set_limits(config.daily_quotas.clone());
+        self.default.reload(config);
+        self.reloads.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!("storage.selection_config.reloads").increment(1);
+    }
+
+    /// Selection settings currently in force, including device overrides
+    pub fn effective_config(&self) -> Arc<FrameSelectionConfig> {
+        self.default.config()
+    }
+
+    /// Number of times the selection settings were reloaded
+    pub fn reload_count(&self) -> u64 {
+        self.reloads.load(Ordering::Relaxed)
+    }
+
+    /// Combine the strategies' decisions according to the chain mode
+    fn run_chain(&self, event: &StorageTriggerEvent) -> StorageDecision {
+        let mut first_skip = None;
+        let mut store_reasons = Vec::new();
+
+        for strategy in &self.strategies {
+            match strategy.decide(event) {
+                StorageDecision::Store { reason } => {
+                    if self.mode == ChainMode::FirstStore {
+                        return StorageDecision::Store { reason };
+                    }
+                    store_reasons.push(reason);
+                }
+                StorageDecision::Skip { reason } => {
+                    if self.mode == ChainMode::Unanimous {
+                        return StorageDecision::Skip { reason };
+                    }
+                    first_skip.get_or_insert(reason);
+                }
+            }
+        }
+
+        match first_skip {
+            Some(reason) => StorageDecision::Skip { reason },
+            None if !store_reasons.is_empty() => StorageDecision::Store {
+                reason: store_reasons.join("; "),
+            },
+            None => StorageDecision::Skip {
+                reason: "No selection strategies configured".to_string(),
+            },
+        }
+    }
+
+    /// Reset counters for a specific device (useful for testing)
+    pub fn reset_device_counter(&self, device_id: &str) {
+        self.default.reset_device_counter(device_id);
+    }
+
+    /// Get current counter value for a device (useful for testing)
+    pub fn get_device_counter(&self, device_id: &str) -> Option<u64> {
+        self.default.get_device_counter(device_id)
+    }
+}
+
+/// Skip decision for a frame over its daily quota
+fn quota_exceeded(event: &StorageTriggerEvent, limit: u64) -> StorageDecision {
+    metrics::counter!("storage.frames.quota_exceeded").increment(1);
+    StorageDecision::Skip {
+        reason: format!(
+            "Daily {:?} quota exceeded for device {} ({} frames)",
+            event.trigger_type, event.device_id, limit
+        ),
+    }
+}
+
+/// Per-device, per-trigger-type daily storage counters
+struct DailyQuotas {
+    limits: RwLock<HashMap<TriggerType, u64>>,
+    usage: Mutex<QuotaUsage>,
+}
+
+#[derive(Default)]
+struct QuotaUsage {
+    day: Option<NaiveDate>,
+    counts: HashMap<(String, TriggerType), u64>,
+}
+
+impl DailyQuotas {
+    fn new(limits: HashMap<TriggerType, u64>) -> Self {
+        Self {
+            limits: RwLock::new(limits),
+            usage: Mutex::new(QuotaUsage::default()),
+        }
+    }
+
+    /// Replace the limits; frames already counted today still count
+    fn set_limits(&self, limits: HashMap<TriggerType, u64>) {
+        *self.limits.write().unwrap() = limits;
+    }
+
+    /// Check that a frame still fits its quota for `today`, without
+    /// counting it.
+    ///
+    /// Returns the limit if the quota is already used up.
+    fn check(
+        &self,
+        device_id: &str,
+        trigger_type: &TriggerType,
+        today: NaiveDate,
+    ) -> Result<(), u64> {
+        self.update(device_id, trigger_type, today, false)
+    }
+
+    /// Count a stored frame against its quota for `today`.
+    ///
+    /// Returns the limit if the quota is already used up. Counters reset
+    /// when the day changes.
+    fn try_consume(
+        &self,
+        device_id: &str,
+        trigger_type: &TriggerType,
+        today: NaiveDate,
+    ) -> Result<(), u64> {
+        self.update(device_id, trigger_type, today, true)
+    }
+
+    fn update(
+        &self,
+        device_id: &str,
+        trigger_type: &TriggerType,
+        today: NaiveDate,
+        consume: bool,
+    ) -> Result<(), u64> {
+        let Some(limit) = self.limits.read().unwrap().get(trigger_type).copied() else {
+            return Ok(());
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != Some(today) {
+            usage.day = Some(today);
+            usage.counts.clear();
+        }
+
+        let count = usage
+            .counts
+            .entry((device_id.to_string(), trigger_type.clone()))
+            .or_insert(0);
+        if *count >= limit {
+            return Err(limit);
+        }
+        if consume {
+            *count += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Count a frame for `device_id` and report whether it falls on the `rate`
+///
+/// The first frame from each device is always selected.
+fn check_rate(counters: &RwLock<HashMap<String, AtomicU64>>, device_id: &str, rate: u32) -> bool {
+    // Get or create counter for device
+    {
+        let counters = counters.read().unwrap();
+        if let Some(counter) = counters.get(device_id) {
+            let count = counter.fetch_add(1, Ordering::Relaxed);
+            return count % rate.max(1) as u64 == 0;
+        }
+    }
+
+    // Counter doesn't exist, create it
+    {
+        let mut counters = counters.write().unwrap();
+        counters
+            .entry(device_id.to_string())
+            .or_insert_with(|| AtomicU64::new(1));
+    }
+
+    // First frame for this device - always store
+    true
+}
+
+/// Reason to skip an inline frame whose data is missing or too small
+///
+/// Referenced frames carry no data in the event and are never skipped here.
+fn empty_frame_reason(event: &StorageTriggerEvent, min_bytes: usize) -> Option<String> {
+    if event.frame_location != FrameLocation::Inline || event.frame_data.len() >= min_bytes {
+        return None;
+    }
+    Some(format!(
+        "Empty frame: {} bytes (minimum {})",
+        event.frame_data.len(),
+        min_bytes
+    ))
+}
+
+/// Distinct types, lowercased, of the detections that qualify `event` for
+/// storage under `config`'s confidence and type filters
+fn qualifying_detection_types(
+    config: &FrameSelectionConfig,
+    event: &StorageTriggerEvent,
+) -> Vec<String> {
+    let min_confidence = config.min_confidence_for(event.model_version());
+    let mut types: Vec<String> = event
+        .detections
+        .iter()
+        .filter(|d| d.confidence >= min_confidence)
+        .filter(|d| {
+            config.detection_types.is_empty()
+                || config
+                    .detection_types
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(&d.detection_type))
+        })
+        .map(|d| d.detection_type.to_ascii_lowercase())
+        .collect();
+    types.sort();
+    types.dedup();
+    types
+}
+
+/// Built-in selection rules
+///
 /// Implements intelligent frame selection based on:
 /// - Detection presence and confidence
 /// - Periodic sampling for non-detection frames
 /// - Debug/manual triggers
 /// - Frame age limits
-pub struct FrameSelector {
-    config: FrameSelectionConfig,
+pub struct DefaultStrategy {
+    config: RwLock<ActiveConfig>,
     /// Frame counters per device for sampling
     device_counters: RwLock<HashMap<String, AtomicU64>>,
-    /// Maximum age for frames
-    max_frame_age: Duration,
+    /// Capture time of the last sample stored per device, for time-based sampling
+    last_sampled: Mutex<HashMap<String, DateTime<Utc>>>,
+    /// Debug frame counters per device for debug sampling
+    debug_counters: RwLock<HashMap<String, AtomicU64>>,
+    /// Debug frames stored per device in the current hour
+    debug_hourly: Mutex<HashMap<String, HourlyCount>>,
+    /// Current run of detection frames per device, for decimation
+    detection_bursts: Mutex<HashMap<String, DetectionBurst>>,
+    /// Capture time of the last stored detection per device and type
+    detection_cooldowns: Mutex<HashMap<String, HashMap<String, DateTime<Utc>>>>,
+    clock: Arc<dyn Clock>,
 }
 
-impl FrameSelector {
-    /// Create a new frame selector with the given configuration
-    pub fn new(config: FrameSelectionConfig) -> Self {
-        let max_frame_age = Duration::from_secs(config.max_frame_age_secs);
+/// Selection settings in force, with device overrides already applied
+struct ActiveConfig {
+    global: Arc<FrameSelectionConfig>,
+    devices: HashMap<String, Arc<FrameSelectionConfig>>,
+}
 
+impl ActiveConfig {
+    fn new(config: FrameSelectionConfig) -> Self {
+        let devices = config
+            .device_overrides
+            .keys()
+            .filter_map(|id| Some((id.clone(), Arc::new(config.for_device(id)?))))
+            .collect();
         Self {
-            config,
-            device_counters: RwLock::new(HashMap::new()),
-            max_frame_age,
+            global: Arc::new(config),
+            devices,
         }
     }
+}
 
-    /// Determine if a frame should be stored
-    pub fn should_store(&self, event: &StorageTriggerEvent) -> StorageDecision {
+/// Frames counted within one clock hour
+struct HourlyCount {
+    hour: DateTime<Utc>,
+    count: u64,
+}
+
+/// A run of detection frames from one device without a long gap
+struct DetectionBurst {
+    /// Timestamp of the latest frame in the burst
+    last_at: DateTime<Utc>,
+    /// Frames seen in the burst so far, minus the first
+    position: u64,
+}
+
+impl SelectionStrategy for DefaultStrategy {
+    fn decide(&self, event: &StorageTriggerEvent) -> StorageDecision {
         // Check frame age first
         if let Some(decision) = self.check_frame_age(event) {
             return decision;
@@ -52,25 +415,71 @@ impl FrameSelector {
 
         // Decision based on trigger type
         match event.trigger_type {
-            TriggerType::Detection => self.evaluate_detection_frame(event),
+            TriggerType::Detection => {
+                let decision = self.evaluate_detection_frame(event);
+                let decision = self.decimate_detection_frame(event, decision);
+                self.apply_detection_cooldown(event, decision)
+            }
             TriggerType::Sample => self.evaluate_sample_frame(event),
             TriggerType::Debug => self.evaluate_debug_frame(event),
             TriggerType::Manual => self.evaluate_manual_frame(event),
             TriggerType::Alert => self.evaluate_alert_frame(event),
         }
     }
+}
+
+impl DefaultStrategy {
+    /// Create the built-in strategy with the given configuration
+    pub fn new(config: FrameSelectionConfig) -> Self {
+        Self::new_with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Create the built-in strategy, reading the time from `clock`
+    pub fn new_with_clock(config: FrameSelectionConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config: RwLock::new(ActiveConfig::new(config)),
+            device_counters: RwLock::new(HashMap::new()),
+            last_sampled: Mutex::new(HashMap::new()),
+            debug_counters: RwLock::new(HashMap::new()),
+            debug_hourly: Mutex::new(HashMap::new()),
+            detection_bursts: Mutex::new(HashMap::new()),
+            detection_cooldowns: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Global selection settings, including device overrides
+    pub fn config(&self) -> Arc<FrameSelectionConfig> {
+        self.config.read().unwrap().global.clone()
+    }
+
+    /// Replace the selection settings
+    pub fn reload(&self, config: FrameSelectionConfig) {
+        *self.config.write().unwrap() = ActiveConfig::new(config);
+    }
+
+    /// Selection settings in force for `device_id`
+    fn config_for(&self, device_id: &str) -> Arc<FrameSelectionConfig> {
+        let active = self.config.read().unwrap();
+        active
+            .devices
+            .get(device_id)
+            .unwrap_or(&active.global)
+            .clone()
+    }
 
     /// Check if frame is too old
     fn check_frame_age(&self, event: &StorageTriggerEvent) -> Option<StorageDecision> {
-        let now = Utc::now();
+        let max_frame_age = Duration::from_secs(self.config().max_frame_age_secs);
+        let now = self.clock.now_utc();
         let frame_age = now.signed_duration_since(event.timestamp);
 
-        if frame_age.num_seconds() > self.max_frame_age.as_secs() as i64 {
+        if frame_age.num_seconds() > max_frame_age.as_secs() as i64 {
             return Some(StorageDecision::Skip {
                 reason: format!(
                     "Frame too old: {}s > max {}s",
                     frame_age.num_seconds(),
-                    self.max_frame_age.as_secs()
+                    max_frame_age.as_secs()
                 ),
             });
         }
@@ -80,7 +489,8 @@ impl FrameSelector {
 
     /// Evaluate whether to store a detection frame
     fn evaluate_detection_frame(&self, event: &StorageTriggerEvent) -> StorageDecision {
-        if !self.config.store_detections {
+        let config = self.config_for(&event.device_id);
+        if !config.store_detections {
             return StorageDecision::Skip {
                 reason: "Detection frame storage disabled".to_string(),
             };
@@ -88,33 +498,40 @@ impl FrameSelector {
 
         // Check if there are any detections
         if event.detections.is_empty() {
+            // Keep frames the model failed on, for debugging model failures
+            if config.store_inference_errors && event.has_inference_error() {
+                return StorageDecision::Store {
+                    reason: "Inference error".to_string(),
+                };
+            }
             return StorageDecision::Skip {
                 reason: "No detections in detection-triggered frame".to_string(),
             };
         }
 
-        // Filter detections by confidence
+        // Filter detections by the threshold calibrated for the producing model
+        let min_confidence = config.min_confidence_for(event.model_version());
         let high_confidence_detections: Vec<_> = event
             .detections
             .iter()
-            .filter(|d| d.confidence >= self.config.min_confidence)
+            .filter(|d| d.confidence >= min_confidence)
             .collect();
 
         if high_confidence_detections.is_empty() {
             return StorageDecision::Skip {
                 reason: format!(
                     "No detections above confidence threshold {}",
-                    self.config.min_confidence
+                    min_confidence
                 ),
             };
         }
 
         // Filter by detection types if configured
-        if !self.config.detection_types.is_empty() {
+        if !config.detection_types.is_empty() {
             let matching_detections: Vec<_> = high_confidence_detections
                 .iter()
                 .filter(|d| {
-                    self.config
+                    config
                         .detection_types
                         .iter()
                         .any(|t| t.eq_ignore_ascii_case(&d.detection_type))
@@ -125,7 +542,7 @@ impl FrameSelector {
                 return StorageDecision::Skip {
                     reason: format!(
                         "No detections matching configured types: {:?}",
-                        self.config.detection_types
+                        config.detection_types
                     ),
                 };
             }
@@ -151,70 +568,238 @@ impl FrameSelector {
         }
     }
 
+    /// Keep 1 of every `detection_decimation` frames of a detection burst
+    ///
+    /// Only frames the detection rules chose to store count towards a burst.
+    /// A burst ends once no such frame has arrived for
+    /// `detection_burst_gap_ms`, so the first detection after a quiet period
+    /// is always stored. Inference-error frames are never decimated.
+    fn decimate_detection_frame(
+        &self,
+        event: &StorageTriggerEvent,
+        decision: StorageDecision,
+    ) -> StorageDecision {
+        let config = self.config_for(&event.device_id);
+        let every = config.detection_decimation;
+        let reason = match decision {
+            StorageDecision::Store { reason } => reason,
+            skip => return skip,
+        };
+        if every <= 1 || event.detections.is_empty() {
+            return StorageDecision::Store { reason };
+        }
+
+        let gap = chrono::Duration::milliseconds(config.detection_burst_gap_ms as i64);
+        let position = {
+            let mut bursts = self.detection_bursts.lock().unwrap();
+            match bursts.get_mut(&event.device_id) {
+                Some(burst) if event.timestamp.signed_duration_since(burst.last_at) <= gap => {
+                    burst.last_at = burst.last_at.max(event.timestamp);
+                    burst.position += 1;
+                    burst.position
+                }
+                _ => {
+                    bursts.insert(
+                        event.device_id.clone(),
+                        DetectionBurst {
+                            last_at: event.timestamp,
+                            position: 0,
+                        },
+                    );
+                    0
+                }
+            }
+        };
+
+        if position % every as u64 == 0 {
+            StorageDecision::Store { reason }
+        } else {
+            metrics::counter!("storage.frames.detection_decimated").increment(1);
+            StorageDecision::Skip {
+                reason: format!("Detection frame decimated (1 per {} in a burst)", every),
+            }
+        }
+    }
+
+    /// Skip a detection frame if every qualifying detection type was stored
+    /// for the device within `detection_cooldown_secs`
+    ///
+    /// Storing a frame starts the cooldown for each of its types that was not
+    /// already cooling down. Inference-error frames are never skipped.
+    fn apply_detection_cooldown(
+        &self,
+        event: &StorageTriggerEvent,
+        decision: StorageDecision,
+    ) -> StorageDecision {
+        let config = self.config_for(&event.device_id);
+        let reason = match decision {
+            StorageDecision::Store { reason } => reason,
+            skip => return skip,
+        };
+        let types = qualifying_detection_types(&config, event);
+        if config.detection_cooldown_secs == 0 || types.is_empty() {
+            return StorageDecision::Store { reason };
+        }
+
+        let cooldown = chrono::Duration::seconds(config.detection_cooldown_secs as i64);
+        let mut cooldowns = self.detection_cooldowns.lock().unwrap();
+        let stored_at = cooldowns.entry(event.device_id.clone()).or_default();
+        let fresh: Vec<String> = types
+            .iter()
+            .filter(|t| match stored_at.get(*t) {
+                Some(at) => event.timestamp.signed_duration_since(*at) >= cooldown,
+                None => true,
+            })
+            .cloned()
+            .collect();
+
+        if fresh.is_empty() {
+            metrics::counter!("storage.frames.detection_cooldown").increment(1);
+            return StorageDecision::Skip {
+                reason: format!(
+                    "Detection cooldown: {} stored within {}s",
+                    types.join(", "),
+                    config.detection_cooldown_secs
+                ),
+            };
+        }
+
+        for detection_type in fresh {
+            stored_at.insert(detection_type, event.timestamp);
+        }
+        StorageDecision::Store { reason }
+    }
+
     /// Evaluate whether to store a sample frame
     fn evaluate_sample_frame(&self, event: &StorageTriggerEvent) -> StorageDecision {
-        if !self.config.store_samples {
+        let config = self.config_for(&event.device_id);
+        if !config.store_samples {
             return StorageDecision::Skip {
                 reason: "Sample frame storage disabled".to_string(),
             };
         }
 
+        if let Some(interval_secs) = config.sample_interval_secs {
+            return self.evaluate_timed_sample(event, interval_secs);
+        }
+
         // Increment counter for this device and check if we should sample
-        let should_store = self.check_sample_rate(&event.device_id);
+        let should_store = check_rate(&self.device_counters, &event.device_id, config.sample_rate);
 
         if should_store {
             StorageDecision::Store {
-                reason: format!(
-                    "Periodic sample (1 per {} frames)",
-                    self.config.sample_rate
-                ),
+                reason: format!("Periodic sample (1 per {} frames)", config.sample_rate),
             }
         } else {
             StorageDecision::Skip {
-                reason: format!(
-                    "Not sampled (rate: 1 per {} frames)",
-                    self.config.sample_rate
-                ),
+                reason: format!("Not sampled (rate: 1 per {} frames)", config.sample_rate),
             }
         }
     }
 
-    /// Check sample rate for a device
-    fn check_sample_rate(&self, device_id: &str) -> bool {
-        // Get or create counter for device
-        {
-            let counters = self.device_counters.read().unwrap();
-            if let Some(counter) = counters.get(device_id) {
-                let count = counter.fetch_add(1, Ordering::Relaxed);
-                return count % self.config.sample_rate as u64 == 0;
+    /// Store a sample frame if `interval_secs` of capture time have passed
+    /// since the device's last stored sample
+    fn evaluate_timed_sample(
+        &self,
+        event: &StorageTriggerEvent,
+        interval_secs: u64,
+    ) -> StorageDecision {
+        let interval = chrono::Duration::seconds(interval_secs as i64);
+        let mut last_sampled = self.last_sampled.lock().unwrap();
+
+        if let Some(last) = last_sampled.get(&event.device_id) {
+            let elapsed = event.timestamp.signed_duration_since(*last);
+            if elapsed < interval {
+                return StorageDecision::Skip {
+                    reason: format!(
+                        "Not sampled ({}ms since last sample, interval {}s)",
+                        elapsed.num_milliseconds(),
+                        interval_secs
+                    ),
+                };
             }
         }
 
-        // Counter doesn't exist, create it
-        {
-            let mut counters = self.device_counters.write().unwrap();
-            counters
-                .entry(device_id.to_string())
-                .or_insert_with(|| AtomicU64::new(1));
+        last_sampled.insert(event.device_id.clone(), event.timestamp);
+        StorageDecision::Store {
+            reason: format!("Periodic sample (1 per {}s)", interval_secs),
         }
-
-        // First frame for this device - always store
-        true
     }
 
     /// Evaluate whether to store a debug frame
     fn evaluate_debug_frame(&self, event: &StorageTriggerEvent) -> StorageDecision {
-        if !self.config.store_debug {
+        self.evaluate_debug_frame_at(event, self.clock.now_utc())
+    }
+
+    /// Evaluate a debug frame, counting the hourly cap against `now`
+    fn evaluate_debug_frame_at(
+        &self,
+        event: &StorageTriggerEvent,
+        now: DateTime<Utc>,
+    ) -> StorageDecision {
+        let config = self.config_for(&event.device_id);
+        if !config.store_debug {
             return StorageDecision::Skip {
                 reason: "Debug frame storage disabled".to_string(),
             };
         }
 
+        let rate = config.debug_sample_rate;
+        if !check_rate(&self.debug_counters, &event.device_id, rate) {
+            return StorageDecision::Skip {
+                reason: format!("Debug frame not sampled (rate: 1 per {} frames)", rate),
+            };
+        }
+
+        if let Some(cap) = config.max_debug_frames_per_hour {
+            if !self.try_consume_debug_hour(&event.device_id, cap, now) {
+                return StorageDecision::Skip {
+                    reason: format!("Debug frame cap reached ({} per hour)", cap),
+                };
+            }
+        }
+
         StorageDecision::Store {
             reason: "Debug frame".to_string(),
         }
     }
 
+    /// Count a debug frame against the device's hourly cap
+    ///
+    /// Returns `false` once the cap for the current hour is used up. The
+    /// first suppression in each hour is logged so a device left in debug
+    /// mode shows up without logging every frame.
+    fn try_consume_debug_hour(&self, device_id: &str, cap: u64, now: DateTime<Utc>) -> bool {
+        let hour = now
+            .duration_trunc(chrono::Duration::hours(1))
+            .unwrap_or(now);
+
+        let mut hourly = self.debug_hourly.lock().unwrap();
+        let entry = hourly
+            .entry(device_id.to_string())
+            .or_insert(HourlyCount { hour, count: 0 });
+        if entry.hour != hour {
+            *entry = HourlyCount { hour, count: 0 };
+        }
+
+        if entry.count >= cap {
+            if entry.count == cap {
+                warn!(
+                    device_id = %device_id,
+                    cap = cap,
+                    "Debug frame cap reached, suppressing debug frames for the rest of the hour"
+                );
+                // Count past the cap so the warning fires once per hour
+                entry.count += 1;
+            }
+            metrics::counter!("storage.frames.debug_suppressed").increment(1);
+            return false;
+        }
+
+        entry.count += 1;
+        true
+    }
+
     /// Evaluate whether to store a manual trigger frame
     fn evaluate_manual_frame(&self, event: &StorageTriggerEvent) -> StorageDecision {
         // Manual triggers are always stored
@@ -241,6 +826,7 @@ impl FrameSelector {
     pub fn reset_device_counter(&self, device_id: &str) {
         let mut counters = self.device_counters.write().unwrap();
         counters.remove(device_id);
+        self.last_sampled.lock().unwrap().remove(device_id);
     }
 
     /// Get current counter value for a device (useful for testing)
@@ -253,6 +839,9 @@ impl FrameSelector {
 /// Builder for creating FrameSelector with custom settings
 pub struct FrameSelectorBuilder {
     config: FrameSelectionConfig,
+    strategies: Vec<Arc<dyn SelectionStrategy>>,
+    mode: ChainMode,
+    clock: Arc<dyn Clock>,
 }
 
 impl FrameSelectorBuilder {
@@ -262,11 +851,27 @@ impl FrameSelectorBuilder {
                 store_detections: true,
                 store_samples: true,
                 sample_rate: 30,
+                sample_interval_secs: None,
                 store_debug: true,
+                debug_sample_rate: 1,
+                max_debug_frames_per_hour: None,
+                store_inference_errors: false,
+                detection_decimation: 1,
+                detection_burst_gap_ms: 1000,
+                detection_cooldown_secs: 0,
                 min_confidence: 0.5,
                 detection_types: vec![],
                 max_frame_age_secs: 300,
+                min_frame_bytes: 1,
+                confidence_profiles: HashMap::new(),
+                device_allowlist: vec![],
+                device_denylist: vec![],
+                daily_quotas: HashMap::new(),
+                device_overrides: HashMap::new(),
             },
+            strategies: Vec::new(),
+            mode: ChainMode::default(),
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -285,6 +890,11 @@ impl FrameSelectorBuilder {
         self
     }
 
+    pub fn sample_interval_secs(mut self, secs: u64) -> Self {
+        self.config.sample_interval_secs = Some(secs);
+        self
+    }
+
     pub fn min_confidence(mut self, confidence: f32) -> Self {
         self.config.min_confidence = confidence;
         self
@@ -300,8 +910,84 @@ impl FrameSelectorBuilder {
         self
     }
 
+    pub fn confidence_profile(
+        mut self,
+        model_version: impl Into<String>,
+        min_confidence: f32,
+    ) -> Self {
+        self.config
+            .confidence_profiles
+            .insert(model_version.into(), ConfidenceProfile { min_confidence });
+        self
+    }
+
+    pub fn device_allowlist(mut self, devices: Vec<String>) -> Self {
+        self.config.device_allowlist = devices;
+        self
+    }
+
+    pub fn device_denylist(mut self, devices: Vec<String>) -> Self {
+        self.config.device_denylist = devices;
+        self
+    }
+
+    pub fn debug_sample_rate(mut self, rate: u32) -> Self {
+        self.config.debug_sample_rate = rate;
+        self
+    }
+
+    pub fn max_debug_frames_per_hour(mut self, cap: u64) -> Self {
+        self.config.max_debug_frames_per_hour = Some(cap);
+        self
+    }
+
+    pub fn store_inference_errors(mut self, enabled: bool) -> Self {
+        self.config.store_inference_errors = enabled;
+        self
+    }
+
+    pub fn detection_decimation(mut self, every: u32, burst_gap_ms: u64) -> Self {
+        self.config.detection_decimation = every;
+        self.config.detection_burst_gap_ms = burst_gap_ms;
+        self
+    }
+
+    pub fn detection_cooldown_secs(mut self, secs: u64) -> Self {
+        self.config.detection_cooldown_secs = secs;
+        self
+    }
+
+    pub fn min_frame_bytes(mut self, bytes: usize) -> Self {
+        self.config.min_frame_bytes = bytes;
+        self
+    }
+
+    pub fn daily_quota(mut self, trigger_type: TriggerType, max_per_device: u64) -> Self {
+        self.config.daily_quotas.insert(trigger_type, max_per_device);
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Arc<dyn SelectionStrategy>) -> Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    pub fn chain_mode(mut self, mode: ChainMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn build(self) -> FrameSelector {
-        FrameSelector::new(self.config)
+        let selector = FrameSelector::new_with_clock(self.config, self.clock);
+        self.strategies
+            .into_iter()
+            .fold(selector, FrameSelector::with_strategy)
+            .with_chain_mode(self.mode)
     }
 }
 
@@ -314,7 +1000,9 @@ impl Default for FrameSelectorBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::kafka_consumer::Detection;
+    use crate::clock::MockClock;
+    use crate::config::DeviceSelectionOverride;
+    use crate::kafka_consumer::{Detection, FrameLocation};
     use chrono::TimeZone;
     use uuid::Uuid;
 
@@ -324,13 +1012,15 @@ mod tests {
             device_id: "test-device".to_string(),
             timestamp: Utc::now(),
             frame_number: 1,
-            frame_data: vec![],
+            session_id: None,
+            frame_data: vec![0xFF; 64],
             width: 1920,
             height: 1080,
             format: "jpeg".to_string(),
             detections: vec![],
             trigger_type,
             metadata: serde_json::Value::Null,
+            frame_location: FrameLocation::Inline,
         }
     }
 
@@ -381,6 +1071,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_confidence_profile_per_model_version() {
+        let selector = FrameSelectorBuilder::new()
+            .min_confidence(0.5)
+            .confidence_profile("yolov8-2023.10", 0.4)
+            .confidence_profile("yolov8-2024.01", 0.8)
+            .build();
+
+        let mut event = create_test_event(TriggerType::Detection);
+        event.detections = vec![create_detection("safety_vest", 0.7)];
+
+        event.metadata = serde_json::json!({ "model_version": "yolov8-2023.10" });
+        assert!(matches!(
+            selector.should_store(&event),
+            StorageDecision::Store { .. }
+        ));
+
+        event.metadata = serde_json::json!({ "model_version": "yolov8-2024.01" });
+        match selector.should_store(&event) {
+            StorageDecision::Skip { reason } => assert!(reason.contains("0.8")),
+            StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+        }
+
+        // Unknown versions use the global threshold
+        event.metadata = serde_json::json!({ "model_version": "unknown" });
+        assert!(matches!(
+            selector.should_store(&event),
+            StorageDecision::Store { .. }
+        ));
+    }
+
+    #[test]
+    fn test_inference_error_frame_stored_when_enabled() {
+        let mut event = create_test_event(TriggerType::Detection);
+        event.metadata = serde_json::json!({ "inference_error": "CUDA out of memory" });
+
+        let selector = FrameSelectorBuilder::new()
+            .store_inference_errors(true)
+            .build();
+        match selector.should_store(&event) {
+            StorageDecision::Store { reason } => assert_eq!(reason, "Inference error"),
+            StorageDecision::Skip { reason } => panic!("Expected Store, got Skip: {}", reason),
+        }
+
+        // Disabled by default
+        let selector = FrameSelectorBuilder::new().build();
+        assert!(matches!(
+            selector.should_store(&event),
+            StorageDecision::Skip { .. }
+        ));
+
+        // Frames without the flag are still skipped
+        let selector = FrameSelectorBuilder::new()
+            .store_inference_errors(true)
+            .build();
+        event.metadata = serde_json::json!({ "inference_error": false });
+        assert!(matches!(
+            selector.should_store(&event),
+            StorageDecision::Skip { .. }
+        ));
+    }
+
     #[test]
     fn test_detection_type_filter() {
         let selector = FrameSelectorBuilder::new()
@@ -438,6 +1190,80 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_detection_cooldown_per_type() {
+        let clock = Arc::new(MockClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap(),
+        ));
+        let selector = FrameSelectorBuilder::new()
+            .detection_cooldown_secs(60)
+            .clock(clock.clone())
+            .build();
+        let start = clock.now_utc();
+        let detection_at = |offset_secs: i64, detection_type: &str| {
+            let mut event = create_test_event(TriggerType::Detection);
+            event.timestamp = start + chrono::Duration::seconds(offset_secs);
+            event.detections = vec![create_detection(detection_type, 0.9)];
+            event
+        };
+
+        let decide = |event: &StorageTriggerEvent| selector.should_store(event);
+        assert!(matches!(decide(&detection_at(0, "no_helmet")), StorageDecision::Store { .. }));
+
+        // Rapid-fire detections of the same type are skipped
+        for offset in [1, 2, 30, 59] {
+            match decide(&detection_at(offset, "no_helmet")) {
+                StorageDecision::Skip { reason } => assert!(reason.contains("cooldown")),
+                StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+            }
+        }
+
+        // A different type has its own cooldown
+        assert!(matches!(decide(&detection_at(10, "no_vest")), StorageDecision::Store { .. }));
+        assert!(matches!(decide(&detection_at(11, "no_vest")), StorageDecision::Skip { .. }));
+
+        // So does the same type on another device
+        let mut other_device = detection_at(20, "no_helmet");
+        other_device.device_id = "camera-002".to_string();
+        assert!(matches!(decide(&other_device), StorageDecision::Store { .. }));
+
+        // After the window the type is stored again
+        assert!(matches!(decide(&detection_at(60, "no_helmet")), StorageDecision::Store { .. }));
+        assert!(matches!(decide(&detection_at(61, "no_helmet")), StorageDecision::Skip { .. }));
+    }
+
+    #[test]
+    fn test_time_based_sampling() {
+        let clock = Arc::new(MockClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap(),
+        ));
+        let selector = FrameSelectorBuilder::new()
+            .sample_rate(1)
+            .sample_interval_secs(5)
+            .clock(clock.clone())
+            .build();
+        let start = clock.now_utc();
+        let sample_at = |offset_ms: i64| {
+            let mut event = create_test_event(TriggerType::Sample);
+            event.timestamp = start + chrono::Duration::milliseconds(offset_ms);
+            event
+        };
+
+        // The first frame is stored, frames inside the interval are not
+        assert!(matches!(selector.should_store(&sample_at(0)), StorageDecision::Store { .. }));
+        assert!(matches!(selector.should_store(&sample_at(1_000)), StorageDecision::Skip { .. }));
+        assert!(matches!(selector.should_store(&sample_at(4_999)), StorageDecision::Skip { .. }));
+
+        // The first frame once the interval has passed is stored
+        assert!(matches!(selector.should_store(&sample_at(5_000)), StorageDecision::Store { .. }));
+        assert!(matches!(selector.should_store(&sample_at(6_000)), StorageDecision::Skip { .. }));
+
+        // Devices are sampled independently
+        let mut other = sample_at(6_000);
+        other.device_id = "camera-002".to_string();
+        assert!(matches!(selector.should_store(&other), StorageDecision::Store { .. }));
+    }
+
     #[test]
     fn test_old_frame_rejection() {
         let selector = FrameSelectorBuilder::new()
@@ -457,6 +1283,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_frame_age_uses_injected_clock() {
+        let clock = Arc::new(MockClock::new(
+            Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap(),
+        ));
+        let selector = FrameSelectorBuilder::new()
+            .max_frame_age_secs(60)
+            .clock(clock.clone())
+            .build();
+
+        let mut event = create_test_event(TriggerType::Manual);
+        event.timestamp = clock.now_utc() - chrono::Duration::seconds(30);
+        assert!(matches!(selector.should_store(&event), StorageDecision::Store { .. }));
+
+        clock.advance(Duration::from_secs(31));
+        match selector.should_store(&event) {
+            StorageDecision::Skip { reason } => assert!(reason.contains("too old: 61s")),
+            StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+        }
+    }
+
     #[test]
     fn test_manual_trigger_always_stores() {
         let selector = FrameSelectorBuilder::new().build();
@@ -478,4 +1325,303 @@ mod tests {
             StorageDecision::Store { .. }
         ));
     }
+
+    /// Stores frames whose producer flagged them as important
+    struct ImportanceStrategy {
+        min_importance: f64,
+    }
+
+    impl SelectionStrategy for ImportanceStrategy {
+        fn decide(&self, event: &StorageTriggerEvent) -> StorageDecision {
+            let importance = event
+                .metadata
+                .get("importance")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0);
+
+            if importance >= self.min_importance {
+                StorageDecision::Store {
+                    reason: format!("Importance {:.2}", importance),
+                }
+            } else {
+                StorageDecision::Skip {
+                    reason: format!("Importance {:.2} below {}", importance, self.min_importance),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_strategy_overrides_default() {
+        let mut event = create_test_event(TriggerType::Detection);
+        event.detections = vec![create_detection("safety_vest", 0.3)];
+        event.metadata = serde_json::json!({ "importance": 0.95 });
+
+        // The default rules skip the low-confidence frame; the custom strategy stores it
+        let selector = FrameSelectorBuilder::new()
+            .min_confidence(0.5)
+            .strategy(Arc::new(ImportanceStrategy { min_importance: 0.9 }))
+            .build();
+        match selector.should_store(&event) {
+            StorageDecision::Store { reason } => assert!(reason.contains("Importance")),
+            StorageDecision::Skip { reason } => panic!("Expected Store, got Skip: {}", reason),
+        }
+
+        // When all must agree, the custom strategy can veto a default Store
+        event.detections = vec![create_detection("safety_vest", 0.9)];
+        event.metadata = serde_json::json!({ "importance": 0.1 });
+        let selector = FrameSelectorBuilder::new()
+            .min_confidence(0.5)
+            .strategy(Arc::new(ImportanceStrategy { min_importance: 0.9 }))
+            .chain_mode(ChainMode::Unanimous)
+            .build();
+        match selector.should_store(&event) {
+            StorageDecision::Skip { reason } => assert!(reason.contains("below")),
+            StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+        }
+    }
+
+    #[test]
+    fn test_denylisted_device_is_always_skipped() {
+        let selector = FrameSelectorBuilder::new()
+            .device_allowlist(vec!["restricted-*".to_string()])
+            .device_denylist(vec!["restricted-*".to_string()])
+            .strategy(Arc::new(ImportanceStrategy { min_importance: 0.0 }))
+            .build();
+
+        let mut event = create_test_event(TriggerType::Manual);
+        event.device_id = "restricted-lab-02".to_string();
+
+        match selector.should_store(&event) {
+            StorageDecision::Skip { reason } => assert!(reason.contains("denylisted")),
+            StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+        }
+    }
+
+    #[test]
+    fn test_allowlist_excludes_other_devices() {
+        let selector = FrameSelectorBuilder::new()
+            .device_allowlist(vec!["pilot-*".to_string(), "glasses-007".to_string()])
+            .build();
+
+        let mut event = create_test_event(TriggerType::Manual);
+        for device in ["pilot-01", "glasses-007"] {
+            event.device_id = device.to_string();
+            assert!(matches!(
+                selector.should_store(&event),
+                StorageDecision::Store { .. }
+            ));
+        }
+
+        for device in ["glasses-001", "glasses-0071"] {
+            event.device_id = device.to_string();
+            match selector.should_store(&event) {
+                StorageDecision::Skip { reason } => assert!(reason.contains("allowlist")),
+                StorageDecision::Store { reason } => {
+                    panic!("Expected Skip for {}, got Store: {}", device, reason)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_quota_does_not_cap_detections() {
+        let selector = FrameSelectorBuilder::new()
+            .sample_rate(1)
+            .daily_quota(TriggerType::Sample, 2)
+            .build();
+
+        let sample = create_test_event(TriggerType::Sample);
+        for _ in 0..2 {
+            assert!(matches!(
+                selector.should_store(&sample),
+                StorageDecision::Store { .. }
+            ));
+        }
+        match selector.should_store(&sample) {
+            StorageDecision::Skip { reason } => assert!(reason.contains("quota exceeded")),
+            StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+        }
+
+        // Other devices have their own quota
+        let mut other = create_test_event(TriggerType::Sample);
+        other.device_id = "other-device".to_string();
+        assert!(matches!(
+            selector.should_store(&other),
+            StorageDecision::Store { .. }
+        ));
+
+        // Detections are never capped
+        let mut detection = create_test_event(TriggerType::Detection);
+        detection.detections = vec![create_detection("safety_vest", 0.9)];
+        for _ in 0..5 {
+            assert!(matches!(
+                selector.should_store(&detection),
+                StorageDecision::Store { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_exhausted_quota_does_not_advance_sampling() {
+        let selector = FrameSelectorBuilder::new()
+            .sample_rate(3)
+            .daily_quota(TriggerType::Sample, 1)
+            .build();
+        let sample = create_test_event(TriggerType::Sample);
+
+        assert!(matches!(selector.should_store(&sample), StorageDecision::Store { .. }));
+        assert_eq!(selector.get_device_counter(&sample.device_id), Some(1));
+
+        for _ in 0..5 {
+            match selector.should_store(&sample) {
+                StorageDecision::Skip { reason } => assert!(reason.contains("quota exceeded")),
+                StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+            }
+        }
+        assert_eq!(selector.get_device_counter(&sample.device_id), Some(1));
+    }
+
+    #[test]
+    fn test_daily_quota_resets_on_new_day() {
+        let mut limits = HashMap::new();
+        limits.insert(TriggerType::Sample, 1);
+        let quotas = DailyQuotas::new(limits);
+
+        let day = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        assert!(quotas.try_consume("dev", &TriggerType::Sample, day).is_ok());
+        assert_eq!(quotas.try_consume("dev", &TriggerType::Sample, day), Err(1));
+
+        let next_day = day.succ_opt().unwrap();
+        assert!(quotas.try_consume("dev", &TriggerType::Sample, next_day).is_ok());
+    }
+
+    #[test]
+    fn test_debug_frames_beyond_hourly_cap_are_skipped() {
+        let strategy = DefaultStrategy::new(
+            FrameSelectorBuilder::new()
+                .max_debug_frames_per_hour(3)
+                .config,
+        );
+        let event = create_test_event(TriggerType::Debug);
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+
+        let stored = (0..5)
+            .map(|i| hour + chrono::Duration::minutes(i * 10))
+            .filter(|&now| {
+                matches!(
+                    strategy.evaluate_debug_frame_at(&event, now),
+                    StorageDecision::Store { .. }
+                )
+            })
+            .count();
+        assert_eq!(stored, 3);
+
+        // The cap resets in the next hour
+        let next_hour = hour + chrono::Duration::hours(1);
+        assert!(matches!(
+            strategy.evaluate_debug_frame_at(&event, next_hour),
+            StorageDecision::Store { .. }
+        ));
+    }
+
+    #[test]
+    fn test_debug_sample_rate() {
+        let selector = FrameSelectorBuilder::new().debug_sample_rate(10).build();
+        let event = create_test_event(TriggerType::Debug);
+
+        let stored = (0..30)
+            .filter(|_| matches!(selector.should_store(&event), StorageDecision::Store { .. }))
+            .count();
+        assert_eq!(stored, 3);
+    }
+
+    #[test]
+    fn test_detection_bursts_are_decimated_and_gap_resets() {
+        let selector = FrameSelectorBuilder::new()
+            .detection_decimation(3, 1000)
+            .build();
+        let start = Utc::now();
+
+        let stored_at = |offsets_ms: &[i64]| -> Vec<bool> {
+            offsets_ms
+                .iter()
+                .map(|&offset| {
+                    let mut event = create_test_event(TriggerType::Detection);
+                    event.timestamp = start + chrono::Duration::milliseconds(offset);
+                    event.detections = vec![create_detection("person", 0.9)];
+                    matches!(selector.should_store(&event), StorageDecision::Store { .. })
+                })
+                .collect()
+        };
+
+        // A 10 FPS burst keeps every third frame, starting with the first
+        assert_eq!(
+            stored_at(&[0, 100, 200, 300, 400, 500, 600]),
+            vec![true, false, false, true, false, false, true]
+        );
+
+        // After a 2s gap the next detection starts a new burst and is stored
+        assert_eq!(
+            stored_at(&[2600, 2700, 2800, 2900]),
+            vec![true, false, false, true]
+        );
+
+        // Frames the detection rules skip do not advance the burst
+        let mut low = create_test_event(TriggerType::Detection);
+        low.timestamp = start + chrono::Duration::milliseconds(3000);
+        low.detections = vec![create_detection("person", 0.1)];
+        assert!(matches!(selector.should_store(&low), StorageDecision::Skip { .. }));
+        assert_eq!(stored_at(&[3100, 3200]), vec![false, false]);
+    }
+
+    #[test]
+    fn test_device_override_applies_only_to_that_device() {
+        let selector = FrameSelectorBuilder::new().min_confidence(0.5).build();
+        let mut event = create_test_event(TriggerType::Detection);
+        event.detections = vec![create_detection("person", 0.6)];
+        assert!(matches!(selector.should_store(&event), StorageDecision::Store { .. }));
+
+        let mut config = FrameSelectionConfig::clone(&selector.effective_config());
+        config.device_overrides.insert(
+            "test-device".to_string(),
+            DeviceSelectionOverride {
+                min_confidence: Some(0.8),
+                ..Default::default()
+            },
+        );
+        selector.reload(config);
+        assert!(matches!(selector.should_store(&event), StorageDecision::Skip { .. }));
+
+        event.device_id = "other-device".to_string();
+        assert!(matches!(selector.should_store(&event), StorageDecision::Store { .. }));
+        assert_eq!(selector.reload_count(), 1);
+    }
+
+    #[test]
+    fn test_reload_applies_quota_limits_and_keeps_todays_counts() {
+        let selector = FrameSelectorBuilder::new()
+            .sample_rate(1)
+            .daily_quota(TriggerType::Sample, 2)
+            .build();
+        let sample = create_test_event(TriggerType::Sample);
+        for _ in 0..2 {
+            assert!(matches!(selector.should_store(&sample), StorageDecision::Store { .. }));
+        }
+        assert!(matches!(selector.should_store(&sample), StorageDecision::Skip { .. }));
+
+        // Raising the limit allows one more frame today, not three
+        let mut config = FrameSelectionConfig::clone(&selector.effective_config());
+        config.daily_quotas.insert(TriggerType::Sample, 3);
+        selector.reload(config);
+        assert_eq!(selector.effective_config().daily_quotas[&TriggerType::Sample], 3);
+        assert!(matches!(selector.should_store(&sample), StorageDecision::Store { .. }));
+        assert!(matches!(selector.should_store(&sample), StorageDecision::Skip { .. }));
+
+        // Removing the quota lifts the cap
+        let mut config = FrameSelectionConfig::clone(&selector.effective_config());
+        config.daily_quotas.clear();
+        selector.reload(config);
+        assert!(matches!(selector.should_store(&sample), StorageDecision::Store { .. }));
+    }
 }
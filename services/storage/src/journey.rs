@@ -0,0 +1,161 @@
+use crate::decision_log::StoredDecision;
+use crate::metadata_store::{DetectionRecord, FrameMetadata};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Everything the storage service knows about one frame, for support lookups
+///
+/// Reconstructed from the metadata store and, when the Postgres decision log
+/// is enabled, the `frame_selection_decisions` table. A skipped frame has
+/// decisions but no `frame`.
+#[derive(Debug, Serialize)]
+pub struct FrameJourney {
+    /// Trace ID the journey was looked up by
+    pub trace_id: String,
+    pub event_id: Uuid,
+    pub device_id: String,
+    pub trigger_type: String,
+    /// Frame capture time
+    pub captured_at: DateTime<Utc>,
+    /// Every store/skip decision recorded for the event, oldest first
+    pub decisions: Vec<StoredDecision>,
+    /// Stored frame, including its S3 key and storage reason
+    pub frame: Option<FrameMetadata>,
+    pub detections: Vec<DetectionRecord>,
+    pub timing: JourneyTiming,
+}
+
+/// Latencies from capture to each step, in milliseconds
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct JourneyTiming {
+    /// Capture to the first frame-selection decision
+    pub decided_after_ms: Option<i64>,
+    /// Capture to the frame being indexed
+    pub indexed_after_ms: Option<i64>,
+}
+
+impl FrameJourney {
+    /// Assemble a journey from its stored pieces
+    ///
+    /// Returns `None` if there is neither a stored frame nor a decision.
+    pub fn assemble(
+        trace_id: impl Into<String>,
+        frame: Option<FrameMetadata>,
+        detections: Vec<DetectionRecord>,
+        mut decisions: Vec<StoredDecision>,
+    ) -> Option<Self> {
+        decisions.sort_by_key(|d| d.decided_at);
+
+        let (event_id, device_id, trigger_type, captured_at) = match (&frame, decisions.first()) {
+            (Some(f), _) => (f.event_id, &f.device_id, &f.trigger_type, f.timestamp),
+            (None, Some(d)) => (d.event_id, &d.device_id, &d.trigger_type, d.frame_timestamp),
+            (None, None) => return None,
+        };
+        let since_capture = |at: DateTime<Utc>| (at - captured_at).num_milliseconds();
+
+        Some(Self {
+            trace_id: trace_id.into(),
+            event_id,
+            device_id: device_id.clone(),
+            trigger_type: trigger_type.clone(),
+            captured_at,
+            timing: JourneyTiming {
+                decided_after_ms: decisions.first().map(|d| since_capture(d.decided_at)),
+                indexed_after_ms: frame.as_ref().map(|f| since_capture(f.created_at)),
+            },
+            decisions,
+            frame,
+            detections,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_store::testing;
+    use chrono::Duration;
+
+    fn stored_frame(captured_at: DateTime<Utc>) -> FrameMetadata {
+        FrameMetadata {
+            timestamp: captured_at,
+            storage_reason: "Detection: person (0.92)".to_string(),
+            created_at: captured_at + Duration::milliseconds(450),
+            ..testing::stored_frame(Uuid::new_v4(), "frames/glasses-001/frame.jpeg")
+        }
+    }
+
+    fn decision(
+        frame: &FrameMetadata,
+        decision: &str,
+        decided_at: DateTime<Utc>,
+    ) -> StoredDecision {
+        StoredDecision {
+            event_id: frame.event_id,
+            device_id: frame.device_id.clone(),
+            trigger_type: frame.trigger_type.clone(),
+            decision: decision.to_string(),
+            reason: frame.storage_reason.clone(),
+            frame_timestamp: frame.timestamp,
+            decided_at,
+        }
+    }
+
+    #[test]
+    fn test_assemble_journey_from_stored_frame() {
+        let captured_at = Utc::now() - Duration::minutes(5);
+        let frame = stored_frame(captured_at);
+        let detection = DetectionRecord {
+            id: Uuid::new_v4(),
+            frame_id: frame.id,
+            detection_type: "person".to_string(),
+            confidence: 0.92,
+            bbox: serde_json::json!([0.1, 0.1, 0.2, 0.4]),
+            attributes: serde_json::Value::Null,
+            created_at: frame.created_at,
+        };
+        // Redelivered event: decided twice, listed oldest first
+        let decisions = vec![
+            decision(&frame, "store", captured_at + Duration::milliseconds(900)),
+            decision(&frame, "store", captured_at + Duration::milliseconds(120)),
+        ];
+
+        let journey =
+            FrameJourney::assemble("trace-1", Some(frame.clone()), vec![detection], decisions)
+                .unwrap();
+
+        assert_eq!(journey.trace_id, "trace-1");
+        assert_eq!(journey.event_id, frame.event_id);
+        assert_eq!(journey.captured_at, captured_at);
+        assert_eq!(journey.decisions[0].decided_at, captured_at + Duration::milliseconds(120));
+        assert_eq!(journey.detections.len(), 1);
+        assert_eq!(
+            journey.timing,
+            JourneyTiming {
+                decided_after_ms: Some(120),
+                indexed_after_ms: Some(450),
+            }
+        );
+
+        let json = serde_json::to_value(&journey).unwrap();
+        assert_eq!(json["frame"]["s3_key"], "frames/glasses-001/frame.jpeg");
+        assert_eq!(json["frame"]["storage_reason"], "Detection: person (0.92)");
+    }
+
+    #[test]
+    fn test_skipped_frame_journey_has_no_frame() {
+        let captured_at = Utc::now();
+        let frame = stored_frame(captured_at);
+        let skipped = decision(&frame, "skip", captured_at + Duration::milliseconds(5));
+
+        let journey = FrameJourney::assemble("trace-2", None, vec![], vec![skipped]).unwrap();
+
+        assert_eq!(journey.event_id, frame.event_id);
+        assert!(journey.frame.is_none());
+        assert_eq!(journey.timing.decided_after_ms, Some(5));
+        assert_eq!(journey.timing.indexed_after_ms, None);
+
+        assert!(FrameJourney::assemble("trace-3", None, vec![], vec![]).is_none());
+    }
+}
@@ -1,16 +1,23 @@
 use crate::config::KafkaConfig;
+use crate::decision_log::{DecisionLog, DecisionRecord};
 use crate::frame_selector::{FrameSelector, StorageDecision};
 use crate::metadata_store::MetadataStore;
-use crate::s3_uploader::S3Uploader;
-use anyhow::{Context, Result};
+use crate::s3_uploader::{FrameObjectStore, PutOutcome, PutResult, S3Uploader};
+use crate::sessions::SessionTracker;
+use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use rdkafka::config::ClientConfig;
-use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
-use rdkafka::message::{BorrowedMessage, Message};
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::{BorrowedMessage, Headers, Message};
+use rdkafka::{ClientContext, Offset, TopicPartitionList};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use std::time::Duration;
+use tokio::sync::{watch, Semaphore};
 use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
@@ -25,8 +32,11 @@ pub struct StorageTriggerEvent {
     pub timestamp: DateTime<Utc>,
     /// Frame sequence number within the stream
     pub frame_number: u64,
-    /// Raw frame data (JPEG/PNG encoded)
-    #[serde(with = "base64_serde")]
+    /// Stream session the frame number belongs to; restarts reset the sequence
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Raw frame data (JPEG/PNG encoded); empty when the frame is referenced
+    #[serde(default, with = "base64_serde")]
     pub frame_data: Vec<u8>,
     /// Frame width in pixels
     pub width: u32,
@@ -42,6 +52,190 @@ pub struct StorageTriggerEvent {
     /// Additional metadata
     #[serde(default)]
     pub metadata: serde_json::Value,
+    /// Where the frame bytes live
+    #[serde(default)]
+    pub frame_location: FrameLocation,
+}
+
+/// Where a storage event's frame bytes are carried
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum FrameLocation {
+    /// Bytes are in `frame_data` and uploaded by the storage service
+    #[default]
+    Inline,
+    /// The producer already uploaded the frame; the event is only indexed
+    Reference {
+        /// Object location, e.g. `s3://bucket/frames/...`
+        s3_uri: String,
+    },
+}
+
+impl StorageTriggerEvent {
+    /// Start building an event
+    pub fn builder() -> StorageTriggerEventBuilder {
+        StorageTriggerEventBuilder::new()
+    }
+
+    /// Version of the model that produced the detections
+    ///
+    /// Read from `metadata.model_version`, falling back to the first detection
+    /// that carries a `model_version` attribute.
+    pub fn model_version(&self) -> Option<&str> {
+        self.metadata
+            .get("model_version")
+            .and_then(|v| v.as_str())
+            .or_else(|| {
+                self.detections
+                    .iter()
+                    .find_map(|d| d.attributes.get("model_version").and_then(|v| v.as_str()))
+            })
+    }
+
+    /// Whether inference failed on this frame
+    ///
+    /// Set by `metadata.inference_error`, which holds the error message or
+    /// `true`. Absent, `null` and `false` mean no error.
+    pub fn has_inference_error(&self) -> bool {
+        match self.metadata.get("inference_error") {
+            None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false)) => false,
+            Some(_) => true,
+        }
+    }
+}
+
+/// Builder for `StorageTriggerEvent`
+///
+/// `event_id` defaults to a fresh UUID, `timestamp` to now and `format` to
+/// `jpeg`. `device_id`, `frame_data`, `width`, `height` and `trigger_type` are
+/// required and checked by `build()`.
+#[derive(Debug, Default)]
+pub struct StorageTriggerEventBuilder {
+    event_id: Option<Uuid>,
+    device_id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    frame_number: u64,
+    session_id: Option<String>,
+    frame_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    format: Option<String>,
+    detections: Vec<Detection>,
+    trigger_type: Option<TriggerType>,
+    metadata: serde_json::Value,
+    frame_location: FrameLocation,
+}
+
+impl StorageTriggerEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn event_id(mut self, event_id: Uuid) -> Self {
+        self.event_id = Some(event_id);
+        self
+    }
+
+    pub fn device_id(mut self, device_id: impl Into<String>) -> Self {
+        self.device_id = Some(device_id.into());
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn frame_number(mut self, frame_number: u64) -> Self {
+        self.frame_number = frame_number;
+        self
+    }
+
+    pub fn session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    pub fn frame_data(mut self, frame_data: Vec<u8>) -> Self {
+        self.frame_data = frame_data;
+        self
+    }
+
+    pub fn dimensions(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    pub fn detection(mut self, detection: Detection) -> Self {
+        self.detections.push(detection);
+        self
+    }
+
+    pub fn detections(mut self, detections: Vec<Detection>) -> Self {
+        self.detections = detections;
+        self
+    }
+
+    pub fn trigger_type(mut self, trigger_type: TriggerType) -> Self {
+        self.trigger_type = Some(trigger_type);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Reference an already-uploaded frame instead of carrying its bytes
+    pub fn frame_reference(mut self, s3_uri: impl Into<String>) -> Self {
+        self.frame_location = FrameLocation::Reference {
+            s3_uri: s3_uri.into(),
+        };
+        self
+    }
+
+    /// Validate required fields and build the event
+    pub fn build(self) -> Result<StorageTriggerEvent> {
+        let device_id = match self.device_id {
+            Some(id) if !id.trim().is_empty() => id,
+            _ => bail!("device_id is required"),
+        };
+        if self.frame_data.is_empty() && self.frame_location == FrameLocation::Inline {
+            bail!("frame_data is required");
+        }
+        if self.width == 0 || self.height == 0 {
+            bail!(
+                "frame dimensions must be non-zero, got {}x{}",
+                self.width,
+                self.height
+            );
+        }
+        let Some(trigger_type) = self.trigger_type else {
+            bail!("trigger_type is required");
+        };
+
+        Ok(StorageTriggerEvent {
+            event_id: self.event_id.unwrap_or_else(Uuid::new_v4),
+            device_id,
+            timestamp: self.timestamp.unwrap_or_else(Utc::now),
+            frame_number: self.frame_number,
+            session_id: self.session_id,
+            frame_data: self.frame_data,
+            width: self.width,
+            height: self.height,
+            format: self.format.unwrap_or_else(|| "jpeg".to_string()),
+            detections: self.detections,
+            trigger_type,
+            metadata: self.metadata,
+            frame_location: self.frame_location,
+        })
+    }
 }
 
 /// Detection information
@@ -59,7 +253,7 @@ pub struct Detection {
 }
 
 /// Type of event that triggered storage consideration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum TriggerType {
     /// Frame contains detections
@@ -74,6 +268,82 @@ pub enum TriggerType {
     Alert,
 }
 
+/// Upload an inline frame, or resolve a referenced one to its object key
+///
+/// Referenced frames were uploaded by the producer, so they are reported as
+/// `PutOutcome::AlreadyExists` after a lookup of the object's size, without
+/// being written. They must live in `bucket` so presigned URLs resolve. Inline frames without data
+/// are refused rather than stored as zero-byte objects.
+async fn upload_or_reference(
+    objects: &dyn FrameObjectStore,
+    bucket: &str,
+    event: &StorageTriggerEvent,
+) -> Result<PutResult> {
+    match &event.frame_location {
+        FrameLocation::Inline if event.frame_data.is_empty() => {
+            bail!("Refusing to upload empty frame {}", event.event_id)
+        }
+        FrameLocation::Inline => objects.put_frame(event).await,
+        FrameLocation::Reference { s3_uri } => {
+            let (uri_bucket, key) = parse_s3_uri(s3_uri)?;
+            if uri_bucket != bucket {
+                bail!(
+                    "Referenced frame {} is outside the storage bucket {}",
+                    s3_uri,
+                    bucket
+                );
+            }
+            let size_bytes = objects
+                .frame_size(key)
+                .await?
+                .with_context(|| format!("Referenced frame {} does not exist", s3_uri))?;
+            metrics::counter!("storage.frames.referenced").increment(1);
+            Ok(PutResult {
+                s3_key: key.to_string(),
+                outcome: PutOutcome::AlreadyExists,
+                version_id: None,
+                size_bytes,
+            })
+        }
+    }
+}
+
+/// Value of a UTF-8 message header
+fn header_str<'a>(message: &'a BorrowedMessage<'_>, key: &str) -> Option<&'a str> {
+    message
+        .headers()?
+        .iter()
+        .find(|header| header.key == key)
+        .and_then(|header| std::str::from_utf8(header.value?).ok())
+}
+
+/// Record the producer's correlation ID as the event's `trace_id` metadata,
+/// which `/api/v1/trace/:trace_id` looks frames up by
+///
+/// A `trace_id` already set by the producer is kept.
+fn attach_trace_id(event: &mut StorageTriggerEvent, trace_id: &str) {
+    if event.metadata.is_null() {
+        event.metadata = serde_json::json!({});
+    }
+    if let Some(metadata) = event.metadata.as_object_mut() {
+        metadata
+            .entry("trace_id")
+            .or_insert_with(|| serde_json::Value::String(trace_id.to_string()));
+    }
+}
+
+/// Split `s3://bucket/key` into bucket and key
+fn parse_s3_uri(uri: &str) -> Result<(&str, &str)> {
+    let (bucket, key) = uri
+        .strip_prefix("s3://")
+        .and_then(|rest| rest.split_once('/'))
+        .with_context(|| format!("Invalid S3 URI: {}", uri))?;
+    if bucket.is_empty() || key.is_empty() {
+        bail!("Invalid S3 URI: {}", uri);
+    }
+    Ok((bucket, key))
+}
+
 /// Base64 serialization helper
 mod base64_serde {
     use base64::{engine::general_purpose::STANDARD, Engine};
@@ -95,13 +365,175 @@ mod base64_serde {
     }
 }
 
+/// Build the connection settings shared by all storage-service Kafka clients
+pub(crate) fn base_client_config(config: &KafkaConfig) -> ClientConfig {
+    let mut client_config = ClientConfig::new();
+
+    client_config.set("bootstrap.servers", &config.bootstrap_servers);
+
+    // Configure SSL if enabled
+    if config.ssl_enabled {
+        client_config.set("security.protocol", "SASL_SSL");
+        if let Some(ref ca_location) = config.ssl_ca_location {
+            client_config.set("ssl.ca.location", ca_location);
+        }
+    }
+
+    // Configure SASL if credentials provided
+    if let (Some(ref username), Some(ref password)) =
+        (&config.sasl_username, &config.sasl_password)
+    {
+        client_config
+            .set("sasl.mechanisms", "PLAIN")
+            .set("sasl.username", username)
+            .set("sasl.password", password);
+    }
+
+    client_config
+}
+
+/// Counters of the consumer's work since startup
+#[derive(Debug, Default)]
+pub struct ConsumerStats {
+    messages_processed: AtomicU64,
+    messages_failed: AtomicU64,
+    frames_stored: AtomicU64,
+    frames_skipped: AtomicU64,
+    bytes_uploaded: AtomicU64,
+    commit_failures: AtomicU64,
+    uncommitted_offsets: AtomicU64,
+}
+
+impl ConsumerStats {
+    pub fn snapshot(&self) -> ConsumerStatsSnapshot {
+        ConsumerStatsSnapshot {
+            messages_processed: self.messages_processed.load(Ordering::Relaxed),
+            messages_failed: self.messages_failed.load(Ordering::Relaxed),
+            frames_stored: self.frames_stored.load(Ordering::Relaxed),
+            frames_skipped: self.frames_skipped.load(Ordering::Relaxed),
+            bytes_uploaded: self.bytes_uploaded.load(Ordering::Relaxed),
+            commit_failures: self.commit_failures.load(Ordering::Relaxed),
+            uncommitted_offsets: self.uncommitted_offsets.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// Consumer context that counts offsets whose asynchronous commit failed
+///
+/// `commit_message` with `CommitMode::Async` only reports enqueue errors; the
+/// broker's answer arrives here.
+pub struct CommitStatsContext {
+    stats: Arc<ConsumerStats>,
+}
+
+impl ClientContext for CommitStatsContext {}
+
+impl ConsumerContext for CommitStatsContext {
+    fn commit_callback(&self, result: KafkaResult<()>, offsets: &TopicPartitionList) {
+        if let Err(e) = result {
+            warn!(error = %e, partitions = offsets.count(), "Offset commit failed");
+            metrics::counter!("storage.kafka.commit_failures").increment(1);
+            ConsumerStats::record(&self.stats.commit_failures, offsets.count() as u64);
+        }
+    }
+}
+
+/// Next offset to commit for every partition the consumer has handled
+/// messages from
+#[derive(Debug, Default)]
+struct HandledOffsets {
+    next: HashMap<(String, i32), i64>,
+}
+
+impl HandledOffsets {
+    fn record(&mut self, topic: &str, partition: i32, offset: i64) {
+        let next = self.next.entry((topic.to_string(), partition)).or_insert(0);
+        *next = (*next).max(offset + 1);
+    }
+
+    fn commit_list(&self) -> Result<TopicPartitionList> {
+        let mut list = TopicPartitionList::new();
+        for ((topic, partition), next) in &self.next {
+            list.add_partition_offset(topic, *partition, Offset::Offset(*next))
+                .context("Failed to build offset commit list")?;
+        }
+        Ok(list)
+    }
+}
+
+/// Point-in-time copy of `ConsumerStats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ConsumerStatsSnapshot {
+    /// Messages handled successfully, whether stored or skipped
+    pub messages_processed: u64,
+    /// Messages that failed processing and were not committed
+    pub messages_failed: u64,
+    pub frames_stored: u64,
+    pub frames_skipped: u64,
+    /// Bytes of newly created objects
+    pub bytes_uploaded: u64,
+    /// Offsets whose commit failed; they are redelivered after a restart
+    pub commit_failures: u64,
+    /// Partitions whose handled offsets the final commit on shutdown could
+    /// not store; their messages since the last commit are redelivered
+    pub uncommitted_offsets: u64,
+}
+
+/// Summary of the service's work since startup, logged once on shutdown
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShutdownReport {
+    pub uptime_secs: u64,
+    pub frames_processed: u64,
+    pub frames_stored: u64,
+    pub frames_skipped: u64,
+    pub frames_failed: u64,
+    pub bytes_uploaded: u64,
+    /// Partitions whose handled offsets were not committed on shutdown
+    pub uncommitted_offsets: u64,
+}
+
+impl ShutdownReport {
+    pub fn new(stats: &ConsumerStatsSnapshot, uptime: Duration) -> Self {
+        Self {
+            uptime_secs: uptime.as_secs(),
+            frames_processed: stats.messages_processed + stats.messages_failed,
+            frames_stored: stats.frames_stored,
+            frames_skipped: stats.frames_skipped,
+            frames_failed: stats.messages_failed,
+            bytes_uploaded: stats.bytes_uploaded,
+            uncommitted_offsets: stats.uncommitted_offsets,
+        }
+    }
+
+    /// Log the report as one structured event
+    pub fn log(&self) {
+        info!(
+            uptime_secs = self.uptime_secs,
+            frames_processed = self.frames_processed,
+            frames_stored = self.frames_stored,
+            frames_skipped = self.frames_skipped,
+            frames_failed = self.frames_failed,
+            bytes_uploaded = self.bytes_uploaded,
+            uncommitted_offsets = self.uncommitted_offsets,
+            "Storage service shutdown report"
+        );
+    }
+}
+
 /// Kafka consumer for storage trigger events
 pub struct StorageKafkaConsumer {
-    consumer: StreamConsumer,
+    consumer: StreamConsumer<CommitStatsContext>,
     frame_selector: Arc<FrameSelector>,
     s3_uploader: Arc<S3Uploader>,
     metadata_store: Arc<MetadataStore>,
     upload_semaphore: Arc<Semaphore>,
+    decision_log: Option<DecisionLog>,
+    sessions: SessionTracker,
+    stats: Arc<ConsumerStats>,
 }
 
 impl StorageKafkaConsumer {
@@ -113,36 +545,20 @@ impl StorageKafkaConsumer {
         metadata_store: Arc<MetadataStore>,
         upload_concurrency: usize,
     ) -> Result<Self> {
-        let mut client_config = ClientConfig::new();
+        let mut client_config = base_client_config(config);
 
         client_config
-            .set("bootstrap.servers", &config.bootstrap_servers)
             .set("group.id", &config.consumer_group)
             .set("auto.offset.reset", &config.auto_offset_reset)
             .set("enable.auto.commit", "false")
             .set("session.timeout.ms", config.session_timeout_ms.to_string())
             .set("max.poll.interval.ms", config.max_poll_interval_ms.to_string());
 
-        // Configure SSL if enabled
-        if config.ssl_enabled {
-            client_config.set("security.protocol", "SASL_SSL");
-            if let Some(ref ca_location) = config.ssl_ca_location {
-                client_config.set("ssl.ca.location", ca_location);
-            }
-        }
-
-        // Configure SASL if credentials provided
-        if let (Some(ref username), Some(ref password)) =
-            (&config.sasl_username, &config.sasl_password)
-        {
-            client_config
-                .set("sasl.mechanisms", "PLAIN")
-                .set("sasl.username", username)
-                .set("sasl.password", password);
-        }
-
-        let consumer: StreamConsumer = client_config
-            .create()
+        let stats = Arc::new(ConsumerStats::default());
+        let consumer: StreamConsumer<CommitStatsContext> = client_config
+            .create_with_context(CommitStatsContext {
+                stats: stats.clone(),
+            })
             .context("Failed to create Kafka consumer")?;
 
         consumer
@@ -161,17 +577,46 @@ impl StorageKafkaConsumer {
             s3_uploader,
             metadata_store,
             upload_semaphore: Arc::new(Semaphore::new(upload_concurrency)),
+            decision_log: None,
+            sessions: SessionTracker::new(config.session_reset_threshold),
+            stats,
         })
     }
 
-    /// Start consuming and processing messages
-    #[instrument(skip(self))]
-    pub async fn run(&self) -> Result<()> {
+    /// Counters of the consumer's work, shared so they outlive the consumer
+    pub fn stats(&self) -> Arc<ConsumerStats> {
+        self.stats.clone()
+    }
+
+    /// Record every frame-selection decision to the given log
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.decision_log = Some(log);
+        self
+    }
+
+    /// Consume and process messages until `shutdown` is set
+    ///
+    /// A message already being stored is finished before the consumer stops,
+    /// and the offsets of every handled message are then committed
+    /// synchronously, so a restart resumes after the last stored frame.
+    #[instrument(skip(self, shutdown))]
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) -> Result<()> {
         info!("Starting storage Kafka consumer");
 
         let mut message_stream = self.consumer.stream();
+        let mut handled = HandledOffsets::default();
+
+        while !*shutdown.borrow() {
+            let message_result = tokio::select! {
+                biased;
+                // A dropped sender also stops the consumer
+                _ = shutdown.changed() => break,
+                next = message_stream.next() => match next {
+                    Some(message_result) => message_result,
+                    None => break,
+                },
+            };
 
-        while let Some(message_result) = message_stream.next().await {
             match message_result {
                 Ok(message) => {
                     if let Err(e) = self.process_message(&message).await {
@@ -183,12 +628,16 @@ impl StorageKafkaConsumer {
                         );
                         // Continue processing other messages
                         metrics::counter!("storage.messages.failed").increment(1);
+                        ConsumerStats::record(&self.stats.messages_failed, 1);
                     } else {
                         // Commit offset on success
                         if let Err(e) = self.consumer.commit_message(&message, CommitMode::Async) {
                             warn!(error = %e, "Failed to commit offset");
+                            ConsumerStats::record(&self.stats.commit_failures, 1);
                         }
+                        handled.record(message.topic(), message.partition(), message.offset());
                         metrics::counter!("storage.messages.processed").increment(1);
+                        ConsumerStats::record(&self.stats.messages_processed, 1);
                     }
                 }
                 Err(e) => {
@@ -198,6 +647,23 @@ impl StorageKafkaConsumer {
             }
         }
 
+        info!("Storage Kafka consumer stopping; committing handled offsets");
+        self.commit_handled(&handled)
+    }
+
+    /// Synchronously commit the offsets of every handled message
+    fn commit_handled(&self, handled: &HandledOffsets) -> Result<()> {
+        let offsets = handled.commit_list()?;
+        if offsets.count() == 0 {
+            return Ok(());
+        }
+
+        if let Err(e) = self.consumer.commit(&offsets, CommitMode::Sync) {
+            self.stats
+                .uncommitted_offsets
+                .store(offsets.count() as u64, Ordering::Relaxed);
+            return Err(e).context("Failed to commit offsets on shutdown");
+        }
         Ok(())
     }
 
@@ -208,8 +674,12 @@ impl StorageKafkaConsumer {
             .payload()
             .context("Message has no payload")?;
 
-        let event: StorageTriggerEvent = serde_json::from_slice(payload)
+        let mut event: StorageTriggerEvent = serde_json::from_slice(payload)
             .context("Failed to deserialize storage trigger event")?;
+        if let Some(correlation_id) = header_str(message, "correlation-id") {
+            attach_trace_id(&mut event, correlation_id);
+        }
+        self.sessions.assign(&mut event);
 
         debug!(
             event_id = %event.event_id,
@@ -221,6 +691,10 @@ impl StorageKafkaConsumer {
         // Check if frame should be stored
         let decision = self.frame_selector.should_store(&event);
 
+        if let Some(ref log) = self.decision_log {
+            log.submit(DecisionRecord::new(&event, &decision));
+        }
+
         match decision {
             StorageDecision::Store { reason } => {
                 info!(
@@ -239,6 +713,7 @@ impl StorageKafkaConsumer {
                     "Skipping frame storage"
                 );
                 metrics::counter!("storage.frames.skipped").increment(1);
+                ConsumerStats::record(&self.stats.frames_skipped, 1);
             }
         }
 
@@ -255,25 +730,50 @@ impl StorageKafkaConsumer {
             .await
             .context("Failed to acquire upload semaphore")?;
 
+        // Downscale the stored copy if configured
+        let original_dimensions = (event.width, event.height);
+        let event = match event.frame_location {
+            FrameLocation::Inline => self.s3_uploader.prepare_for_storage(event).await?,
+            FrameLocation::Reference { .. } => event,
+        };
+
         let timer = metrics::histogram!("storage.upload.duration_seconds").start_timer();
 
-        // Upload to S3
-        let s3_key = self.s3_uploader.upload_frame(&event).await?;
+        // Upload to S3, unless the producer already did
+        let put = upload_or_reference(
+            self.s3_uploader.as_ref(),
+            self.s3_uploader.bucket(),
+            &event,
+        )
+        .await?;
 
         timer.stop();
 
-        // Store metadata in Postgres
+        // Store metadata in Postgres. An already-stored object still gets
+        // indexed: a previous attempt may have failed between upload and index.
         self.metadata_store
-            .index_frame(&event, &s3_key, &storage_reason)
+            .index_frame(
+                &event,
+                original_dimensions,
+                &put.s3_key,
+                put.version_id.as_deref(),
+                put.size_bytes,
+                self.s3_uploader.shift_for(event.timestamp),
+                &storage_reason,
+            )
             .await?;
 
         metrics::counter!("storage.frames.stored").increment(1);
-        metrics::counter!("storage.bytes.uploaded").increment(event.frame_data.len() as u64);
+        ConsumerStats::record(&self.stats.frames_stored, 1);
+        if put.outcome == PutOutcome::Created {
+            metrics::counter!("storage.bytes.uploaded").increment(event.frame_data.len() as u64);
+            ConsumerStats::record(&self.stats.bytes_uploaded, event.frame_data.len() as u64);
+        }
 
         info!(
             event_id = %event.event_id,
-            s3_key = %s3_key,
-            size_bytes = event.frame_data.len(),
+            s3_key = %put.s3_key,
+            size_bytes = put.size_bytes,
             "Frame stored successfully"
         );
 
@@ -284,6 +784,7 @@ impl StorageKafkaConsumer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::s3_uploader::testing::InMemoryObjectStore;
 
     #[test]
     fn test_deserialize_storage_trigger_event() {
@@ -312,6 +813,60 @@ mod tests {
         assert_eq!(event.trigger_type, TriggerType::Detection);
     }
 
+    #[test]
+    fn test_builder_produces_serializable_event() {
+        let event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_number(42)
+            .frame_data(b"Hello World".to_vec())
+            .dimensions(1920, 1080)
+            .trigger_type(TriggerType::Sample)
+            .build()
+            .unwrap();
+
+        assert_eq!(event.format, "jpeg");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["frame_data"], "SGVsbG8gV29ybGQ=");
+        assert_eq!(json["trigger_type"], "sample");
+
+        let decoded: StorageTriggerEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.event_id, event.event_id);
+        assert_eq!(decoded.frame_data, b"Hello World");
+    }
+
+    #[test]
+    fn test_builder_rejects_missing_required_fields() {
+        let complete = || {
+            StorageTriggerEvent::builder()
+                .device_id("glasses-001")
+                .frame_data(vec![1, 2, 3])
+                .dimensions(640, 480)
+                .trigger_type(TriggerType::Detection)
+        };
+        assert!(complete().build().is_ok());
+
+        assert!(complete().device_id("  ").build().is_err());
+        assert!(complete().frame_data(vec![]).build().is_err());
+        assert!(complete().dimensions(0, 480).build().is_err());
+
+        let err = StorageTriggerEvent::builder()
+            .frame_data(vec![1])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Manual)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("device_id"));
+
+        let err = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_data(vec![1])
+            .dimensions(640, 480)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("trigger_type"));
+    }
+
     #[test]
     fn test_trigger_type_serialization() {
         assert_eq!(
@@ -323,4 +878,178 @@ mod tests {
             "\"sample\""
         );
     }
+
+    #[tokio::test]
+    async fn test_reference_event_is_indexed_without_upload() {
+        let objects = InMemoryObjectStore::default();
+        let event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .dimensions(1920, 1080)
+            .trigger_type(TriggerType::Detection)
+            .frame_reference("s3://nier-frames/frames/2024-01-15/glasses_001/detections/a.jpeg")
+            .build()
+            .unwrap();
+
+        // The referenced object must exist
+        assert!(upload_or_reference(&objects, "nier-frames", &event)
+            .await
+            .is_err());
+        objects.insert("frames/2024-01-15/glasses_001/detections/a.jpeg", vec![0u8; 2048]);
+
+        let put = upload_or_reference(&objects, "nier-frames", &event)
+            .await
+            .unwrap();
+
+        assert_eq!(put.s3_key, "frames/2024-01-15/glasses_001/detections/a.jpeg");
+        assert_eq!(put.outcome, PutOutcome::AlreadyExists);
+        assert_eq!(put.size_bytes, 2048);
+        assert!(!objects.contains(&format!("frames/{}.jpeg", event.event_id)));
+
+        // References must point into the service's bucket
+        assert!(upload_or_reference(&objects, "other-bucket", &event)
+            .await
+            .is_err());
+        assert!(parse_s3_uri("https://nier-frames/a.jpeg").is_err());
+        assert!(parse_s3_uri("s3://nier-frames/").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_reference_event_without_frame_data() {
+        let json = r#"{
+            "event_id": "550e8400-e29b-41d4-a716-446655440000",
+            "device_id": "glasses-001",
+            "timestamp": "2024-01-15T10:30:00Z",
+            "frame_number": 1,
+            "width": 1920,
+            "height": 1080,
+            "format": "jpeg",
+            "trigger_type": "sample",
+            "frame_location": {"mode": "reference", "s3_uri": "s3://nier-frames/frames/a.jpeg"}
+        }"#;
+
+        let event: StorageTriggerEvent = serde_json::from_str(json).unwrap();
+        assert!(event.frame_data.is_empty());
+        assert_eq!(
+            event.frame_location,
+            FrameLocation::Reference {
+                s3_uri: "s3://nier-frames/frames/a.jpeg".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zero_byte_frame_is_skipped_without_upload() {
+        let objects = InMemoryObjectStore::default();
+        let selector = crate::frame_selector::FrameSelectorBuilder::new().build();
+        let mut event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_data(vec![1, 2, 3])
+            .dimensions(1920, 1080)
+            .trigger_type(TriggerType::Manual)
+            .build()
+            .unwrap();
+        event.frame_data.clear();
+
+        match selector.should_store(&event) {
+            StorageDecision::Skip { reason } => assert!(reason.starts_with("Empty frame")),
+            StorageDecision::Store { reason } => panic!("Expected Skip, got Store: {}", reason),
+        }
+        assert!(upload_or_reference(&objects, "nier-frames", &event)
+            .await
+            .is_err());
+        assert!(!objects.contains(&format!("frames/{}.jpeg", event.event_id)));
+
+        // Frames below a raised minimum are skipped too
+        event.frame_data = vec![0xFF; 16];
+        let strict = crate::frame_selector::FrameSelectorBuilder::new()
+            .min_frame_bytes(100)
+            .build();
+        assert!(matches!(strict.should_store(&event), StorageDecision::Skip { .. }));
+        assert!(matches!(selector.should_store(&event), StorageDecision::Store { .. }));
+    }
+
+    #[test]
+    fn test_shutdown_report_aggregates_consumer_stats() {
+        let stats = ConsumerStats::default();
+        ConsumerStats::record(&stats.messages_processed, 10);
+        ConsumerStats::record(&stats.frames_stored, 4);
+        ConsumerStats::record(&stats.frames_skipped, 6);
+        ConsumerStats::record(&stats.messages_failed, 2);
+        ConsumerStats::record(&stats.bytes_uploaded, 4096);
+        ConsumerStats::record(&stats.commit_failures, 3);
+        ConsumerStats::record(&stats.uncommitted_offsets, 1);
+
+        let report = ShutdownReport::new(&stats.snapshot(), Duration::from_secs(3600));
+        assert_eq!(
+            report,
+            ShutdownReport {
+                uptime_secs: 3600,
+                frames_processed: 12,
+                frames_stored: 4,
+                frames_skipped: 6,
+                frames_failed: 2,
+                bytes_uploaded: 4096,
+                uncommitted_offsets: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_handled_offsets_commit_after_last_handled_message() {
+        let mut handled = HandledOffsets::default();
+        assert_eq!(handled.commit_list().unwrap().count(), 0);
+
+        handled.record("storage-triggers", 0, 41);
+        handled.record("storage-triggers", 0, 40);
+        handled.record("storage-triggers", 1, 7);
+
+        let offsets = handled.commit_list().unwrap();
+        assert_eq!(offsets.count(), 2);
+        let next = |partition| {
+            offsets
+                .find_partition("storage-triggers", partition)
+                .unwrap()
+                .offset()
+        };
+        assert_eq!(next(0), Offset::Offset(42));
+        assert_eq!(next(1), Offset::Offset(8));
+    }
+
+    #[test]
+    fn test_failed_async_commits_are_counted() {
+        use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+
+        let stats = Arc::new(ConsumerStats::default());
+        let context = CommitStatsContext {
+            stats: stats.clone(),
+        };
+        let mut offsets = TopicPartitionList::new();
+        offsets.add_partition("storage-triggers", 0);
+        offsets.add_partition("storage-triggers", 1);
+
+        context.commit_callback(Ok(()), &offsets);
+        assert_eq!(stats.snapshot().commit_failures, 0);
+
+        let error = KafkaError::ConsumerCommit(RDKafkaErrorCode::RebalanceInProgress);
+        context.commit_callback(Err(error), &offsets);
+        assert_eq!(stats.snapshot().commit_failures, 2);
+    }
+
+    #[test]
+    fn test_correlation_id_recorded_as_trace_id() {
+        let mut event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_data(vec![0u8; 4])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Sample)
+            .build()
+            .unwrap();
+
+        attach_trace_id(&mut event, "trace-1");
+        assert_eq!(event.metadata["trace_id"], "trace-1");
+
+        // The producer's own trace ID wins
+        attach_trace_id(&mut event, "trace-2");
+        assert_eq!(event.metadata["trace_id"], "trace-1");
+    }
 }
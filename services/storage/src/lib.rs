@@ -45,16 +45,41 @@
 //!                            └──────────────┘
 //! ```
 
+pub mod annotations;
+pub mod clock;
 pub mod config;
+pub mod decision_log;
 pub mod frame_selector;
+pub mod journey;
 pub mod kafka_consumer;
 pub mod metadata_store;
 pub mod presigned_urls;
+pub mod retention;
 pub mod s3_uploader;
+pub mod sessions;
+pub mod shifts;
+pub mod zip_export;
 
+pub use annotations::{DetectionAttributes, Keypoint, MaskRef};
+pub use clock::{Clock, MockClock, SystemClock};
 pub use config::Config;
-pub use frame_selector::{FrameSelector, FrameSelectorBuilder, StorageDecision};
-pub use kafka_consumer::{Detection, StorageKafkaConsumer, StorageTriggerEvent, TriggerType};
+pub use decision_log::{DecisionLog, DecisionRecord, DecisionSink};
+pub use frame_selector::{
+    ChainMode, DefaultStrategy, FrameSelector, FrameSelectorBuilder, SelectionStrategy,
+    StorageDecision,
+};
+pub use journey::FrameJourney;
+pub use kafka_consumer::{
+    ConsumerStats, ConsumerStatsSnapshot, Detection, FrameLocation, ShutdownReport,
+    StorageKafkaConsumer, StorageTriggerEvent, StorageTriggerEventBuilder, TriggerType,
+};
 pub use metadata_store::{FrameMetadata, FrameQuery, MetadataStore, StorageStats};
+pub use nier_retry::{retry_with_backoff, BackoffPolicy};
 pub use presigned_urls::{AppState, PresignedUrlResponse};
-pub use s3_uploader::{BatchUploader, S3Uploader};
+pub use retention::{PruneReport, RetentionManager, RetentionReport};
+pub use s3_uploader::{
+    BatchUploadResult, BatchUploader, FrameObjectStore, PutOutcome, PutResult, S3Uploader,
+};
+pub use sessions::SessionTracker;
+pub use shifts::ShiftSchedule;
+pub use zip_export::ZipWriter;
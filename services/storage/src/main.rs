@@ -1,24 +1,42 @@
+mod annotations;
+mod clock;
 mod config;
+mod decision_log;
 mod frame_selector;
+mod journey;
 mod kafka_consumer;
 mod metadata_store;
 mod presigned_urls;
+mod retention;
 mod s3_uploader;
+mod sessions;
+mod shifts;
+mod zip_export;
 
 use anyhow::{Context, Result};
-use config::Config;
+use config::{Config, DecisionLogSink};
+use decision_log::{DecisionLog, DecisionSink, KafkaDecisionSink, PostgresDecisionSink};
 use frame_selector::FrameSelector;
-use kafka_consumer::StorageKafkaConsumer;
+use kafka_consumer::{ShutdownReport, StorageKafkaConsumer};
 use metadata_store::MetadataStore;
 use presigned_urls::{start_api_server, AppState};
+use retention::RetentionManager;
 use s3_uploader::S3Uploader;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::signal;
+use tokio::sync::watch;
 use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
+/// How long the Kafka consumer may take to finish its current frame and
+/// commit on shutdown
+const CONSUMER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let started_at = Instant::now();
+
     // Load configuration
     let config = Config::load().context("Failed to load configuration")?;
 
@@ -57,7 +75,7 @@ async fn main() -> Result<()> {
     let frame_selector = Arc::new(FrameSelector::new(config.frame_selection.clone()));
 
     // Create Kafka consumer
-    let kafka_consumer = StorageKafkaConsumer::new(
+    let mut kafka_consumer = StorageKafkaConsumer::new(
         &config.kafka,
         frame_selector.clone(),
         s3_uploader.clone(),
@@ -67,16 +85,38 @@ async fn main() -> Result<()> {
     .await
     .context("Failed to initialize Kafka consumer")?;
 
+    if config.decision_log.enabled {
+        let sink: Arc<dyn DecisionSink> = match config.decision_log.sink {
+            DecisionLogSink::Kafka => Arc::new(
+                KafkaDecisionSink::new(&config.kafka, &config.decision_log.topic)
+                    .context("Failed to initialize decision log")?,
+            ),
+            DecisionLogSink::Postgres => {
+                Arc::new(PostgresDecisionSink::new(metadata_store.pool().clone()))
+            }
+        };
+        info!(sink = ?config.decision_log.sink, "Frame-selection decision log enabled");
+        kafka_consumer = kafka_consumer
+            .with_decision_log(DecisionLog::spawn(sink, config.decision_log.queue_size));
+    }
+
     // Create API state
     let api_state = AppState {
         s3_uploader: s3_uploader.clone(),
         metadata_store: metadata_store.clone(),
         presigned_url_expiry: config.presigned_url_expiry(),
+        admin_token: config.api.admin_token.clone(),
+        zip_export_max_frames: config.api.zip_export_max_frames,
+        frame_selector: frame_selector.clone(),
+        decision_log_queryable: config.decision_log.enabled
+            && config.decision_log.sink == DecisionLogSink::Postgres,
     };
 
     // Spawn Kafka consumer task
+    let consumer_stats = kafka_consumer.stats();
+    let (consumer_shutdown, shutdown_requested) = watch::channel(false);
     let consumer_handle = tokio::spawn(async move {
-        if let Err(e) = kafka_consumer.run().await {
+        if let Err(e) = kafka_consumer.run(shutdown_requested).await {
             error!(error = %e, "Kafka consumer error");
         }
     });
@@ -89,6 +129,22 @@ async fn main() -> Result<()> {
         }
     });
 
+    // Spawn retention task
+    let retention_handle = if config.retention.enabled {
+        let retention = RetentionManager::new(
+            config.retention.clone(),
+            s3_uploader.clone(),
+            metadata_store.clone(),
+        );
+        Some(tokio::spawn(async move { retention.run().await }))
+    } else {
+        None
+    };
+
+    // Re-read frame-selection settings on SIGHUP
+    #[cfg(unix)]
+    let reload_handle = tokio::spawn(reload_selection_on_sighup(frame_selector.clone()));
+
     info!("Storage service started successfully");
 
     // Wait for shutdown signal
@@ -96,10 +152,27 @@ async fn main() -> Result<()> {
 
     info!("Shutting down storage service");
 
+    // Let the consumer finish the frame it is storing and commit its offsets,
+    // so the report covers everything it handled
+    let _ = consumer_shutdown.send(true);
+    match tokio::time::timeout(CONSUMER_SHUTDOWN_TIMEOUT, consumer_handle).await {
+        Ok(Err(e)) => error!(error = %e, "Kafka consumer task failed"),
+        Err(_) => warn!(
+            timeout_secs = CONSUMER_SHUTDOWN_TIMEOUT.as_secs(),
+            "Kafka consumer did not stop in time"
+        ),
+        Ok(Ok(())) => {}
+    }
+
     // Abort tasks
-    consumer_handle.abort();
     api_handle.abort();
+    if let Some(handle) = retention_handle {
+        handle.abort();
+    }
+    #[cfg(unix)]
+    reload_handle.abort();
 
+    ShutdownReport::new(&consumer_stats.snapshot(), started_at.elapsed()).log();
     info!("Storage service stopped");
 
     Ok(())
@@ -130,6 +203,32 @@ fn init_metrics(port: u16) -> Result<()> {
     Ok(())
 }
 
+/// Reload the frame-selection settings from the configuration sources on
+/// every SIGHUP
+///
+/// Only `frame_selection` is applied; other sections need a restart. A
+/// configuration that fails to load leaves the current settings in force.
+#[cfg(unix)]
+async fn reload_selection_on_sighup(frame_selector: Arc<FrameSelector>) {
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+        .expect("Failed to install SIGHUP handler");
+
+    while hangup.recv().await.is_some() {
+        match Config::load() {
+            Ok(config) => {
+                frame_selector.reload(config.frame_selection);
+                info!(
+                    reloads = frame_selector.reload_count(),
+                    "Reloaded frame-selection settings"
+                );
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to reload configuration, keeping current settings");
+            }
+        }
+    }
+}
+
 /// Wait for shutdown signal (SIGINT or SIGTERM)
 async fn shutdown_signal() {
     let ctrl_c = async {
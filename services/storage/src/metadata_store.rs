@@ -1,14 +1,26 @@
+use crate::annotations::DetectionAttributes;
 use crate::config::DatabaseConfig;
 use crate::kafka_consumer::{Detection, StorageTriggerEvent, TriggerType};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::{SinkExt, Stream, StreamExt};
+use nier_retry::{retry_with_backoff, BackoffPolicy};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::{PgPool, PgPoolOptions};
-use sqlx::FromRow;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgArguments, PgPool, PgPoolOptions};
+use sqlx::query::QueryAs;
+use sqlx::{Connection, FromRow, Postgres, Row};
+use std::future::Future;
 use std::time::Duration;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 
+/// Rows buffered between the database cursor and a `stream_frames` consumer
+const STREAM_BUFFER_ROWS: usize = 256;
+
 /// Stored frame metadata
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct FrameMetadata {
@@ -22,12 +34,22 @@ pub struct FrameMetadata {
     pub timestamp: DateTime<Utc>,
     /// Frame sequence number
     pub frame_number: i64,
-    /// S3 object key
-    pub s3_key: String,
-    /// Frame width
+    /// Stream session the frame number belongs to
+    pub session_id: Option<String>,
+    /// Factory shift the frame was captured in, if shifts are configured
+    pub shift: Option<String>,
+    /// S3 object key; `None` once the frame is archived
+    pub s3_key: Option<String>,
+    /// S3 object version, on versioned buckets
+    pub s3_version_id: Option<String>,
+    /// Stored frame width
     pub width: i32,
-    /// Frame height
+    /// Stored frame height
     pub height: i32,
+    /// Frame width as received, before any storage downscale
+    pub original_width: i32,
+    /// Frame height as received, before any storage downscale
+    pub original_height: i32,
     /// Frame format
     pub format: String,
     /// Trigger type that caused storage
@@ -44,6 +66,8 @@ pub struct FrameMetadata {
     pub size_bytes: i64,
     /// Additional metadata as JSON
     pub metadata: serde_json::Value,
+    /// Whether the S3 object has been removed by retention
+    pub archived: bool,
     /// When the record was created
     pub created_at: DateTime<Utc>,
 }
@@ -69,6 +93,10 @@ pub struct FrameQuery {
     pub offset: Option<i64>,
     /// Order by timestamp (true = ascending, false = descending)
     pub ascending: bool,
+    /// Filter by promoted detection attributes (key, value)
+    pub attribute_filters: Vec<(String, serde_json::Value)>,
+    /// Filter by shift
+    pub shift: Option<String>,
 }
 
 /// Detection metadata stored in database
@@ -90,136 +118,251 @@ pub struct DetectionRecord {
     pub created_at: DateTime<Utc>,
 }
 
+impl DetectionRecord {
+    /// Attributes parsed into known annotation schemas
+    pub fn typed_attributes(&self) -> DetectionAttributes {
+        DetectionAttributes::from_value(&self.attributes)
+    }
+}
+
 /// Metadata store for frame indexing in PostgreSQL
 pub struct MetadataStore {
     pool: PgPool,
+    /// Detection attribute keys promoted to the `promoted_attributes` column
+    indexed_attribute_keys: Vec<String>,
+    retry: RetryPolicy,
+    /// Cap on distinct types in the `detection_types` summary
+    max_detection_types: usize,
+    /// Cap on each summarized type name's length, in characters
+    max_detection_type_len: usize,
+    /// Decimal places confidences are rounded to (None = raw)
+    confidence_precision: Option<u32>,
 }
 
 impl MetadataStore {
     /// Create a new metadata store with connection pool
+    ///
+    /// Connection errors while connecting are retried per the retry policy,
+    /// so the service can start while Postgres is still coming up.
     pub async fn new(config: &DatabaseConfig) -> Result<Self> {
-        let pool = PgPoolOptions::new()
-            .max_connections(config.max_connections)
-            .min_connections(config.min_connections)
-            .acquire_timeout(Duration::from_secs(config.connect_timeout_secs))
-            .idle_timeout(Some(Duration::from_secs(config.idle_timeout_secs)))
-            .connect(&config.url)
-            .await
-            .context("Failed to connect to PostgreSQL")?;
+        let retry = RetryPolicy {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+        };
+
+        let pool = retry_with_backoff(
+            || async {
+                PgPoolOptions::new()
+                    .max_connections(config.max_connections)
+                    .min_connections(config.min_connections)
+                    .acquire_timeout(Duration::from_secs(config.connect_timeout_secs))
+                    .idle_timeout(Some(Duration::from_secs(config.idle_timeout_secs)))
+                    .connect(&config.url)
+                    .await
+                    .context("Failed to connect to PostgreSQL")
+            },
+            &retry.backoff(),
+            is_connection_error,
+        )
+        .await?;
 
         info!("Connected to PostgreSQL database");
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            indexed_attribute_keys: config.indexed_attribute_keys.clone(),
+            retry,
+            max_detection_types: config.max_detection_types,
+            max_detection_type_len: config.max_detection_type_len,
+            confidence_precision: config.confidence_precision,
+        })
     }
 
     /// Run database migrations
+    ///
+    /// Replicas starting together serialize on a Postgres advisory lock; later
+    /// instances wait, then find the migrations already applied. Migrations run
+    /// on the connection holding the lock, so a one-connection pool suffices.
     pub async fn run_migrations(&self) -> Result<()> {
-        info!("Running database migrations");
+        let mut lock = PgAdvisoryLock::new(&self.pool, MIGRATION_LOCK_KEY).await?;
 
-        sqlx::migrate!("./migrations")
-            .run(&self.pool)
-            .await
-            .context("Failed to run migrations")?;
+        migrate_exclusively(&mut lock, |lock| {
+            Box::pin(async move {
+                info!("Running database migrations");
 
-        info!("Database migrations completed");
-        Ok(())
+                sqlx::migrate!("./migrations")
+                    .run(&mut **lock.conn()?)
+                    .await
+                    .context("Failed to run migrations")?;
+
+                info!("Database migrations completed");
+                Ok(())
+            })
+        })
+        .await
     }
 
     /// Index a frame in the metadata store
+    ///
+    /// `original_dimensions` is the (width, height) the frame arrived with;
+    /// `event` carries the stored copy, which may have been downscaled.
+    /// `size_bytes` is the stored object's size, which for a referenced frame
+    /// is not the (empty) event payload.
+    ///
+    /// Upserts on `event_id`: indexing an event again (e.g. on reprocessing)
+    /// updates the existing row and replaces its detections, returning the
+    /// original frame ID.
     #[instrument(skip(self, event), fields(event_id = %event.event_id, device_id = %event.device_id))]
     pub async fn index_frame(
         &self,
         event: &StorageTriggerEvent,
+        original_dimensions: (u32, u32),
         s3_key: &str,
+        s3_version_id: Option<&str>,
+        size_bytes: u64,
+        shift: Option<&str>,
         storage_reason: &str,
     ) -> Result<Uuid> {
-        let frame_id = Uuid::new_v4();
         let trigger_type = format!("{:?}", event.trigger_type).to_lowercase();
 
         // Extract detection summary
         let detection_count = event.detections.len() as i32;
-        let detection_types: Option<String> = if event.detections.is_empty() {
-            None
-        } else {
-            let types: Vec<String> = event
-                .detections
-                .iter()
-                .map(|d| d.detection_type.clone())
-                .collect();
-            Some(types.join(","))
-        };
+        let summary = summarize_detection_types(
+            &event.detections,
+            self.max_detection_types,
+            self.max_detection_type_len,
+        );
+        if summary.omitted > 0 {
+            warn!(
+                event_id = %event.event_id,
+                kept = summary.types.len(),
+                omitted = summary.omitted,
+                "Detection types capped for frame summary"
+            );
+            metrics::counter!("storage.detection_types.capped").increment(1);
+        }
+        let detection_types = summary.joined();
         let max_confidence: Option<f32> = event
             .detections
             .iter()
             .map(|d| d.confidence)
-            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Start transaction
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Insert frame metadata
-        sqlx::query(
-            r#"
-            INSERT INTO frames (
-                id, event_id, device_id, timestamp, frame_number,
-                s3_key, width, height, format, trigger_type,
-                storage_reason, detection_count, detection_types,
-                max_confidence, size_bytes, metadata, created_at
-            ) VALUES (
-                $1, $2, $3, $4, $5,
-                $6, $7, $8, $9, $10,
-                $11, $12, $13, $14, $15,
-                $16, NOW()
-            )
-            "#,
-        )
-        .bind(frame_id)
-        .bind(event.event_id)
-        .bind(&event.device_id)
-        .bind(event.timestamp)
-        .bind(event.frame_number as i64)
-        .bind(s3_key)
-        .bind(event.width as i32)
-        .bind(event.height as i32)
-        .bind(&event.format)
-        .bind(&trigger_type)
-        .bind(storage_reason)
-        .bind(detection_count)
-        .bind(&detection_types)
-        .bind(max_confidence)
-        .bind(event.frame_data.len() as i64)
-        .bind(&event.metadata)
-        .execute(&mut *tx)
-        .await
-        .context("Failed to insert frame metadata")?;
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|c| round_confidence(c, self.confidence_precision));
+        let promoted_attributes =
+            extract_promoted_attributes(&event.detections, &self.indexed_attribute_keys);
 
-        // Insert detection records
-        for detection in &event.detections {
-            let detection_id = Uuid::new_v4();
-            let bbox_json = serde_json::to_value(&detection.bbox)?;
+        let frame_id = retry_on_connection_error(self.retry, "index_frame", || async {
+            // Start transaction
+            let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
 
-            sqlx::query(
+            // Insert frame metadata, or refresh the row from an earlier attempt
+            let frame_id: Uuid = sqlx::query_scalar(
                 r#"
-                INSERT INTO detections (
-                    id, frame_id, detection_type, confidence,
-                    bbox, attributes, created_at
+                INSERT INTO frames (
+                    id, event_id, device_id, timestamp, frame_number,
+                    s3_key, width, height, original_width, original_height,
+                    format, trigger_type, storage_reason, detection_count,
+                    detection_types, max_confidence, size_bytes, metadata,
+                    promoted_attributes, session_id, s3_version_id, shift, created_at
                 ) VALUES (
-                    $1, $2, $3, $4, $5, $6, NOW()
+                    $1, $2, $3, $4, $5,
+                    $6, $7, $8, $9, $10,
+                    $11, $12, $13, $14, $15,
+                    $16, $17, $18, $19, $20, $21, $22, NOW()
                 )
+                ON CONFLICT (event_id) DO UPDATE SET
+                    s3_key = EXCLUDED.s3_key,
+                    width = EXCLUDED.width,
+                    height = EXCLUDED.height,
+                    original_width = EXCLUDED.original_width,
+                    original_height = EXCLUDED.original_height,
+                    format = EXCLUDED.format,
+                    trigger_type = EXCLUDED.trigger_type,
+                    storage_reason = EXCLUDED.storage_reason,
+                    detection_count = EXCLUDED.detection_count,
+                    detection_types = EXCLUDED.detection_types,
+                    max_confidence = EXCLUDED.max_confidence,
+                    size_bytes = EXCLUDED.size_bytes,
+                    metadata = EXCLUDED.metadata,
+                    promoted_attributes = EXCLUDED.promoted_attributes,
+                    session_id = EXCLUDED.session_id,
+                    shift = EXCLUDED.shift,
+                    -- An already-stored object reports no version; keep the known
+                    -- one unless retention deleted that object
+                    s3_version_id = CASE
+                        WHEN frames.archived THEN EXCLUDED.s3_version_id
+                        ELSE COALESCE(EXCLUDED.s3_version_id, frames.s3_version_id)
+                    END,
+                    -- The frame was stored again, so it is no longer archived
+                    archived = FALSE,
+                    archived_at = NULL
+                RETURNING id
                 "#,
             )
-            .bind(detection_id)
-            .bind(frame_id)
-            .bind(&detection.detection_type)
-            .bind(detection.confidence)
-            .bind(&bbox_json)
-            .bind(&detection.attributes)
-            .execute(&mut *tx)
+            .bind(Uuid::new_v4())
+            .bind(event.event_id)
+            .bind(&event.device_id)
+            .bind(event.timestamp)
+            .bind(event.frame_number as i64)
+            .bind(s3_key)
+            .bind(event.width as i32)
+            .bind(event.height as i32)
+            .bind(original_dimensions.0 as i32)
+            .bind(original_dimensions.1 as i32)
+            .bind(&event.format)
+            .bind(&trigger_type)
+            .bind(storage_reason)
+            .bind(detection_count)
+            .bind(&detection_types)
+            .bind(max_confidence)
+            .bind(size_bytes as i64)
+            .bind(&event.metadata)
+            .bind(&promoted_attributes)
+            .bind(&event.session_id)
+            .bind(s3_version_id)
+            .bind(shift)
+            .fetch_one(&mut *tx)
             .await
-            .context("Failed to insert detection record")?;
-        }
+            .context("Failed to upsert frame metadata")?;
+
+            // Replace detections from any earlier attempt
+            sqlx::query("DELETE FROM detections WHERE frame_id = $1")
+                .bind(frame_id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to clear previous detection records")?;
+
+            // Insert detection records
+            for detection in &event.detections {
+                let detection_id = Uuid::new_v4();
+                let bbox_json = serde_json::to_value(&detection.bbox)?;
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO detections (
+                        id, frame_id, detection_type, confidence,
+                        bbox, attributes, created_at
+                    ) VALUES (
+                        $1, $2, $3, $4, $5, $6, NOW()
+                    )
+                    "#,
+                )
+                .bind(detection_id)
+                .bind(frame_id)
+                .bind(&detection.detection_type)
+                .bind(round_confidence(detection.confidence, self.confidence_precision))
+                .bind(&bbox_json)
+                .bind(&detection.attributes)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to insert detection record")?;
+            }
 
-        tx.commit().await.context("Failed to commit transaction")?;
+            tx.commit().await.context("Failed to commit transaction")?;
+
+            Ok(frame_id)
+        })
+        .await?;
 
         debug!(
             frame_id = %frame_id,
@@ -235,162 +378,175 @@ impl MetadataStore {
 
     /// Get frame metadata by ID
     pub async fn get_frame(&self, frame_id: Uuid) -> Result<Option<FrameMetadata>> {
-        let frame = sqlx::query_as::<_, FrameMetadata>(
-            r#"
-            SELECT id, event_id, device_id, timestamp, frame_number,
-                   s3_key, width, height, format, trigger_type,
-                   storage_reason, detection_count, detection_types,
-                   max_confidence, size_bytes, metadata, created_at
-            FROM frames
-            WHERE id = $1
-            "#,
-        )
-        .bind(frame_id)
-        .fetch_optional(&self.pool)
-        .await
-        .context("Failed to query frame")?;
+        retry_on_connection_error(self.retry, "get_frame", || async {
+            let frame = sqlx::query_as::<_, FrameMetadata>(
+                r#"
+                SELECT id, event_id, device_id, timestamp, frame_number, session_id, shift,
+                       s3_key, s3_version_id, width, height, original_width, original_height,
+                       format, trigger_type, storage_reason, detection_count, detection_types,
+                       max_confidence, size_bytes, metadata, archived, created_at
+                FROM frames
+                WHERE id = $1
+                "#,
+            )
+            .bind(frame_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query frame")?;
 
-        Ok(frame)
+            Ok(frame)
+        })
+        .await
     }
 
     /// Get frame metadata by S3 key
     pub async fn get_frame_by_s3_key(&self, s3_key: &str) -> Result<Option<FrameMetadata>> {
-        let frame = sqlx::query_as::<_, FrameMetadata>(
-            r#"
-            SELECT id, event_id, device_id, timestamp, frame_number,
-                   s3_key, width, height, format, trigger_type,
-                   storage_reason, detection_count, detection_types,
-                   max_confidence, size_bytes, metadata, created_at
-            FROM frames
-            WHERE s3_key = $1
-            "#,
-        )
-        .bind(s3_key)
-        .fetch_optional(&self.pool)
-        .await
-        .context("Failed to query frame by S3 key")?;
+        retry_on_connection_error(self.retry, "get_frame_by_s3_key", || async {
+            let frame = sqlx::query_as::<_, FrameMetadata>(
+                r#"
+                SELECT id, event_id, device_id, timestamp, frame_number, session_id, shift,
+                       s3_key, s3_version_id, width, height, original_width, original_height,
+                       format, trigger_type, storage_reason, detection_count, detection_types,
+                       max_confidence, size_bytes, metadata, archived, created_at
+                FROM frames
+                WHERE s3_key = $1
+                "#,
+            )
+            .bind(s3_key)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query frame by S3 key")?;
 
-        Ok(frame)
+            Ok(frame)
+        })
+        .await
     }
 
-    /// Query frames with filters
-    #[instrument(skip(self))]
-    pub async fn query_frames(&self, query: &FrameQuery) -> Result<Vec<FrameMetadata>> {
-        let mut sql = String::from(
-            r#"
-            SELECT id, event_id, device_id, timestamp, frame_number,
-                   s3_key, width, height, format, trigger_type,
-                   storage_reason, detection_count, detection_types,
-                   max_confidence, size_bytes, metadata, created_at
-            FROM frames
-            WHERE 1=1
-            "#,
-        );
-
-        let mut bindings: Vec<String> = Vec::new();
-        let mut param_count = 0;
-
-        if query.device_id.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND device_id = ${}", param_count));
-        }
-
-        if query.start_time.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND timestamp >= ${}", param_count));
-        }
+    /// Find the frame for a trace ID
+    ///
+    /// Matches the event's `trace_id` metadata field, which the consumer fills
+    /// from the message's `correlation-id` header, or its event ID when the
+    /// event carried no trace ID of its own.
+    pub async fn find_frame_by_trace_id(&self, trace_id: &str) -> Result<Option<FrameMetadata>> {
+        let event_id = Uuid::parse_str(trace_id).ok();
 
-        if query.end_time.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND timestamp < ${}", param_count));
-        }
+        retry_on_connection_error(self.retry, "find_frame_by_trace_id", || async {
+            let frame = sqlx::query_as::<_, FrameMetadata>(
+                r#"
+                SELECT id, event_id, device_id, timestamp, frame_number, session_id, shift,
+                       s3_key, s3_version_id, width, height, original_width, original_height,
+                       format, trigger_type, storage_reason, detection_count, detection_types,
+                       max_confidence, size_bytes, metadata, archived, created_at
+                FROM frames
+                WHERE metadata->>'trace_id' = $1 OR event_id = $2
+                ORDER BY created_at ASC
+                LIMIT 1
+                "#,
+            )
+            .bind(trace_id)
+            .bind(event_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to query frame by trace ID")?;
 
-        if query.trigger_type.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND trigger_type = ${}", param_count));
-        }
+            Ok(frame)
+        })
+        .await
+    }
 
-        if query.detection_type.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND detection_types LIKE ${}", param_count));
-        }
+    /// Newest stored frame for every device
+    ///
+    /// Frames whose object was archived by retention are skipped, so each
+    /// device's entry can still be viewed. Served by the
+    /// `(device_id, timestamp DESC)` index.
+    pub async fn latest_frame_per_device(&self) -> Result<Vec<FrameMetadata>> {
+        retry_on_connection_error(self.retry, "latest_frame_per_device", || async {
+            let frames = sqlx::query_as::<_, FrameMetadata>(
+                r#"
+                SELECT DISTINCT ON (device_id)
+                       id, event_id, device_id, timestamp, frame_number, session_id, shift,
+                       s3_key, s3_version_id, width, height, original_width, original_height,
+                       format, trigger_type, storage_reason, detection_count, detection_types,
+                       max_confidence, size_bytes, metadata, archived, created_at
+                FROM frames
+                WHERE archived = FALSE
+                ORDER BY device_id, timestamp DESC
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query latest frame per device")?;
 
-        if query.min_confidence.is_some() {
-            param_count += 1;
-            sql.push_str(&format!(" AND max_confidence >= ${}", param_count));
-        }
+            Ok(frames)
+        })
+        .await
+    }
 
-        // Order by timestamp
-        if query.ascending {
-            sql.push_str(" ORDER BY timestamp ASC");
-        } else {
-            sql.push_str(" ORDER BY timestamp DESC");
-        }
+    /// Query frames with filters
+    #[instrument(skip(self))]
+    pub async fn query_frames(&self, query: &FrameQuery) -> Result<Vec<FrameMetadata>> {
+        retry_on_connection_error(self.retry, "query_frames", || async {
+            let sql = frame_query_sql(query);
+            let frames = bind_frame_query(&sql, query)
+                .fetch_all(&self.pool)
+                .await
+                .context("Failed to query frames")?;
 
-        // Limit and offset
-        if let Some(limit) = query.limit {
-            param_count += 1;
-            sql.push_str(&format!(" LIMIT ${}", param_count));
-        }
+            Ok(frames)
+        })
+        .await
+    }
 
-        if let Some(offset) = query.offset {
-            param_count += 1;
-            sql.push_str(&format!(" OFFSET ${}", param_count));
-        }
+    /// Stream frames matching a query without loading them all into memory
+    ///
+    /// Rows are read with a cursor on a background task and handed over
+    /// through a bounded channel, so large exports stay at constant memory.
+    /// The stream ends after the first error.
+    pub fn stream_frames(
+        &self,
+        query: &FrameQuery,
+    ) -> impl Stream<Item = Result<FrameMetadata>> {
+        let (mut tx, rx) = mpsc::channel(STREAM_BUFFER_ROWS);
+        let pool = self.pool.clone();
+        let query = query.clone();
 
-        // Build and execute query
-        let mut query_builder = sqlx::query_as::<_, FrameMetadata>(&sql);
+        tokio::spawn(async move {
+            let sql = frame_query_sql(&query);
+            let mut rows = bind_frame_query(&sql, &query).fetch(&pool);
 
-        if let Some(ref device_id) = query.device_id {
-            query_builder = query_builder.bind(device_id);
-        }
-        if let Some(start_time) = query.start_time {
-            query_builder = query_builder.bind(start_time);
-        }
-        if let Some(end_time) = query.end_time {
-            query_builder = query_builder.bind(end_time);
-        }
-        if let Some(ref trigger_type) = query.trigger_type {
-            query_builder = query_builder.bind(trigger_type);
-        }
-        if let Some(ref detection_type) = query.detection_type {
-            query_builder = query_builder.bind(format!("%{}%", detection_type));
-        }
-        if let Some(min_confidence) = query.min_confidence {
-            query_builder = query_builder.bind(min_confidence);
-        }
-        if let Some(limit) = query.limit {
-            query_builder = query_builder.bind(limit);
-        }
-        if let Some(offset) = query.offset {
-            query_builder = query_builder.bind(offset);
-        }
+            while let Some(row) = rows.next().await {
+                let row = row.context("Failed to stream frames");
+                let failed = row.is_err();
+                // A closed channel means the consumer went away
+                if tx.send(row).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
 
-        let frames = query_builder
-            .fetch_all(&self.pool)
-            .await
-            .context("Failed to query frames")?;
-
-        Ok(frames)
+        rx
     }
 
     /// Get detections for a frame
     pub async fn get_frame_detections(&self, frame_id: Uuid) -> Result<Vec<DetectionRecord>> {
-        let detections = sqlx::query_as::<_, DetectionRecord>(
-            r#"
-            SELECT id, frame_id, detection_type, confidence,
-                   bbox, attributes, created_at
-            FROM detections
-            WHERE frame_id = $1
-            ORDER BY confidence DESC
-            "#,
-        )
-        .bind(frame_id)
-        .fetch_all(&self.pool)
-        .await
-        .context("Failed to query detections")?;
+        retry_on_connection_error(self.retry, "get_frame_detections", || async {
+            let detections = sqlx::query_as::<_, DetectionRecord>(
+                r#"
+                SELECT id, frame_id, detection_type, confidence,
+                       bbox, attributes, created_at
+                FROM detections
+                WHERE frame_id = $1
+                ORDER BY confidence DESC
+                "#,
+            )
+            .bind(frame_id)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query detections")?;
 
-        Ok(detections)
+            Ok(detections)
+        })
+        .await
     }
 
     /// Get frame count by device and time range
@@ -400,41 +556,47 @@ impl MetadataStore {
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
     ) -> Result<i64> {
-        let count: (i64,) = sqlx::query_as(
-            r#"
-            SELECT COUNT(*) FROM frames
-            WHERE ($1::text IS NULL OR device_id = $1)
-              AND ($2::timestamptz IS NULL OR timestamp >= $2)
-              AND ($3::timestamptz IS NULL OR timestamp < $3)
-            "#,
-        )
-        .bind(device_id)
-        .bind(start_time)
-        .bind(end_time)
-        .fetch_one(&self.pool)
-        .await
-        .context("Failed to count frames")?;
+        retry_on_connection_error(self.retry, "get_frame_count", || async {
+            let count: (i64,) = sqlx::query_as(
+                r#"
+                SELECT COUNT(*) FROM frames
+                WHERE ($1::text IS NULL OR device_id = $1)
+                  AND ($2::timestamptz IS NULL OR timestamp >= $2)
+                  AND ($3::timestamptz IS NULL OR timestamp < $3)
+                "#,
+            )
+            .bind(device_id)
+            .bind(start_time)
+            .bind(end_time)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count frames")?;
 
-        Ok(count.0)
+            Ok(count.0)
+        })
+        .await
     }
 
     /// Get storage statistics
     pub async fn get_storage_stats(&self) -> Result<StorageStats> {
-        let stats: StorageStats = sqlx::query_as(
-            r#"
-            SELECT
-                COUNT(*) as total_frames,
-                COALESCE(SUM(size_bytes), 0) as total_bytes,
-                COALESCE(SUM(detection_count), 0) as total_detections,
-                COUNT(DISTINCT device_id) as device_count
-            FROM frames
-            "#,
-        )
-        .fetch_one(&self.pool)
-        .await
-        .context("Failed to get storage stats")?;
+        retry_on_connection_error(self.retry, "get_storage_stats", || async {
+            let stats: StorageStats = sqlx::query_as(
+                r#"
+                SELECT
+                    COUNT(*) as total_frames,
+                    COALESCE(SUM(size_bytes), 0) as total_bytes,
+                    COALESCE(SUM(detection_count), 0) as total_detections,
+                    COUNT(DISTINCT device_id) as device_count
+                FROM frames
+                "#,
+            )
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to get storage stats")?;
 
-        Ok(stats)
+            Ok(stats)
+        })
+        .await
     }
 
     /// Delete old frames (for retention policy)
@@ -462,12 +624,307 @@ impl MetadataStore {
         Ok(count)
     }
 
+    /// Get frames older than `before` whose S3 object has not been archived yet
+    pub async fn frames_to_archive(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>> {
+        retry_on_connection_error(self.retry, "frames_to_archive", || async {
+            let rows: Vec<(Uuid, String)> = sqlx::query_as(
+                r#"
+                SELECT id, s3_key FROM frames
+                WHERE timestamp < $1 AND archived = FALSE
+                ORDER BY timestamp ASC
+                LIMIT $2
+                "#,
+            )
+            .bind(before)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query frames to archive")?;
+
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Get unarchived frames with an id greater than `after`, in id order
+    pub async fn frames_to_verify(
+        &self,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>> {
+        retry_on_connection_error(self.retry, "frames_to_verify", || async {
+            let rows: Vec<(Uuid, String)> = sqlx::query_as(
+                r#"
+                SELECT id, s3_key FROM frames
+                WHERE archived = FALSE AND ($1::uuid IS NULL OR id > $1)
+                ORDER BY id ASC
+                LIMIT $2
+                "#,
+            )
+            .bind(after)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to query frames to verify")?;
+
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Mark a frame as archived (S3 object removed, metadata kept)
+    ///
+    /// Clears `s3_key`, since it no longer names an object.
+    pub async fn mark_archived(&self, frame_id: Uuid) -> Result<()> {
+        retry_on_connection_error(self.retry, "mark_archived", || async {
+            sqlx::query(
+                r#"
+                UPDATE frames
+                SET archived = TRUE, archived_at = NOW(), s3_key = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(frame_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to mark frame archived")?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Delete a frame and its detections in a single transaction
+    ///
+    /// Returns `false` if the frame did not exist.
+    #[instrument(skip(self))]
+    pub async fn delete_frame(&self, frame_id: Uuid) -> Result<bool> {
+        retry_on_connection_error(self.retry, "delete_frame", || async {
+            let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+
+            sqlx::query("DELETE FROM detections WHERE frame_id = $1")
+                .bind(frame_id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to delete frame detections")?;
+
+            let deleted = sqlx::query("DELETE FROM frames WHERE id = $1")
+                .bind(frame_id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to delete frame")?
+                .rows_affected();
+
+            tx.commit().await.context("Failed to commit frame deletion")?;
+
+            Ok(deleted > 0)
+        })
+        .await
+    }
+
     /// Get the connection pool (for health checks)
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 }
 
+/// Collect the configured attribute keys from all detections in a frame.
+///
+/// Each key maps to the distinct values seen across the frame's detections,
+/// so a frame can be matched by containment (`@>`) on any one of them.
+fn extract_promoted_attributes(detections: &[Detection], keys: &[String]) -> serde_json::Value {
+    let mut promoted = serde_json::Map::new();
+
+    for key in keys {
+        let mut values: Vec<serde_json::Value> = Vec::new();
+        for detection in detections {
+            if let Some(value) = detection.attributes.get(key) {
+                if !values.contains(value) {
+                    values.push(value.clone());
+                }
+            }
+        }
+        if !values.is_empty() {
+            promoted.insert(key.clone(), serde_json::Value::Array(values));
+        }
+    }
+
+    serde_json::Value::Object(promoted)
+}
+
+/// Sanitized, capped set of a frame's detection types
+#[derive(Debug, Default, PartialEq)]
+struct DetectionTypeSummary {
+    /// Distinct types in first-seen order
+    types: Vec<String>,
+    /// Distinct types left out once the cap was reached
+    omitted: usize,
+}
+
+impl DetectionTypeSummary {
+    /// Comma-joined types for the `detection_types` column
+    fn joined(&self) -> Option<String> {
+        (!self.types.is_empty()).then(|| self.types.join(","))
+    }
+}
+
+/// Summarize detection types for the `detection_types` column
+///
+/// Names are trimmed, stripped of commas (the column separator) and control
+/// characters, and truncated to `max_len` characters. At most `max_types`
+/// distinct names are kept, so a model emitting unbounded labels cannot
+/// bloat the column.
+fn summarize_detection_types(
+    detections: &[Detection],
+    max_types: usize,
+    max_len: usize,
+) -> DetectionTypeSummary {
+    let mut summary = DetectionTypeSummary::default();
+    let mut seen = std::collections::HashSet::new();
+
+    for detection in detections {
+        let name: String = detection
+            .detection_type
+            .trim()
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| if c == ',' { '_' } else { c })
+            .take(max_len)
+            .collect();
+        let name = name.trim_end().to_string();
+        if name.is_empty() || !seen.insert(name.clone()) {
+            continue;
+        }
+        if summary.types.len() < max_types {
+            summary.types.push(name);
+        } else {
+            summary.omitted += 1;
+        }
+    }
+
+    summary
+}
+
+/// Round a confidence to `precision` decimal places (None = unchanged)
+///
+/// Bucketed values keep aggregate queries and cache keys stable across
+/// frames whose raw scores differ only in noise.
+fn round_confidence(confidence: f32, precision: Option<u32>) -> f32 {
+    match precision {
+        Some(places) => {
+            let scale = 10f64.powi(places.min(9) as i32);
+            ((confidence as f64 * scale).round() / scale) as f32
+        }
+        None => confidence,
+    }
+}
+
+/// Build the jsonb containment value for a promoted attribute filter
+///
+/// The value keeps its JSON type, so numeric and boolean attributes match
+/// only numbers and booleans, as jsonb containment compares typed values.
+fn attribute_filter(key: &str, value: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ key: [value] })
+}
+
+/// Advisory lock key guarding schema migrations
+const MIGRATION_LOCK_KEY: i64 = 0x6e69_6572_6d69_6772;
+
+/// Cross-instance lock held while migrating
+#[async_trait]
+trait MigrationLock: Send {
+    /// Block until the lock is held
+    async fn lock(&mut self) -> Result<()>;
+
+    /// Release a held lock
+    async fn unlock(&mut self) -> Result<()>;
+}
+
+/// Session-level `pg_advisory_lock` held on a dedicated pooled connection
+///
+/// If unlocking fails the connection is closed instead of going back to the
+/// pool, since the session, and with it the lock, would otherwise live on.
+struct PgAdvisoryLock {
+    conn: Option<PoolConnection<Postgres>>,
+    key: i64,
+}
+
+impl PgAdvisoryLock {
+    async fn new(pool: &PgPool, key: i64) -> Result<Self> {
+        let conn = pool
+            .acquire()
+            .await
+            .context("Failed to acquire connection for migration lock")?;
+
+        Ok(Self {
+            conn: Some(conn),
+            key,
+        })
+    }
+
+    fn conn(&mut self) -> Result<&mut PoolConnection<Postgres>> {
+        self.conn
+            .as_mut()
+            .context("Migration lock connection was closed")
+    }
+}
+
+#[async_trait]
+impl MigrationLock for PgAdvisoryLock {
+    async fn lock(&mut self) -> Result<()> {
+        let key = self.key;
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(key)
+            .execute(&mut **self.conn()?)
+            .await
+            .context("Failed to acquire migration lock")?;
+        Ok(())
+    }
+
+    async fn unlock(&mut self) -> Result<()> {
+        let key = self.key;
+        let result = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(key)
+            .execute(&mut **self.conn()?)
+            .await;
+
+        if let Err(e) = result {
+            if let Some(conn) = self.conn.take() {
+                // Ending the session releases the lock
+                if let Err(close_err) = conn.detach().close().await {
+                    warn!(error = %close_err, "Failed to close migration lock connection");
+                }
+            }
+            return Err(e).context("Failed to release migration lock");
+        }
+        Ok(())
+    }
+}
+
+/// Run `migrate` while holding `lock`, releasing it even if migration fails
+///
+/// `migrate` is handed the held lock so it can work on the lock's connection.
+async fn migrate_exclusively<L, F>(lock: &mut L, migrate: F) -> Result<()>
+where
+    L: MigrationLock,
+    F: for<'a> FnOnce(&'a mut L) -> BoxFuture<'a, Result<()>>,
+{
+    lock.lock().await?;
+    let result = migrate(lock).await;
+
+    if let Err(e) = lock.unlock().await {
+        // The lock is session-scoped; the lock's connection was closed rather
+        // than returned to the pool, which drops it
+        warn!(error = %e, "Failed to release migration lock");
+    }
+
+    result
+}
+
 /// Storage statistics
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct StorageStats {
@@ -477,9 +934,337 @@ pub struct StorageStats {
     pub device_count: i64,
 }
 
+/// Retry settings for transient Postgres failures
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Shared backoff equivalent: doubling from `base_delay`, capped at 1024x
+    fn backoff(&self) -> BackoffPolicy {
+        BackoffPolicy::new(self.max_retries.saturating_add(1), self.base_delay)
+            .with_max_delay(self.base_delay * 1024)
+    }
+}
+
+/// Whether an error means the database connection was lost or unavailable
+///
+/// Constraint violations and other query errors are not retryable: running
+/// the same statement again would fail the same way.
+fn is_connection_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<sqlx::Error>())
+        .any(|e| match e {
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut => true,
+            // Class 08 is connection exceptions; 57P01-03 are server shutdown/startup
+            sqlx::Error::Database(db) => db
+                .code()
+                .is_some_and(|code| code.starts_with("08") || code.starts_with("57P0")),
+            _ => false,
+        })
+}
+
+/// Run a database operation, retrying with backoff on connection errors
+///
+/// PgPool replaces broken connections on its own, but an outage longer than
+/// one attempt still reaches the caller; this rides out short ones.
+async fn retry_on_connection_error<T, F, Fut>(
+    policy: RetryPolicy,
+    operation: &str,
+    mut f: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    retry_with_backoff(
+        || {
+            if attempt > 0 {
+                warn!(
+                    operation = operation,
+                    attempt = attempt,
+                    "Retrying database operation after connection error"
+                );
+                metrics::counter!("storage.db.retries").increment(1);
+            }
+            attempt += 1;
+            f()
+        },
+        &policy.backoff(),
+        is_connection_error,
+    )
+    .await
+}
+
+/// SQL for a frame query; placeholders are bound by `bind_frame_query`
+fn frame_query_sql(query: &FrameQuery) -> String {
+    let mut sql = String::from(
+        r#"
+        SELECT id, event_id, device_id, timestamp, frame_number, session_id, shift,
+               s3_key, s3_version_id, width, height, original_width, original_height,
+               format, trigger_type, storage_reason, detection_count, detection_types,
+               max_confidence, size_bytes, metadata, archived, created_at
+        FROM frames
+        WHERE 1=1
+        "#,
+    );
+
+    let mut param_count = 0;
+
+    if query.device_id.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND device_id = ${}", param_count));
+    }
+
+    if query.start_time.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND timestamp >= ${}", param_count));
+    }
+
+    if query.end_time.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND timestamp < ${}", param_count));
+    }
+
+    if query.trigger_type.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND trigger_type = ${}", param_count));
+    }
+
+    if query.detection_type.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND detection_types LIKE ${}", param_count));
+    }
+
+    if query.min_confidence.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND max_confidence >= ${}", param_count));
+    }
+
+    if query.shift.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" AND shift = ${}", param_count));
+    }
+
+    for _ in &query.attribute_filters {
+        param_count += 1;
+        sql.push_str(&format!(" AND promoted_attributes @> ${}", param_count));
+    }
+
+    // Order by timestamp
+    if query.ascending {
+        sql.push_str(" ORDER BY timestamp ASC");
+    } else {
+        sql.push_str(" ORDER BY timestamp DESC");
+    }
+
+    // Limit and offset
+    if query.limit.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" LIMIT ${}", param_count));
+    }
+
+    if query.offset.is_some() {
+        param_count += 1;
+        sql.push_str(&format!(" OFFSET ${}", param_count));
+    }
+
+    sql
+}
+
+/// Bind a frame query's parameters in the order `frame_query_sql` numbers them
+fn bind_frame_query<'q>(
+    sql: &'q str,
+    query: &'q FrameQuery,
+) -> QueryAs<'q, Postgres, FrameMetadata, PgArguments> {
+    let mut query_builder = sqlx::query_as::<_, FrameMetadata>(sql);
+
+    if let Some(ref device_id) = query.device_id {
+        query_builder = query_builder.bind(device_id);
+    }
+    if let Some(start_time) = query.start_time {
+        query_builder = query_builder.bind(start_time);
+    }
+    if let Some(end_time) = query.end_time {
+        query_builder = query_builder.bind(end_time);
+    }
+    if let Some(ref trigger_type) = query.trigger_type {
+        query_builder = query_builder.bind(trigger_type);
+    }
+    if let Some(ref detection_type) = query.detection_type {
+        query_builder = query_builder.bind(format!("%{}%", detection_type));
+    }
+    if let Some(min_confidence) = query.min_confidence {
+        query_builder = query_builder.bind(min_confidence);
+    }
+    if let Some(ref shift) = query.shift {
+        query_builder = query_builder.bind(shift);
+    }
+    for (key, value) in &query.attribute_filters {
+        query_builder = query_builder.bind(attribute_filter(key, value));
+    }
+    if let Some(limit) = query.limit {
+        query_builder = query_builder.bind(limit);
+    }
+    if let Some(offset) = query.offset {
+        query_builder = query_builder.bind(offset);
+    }
+
+    query_builder
+}
+
+/// Frame metadata fixtures for tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::FrameMetadata;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    /// A stored detection frame under `s3_key`
+    pub fn stored_frame(id: Uuid, s3_key: &str) -> FrameMetadata {
+        FrameMetadata {
+            id,
+            event_id: Uuid::new_v4(),
+            device_id: "glasses-001".to_string(),
+            timestamp: Utc::now(),
+            frame_number: 1,
+            session_id: None,
+            shift: None,
+            s3_key: Some(s3_key.to_string()),
+            s3_version_id: None,
+            width: 1920,
+            height: 1080,
+            original_width: 1920,
+            original_height: 1080,
+            format: "jpeg".to_string(),
+            trigger_type: "detection".to_string(),
+            storage_reason: "test".to_string(),
+            detection_count: 1,
+            detection_types: Some("person".to_string()),
+            max_confidence: Some(0.9),
+            size_bytes: 4,
+            metadata: serde_json::Value::Null,
+            archived: false,
+            created_at: Utc::now(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+    /// Stands in for `pg_advisory_lock`: one holder across all clones
+    struct FakeLock {
+        shared: Arc<Semaphore>,
+        permit: Option<OwnedSemaphorePermit>,
+    }
+
+    #[async_trait]
+    impl MigrationLock for FakeLock {
+        async fn lock(&mut self) -> Result<()> {
+            self.permit = Some(self.shared.clone().acquire_owned().await?);
+            Ok(())
+        }
+
+        async fn unlock(&mut self) -> Result<()> {
+            self.permit = None;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_migrations_are_serialized() {
+        let shared = Arc::new(Semaphore::new(1));
+        let applied = Arc::new(AtomicBool::new(false));
+        let migrations_run = Arc::new(AtomicUsize::new(0));
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_active = Arc::new(AtomicUsize::new(0));
+
+        let instances: Vec<_> = (0..4)
+            .map(|_| {
+                let mut lock = FakeLock {
+                    shared: shared.clone(),
+                    permit: None,
+                };
+                let applied = applied.clone();
+                let migrations_run = migrations_run.clone();
+                let active = active.clone();
+                let max_active = max_active.clone();
+
+                tokio::spawn(async move {
+                    migrate_exclusively(&mut lock, |_| {
+                        Box::pin(async move {
+                            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                            max_active.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            // Like sqlx's migrator, skip work that is already applied
+                            if !applied.swap(true, Ordering::SeqCst) {
+                                migrations_run.fetch_add(1, Ordering::SeqCst);
+                            }
+                            active.fetch_sub(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        for instance in instances {
+            instance.await.unwrap().unwrap();
+        }
+
+        assert_eq!(max_active.load(Ordering::SeqCst), 1);
+        assert_eq!(migrations_run.load(Ordering::SeqCst), 1);
+        assert_eq!(shared.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_connection_error_then_succeeds() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let attempts = AtomicUsize::new(0);
+
+        // Stands in for a pool whose connection drops on the first call
+        let result = retry_on_connection_error(policy, "get_frame", || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+                return Err(sqlx::Error::Io(reset)).context("Failed to query frame");
+            }
+            Ok(42)
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_errors_are_not_retried() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<()> = retry_on_connection_error(policy, "index_frame", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(sqlx::Error::RowNotFound).context("Failed to upsert frame metadata")
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
 
     #[test]
     fn test_frame_query_builder() {
@@ -493,4 +1278,397 @@ mod tests {
         assert_eq!(query.device_id, Some("glasses-001".to_string()));
         assert_eq!(query.limit, Some(100));
     }
+
+    fn detection_with_attributes(attributes: serde_json::Value) -> Detection {
+        Detection {
+            detection_type: "person".to_string(),
+            confidence: 0.9,
+            bbox: [0.0, 0.0, 0.5, 0.5],
+            attributes,
+        }
+    }
+
+    #[test]
+    fn test_promoted_attributes_collect_distinct_values() {
+        let detections = vec![
+            detection_with_attributes(serde_json::json!({
+                "worker_posture": "fallen",
+                "tracking_id": 7
+            })),
+            detection_with_attributes(serde_json::json!({ "worker_posture": "standing" })),
+            detection_with_attributes(serde_json::Value::Null),
+        ];
+        let keys = vec!["worker_posture".to_string()];
+
+        let promoted = extract_promoted_attributes(&detections, &keys);
+
+        assert_eq!(
+            promoted,
+            serde_json::json!({ "worker_posture": ["fallen", "standing"] })
+        );
+        assert_eq!(
+            attribute_filter("tracking_id", &serde_json::json!(7)),
+            serde_json::json!({ "tracking_id": [7] })
+        );
+    }
+
+    fn detection_of_type(detection_type: &str) -> Detection {
+        Detection {
+            detection_type: detection_type.to_string(),
+            ..detection_with_attributes(serde_json::Value::Null)
+        }
+    }
+
+    #[test]
+    fn test_detection_types_are_sanitized_and_capped() {
+        let mut detections = vec![
+            detection_of_type("person"),
+            detection_of_type("  person "),
+            detection_of_type("hard_hat,missing"),
+            detection_of_type("vest\n"),
+            detection_of_type(""),
+            detection_of_type(&"x".repeat(100)),
+        ];
+        // A runaway model emitting a new label per detection
+        detections.extend((0..50).map(|i| detection_of_type(&format!("label_{}", i))));
+
+        let summary = summarize_detection_types(&detections, 5, 16);
+
+        assert_eq!(
+            summary.types,
+            vec![
+                "person".to_string(),
+                "hard_hat_missing".to_string(),
+                "vest".to_string(),
+                "x".repeat(16),
+                "label_0".to_string(),
+            ]
+        );
+        assert_eq!(summary.omitted, 49);
+        assert!(summary.types.iter().all(|t| !t.contains(',')));
+        assert_eq!(
+            summary.joined().unwrap(),
+            format!("person,hard_hat_missing,vest,{},label_0", "x".repeat(16))
+        );
+
+        assert_eq!(summarize_detection_types(&[], 5, 16).joined(), None);
+    }
+
+    #[test]
+    fn test_confidence_rounded_to_configured_precision() {
+        assert_eq!(round_confidence(0.9567, Some(2)), 0.96);
+        assert_eq!(round_confidence(0.9567, Some(1)), 1.0);
+        assert_eq!(round_confidence(0.9567, Some(0)), 1.0);
+        assert_eq!(round_confidence(0.9567, None), 0.9567);
+    }
+
+    async fn test_store() -> MetadataStore {
+        test_store_with_keys(vec![]).await
+    }
+
+    fn test_database_config(indexed_attribute_keys: Vec<String>) -> DatabaseConfig {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
+        DatabaseConfig {
+            url,
+            max_connections: 2,
+            min_connections: 1,
+            connect_timeout_secs: 5,
+            idle_timeout_secs: 60,
+            run_migrations: true,
+            indexed_attribute_keys,
+            max_retries: 0,
+            retry_base_delay_ms: 0,
+            max_detection_types: 32,
+            max_detection_type_len: 64,
+            confidence_precision: None,
+        }
+    }
+
+    async fn test_store_with_keys(indexed_attribute_keys: Vec<String>) -> MetadataStore {
+        let store = MetadataStore::new(&test_database_config(indexed_attribute_keys))
+            .await
+            .unwrap();
+        store.run_migrations().await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_migrations_run_on_a_single_connection_pool() {
+        let config = DatabaseConfig {
+            max_connections: 1,
+            connect_timeout_secs: 2,
+            ..test_database_config(vec![])
+        };
+        let store = MetadataStore::new(&config).await.unwrap();
+
+        // The migrator must not wait on a second connection from the pool
+        tokio::time::timeout(Duration::from_secs(30), store.run_migrations())
+            .await
+            .expect("migrations blocked on the pool")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_index_frame_upserts_on_event_id() {
+        let store = test_store().await;
+
+        let mut event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_data(vec![0u8; 16])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Sample)
+            .build()
+            .unwrap();
+
+        let key = format!("frames/test/{}.jpeg", event.event_id);
+        let first = store
+            .index_frame(&event, (640, 480), &key, None, 16, None, "sample")
+            .await
+            .unwrap();
+
+        // Reprocessing the same event with a detection attached
+        event.detections.push(Detection {
+            detection_type: "person".to_string(),
+            confidence: 0.9,
+            bbox: [0.1, 0.1, 0.2, 0.2],
+            attributes: serde_json::Value::Null,
+        });
+        let second = store
+            .index_frame(&event, (640, 480), &key, None, 16, None, "reprocessed")
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM frames WHERE event_id = $1")
+            .bind(event.event_id)
+            .fetch_one(store.pool())
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        let frame = store.get_frame(first).await.unwrap().unwrap();
+        assert_eq!(frame.detection_count, 1);
+        assert_eq!(frame.storage_reason, "reprocessed");
+        assert_eq!(store.get_frame_detections(first).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_reindexing_archived_frame_restores_it() {
+        let store = test_store().await;
+
+        let event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_data(vec![0u8; 16])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Sample)
+            .build()
+            .unwrap();
+
+        let key = format!("frames/test/{}.jpeg", event.event_id);
+        let frame_id = store
+            .index_frame(&event, (640, 480), &key, Some("v1"), 16, None, "sample")
+            .await
+            .unwrap();
+        store.mark_archived(frame_id).await.unwrap();
+
+        // Replayed after retention deleted the object: uploaded again
+        store
+            .index_frame(&event, (640, 480), &key, None, 16, None, "sample")
+            .await
+            .unwrap();
+
+        let frame = store.get_frame(frame_id).await.unwrap().unwrap();
+        assert!(!frame.archived);
+        let archived_at: Option<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT archived_at FROM frames WHERE id = $1")
+                .bind(frame_id)
+                .fetch_one(store.pool())
+                .await
+                .unwrap();
+        assert_eq!(archived_at, None);
+        assert_eq!(frame.s3_key.as_deref(), Some(key.as_str()));
+        assert_eq!(frame.s3_version_id, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_latest_frame_per_device_returns_newest_only() {
+        let store = test_store().await;
+        let devices = [Uuid::new_v4().to_string(), Uuid::new_v4().to_string()];
+        let base = Utc::now() - chrono::Duration::hours(1);
+
+        let mut newest = Vec::new();
+        for device in &devices {
+            let mut last = None;
+            for minute in 0..3 {
+                let event = StorageTriggerEvent::builder()
+                    .device_id(device.as_str())
+                    .timestamp(base + chrono::Duration::minutes(minute))
+                    .frame_data(vec![0u8; 16])
+                    .dimensions(640, 480)
+                    .trigger_type(TriggerType::Sample)
+                    .build()
+                    .unwrap();
+                let key = format!("frames/test/{}.jpeg", event.event_id);
+                let frame_id = store
+                    .index_frame(&event, (640, 480), &key, None, 16, None, "sample")
+                    .await
+                    .unwrap();
+                last = Some(frame_id);
+            }
+            newest.push(last.unwrap());
+        }
+
+        let latest: Vec<_> = store
+            .latest_frame_per_device()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|f| devices.contains(&f.device_id))
+            .collect();
+
+        assert_eq!(latest.len(), 2);
+        for frame in latest {
+            assert!(newest.contains(&frame.id));
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_stream_frames_matches_query_frames() {
+        let store = test_store().await;
+        let device = Uuid::new_v4().to_string();
+        let base = Utc::now() - chrono::Duration::hours(1);
+
+        for minute in 0..5 {
+            let event = StorageTriggerEvent::builder()
+                .device_id(device.as_str())
+                .timestamp(base + chrono::Duration::minutes(minute))
+                .frame_data(vec![0u8; 16])
+                .dimensions(640, 480)
+                .trigger_type(TriggerType::Sample)
+                .build()
+                .unwrap();
+            let key = format!("frames/test/{}.jpeg", event.event_id);
+            store
+                .index_frame(&event, (640, 480), &key, None, 16, None, "sample")
+                .await
+                .unwrap();
+        }
+
+        let query = FrameQuery {
+            device_id: Some(device),
+            ascending: true,
+            ..Default::default()
+        };
+        let eager: Vec<Uuid> = store
+            .query_frames(&query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| f.id)
+            .collect();
+        let streamed: Vec<Uuid> = store
+            .stream_frames(&query)
+            .map(|f| f.unwrap().id)
+            .collect()
+            .await;
+
+        assert_eq!(eager.len(), 5);
+        assert_eq!(streamed, eager);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_keypoints_round_trip_as_structured_data() {
+        let store = test_store().await;
+        let keypoints = serde_json::json!([
+            { "name": "left_wrist", "x": 0.25, "y": 0.5, "confidence": 0.75 },
+            { "name": "right_wrist", "x": 0.625, "y": 0.5 }
+        ]);
+
+        let event = StorageTriggerEvent::builder()
+            .device_id("glasses-001")
+            .frame_data(vec![0u8; 16])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Detection)
+            .detection(Detection {
+                detection_type: "person".to_string(),
+                confidence: 0.9,
+                bbox: [0.1, 0.1, 0.5, 0.8],
+                attributes: serde_json::json!({ "keypoints": keypoints, "tracking_id": 7 }),
+            })
+            .build()
+            .unwrap();
+
+        let key = format!("frames/test/{}.jpeg", event.event_id);
+        let frame_id = store
+            .index_frame(&event, (640, 480), &key, None, 16, None, "detection")
+            .await
+            .unwrap();
+
+        let detections = store.get_frame_detections(frame_id).await.unwrap();
+        let attributes = detections[0].typed_attributes();
+
+        let stored = attributes.keypoints.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].name.as_deref(), Some("left_wrist"));
+        assert_eq!((stored[0].x, stored[0].y), (0.25, 0.5));
+        assert_eq!(stored[0].confidence, Some(0.75));
+        assert_eq!(stored[1].confidence, None);
+        assert_eq!(attributes.extra["tracking_id"], 7);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires PostgreSQL; set TEST_DATABASE_URL"]
+    async fn test_attribute_filters_match_typed_values() {
+        let store = test_store_with_keys(vec![
+            "worker_posture".to_string(),
+            "tracking_id".to_string(),
+            "occluded".to_string(),
+        ])
+        .await;
+        let device = Uuid::new_v4().to_string();
+
+        let event = StorageTriggerEvent::builder()
+            .device_id(device.as_str())
+            .frame_data(vec![0u8; 16])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Detection)
+            .detection(detection_with_attributes(serde_json::json!({
+                "worker_posture": "fallen",
+                "tracking_id": 7,
+                "occluded": false
+            })))
+            .build()
+            .unwrap();
+        let key = format!("frames/test/{}.jpeg", event.event_id);
+        let frame_id = store
+            .index_frame(&event, (640, 480), &key, None, 16, None, "detection")
+            .await
+            .unwrap();
+
+        let matches = |key: &str, value: serde_json::Value| {
+            let query = FrameQuery {
+                device_id: Some(device.clone()),
+                attribute_filters: vec![(key.to_string(), value)],
+                ..Default::default()
+            };
+            let store = &store;
+            async move {
+                let frames = store.query_frames(&query).await.unwrap();
+                frames.iter().any(|f| f.id == frame_id)
+            }
+        };
+
+        assert!(matches("worker_posture", serde_json::json!("fallen")).await);
+        assert!(!matches("worker_posture", serde_json::json!("kneeling")).await);
+        assert!(matches("tracking_id", serde_json::json!(7)).await);
+        assert!(!matches("tracking_id", serde_json::json!("7")).await);
+        assert!(matches("occluded", serde_json::json!(false)).await);
+        assert!(!matches("occluded", serde_json::json!(true)).await);
+    }
 }
@@ -1,22 +1,31 @@
-use crate::config::{ApiConfig, S3Config};
-use crate::metadata_store::{FrameMetadata, FrameQuery, MetadataStore};
-use crate::s3_uploader::S3Uploader;
+use crate::annotations::DetectionAttributes;
+use crate::config::{ApiConfig, DeviceSelectionOverride, FrameSelectionConfig, S3Config};
+use crate::decision_log;
+use crate::frame_selector::FrameSelector;
+use crate::journey::FrameJourney;
+use crate::metadata_store::{DetectionRecord, FrameMetadata, FrameQuery, MetadataStore};
+use crate::s3_uploader::{FrameObjectStore, S3Uploader};
+use crate::zip_export::{sanitize_path_segment, zip_frames};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use aws_sdk_s3::presigning::PresigningConfig;
 use axum::{
+    body::{Body, Bytes},
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, request, HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::{error, info, instrument};
+use tracing::{error, info, instrument, warn};
 use uuid::Uuid;
 
 /// Application state shared across handlers
@@ -25,6 +34,35 @@ pub struct AppState {
     pub s3_uploader: Arc<S3Uploader>,
     pub metadata_store: Arc<MetadataStore>,
     pub presigned_url_expiry: Duration,
+    /// Bearer token for destructive endpoints (None = disabled)
+    pub admin_token: Option<String>,
+    /// Decisions are logged to Postgres and can be included in frame journeys
+    pub decision_log_queryable: bool,
+    /// Most frames a single ZIP export may contain
+    pub zip_export_max_frames: usize,
+    /// Frame selector used by the Kafka consumer, for config snapshots
+    pub frame_selector: Arc<FrameSelector>,
+}
+
+/// Frame metadata operations needed to delete a single frame
+#[async_trait]
+pub trait FrameRecords: Send + Sync {
+    /// Look up a frame by ID
+    async fn get_frame(&self, frame_id: Uuid) -> Result<Option<FrameMetadata>>;
+
+    /// Delete a frame and its detections, returning whether it existed
+    async fn delete_frame(&self, frame_id: Uuid) -> Result<bool>;
+}
+
+#[async_trait]
+impl FrameRecords for MetadataStore {
+    async fn get_frame(&self, frame_id: Uuid) -> Result<Option<FrameMetadata>> {
+        MetadataStore::get_frame(self, frame_id).await
+    }
+
+    async fn delete_frame(&self, frame_id: Uuid) -> Result<bool> {
+        MetadataStore::delete_frame(self, frame_id).await
+    }
 }
 
 /// Presigned URL response
@@ -34,10 +72,20 @@ pub struct PresignedUrlResponse {
     pub url: String,
     /// URL expiration time
     pub expires_at: DateTime<Utc>,
+    /// S3 object version the URL is pinned to (None = latest)
+    pub version_id: Option<String>,
     /// Frame metadata
     pub frame: FrameMetadataResponse,
 }
 
+/// Query parameters for a single presigned URL
+#[derive(Debug, Default, Deserialize)]
+pub struct PresignedUrlQuery {
+    /// Object version to link to; defaults to the version the frame was
+    /// indexed with
+    pub version_id: Option<String>,
+}
+
 /// Frame metadata in API responses
 #[derive(Debug, Serialize)]
 pub struct FrameMetadataResponse {
@@ -52,6 +100,7 @@ pub struct FrameMetadataResponse {
     pub detection_count: i32,
     pub detection_types: Option<String>,
     pub max_confidence: Option<f32>,
+    pub archived: bool,
 }
 
 impl From<FrameMetadata> for FrameMetadataResponse {
@@ -68,10 +117,40 @@ impl From<FrameMetadata> for FrameMetadataResponse {
             detection_count: f.detection_count,
             detection_types: f.detection_types,
             max_confidence: f.max_confidence,
+            archived: f.archived,
         }
     }
 }
 
+/// Detection in API responses, with known attribute schemas typed
+#[derive(Debug, Serialize)]
+pub struct DetectionResponse {
+    pub id: Uuid,
+    pub detection_type: String,
+    pub confidence: f32,
+    pub bbox: serde_json::Value,
+    pub attributes: DetectionAttributes,
+}
+
+impl From<DetectionRecord> for DetectionResponse {
+    fn from(d: DetectionRecord) -> Self {
+        Self {
+            attributes: d.typed_attributes(),
+            id: d.id,
+            detection_type: d.detection_type,
+            confidence: d.confidence,
+            bbox: d.bbox,
+        }
+    }
+}
+
+/// Frame detections response
+#[derive(Debug, Serialize)]
+pub struct FrameDetectionsResponse {
+    pub frame_id: Uuid,
+    pub detections: Vec<DetectionResponse>,
+}
+
 /// Query parameters for frame list
 #[derive(Debug, Deserialize)]
 pub struct FrameListQuery {
@@ -87,6 +166,10 @@ pub struct FrameListQuery {
     pub detection_type: Option<String>,
     /// Minimum confidence
     pub min_confidence: Option<f32>,
+    /// Filter by a promoted detection attribute (`key:value`)
+    pub attribute: Option<String>,
+    /// Filter by shift
+    pub shift: Option<String>,
     /// Maximum results
     #[serde(default = "default_limit")]
     pub limit: i64,
@@ -102,6 +185,137 @@ fn default_limit() -> i64 {
     50
 }
 
+/// Query parameters for frame export
+#[derive(Debug, Deserialize)]
+pub struct FrameExportQuery {
+    /// Filter by device ID
+    pub device_id: Option<String>,
+    /// Start time (ISO 8601)
+    pub start_time: Option<DateTime<Utc>>,
+    /// End time (ISO 8601)
+    pub end_time: Option<DateTime<Utc>>,
+    /// Filter by trigger type
+    pub trigger_type: Option<String>,
+    /// Filter by detection type
+    pub detection_type: Option<String>,
+    /// Minimum confidence
+    pub min_confidence: Option<f32>,
+    /// Filter by a promoted detection attribute (`key:value`)
+    pub attribute: Option<String>,
+    /// Filter by shift
+    pub shift: Option<String>,
+    /// Output format
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Query parameters for ZIP export of an incident's frames
+#[derive(Debug, Deserialize)]
+pub struct ZipExportQuery {
+    /// Device the frames were captured on
+    pub device_id: String,
+    /// Start time (ISO 8601, inclusive)
+    pub start: DateTime<Utc>,
+    /// End time (ISO 8601, exclusive)
+    pub end: DateTime<Utc>,
+}
+
+/// Output format for frame exports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON object per line
+    #[default]
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// Columns written by CSV exports, in order
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "event_id",
+    "device_id",
+    "timestamp",
+    "frame_number",
+    "session_id",
+    "s3_key",
+    "width",
+    "height",
+    "format",
+    "trigger_type",
+    "storage_reason",
+    "detection_count",
+    "detection_types",
+    "max_confidence",
+    "size_bytes",
+    "archived",
+    "created_at",
+];
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+
+    /// Line written before any rows
+    fn header(self) -> Option<String> {
+        match self {
+            ExportFormat::Ndjson => None,
+            ExportFormat::Csv => Some(format!("{}\n", CSV_COLUMNS.join(","))),
+        }
+    }
+
+    /// Encode one frame as a newline-terminated line
+    fn encode(self, frame: &FrameMetadata) -> String {
+        match self {
+            ExportFormat::Ndjson => {
+                let mut line = serde_json::to_string(frame).unwrap_or_default();
+                line.push('\n');
+                line
+            }
+            ExportFormat::Csv => {
+                let fields = [
+                    frame.id.to_string(),
+                    frame.event_id.to_string(),
+                    csv_field(&frame.device_id),
+                    frame.timestamp.to_rfc3339(),
+                    frame.frame_number.to_string(),
+                    csv_field(frame.session_id.as_deref().unwrap_or_default()),
+                    csv_field(frame.s3_key.as_deref().unwrap_or_default()),
+                    frame.width.to_string(),
+                    frame.height.to_string(),
+                    csv_field(&frame.format),
+                    csv_field(&frame.trigger_type),
+                    csv_field(&frame.storage_reason),
+                    frame.detection_count.to_string(),
+                    csv_field(frame.detection_types.as_deref().unwrap_or_default()),
+                    frame
+                        .max_confidence
+                        .map(|c| c.to_string())
+                        .unwrap_or_default(),
+                    frame.size_bytes.to_string(),
+                    frame.archived.to_string(),
+                    frame.created_at.to_rfc3339(),
+                ];
+                format!("{}\n", fields.join(","))
+            }
+        }
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 /// Frame list response
 #[derive(Debug, Serialize)]
 pub struct FrameListResponse {
@@ -150,6 +364,44 @@ pub struct ErrorResponse {
     pub code: String,
 }
 
+/// Frame deletion response
+#[derive(Debug, Serialize)]
+pub struct DeleteFrameResponse {
+    pub frame_id: Uuid,
+    pub deleted: bool,
+}
+
+/// Matches request origins against exact origins and `*` subdomain patterns
+///
+/// A pattern like `https://*.nier.example.com` matches any subdomain
+/// (`https://dash.nier.example.com`) but not the bare domain or other hosts.
+#[derive(Debug, Clone)]
+struct OriginMatcher {
+    patterns: Vec<String>,
+}
+
+impl OriginMatcher {
+    fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| match pattern.split_once('*') {
+                Some((prefix, suffix)) => {
+                    origin.len() > prefix.len() + suffix.len()
+                        && origin.starts_with(prefix)
+                        && origin.ends_with(suffix)
+                        && origin[prefix.len()..origin.len() - suffix.len()]
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }
+                None => pattern == origin,
+            })
+    }
+}
+
 /// Create the API router
 pub fn create_router(state: AppState, config: &ApiConfig) -> Router {
     let cors = if config.cors_enabled {
@@ -158,6 +410,16 @@ pub fn create_router(state: AppState, config: &ApiConfig) -> Router {
                 .allow_origin(Any)
                 .allow_methods(Any)
                 .allow_headers(Any)
+        } else if config.cors_origins.iter().any(|o| o.contains('*')) {
+            let matcher = OriginMatcher::new(config.cors_origins.clone());
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::predicate(
+                    move |origin: &HeaderValue, _parts: &request::Parts| {
+                        origin.to_str().map(|o| matcher.allows(o)).unwrap_or(false)
+                    },
+                ))
+                .allow_methods(Any)
+                .allow_headers(Any)
         } else {
             let origins: Vec<_> = config
                 .cors_origins
@@ -177,10 +439,16 @@ pub fn create_router(state: AppState, config: &ApiConfig) -> Router {
         .route("/health", get(health_check))
         .route("/ready", get(readiness_check))
         .route("/api/v1/frames", get(list_frames))
-        .route("/api/v1/frames/:frame_id", get(get_frame))
+        .route("/api/v1/frames/export", get(export_frames))
+        .route("/api/v1/frames/export.zip", get(export_frames_zip))
+        .route("/api/v1/frames/:frame_id", get(get_frame).delete(delete_frame))
         .route("/api/v1/frames/:frame_id/url", get(get_presigned_url))
+        .route("/api/v1/frames/:frame_id/detections", get(get_frame_detections))
         .route("/api/v1/frames/batch-urls", post(batch_presigned_urls))
         .route("/api/v1/playback/:device_id", get(get_playback_urls))
+        .route("/api/v1/devices/latest-frames", get(get_latest_frames))
+        .route("/api/v1/trace/:trace_id", get(get_frame_journey))
+        .route("/api/v1/selection-config", get(get_selection_config))
         .layer(TraceLayer::new_for_http())
         .layer(cors)
         .with_state(state)
@@ -235,6 +503,8 @@ async fn list_frames(
         limit: Some(params.limit + 1), // Fetch one extra to check has_more
         offset: Some(params.offset),
         ascending: false,
+        attribute_filters: attribute_filters(params.attribute.as_deref()),
+        shift: params.shift,
     };
 
     let mut frames = state
@@ -260,16 +530,19 @@ async fn list_frames(
     let mut frame_responses = Vec::with_capacity(frames.len());
 
     for frame in frames {
-        let (url, url_expires_at) = if params.include_urls {
-            match generate_presigned_url(&state, &frame.s3_key).await {
-                Ok((url, expires)) => (Some(url), Some(expires)),
-                Err(e) => {
-                    error!(error = %e, s3_key = %frame.s3_key, "Failed to generate presigned URL");
-                    (None, None)
+        let stored_key = frame.s3_key.as_deref().filter(|_| !frame.archived);
+        let (url, url_expires_at) = match stored_key {
+            Some(s3_key) if params.include_urls => {
+                let version_id = frame.s3_version_id.as_deref();
+                match generate_presigned_url(&state, s3_key, version_id).await {
+                    Ok((url, expires)) => (Some(url), Some(expires)),
+                    Err(e) => {
+                        error!(error = %e, s3_key = %s3_key, "Failed to generate presigned URL");
+                        (None, None)
+                    }
                 }
             }
-        } else {
-            (None, None)
+            _ => (None, None),
         };
 
         frame_responses.push(FrameWithUrl {
@@ -297,6 +570,152 @@ async fn list_frames(
     }))
 }
 
+/// Stream all matching frame metadata as CSV or NDJSON
+///
+/// Rows are written to the response as they are read from the database, so
+/// exports spanning months of frames do not have to fit in memory.
+#[instrument(skip(state))]
+async fn export_frames(
+    State(state): State<AppState>,
+    Query(params): Query<FrameExportQuery>,
+) -> impl IntoResponse {
+    let query = FrameQuery {
+        device_id: params.device_id,
+        start_time: params.start_time,
+        end_time: params.end_time,
+        trigger_type: params.trigger_type,
+        detection_type: params.detection_type,
+        min_confidence: params.min_confidence,
+        ascending: true,
+        attribute_filters: attribute_filters(params.attribute.as_deref()),
+        shift: params.shift,
+        ..Default::default()
+    };
+
+    let format = params.format;
+    let header_row = format.header().map(|h| Ok(Bytes::from(h)));
+    let rows = state
+        .metadata_store
+        .stream_frames(&query)
+        .map(move |frame| {
+            frame
+                .map(|f| Bytes::from(format.encode(&f)))
+                .inspect_err(|e| error!(error = %e, "Frame export failed mid-stream"))
+        });
+
+    (
+        [(header::CONTENT_TYPE, format.content_type())],
+        Body::from_stream(stream::iter(header_row).chain(rows)),
+    )
+}
+
+/// Stream a device's frames in a time range as a ZIP archive
+///
+/// Objects are fetched and written into the archive one at a time, so only
+/// the frame metadata is held in memory. Requests matching more than
+/// `zip_export_max_frames` frames are rejected rather than truncated, and
+/// frames whose objects are expired or cannot be fetched are listed in the
+/// archive's `MANIFEST.json`, so an investigator never receives a silently
+/// incomplete incident.
+#[instrument(skip(state))]
+async fn export_frames_zip(
+    State(state): State<AppState>,
+    Query(params): Query<ZipExportQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if params.end <= params.start {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "end must be after start".to_string(),
+                code: "INVALID_TIME_RANGE".to_string(),
+            }),
+        ));
+    }
+
+    let max_frames = state.zip_export_max_frames;
+    let query = FrameQuery {
+        device_id: Some(params.device_id.clone()),
+        start_time: Some(params.start),
+        end_time: Some(params.end),
+        limit: Some(max_frames as i64 + 1),
+        ascending: true,
+        ..Default::default()
+    };
+
+    let frames = state
+        .metadata_store
+        .query_frames(&query)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to query frames for ZIP export");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to query frames".to_string(),
+                    code: "QUERY_ERROR".to_string(),
+                }),
+            )
+        })?;
+
+    if frames.len() > max_frames {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(ErrorResponse {
+                error: format!(
+                    "More than {} frames match; narrow the time range",
+                    max_frames
+                ),
+                code: "EXPORT_TOO_LARGE".to_string(),
+            }),
+        ));
+    }
+
+    info!(
+        device_id = %params.device_id,
+        frames = frames.len(),
+        "Streaming ZIP export"
+    );
+    metrics::counter!("storage.export.zip_requests").increment(1);
+
+    let filename = format!(
+        "{}_{}.zip",
+        sanitize_path_segment(&params.device_id),
+        params.start.format("%Y%m%dT%H%M%SZ")
+    );
+    let archive = zip_frames(state.s3_uploader.clone(), frames).inspect(|chunk| {
+        if let Err(e) = chunk {
+            error!(error = %e, "ZIP export failed mid-stream");
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(archive),
+    ))
+}
+
+/// Parse an `attribute=key:value` filter
+///
+/// Values that parse as JSON keep their type (`tracking_id:7`,
+/// `occluded:true`, or `tracking_id:"7"` for the string); anything else is
+/// matched as a string.
+fn attribute_filters(attribute: Option<&str>) -> Vec<(String, serde_json::Value)> {
+    attribute
+        .and_then(|a| a.split_once(':'))
+        .map(|(k, v)| {
+            let value = serde_json::from_str(v)
+                .unwrap_or_else(|_| serde_json::Value::String(v.to_string()));
+            vec![(k.to_string(), value)]
+        })
+        .unwrap_or_default()
+}
+
 /// Get single frame metadata
 #[instrument(skip(state))]
 async fn get_frame(
@@ -330,11 +749,149 @@ async fn get_frame(
     }
 }
 
+/// Get a frame's detections with typed annotation attributes
+#[instrument(skip(state))]
+async fn get_frame_detections(
+    State(state): State<AppState>,
+    Path(frame_id): Path<Uuid>,
+) -> Result<Json<FrameDetectionsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let detections = state
+        .metadata_store
+        .get_frame_detections(frame_id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to get frame detections");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to get frame detections".to_string(),
+                    code: "QUERY_ERROR".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(FrameDetectionsResponse {
+        frame_id,
+        detections: detections.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// Delete a frame's S3 object and metadata (e.g. for privacy requests)
+#[instrument(skip(state, headers))]
+async fn delete_frame(
+    State(state): State<AppState>,
+    Path(frame_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Json<DeleteFrameResponse>, (StatusCode, Json<ErrorResponse>)> {
+    authorize_admin(&headers, state.admin_token.as_deref())?;
+
+    let deleted = delete_frame_everywhere(
+        state.s3_uploader.as_ref(),
+        state.metadata_store.as_ref(),
+        frame_id,
+    )
+    .await
+    .map_err(|e| {
+        error!(error = %e, "Failed to delete frame");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to delete frame".to_string(),
+                code: "DELETE_ERROR".to_string(),
+            }),
+        )
+    })?;
+
+    if deleted.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Frame not found".to_string(),
+                code: "NOT_FOUND".to_string(),
+            }),
+        ));
+    }
+
+    info!(frame_id = %frame_id, "Frame deleted");
+    metrics::counter!("storage.frames.deleted").increment(1);
+
+    Ok(Json(DeleteFrameResponse {
+        frame_id,
+        deleted: true,
+    }))
+}
+
+/// Delete a frame's S3 object, then its metadata rows.
+///
+/// The object goes first so a failed S3 call leaves the row in place and the
+/// request can be retried. Returns the deleted frame, or `None` if unknown.
+pub async fn delete_frame_everywhere(
+    objects: &dyn FrameObjectStore,
+    records: &dyn FrameRecords,
+    frame_id: Uuid,
+) -> Result<Option<FrameMetadata>> {
+    let Some(frame) = records.get_frame(frame_id).await? else {
+        return Ok(None);
+    };
+
+    if let Some(s3_key) = frame.s3_key.as_deref().filter(|_| !frame.archived) {
+        objects.delete_frame(s3_key).await?;
+    }
+
+    if !records.delete_frame(frame_id).await? {
+        // Deleted concurrently between lookup and delete
+        return Ok(None);
+    }
+
+    Ok(Some(frame))
+}
+
+/// Check the request's bearer token against the configured admin token
+fn authorize_admin(
+    headers: &HeaderMap,
+    admin_token: Option<&str>,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let Some(expected) = admin_token else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Admin endpoints are disabled".to_string(),
+                code: "FORBIDDEN".to_string(),
+            }),
+        ));
+    };
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => {
+            warn!("Rejected unauthorized admin request");
+            Err((
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: "Missing or invalid bearer token".to_string(),
+                    code: "UNAUTHORIZED".to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// Get presigned URL for a frame
 #[instrument(skip(state))]
 async fn get_presigned_url(
     State(state): State<AppState>,
     Path(frame_id): Path<Uuid>,
+    Query(params): Query<PresignedUrlQuery>,
 ) -> Result<Json<PresignedUrlResponse>, (StatusCode, Json<ErrorResponse>)> {
     let frame = state
         .metadata_store
@@ -361,7 +918,18 @@ async fn get_presigned_url(
         )
     })?;
 
-    let (url, expires_at) = generate_presigned_url(&state, &frame.s3_key)
+    let Some(s3_key) = frame.s3_key.as_deref().filter(|_| !frame.archived) else {
+        return Err((
+            StatusCode::GONE,
+            Json(ErrorResponse {
+                error: "Frame has been archived".to_string(),
+                code: "ARCHIVED".to_string(),
+            }),
+        ));
+    };
+
+    let version_id = params.version_id.or_else(|| frame.s3_version_id.clone());
+    let (url, expires_at) = generate_presigned_url(&state, s3_key, version_id.as_deref())
         .await
         .map_err(|e| {
             error!(error = %e, "Failed to generate presigned URL");
@@ -377,6 +945,7 @@ async fn get_presigned_url(
     Ok(Json(PresignedUrlResponse {
         url,
         expires_at,
+        version_id,
         frame: frame.into(),
     }))
 }
@@ -401,19 +970,30 @@ async fn batch_presigned_urls(
 
     for frame_id in request.frame_ids {
         let result = match state.metadata_store.get_frame(frame_id).await {
-            Ok(Some(frame)) => match generate_presigned_url(&state, &frame.s3_key).await {
-                Ok((url, expires_at)) => PresignedUrlResult {
-                    frame_id,
-                    url: Some(url),
-                    expires_at: Some(expires_at),
-                    error: None,
-                },
-                Err(e) => PresignedUrlResult {
+            Ok(Some(frame)) => match frame.s3_key.as_deref().filter(|_| !frame.archived) {
+                None => PresignedUrlResult {
                     frame_id,
                     url: None,
                     expires_at: None,
-                    error: Some(e.to_string()),
+                    error: Some("Frame has been archived".to_string()),
                 },
+                Some(s3_key) => {
+                    let version_id = frame.s3_version_id.as_deref();
+                    match generate_presigned_url(&state, s3_key, version_id).await {
+                        Ok((url, expires_at)) => PresignedUrlResult {
+                            frame_id,
+                            url: Some(url),
+                            expires_at: Some(expires_at),
+                            error: None,
+                        },
+                        Err(e) => PresignedUrlResult {
+                            frame_id,
+                            url: None,
+                            expires_at: None,
+                            error: Some(e.to_string()),
+                        },
+                    }
+                }
             },
             Ok(None) => PresignedUrlResult {
                 frame_id,
@@ -466,10 +1046,20 @@ async fn get_playback_urls(
             )
         })?;
 
-    let mut playback_frames = Vec::with_capacity(frames.len());
+    let frames: Vec<FrameMetadata> = frames
+        .into_iter()
+        .filter(|f| !f.archived && f.s3_key.is_some())
+        .collect();
+    let lateness = chrono::Duration::milliseconds(params.lateness_ms.unwrap_or(0).max(0));
+    let ordered = order_for_playback(frames, params.order, lateness);
+    let out_of_order_count = ordered.iter().filter(|(_, late)| *late).count();
 
-    for frame in frames {
-        let (url, expires_at) = generate_presigned_url(&state, &frame.s3_key)
+    let mut playback_frames = Vec::with_capacity(ordered.len());
+
+    for (frame, out_of_order) in ordered {
+        let s3_key = frame.s3_key.as_deref().unwrap_or_default();
+        let version_id = frame.s3_version_id.as_deref();
+        let (url, expires_at) = generate_presigned_url(&state, s3_key, version_id)
             .await
             .map_err(|e| {
                 error!(error = %e, "Failed to generate presigned URL");
@@ -486,30 +1076,253 @@ async fn get_playback_urls(
             frame_id: frame.id,
             timestamp: frame.timestamp,
             frame_number: frame.frame_number,
+            session_id: frame.session_id,
             url,
             expires_at,
             detection_count: frame.detection_count,
+            out_of_order,
         });
     }
 
     Ok(Json(PlaybackResponse {
         device_id,
+        order: params.order,
+        out_of_order_count,
         frames: playback_frames,
     }))
 }
 
+/// Put timestamp-ordered frames into playback order and flag late arrivals
+///
+/// With `PlaybackOrder::Session` frames are grouped by session, sessions in
+/// order of their first frame, and sorted by `frame_number` within each
+/// session. A frame is flagged out of order when its timestamp is more than
+/// `lateness` before the previous frame of its session in the returned order.
+fn order_for_playback(
+    mut frames: Vec<FrameMetadata>,
+    order: PlaybackOrder,
+    lateness: chrono::Duration,
+) -> Vec<(FrameMetadata, bool)> {
+    if order == PlaybackOrder::Session {
+        let mut sessions: Vec<Option<String>> = Vec::new();
+        for frame in &frames {
+            if !sessions.contains(&frame.session_id) {
+                sessions.push(frame.session_id.clone());
+            }
+        }
+        // Stable, so frames sharing a frame number keep timestamp order
+        frames.sort_by_key(|f| {
+            let session = sessions.iter().position(|s| *s == f.session_id);
+            (session, f.frame_number)
+        });
+    }
+
+    let mut previous: std::collections::HashMap<Option<String>, DateTime<Utc>> =
+        std::collections::HashMap::new();
+    frames
+        .into_iter()
+        .map(|frame| {
+            let late = previous
+                .get(&frame.session_id)
+                .is_some_and(|prev| frame.timestamp + lateness < *prev);
+            let latest = previous
+                .entry(frame.session_id.clone())
+                .or_insert(frame.timestamp);
+            *latest = (*latest).max(frame.timestamp);
+            (frame, late)
+        })
+        .collect()
+}
+
+/// Newest frame from every device with a presigned URL, for live camera walls
+#[instrument(skip(state))]
+async fn get_latest_frames(
+    State(state): State<AppState>,
+) -> Result<Json<LatestFramesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let frames = state
+        .metadata_store
+        .latest_frame_per_device()
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to query latest frames");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to query frames".to_string(),
+                    code: "QUERY_ERROR".to_string(),
+                }),
+            )
+        })?;
+
+    let mut latest = Vec::with_capacity(frames.len());
+
+    for frame in frames {
+        // The latest frame may already be archived
+        let Some(s3_key) = frame.s3_key.clone().filter(|_| !frame.archived) else {
+            latest.push(FrameWithUrl {
+                frame: frame.into(),
+                url: None,
+                url_expires_at: None,
+            });
+            continue;
+        };
+        let version_id = frame.s3_version_id.as_deref();
+        let (url, expires_at) = generate_presigned_url(&state, &s3_key, version_id)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to generate presigned URL");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to generate presigned URL".to_string(),
+                        code: "PRESIGN_ERROR".to_string(),
+                    }),
+                )
+            })?;
+
+        latest.push(FrameWithUrl {
+            frame: frame.into(),
+            url: Some(url),
+            url_expires_at: Some(expires_at),
+        });
+    }
+
+    Ok(Json(LatestFramesResponse { frames: latest }))
+}
+
+/// Frame selection settings currently in force
+async fn get_selection_config(State(state): State<AppState>) -> Json<SelectionConfigResponse> {
+    Json(selection_config_snapshot(&state.frame_selector))
+}
+
+/// Snapshot of the selector's global settings and device overrides
+///
+/// Selection settings hold no credentials, so nothing needs redacting; new
+/// fields that do must be skipped when serializing `FrameSelectionConfig`.
+pub fn selection_config_snapshot(selector: &FrameSelector) -> SelectionConfigResponse {
+    let config = selector.effective_config();
+    SelectionConfigResponse {
+        global: FrameSelectionConfig::clone(&config),
+        device_overrides: config
+            .device_overrides
+            .iter()
+            .map(|(device_id, device)| (device_id.clone(), device.clone()))
+            .collect(),
+        reloads: selector.reload_count(),
+    }
+}
+
+/// Full lifecycle of one frame: capture, decisions, storage and detections
+///
+/// `trace_id` is the event's `trace_id` metadata field or its event ID.
+#[instrument(skip(state))]
+async fn get_frame_journey(
+    State(state): State<AppState>,
+    Path(trace_id): Path<String>,
+) -> Result<Json<FrameJourney>, (StatusCode, Json<ErrorResponse>)> {
+    let query_error = |e: anyhow::Error| {
+        error!(error = %e, trace_id = %trace_id, "Failed to load frame journey");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to load frame journey".to_string(),
+                code: "QUERY_ERROR".to_string(),
+            }),
+        )
+    };
+
+    let frame = state
+        .metadata_store
+        .find_frame_by_trace_id(&trace_id)
+        .await
+        .map_err(query_error)?;
+
+    let detections = match &frame {
+        Some(f) => state
+            .metadata_store
+            .get_frame_detections(f.id)
+            .await
+            .map_err(query_error)?,
+        None => Vec::new(),
+    };
+
+    // Skipped frames are only known to the decision log, by event ID
+    let event_id = frame
+        .as_ref()
+        .map(|f| f.event_id)
+        .or_else(|| Uuid::parse_str(&trace_id).ok());
+    let decisions = match event_id {
+        Some(event_id) if state.decision_log_queryable => {
+            decision_log::decisions_for_event(state.metadata_store.pool(), event_id)
+                .await
+                .map_err(query_error)?
+        }
+        _ => Vec::new(),
+    };
+
+    FrameJourney::assemble(trace_id.clone(), frame, detections, decisions)
+        .map(Json)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "No frame found for trace ID".to_string(),
+                    code: "NOT_FOUND".to_string(),
+                }),
+            )
+        })
+}
+
+/// Latest frame per device response
+#[derive(Debug, Serialize)]
+pub struct LatestFramesResponse {
+    pub frames: Vec<FrameWithUrl>,
+}
+
+/// Selection config snapshot response
+#[derive(Debug, Serialize)]
+pub struct SelectionConfigResponse {
+    /// Settings applied to devices without an override
+    pub global: FrameSelectionConfig,
+    /// Settings replaced per device; unset fields use the global value
+    pub device_overrides: BTreeMap<String, DeviceSelectionOverride>,
+    /// Times the settings were reloaded since startup
+    pub reloads: u64,
+}
+
 /// Query parameters for playback
 #[derive(Debug, Deserialize)]
 pub struct PlaybackQuery {
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub limit: Option<i64>,
+    /// Playback order
+    #[serde(default)]
+    pub order: PlaybackOrder,
+    /// How far a frame's timestamp may fall behind the previous frame of its
+    /// session before it is flagged out of order
+    pub lateness_ms: Option<i64>,
+}
+
+/// Order of frames in a playback response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackOrder {
+    /// By capture timestamp
+    #[default]
+    Timestamp,
+    /// By frame number within each stream session, robust to frames
+    /// arriving out of order
+    Session,
 }
 
 /// Playback response
 #[derive(Debug, Serialize)]
 pub struct PlaybackResponse {
     pub device_id: String,
+    pub order: PlaybackOrder,
+    /// Number of frames flagged `out_of_order`
+    pub out_of_order_count: usize,
     pub frames: Vec<PlaybackFrame>,
 }
 
@@ -519,32 +1332,62 @@ pub struct PlaybackFrame {
     pub frame_id: Uuid,
     pub timestamp: DateTime<Utc>,
     pub frame_number: i64,
+    /// Stream session; frame numbers restart in each new session
+    pub session_id: Option<String>,
     pub url: String,
     pub expires_at: DateTime<Utc>,
     pub detection_count: i32,
+    /// Timestamp is earlier than the previous frame of the session by more
+    /// than the allowed lateness
+    pub out_of_order: bool,
 }
 
 /// Generate a presigned URL for an S3 key
+///
+/// Keys are stored in full, including any configured `key_prefix`, so URLs
+/// always point into the namespace the frame was uploaded under. With a
+/// `version_id` the URL serves that version of the object rather than the
+/// latest.
 async fn generate_presigned_url(
     state: &AppState,
     s3_key: &str,
+    version_id: Option<&str>,
 ) -> Result<(String, DateTime<Utc>)> {
-    let presigning_config = PresigningConfig::expires_in(state.presigned_url_expiry)
-        .context("Failed to create presigning config")?;
+    let url = presign_get_object(
+        state.s3_uploader.client(),
+        state.s3_uploader.bucket(),
+        s3_key,
+        version_id,
+        state.presigned_url_expiry,
+    )
+    .await?;
 
-    let presigned = state
-        .s3_uploader
-        .client()
+    let expires_at = Utc::now() + chrono::Duration::from_std(state.presigned_url_expiry).unwrap();
+
+    Ok((url, expires_at))
+}
+
+/// Presign a `GetObject` request, pinned to `version_id` when given
+async fn presign_get_object(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    s3_key: &str,
+    version_id: Option<&str>,
+    expiry: Duration,
+) -> Result<String> {
+    let presigning_config =
+        PresigningConfig::expires_in(expiry).context("Failed to create presigning config")?;
+
+    let presigned = client
         .get_object()
-        .bucket(state.s3_uploader.bucket())
+        .bucket(bucket)
         .key(s3_key)
+        .set_version_id(version_id.map(String::from))
         .presigned(presigning_config)
         .await
         .context("Failed to generate presigned URL")?;
 
-    let expires_at = Utc::now() + chrono::Duration::from_std(state.presigned_url_expiry).unwrap();
-
-    Ok((presigned.uri().to_string(), expires_at))
+    Ok(presigned.uri().to_string())
 }
 
 /// Start the presigned URL API server
@@ -571,6 +1414,29 @@ pub async fn start_api_server(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metadata_store::testing::stored_frame;
+    use crate::s3_uploader::testing::InMemoryObjectStore;
+
+    #[test]
+    fn test_attribute_filter_values_keep_json_type() {
+        assert_eq!(
+            attribute_filters(Some("worker_posture:fallen")),
+            vec![("worker_posture".to_string(), serde_json::json!("fallen"))]
+        );
+        assert_eq!(
+            attribute_filters(Some("tracking_id:7")),
+            vec![("tracking_id".to_string(), serde_json::json!(7))]
+        );
+        assert_eq!(
+            attribute_filters(Some("occluded:true")),
+            vec![("occluded".to_string(), serde_json::json!(true))]
+        );
+        assert_eq!(
+            attribute_filters(Some("tracking_id:\"7\"")),
+            vec![("tracking_id".to_string(), serde_json::json!("7"))]
+        );
+        assert!(attribute_filters(Some("no-separator")).is_empty());
+    }
 
     #[test]
     fn test_frame_metadata_response_from() {
@@ -580,9 +1446,14 @@ mod tests {
             device_id: "test-device".to_string(),
             timestamp: Utc::now(),
             frame_number: 100,
-            s3_key: "test/key.jpg".to_string(),
+            session_id: None,
+            shift: None,
+            s3_key: Some("test/key.jpg".to_string()),
+            s3_version_id: None,
             width: 1920,
             height: 1080,
+            original_width: 1920,
+            original_height: 1080,
             format: "jpeg".to_string(),
             trigger_type: "detection".to_string(),
             storage_reason: "test".to_string(),
@@ -591,6 +1462,7 @@ mod tests {
             max_confidence: Some(0.95),
             size_bytes: 50000,
             metadata: serde_json::Value::Null,
+            archived: false,
             created_at: Utc::now(),
         };
 
@@ -598,4 +1470,203 @@ mod tests {
         assert_eq!(response.detection_count, 2);
         assert_eq!(response.format, "jpeg");
     }
+
+    #[test]
+    fn test_origin_matcher_wildcard_subdomain() {
+        let matcher = OriginMatcher::new(vec![
+            "https://*.nier.example.com".to_string(),
+            "http://localhost:3000".to_string(),
+        ]);
+
+        assert!(matcher.allows("https://dash.nier.example.com"));
+        assert!(matcher.allows("https://eu.dash.nier.example.com"));
+        assert!(matcher.allows("http://localhost:3000"));
+
+        assert!(!matcher.allows("https://nier.example.com"));
+        assert!(!matcher.allows("https://evil.com"));
+        assert!(!matcher.allows("https://evil.com/.nier.example.com"));
+        assert!(!matcher.allows("http://dash.nier.example.com"));
+    }
+
+    #[derive(Default)]
+    struct FakeRecords {
+        frames: std::sync::Mutex<Vec<FrameMetadata>>,
+    }
+
+    #[async_trait]
+    impl FrameRecords for FakeRecords {
+        async fn get_frame(&self, frame_id: Uuid) -> Result<Option<FrameMetadata>> {
+            Ok(self
+                .frames
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|f| f.id == frame_id)
+                .cloned())
+        }
+
+        async fn delete_frame(&self, frame_id: Uuid) -> Result<bool> {
+            let mut frames = self.frames.lock().unwrap();
+            let len = frames.len();
+            frames.retain(|f| f.id != frame_id);
+            Ok(frames.len() < len)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_frame_removes_object_and_metadata() {
+        let objects = InMemoryObjectStore::default();
+        let records = FakeRecords::default();
+
+        let frame_id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+        for (id, key) in [(frame_id, "frames/a.jpg"), (other_id, "frames/b.jpg")] {
+            objects.insert(key, vec![0u8; 4]);
+            records.frames.lock().unwrap().push(stored_frame(id, key));
+        }
+
+        let deleted = delete_frame_everywhere(&objects, &records, frame_id)
+            .await
+            .unwrap();
+
+        assert_eq!(deleted.map(|f| f.id), Some(frame_id));
+        assert!(!objects.contains("frames/a.jpg"));
+        assert!(records.get_frame(frame_id).await.unwrap().is_none());
+        assert!(objects.contains("frames/b.jpg"));
+        assert!(records.get_frame(other_id).await.unwrap().is_some());
+
+        // A second delete reports not found
+        let again = delete_frame_everywhere(&objects, &records, frame_id)
+            .await
+            .unwrap();
+        assert!(again.is_none());
+    }
+
+    #[test]
+    fn test_authorize_admin() {
+        let mut headers = HeaderMap::new();
+
+        let (status, _) = authorize_admin(&headers, None).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = authorize_admin(&headers, Some("secret")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer wrong"));
+        let (status, _) = authorize_admin(&headers, Some("secret")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(authorize_admin(&headers, Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn test_csv_export_row_matches_header() {
+        let frame = FrameMetadata {
+            frame_number: 7,
+            detection_count: 2,
+            detection_types: Some("safety_vest,hard_hat".to_string()),
+            ..stored_frame(Uuid::new_v4(), "test/key.jpg")
+        };
+
+        let row = ExportFormat::Csv.encode(&frame);
+
+        assert!(row.ends_with('\n'));
+        assert!(row.contains(",\"safety_vest,hard_hat\","));
+        // The quoted comma does not add a column
+        let unquoted = row.replace("\"safety_vest,hard_hat\"", "types");
+        assert_eq!(unquoted.trim_end().split(',').count(), CSV_COLUMNS.len());
+
+        let line = ExportFormat::Ndjson.encode(&frame);
+        let parsed: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed["frame_number"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_presigned_url_carries_version_id() {
+        use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("AKID", "SECRET", None, None, "test"))
+            .build();
+        let client = aws_sdk_s3::Client::from_conf(config);
+        let expiry = Duration::from_secs(60);
+
+        let url = presign_get_object(&client, "nier-frames", "frames/a.jpeg", Some("v3"), expiry)
+            .await
+            .unwrap();
+        assert!(url.contains("versionId=v3"), "{}", url);
+
+        let url = presign_get_object(&client, "nier-frames", "frames/a.jpeg", None, expiry)
+            .await
+            .unwrap();
+        assert!(!url.contains("versionId"), "{}", url);
+    }
+
+    #[test]
+    fn test_session_order_follows_frame_numbers() {
+        let base = Utc::now();
+        let frame = |number: i64, offset_ms: i64| {
+            let mut frame = stored_frame(Uuid::new_v4(), &format!("frames/{}.jpeg", number));
+            frame.frame_number = number;
+            frame.session_id = Some("session-1".to_string());
+            frame.timestamp = base + chrono::Duration::milliseconds(offset_ms);
+            frame
+        };
+        // Timestamp order from the store; jitter reordered frames 2-4
+        let frames = vec![
+            frame(1, 0),
+            frame(3, 60),
+            frame(2, 90),
+            frame(5, 130),
+            frame(4, 140),
+        ];
+
+        let strict = chrono::Duration::zero();
+        let by_timestamp = order_for_playback(frames.clone(), PlaybackOrder::Timestamp, strict);
+        let numbers: Vec<i64> = by_timestamp.iter().map(|(f, _)| f.frame_number).collect();
+        assert_eq!(numbers, vec![1, 3, 2, 5, 4]);
+        assert!(by_timestamp.iter().all(|(_, late)| !late));
+
+        let by_session = order_for_playback(frames.clone(), PlaybackOrder::Session, strict);
+        let numbers: Vec<i64> = by_session.iter().map(|(f, _)| f.frame_number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+        let late: Vec<bool> = by_session.iter().map(|(_, late)| *late).collect();
+        assert_eq!(late, vec![false, false, true, false, true]);
+
+        // Within the lateness allowance nothing is flagged
+        let allowance = chrono::Duration::milliseconds(50);
+        let tolerant = order_for_playback(frames, PlaybackOrder::Session, allowance);
+        assert!(tolerant.iter().all(|(_, late)| !late));
+    }
+
+    #[test]
+    fn test_selection_config_snapshot_reflects_device_override() {
+        let selector = crate::frame_selector::FrameSelectorBuilder::new()
+            .min_confidence(0.5)
+            .build();
+        let mut config = FrameSelectionConfig::clone(&selector.effective_config());
+        config.device_overrides.insert(
+            "glasses-007".to_string(),
+            DeviceSelectionOverride {
+                min_confidence: Some(0.8),
+                store_samples: Some(false),
+                ..Default::default()
+            },
+        );
+        selector.reload(config);
+
+        let snapshot = serde_json::to_value(selection_config_snapshot(&selector)).unwrap();
+        assert_eq!(snapshot["reloads"], 1);
+        assert_eq!(snapshot["global"]["min_confidence"], 0.5);
+        assert_eq!(snapshot["global"]["store_samples"], true);
+        assert!(snapshot["global"].get("device_overrides").is_none());
+
+        let device = &snapshot["device_overrides"]["glasses-007"];
+        assert_eq!(device["min_confidence"].as_f64().unwrap() as f32, 0.8);
+        assert_eq!(device["store_samples"], false);
+        assert!(device.get("sample_rate").is_none());
+    }
 }
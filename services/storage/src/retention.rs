@@ -0,0 +1,484 @@
+//! Retention management for stored frames.
+//!
+//! S3 objects and frame metadata are expired independently: once a frame passes
+//! the S3 cutoff its object is deleted and the row is marked `archived`, so it
+//! stays searchable until the (typically longer) metadata cutoff removes it.
+//!
+//! Objects removed out-of-band (bucket lifecycle rules, manual deletes) leave
+//! rows pointing at missing keys; `verify_and_prune` checks indexed frames
+//! against S3 and tombstones those rows the same way, as `archived`.
+
+use crate::config::RetentionConfig;
+use crate::metadata_store::MetadataStore;
+use crate::s3_uploader::FrameObjectStore;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Metadata operations needed by the retention task
+#[async_trait]
+pub trait RetentionIndex: Send + Sync {
+    /// Unarchived frames older than `before`, oldest first
+    async fn frames_to_archive(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>>;
+
+    /// Unarchived frames with an id greater than `after`, in id order
+    async fn frames_to_verify(
+        &self,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>>;
+
+    /// Mark a frame's S3 object as removed
+    async fn mark_archived(&self, frame_id: Uuid) -> Result<()>;
+
+    /// Delete metadata rows older than `before`
+    async fn delete_frames_before(&self, before: DateTime<Utc>) -> Result<i64>;
+}
+
+#[async_trait]
+impl RetentionIndex for MetadataStore {
+    async fn frames_to_archive(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>> {
+        MetadataStore::frames_to_archive(self, before, limit).await
+    }
+
+    async fn frames_to_verify(
+        &self,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>> {
+        MetadataStore::frames_to_verify(self, after, limit).await
+    }
+
+    async fn mark_archived(&self, frame_id: Uuid) -> Result<()> {
+        MetadataStore::mark_archived(self, frame_id).await
+    }
+
+    async fn delete_frames_before(&self, before: DateTime<Utc>) -> Result<i64> {
+        MetadataStore::delete_frames_before(self, before).await
+    }
+}
+
+/// Result of a single retention pass
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// Frames whose S3 object was deleted
+    pub archived: u64,
+    /// Frames whose S3 deletion failed
+    pub failed: u64,
+    /// Metadata rows deleted
+    pub purged: i64,
+}
+
+/// Result of a single object verification batch
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Frames whose object was checked
+    pub checked: u64,
+    /// Frames marked archived because their object is missing
+    pub pruned: u64,
+    /// Frames whose existence check failed
+    pub failed: u64,
+}
+
+/// Applies the configured S3 and metadata retention policies
+pub struct RetentionManager {
+    config: RetentionConfig,
+    objects: Arc<dyn FrameObjectStore>,
+    index: Arc<dyn RetentionIndex>,
+    /// Last frame id checked by `verify_and_prune`
+    verify_cursor: Mutex<Option<Uuid>>,
+}
+
+impl RetentionManager {
+    /// Create a new retention manager
+    pub fn new(
+        config: RetentionConfig,
+        objects: Arc<dyn FrameObjectStore>,
+        index: Arc<dyn RetentionIndex>,
+    ) -> Self {
+        if let (Some(s3_days), Some(metadata_days)) =
+            (config.s3_retention_days, config.metadata_retention_days)
+        {
+            if metadata_days < s3_days {
+                warn!(
+                    s3_retention_days = s3_days,
+                    metadata_retention_days = metadata_days,
+                    "Metadata retention is shorter than S3 retention; purged rows will leave orphaned S3 objects"
+                );
+            }
+        }
+
+        Self {
+            config,
+            objects,
+            index,
+            verify_cursor: Mutex::new(None),
+        }
+    }
+
+    /// Run retention passes until the task is aborted
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match self.run_once(Utc::now()).await {
+                Ok(report) => info!(
+                    archived = report.archived,
+                    failed = report.failed,
+                    purged = report.purged,
+                    "Retention pass complete"
+                ),
+                Err(e) => error!(error = %e, "Retention pass failed"),
+            }
+
+            if self.config.verify_objects {
+                match self.verify_and_prune(self.config.batch_size).await {
+                    Ok(report) => info!(
+                        checked = report.checked,
+                        pruned = report.pruned,
+                        failed = report.failed,
+                        "Object verification batch complete"
+                    ),
+                    Err(e) => error!(error = %e, "Object verification failed"),
+                }
+            }
+        }
+    }
+
+    /// Apply both retention policies relative to `now`
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<RetentionReport> {
+        let mut report = RetentionReport::default();
+
+        if let Some(days) = self.config.s3_retention_days {
+            let cutoff = now - ChronoDuration::days(days as i64);
+            let (archived, failed) = self.archive_frames_before(cutoff).await?;
+            report.archived = archived;
+            report.failed = failed;
+        }
+
+        if let Some(days) = self.config.metadata_retention_days {
+            let cutoff = now - ChronoDuration::days(days as i64);
+            report.purged = self.purge_metadata_before(cutoff).await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Delete S3 objects for frames older than `cutoff`, keeping their metadata.
+    ///
+    /// Returns the number of frames archived and the number that failed.
+    pub async fn archive_frames_before(&self, cutoff: DateTime<Utc>) -> Result<(u64, u64)> {
+        let mut archived = 0u64;
+        // Failed frames stay unarchived and come back in later batches; they
+        // are attempted and counted once per pass
+        let mut failed = HashSet::new();
+
+        loop {
+            let limit = self.config.batch_size + failed.len() as i64;
+            let batch = self.index.frames_to_archive(cutoff, limit).await?;
+            let exhausted = (batch.len() as i64) < limit;
+
+            let mut progressed = false;
+            for (frame_id, s3_key) in batch {
+                if failed.contains(&frame_id) {
+                    continue;
+                }
+                if let Err(e) = self.objects.delete_frame(&s3_key).await {
+                    warn!(frame_id = %frame_id, s3_key = %s3_key, error = %e, "Failed to delete archived frame object");
+                    metrics::counter!("storage.retention.archive_failed").increment(1);
+                    failed.insert(frame_id);
+                    progressed = true;
+                    continue;
+                }
+
+                self.index.mark_archived(frame_id).await?;
+                metrics::counter!("storage.retention.archived").increment(1);
+                archived += 1;
+                progressed = true;
+            }
+
+            // Only already-failed frames remain; stop rather than re-fetch them
+            if !progressed || exhausted {
+                break;
+            }
+        }
+
+        Ok((archived, failed.len() as u64))
+    }
+
+    /// Check up to `batch` indexed frames against S3 and mark rows whose
+    /// object no longer exists as archived, so presigning stops handing out
+    /// URLs that 404.
+    ///
+    /// Successive calls walk the table in id order, wrapping around at the
+    /// end. Existence checks are paced to `verify_rate_per_sec`.
+    pub async fn verify_and_prune(&self, batch: i64) -> Result<PruneReport> {
+        let after = *self.verify_cursor.lock().unwrap();
+        let rows = self.index.frames_to_verify(after, batch).await?;
+
+        let next = if (rows.len() as i64) < batch {
+            None
+        } else {
+            rows.last().map(|(id, _)| *id)
+        };
+        *self.verify_cursor.lock().unwrap() = next;
+
+        let mut pacer = match self.config.verify_rate_per_sec {
+            0 => None,
+            rate => Some(tokio::time::interval(Duration::from_secs_f64(
+                1.0 / rate as f64,
+            ))),
+        };
+
+        let mut report = PruneReport::default();
+        for (frame_id, s3_key) in rows {
+            if let Some(pacer) = pacer.as_mut() {
+                pacer.tick().await;
+            }
+
+            report.checked += 1;
+            match self.objects.frame_exists(&s3_key).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!(frame_id = %frame_id, s3_key = %s3_key, "Frame object missing, marking archived");
+                    self.index.mark_archived(frame_id).await?;
+                    metrics::counter!("storage.retention.pruned").increment(1);
+                    report.pruned += 1;
+                }
+                Err(e) => {
+                    warn!(frame_id = %frame_id, s3_key = %s3_key, error = %e, "Failed to check frame object");
+                    metrics::counter!("storage.retention.verify_failed").increment(1);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Delete metadata rows older than `cutoff`
+    pub async fn purge_metadata_before(&self, cutoff: DateTime<Utc>) -> Result<i64> {
+        let purged = self.index.delete_frames_before(cutoff).await?;
+        metrics::counter!("storage.retention.purged").increment(purged as u64);
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3_uploader::testing::InMemoryObjectStore;
+    use std::sync::Mutex;
+
+    struct FakeRow {
+        id: Uuid,
+        s3_key: String,
+        timestamp: DateTime<Utc>,
+        archived: bool,
+    }
+
+    #[derive(Default)]
+    struct FakeIndex {
+        rows: Mutex<Vec<FakeRow>>,
+    }
+
+    #[async_trait]
+    impl RetentionIndex for FakeIndex {
+        async fn frames_to_archive(
+            &self,
+            before: DateTime<Utc>,
+            limit: i64,
+        ) -> Result<Vec<(Uuid, String)>> {
+            Ok(self
+                .rows
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| r.timestamp < before && !r.archived)
+                .take(limit as usize)
+                .map(|r| (r.id, r.s3_key.clone()))
+                .collect())
+        }
+
+        async fn frames_to_verify(
+            &self,
+            after: Option<Uuid>,
+            limit: i64,
+        ) -> Result<Vec<(Uuid, String)>> {
+            let mut rows: Vec<(Uuid, String)> = self
+                .rows
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| !r.archived && after.map_or(true, |a| r.id > a))
+                .map(|r| (r.id, r.s3_key.clone()))
+                .collect();
+            rows.sort();
+            rows.truncate(limit as usize);
+            Ok(rows)
+        }
+
+        async fn mark_archived(&self, frame_id: Uuid) -> Result<()> {
+            for row in self.rows.lock().unwrap().iter_mut() {
+                if row.id == frame_id {
+                    row.archived = true;
+                }
+            }
+            Ok(())
+        }
+
+        async fn delete_frames_before(&self, before: DateTime<Utc>) -> Result<i64> {
+            let mut rows = self.rows.lock().unwrap();
+            let len = rows.len();
+            rows.retain(|r| r.timestamp >= before);
+            Ok((len - rows.len()) as i64)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_keeps_metadata_and_removes_object() {
+        let now = Utc::now();
+        let objects = Arc::new(InMemoryObjectStore::default());
+        let index = Arc::new(FakeIndex::default());
+
+        let old_id = Uuid::new_v4();
+        let recent_id = Uuid::new_v4();
+        for (id, key, age_days) in [
+            (old_id, "frames/old.jpg", 40),
+            (recent_id, "frames/new.jpg", 1),
+        ] {
+            objects.insert(key, vec![0u8; 4]);
+            index.rows.lock().unwrap().push(FakeRow {
+                id,
+                s3_key: key.to_string(),
+                timestamp: now - ChronoDuration::days(age_days),
+                archived: false,
+            });
+        }
+
+        let config = RetentionConfig {
+            enabled: true,
+            s3_retention_days: Some(30),
+            metadata_retention_days: Some(365),
+            batch_size: 1,
+            ..Default::default()
+        };
+        let manager = RetentionManager::new(config, objects.clone(), index.clone());
+
+        let report = manager.run_once(now).await.unwrap();
+
+        assert_eq!(report.archived, 1);
+        assert_eq!(report.purged, 0);
+        assert!(!objects.contains("frames/old.jpg"));
+        assert!(objects.contains("frames/new.jpg"));
+
+        let rows = index.rows.lock().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().find(|r| r.id == old_id).unwrap().archived);
+        assert!(!rows.iter().find(|r| r.id == recent_id).unwrap().archived);
+    }
+
+    #[tokio::test]
+    async fn test_verify_and_prune_marks_missing_objects() {
+        let now = Utc::now();
+        let objects = Arc::new(InMemoryObjectStore::default());
+        let index = Arc::new(FakeIndex::default());
+
+        let present_id = Uuid::new_v4();
+        let missing_id = Uuid::new_v4();
+        for (id, key) in [
+            (present_id, "frames/present.jpg"),
+            (missing_id, "frames/missing.jpg"),
+        ] {
+            index.rows.lock().unwrap().push(FakeRow {
+                id,
+                s3_key: key.to_string(),
+                timestamp: now,
+                archived: false,
+            });
+        }
+        // Only one object survives; the other was removed out-of-band
+        objects.insert("frames/present.jpg", vec![0u8; 4]);
+
+        let config = RetentionConfig {
+            verify_objects: true,
+            verify_rate_per_sec: 1000,
+            ..Default::default()
+        };
+        let manager = RetentionManager::new(config, objects.clone(), index.clone());
+
+        let report = manager.verify_and_prune(10).await.unwrap();
+
+        assert_eq!(
+            report,
+            PruneReport {
+                checked: 2,
+                pruned: 1,
+                failed: 0,
+            }
+        );
+        {
+            let rows = index.rows.lock().unwrap();
+            assert!(rows.iter().find(|r| r.id == missing_id).unwrap().archived);
+            assert!(!rows.iter().find(|r| r.id == present_id).unwrap().archived);
+        }
+
+        // Pruned rows are not checked again
+        let report = manager.verify_and_prune(10).await.unwrap();
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.pruned, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_archive_counted_once() {
+        let now = Utc::now();
+        let objects = Arc::new(InMemoryObjectStore::default());
+        let index = Arc::new(FakeIndex::default());
+
+        for (key, age_days) in [
+            ("frames/stuck.jpg", 42),
+            ("frames/a.jpg", 41),
+            ("frames/b.jpg", 40),
+        ] {
+            objects.insert(key, vec![0u8; 4]);
+            index.rows.lock().unwrap().push(FakeRow {
+                id: Uuid::new_v4(),
+                s3_key: key.to_string(),
+                timestamp: now - ChronoDuration::days(age_days),
+                archived: false,
+            });
+        }
+        objects.fail_deletes_for("frames/stuck.jpg");
+
+        let config = RetentionConfig {
+            enabled: true,
+            s3_retention_days: Some(30),
+            batch_size: 1,
+            ..Default::default()
+        };
+        let manager = RetentionManager::new(config, objects.clone(), index.clone());
+
+        let report = manager.run_once(now).await.unwrap();
+
+        assert_eq!(report.archived, 2);
+        assert_eq!(report.failed, 1);
+        assert!(objects.contains("frames/stuck.jpg"));
+        assert!(!objects.contains("frames/a.jpg"));
+        assert!(!objects.contains("frames/b.jpg"));
+    }
+}
@@ -1,26 +1,50 @@
-use crate::config::S3Config;
+use crate::config::{KeyTimestampPrecision, OutputFormat, S3Config};
 use crate::kafka_consumer::{StorageTriggerEvent, TriggerType};
-use anyhow::{Context, Result};
+use crate::shifts::ShiftSchedule;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
 use aws_config::BehaviorVersion;
+use aws_sdk_s3::config::http::HttpResponse;
 use aws_sdk_s3::config::Builder as S3ConfigBuilder;
+use aws_sdk_s3::config::SharedHttpClient;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError;
+use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::operation::list_object_versions::ListObjectVersionsOutput;
+use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_sdk_s3::operation::upload_part::UploadPartError;
 use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedPart, Delete, ObjectIdentifier};
 use aws_sdk_s3::Client as S3Client;
-use chrono::{DateTime, Datelike, Utc};
+use aws_smithy_runtime::client::http::hyper_014::HyperClientBuilder;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use image::codecs::avif::AvifEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use nier_retry::{retry_with_backoff, BackoffPolicy};
+use std::future::Future;
+use std::io::Cursor;
 use std::sync::Arc;
-use tracing::{debug, info, instrument};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
 
 /// S3 uploader for frame storage with proper partitioning
 pub struct S3Uploader {
     client: S3Client,
     bucket: String,
     config: S3Config,
+    shifts: Option<ShiftSchedule>,
 }
 
 impl S3Uploader {
     /// Create a new S3 uploader
     pub async fn new(config: &S3Config) -> Result<Self> {
+        let pool_size = connection_pool_size(config);
         let aws_config = aws_config::defaults(BehaviorVersion::latest())
             .region(aws_config::Region::new(config.region.clone()))
+            .http_client(pooled_http_client(pool_size))
             .load()
             .await;
 
@@ -42,51 +66,111 @@ impl S3Uploader {
         info!(
             bucket = %config.bucket,
             region = %config.region,
+            pool_size = pool_size,
             "S3 uploader initialized"
         );
 
+        Self::from_client(client, config)
+    }
+
+    fn from_client(client: S3Client, config: &S3Config) -> Result<Self> {
+        let shifts = ShiftSchedule::new(&config.partition_timezone, &config.shifts)
+            .context("Invalid shift schedule")?;
+
         Ok(Self {
             client,
             bucket: config.bucket.clone(),
             config: config.clone(),
+            shifts,
+        })
+    }
+
+    /// Apply `storage_max_dimension` and `output_format` to a frame before
+    /// upload
+    ///
+    /// Decoding and re-encoding run on the blocking pool. Events are returned
+    /// unchanged when the frame already fits and no transcoding is configured.
+    /// A frame that can't be decoded or re-encoded is stored as it arrived.
+    pub async fn prepare_for_storage(
+        &self,
+        mut event: StorageTriggerEvent,
+    ) -> Result<StorageTriggerEvent> {
+        let max_dimension = self
+            .config
+            .storage_max_dimension
+            .filter(|&max| event.width.max(event.height) > max);
+        let output_format = self.config.output_format;
+        if max_dimension.is_none() && output_format == OutputFormat::Passthrough {
+            return Ok(event);
+        }
+
+        tokio::task::spawn_blocking(move || {
+            // A failed re-encode leaves the event untouched
+            if let Err(e) = reencode_for_storage(&mut event, max_dimension, output_format) {
+                warn!(
+                    event_id = %event.event_id,
+                    format = %event.format,
+                    error = %e,
+                    "Failed to prepare frame; storing it as received"
+                );
+                metrics::counter!("storage.frames.prepare_failed").increment(1);
+            }
+            event
         })
+        .await
+        .context("Frame preparation task panicked")
     }
 
     /// Generate S3 key with proper partitioning strategy
-    /// Format: frames/{date}/{device_id}/{event_type}/{timestamp}_{event_id}.{format}
+    /// Format: [{key_prefix}/]frames/{date}/[{shift}/]{device_id}/{event_type}/{timestamp}_{frame_number}_{event_id}.{format}
     ///
     /// Partitioning strategy:
-    /// - First level: date (YYYY-MM-DD) for time-based queries and lifecycle policies
+    /// - First level: date (YYYY-MM-DD) for time-based queries and lifecycle policies;
+    ///   UTC, or the `partition_timezone` date when shifts are configured
     /// - Second level: device_id for device-specific queries
     /// - Third level: event_type for filtering by detection, sample, debug, etc.
-    /// - Filename: timestamp + event_id for uniqueness and ordering
+    /// - Filename: timestamp + frame_number for ordering, event_id for uniqueness
     pub fn generate_s3_key(&self, event: &StorageTriggerEvent) -> String {
-        let date = event.timestamp.format("%Y-%m-%d").to_string();
-        let event_type = match event.trigger_type {
-            TriggerType::Detection => "detections",
-            TriggerType::Sample => "samples",
-            TriggerType::Debug => "debug",
-            TriggerType::Manual => "manual",
-            TriggerType::Alert => "alerts",
-        };
+        let partition = self.shifts.as_ref().map(|shifts| {
+            (
+                shifts.local_date(event.timestamp),
+                shifts.shift_for(event.timestamp),
+            )
+        });
+        frame_key(
+            &self.config.key_prefix,
+            partition,
+            self.config.key_timestamp_precision,
+            event,
+        )
+    }
 
-        // Timestamp in sortable format for filename
-        let timestamp_str = event.timestamp.format("%H%M%S%3f").to_string();
+    /// Shift a frame captured at `timestamp` belongs to, if shifts are configured
+    pub fn shift_for(&self, timestamp: DateTime<Utc>) -> Option<&str> {
+        self.shifts.as_ref().map(|s| s.shift_for(timestamp))
+    }
 
-        format!(
-            "frames/{date}/{device_id}/{event_type}/{timestamp}_{event_id}.{format}",
-            date = date,
-            device_id = sanitize_path_component(&event.device_id),
-            event_type = event_type,
-            timestamp = timestamp_str,
-            event_id = event.event_id,
-            format = event.format.to_lowercase()
-        )
+    /// Render the keys `events` would be uploaded under with the current
+    /// configuration, without touching S3
+    ///
+    /// Lets operators check a key layout change (e.g. a new `key_prefix`)
+    /// against real events before rolling it out.
+    pub fn preview_keys(&self, events: &[StorageTriggerEvent]) -> Vec<String> {
+        events.iter().map(|e| self.generate_s3_key(e)).collect()
     }
 
     /// Upload a frame to S3
-    #[instrument(skip(self, event), fields(event_id = %event.event_id, device_id = %event.device_id))]
     pub async fn upload_frame(&self, event: &StorageTriggerEvent) -> Result<String> {
+        Ok(self.put_frame(event).await?.s3_key)
+    }
+
+    /// Upload a frame to S3, reporting whether the object was written
+    ///
+    /// With `conditional_put` enabled, a frame whose key already exists (e.g.
+    /// the same event reprocessed after a consumer restart) is not rewritten
+    /// and comes back as `PutOutcome::AlreadyExists`.
+    #[instrument(skip(self, event), fields(event_id = %event.event_id, device_id = %event.device_id))]
+    pub async fn put_frame(&self, event: &StorageTriggerEvent) -> Result<PutResult> {
         let s3_key = self.generate_s3_key(event);
         let content_type = get_content_type(&event.format);
 
@@ -96,57 +180,106 @@ impl S3Uploader {
             "Uploading frame to S3"
         );
 
-        // Check if we should use multipart upload
-        if event.frame_data.len() > self.config.multipart_threshold_bytes {
-            self.multipart_upload(event, &s3_key, &content_type).await?;
-        } else {
-            self.simple_upload(event, &s3_key, &content_type).await?;
+        // The SDK retries individual requests; this retries the whole upload,
+        // so a multipart upload that failed partway starts over
+        let (outcome, version_id) = retry_with_backoff(
+            || async {
+                if event.frame_data.len() > self.config.multipart_threshold_bytes {
+                    self.multipart_upload(event, &s3_key, &content_type).await
+                } else {
+                    self.simple_upload(event, &s3_key, &content_type).await
+                }
+            },
+            &self.upload_backoff(),
+            is_transient_upload_error,
+        )
+        .await?;
+
+        match outcome {
+            PutOutcome::Created => info!(
+                s3_key = %s3_key,
+                size_bytes = event.frame_data.len(),
+                "Frame uploaded successfully"
+            ),
+            PutOutcome::AlreadyExists => {
+                metrics::counter!("storage.upload.already_exists").increment(1);
+                info!(s3_key = %s3_key, "Frame already stored, object left unchanged");
+            }
         }
 
-        info!(
-            s3_key = %s3_key,
-            size_bytes = event.frame_data.len(),
-            "Frame uploaded successfully"
-        );
+        Ok(PutResult {
+            s3_key,
+            outcome,
+            version_id,
+            size_bytes: event.frame_data.len() as u64,
+        })
+    }
+
+    /// Backoff between attempts of a frame upload
+    fn upload_backoff(&self) -> BackoffPolicy {
+        BackoffPolicy::new(
+            self.config.upload_max_attempts,
+            Duration::from_millis(self.config.upload_retry_base_delay_ms),
+        )
+    }
 
-        Ok(s3_key)
+    /// `If-None-Match` value for puts, when conditional puts are enabled
+    fn if_none_match(&self) -> Option<String> {
+        self.config.conditional_put.then(|| "*".to_string())
     }
 
     /// Simple single-part upload for small files
+    ///
+    /// Returns the outcome and, on versioned buckets, the written version.
     async fn simple_upload(
         &self,
         event: &StorageTriggerEvent,
         s3_key: &str,
         content_type: &str,
-    ) -> Result<()> {
+    ) -> Result<(PutOutcome, Option<String>)> {
         let body = ByteStream::from(event.frame_data.clone());
 
-        self.client
+        let result = self
+            .client
             .put_object()
             .bucket(&self.bucket)
             .key(s3_key)
             .body(body)
             .content_type(content_type)
+            .set_tagging(self.object_tagging(event))
             .metadata("device-id", &event.device_id)
             .metadata("frame-number", &event.frame_number.to_string())
             .metadata("trigger-type", &format!("{:?}", event.trigger_type).to_lowercase())
             .metadata("width", &event.width.to_string())
             .metadata("height", &event.height.to_string())
             .metadata("timestamp", &event.timestamp.to_rfc3339())
+            .set_if_none_match(self.if_none_match())
             .send()
-            .await
-            .context("Failed to upload frame to S3")?;
+            .await;
 
-        Ok(())
+        match result {
+            Ok(output) => Ok((PutOutcome::Created, output.version_id().map(String::from))),
+            Err(e) if is_precondition_failed(e.raw_response().map(|r| r.status().as_u16())) => {
+                Ok((PutOutcome::AlreadyExists, None))
+            }
+            Err(e) => Err(e).context("Failed to upload frame to S3"),
+        }
     }
 
     /// Multipart upload for large files
+    ///
+    /// Returns the outcome and, on versioned buckets, the written version.
     async fn multipart_upload(
         &self,
         event: &StorageTriggerEvent,
         s3_key: &str,
         content_type: &str,
-    ) -> Result<()> {
+    ) -> Result<(PutOutcome, Option<String>)> {
+        // Don't upload every part just to have the completion rejected
+        if self.config.conditional_put && self.frame_exists(s3_key).await? {
+            return Ok((PutOutcome::AlreadyExists, None));
+        }
+
         // Create multipart upload
         let create_response = self
             .client
@@ -154,6 +287,7 @@ impl S3Uploader {
             .bucket(&self.bucket)
             .key(s3_key)
             .content_type(content_type)
+            .set_tagging(self.object_tagging(event))
             .metadata("device-id", &event.device_id)
             .metadata("frame-number", &event.frame_number.to_string())
             .metadata("trigger-type", &format!("{:?}", event.trigger_type).to_lowercase())
@@ -165,70 +299,221 @@ impl S3Uploader {
             .upload_id()
             .context("No upload ID in response")?;
 
-        let mut completed_parts = Vec::new();
-        let part_size = self.config.part_size_bytes;
-        let mut part_number = 1;
+        let started = Instant::now();
 
         // Upload parts
-        for chunk in event.frame_data.chunks(part_size) {
-            let body = ByteStream::from(chunk.to_vec());
+        let upload = upload_parts(
+            &event.frame_data,
+            self.config.part_size_bytes,
+            self.config.multipart_concurrency,
+            |part_number, chunk| async move {
+                let upload_part_response = self
+                    .client
+                    .upload_part()
+                    .bucket(&self.bucket)
+                    .key(s3_key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .body(ByteStream::from(chunk))
+                    .send()
+                    .await
+                    .context("Failed to upload part")?;
+
+                Ok(CompletedPart::builder()
+                    .part_number(part_number)
+                    .e_tag(upload_part_response.e_tag().unwrap_or_default())
+                    .build())
+            },
+            |progress| {
+                info!(
+                    s3_key = %s3_key,
+                    part = progress.part_number,
+                    total_parts = progress.total_parts,
+                    bytes_uploaded = progress.bytes_uploaded,
+                    total_bytes = progress.total_bytes,
+                    "Multipart upload progress"
+                );
+            },
+        );
+        let completed_parts =
+            abort_on_failure(s3_key, upload, || self.abort_multipart(s3_key, upload_id)).await?;
+
+        // Complete multipart upload
+        let completed_upload = aws_sdk_s3::types::CompletedMultipartUpload::builder()
+            .set_parts(Some(completed_parts))
+            .build();
 
-            let upload_part_response = self
+        // The existence check above can race with another writer, so the
+        // completion is conditional too
+        let complete = async {
+            match self
                 .client
-                .upload_part()
+                .complete_multipart_upload()
                 .bucket(&self.bucket)
                 .key(s3_key)
                 .upload_id(upload_id)
-                .part_number(part_number)
-                .body(body)
+                .multipart_upload(completed_upload)
+                .set_if_none_match(self.if_none_match())
                 .send()
                 .await
-                .context("Failed to upload part")?;
+            {
+                Ok(output) => Ok(Some(output)),
+                Err(e) if is_precondition_failed(e.raw_response().map(|r| r.status().as_u16())) => {
+                    Ok(None)
+                }
+                Err(e) => Err(e).context("Failed to complete multipart upload"),
+            }
+        };
+        let completed =
+            abort_on_failure(s3_key, complete, || self.abort_multipart(s3_key, upload_id)).await?;
 
-            let completed_part = aws_sdk_s3::types::CompletedPart::builder()
-                .part_number(part_number)
-                .e_tag(upload_part_response.e_tag().unwrap_or_default())
-                .build();
+        // Another writer won the race; its object stays, our parts go
+        let Some(completed) = completed else {
+            self.abort_multipart(s3_key, upload_id).await?;
+            return Ok((PutOutcome::AlreadyExists, None));
+        };
 
-            completed_parts.push(completed_part);
-            part_number += 1;
+        let elapsed = started.elapsed().as_secs_f64();
+        metrics::histogram!("storage.upload.multipart.duration_seconds").record(elapsed);
+        if elapsed > 0.0 {
+            metrics::histogram!("storage.upload.multipart.throughput_bytes_per_sec")
+                .record(event.frame_data.len() as f64 / elapsed);
         }
 
-        // Complete multipart upload
-        let completed_upload = aws_sdk_s3::types::CompletedMultipartUpload::builder()
-            .set_parts(Some(completed_parts))
-            .build();
+        Ok((PutOutcome::Created, completed.version_id().map(String::from)))
+    }
 
+    /// Abort a multipart upload, discarding the parts uploaded so far
+    async fn abort_multipart(&self, s3_key: &str, upload_id: &str) -> Result<()> {
         self.client
-            .complete_multipart_upload()
+            .abort_multipart_upload()
             .bucket(&self.bucket)
             .key(s3_key)
             .upload_id(upload_id)
-            .multipart_upload(completed_upload)
             .send()
             .await
-            .context("Failed to complete multipart upload")?;
-
+            .context("Failed to abort multipart upload")?;
         Ok(())
     }
 
-    /// Delete a frame from S3
+    /// Build the URL-encoded tag set for an event's configured metadata keys
+    fn object_tagging(&self, event: &StorageTriggerEvent) -> Option<String> {
+        let tags = select_object_tags(&event.metadata, &self.config.tag_metadata_keys);
+        if tags.is_empty() {
+            None
+        } else {
+            Some(encode_tagging(&tags))
+        }
+    }
+
+    /// Delete a frame from S3, including every stored version
+    ///
+    /// On a versioned bucket a plain delete only adds a delete marker and
+    /// earlier versions stay readable, so with `versioned_bucket` set each
+    /// version and delete marker under the key is removed by version id.
     #[instrument(skip(self), fields(s3_key = %s3_key))]
     pub async fn delete_frame(&self, s3_key: &str) -> Result<()> {
-        self.client
-            .delete_object()
+        if !self.config.versioned_bucket {
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(s3_key)
+                .send()
+                .await
+                .context("Failed to delete frame from S3")?;
+
+            debug!(s3_key = %s3_key, "Frame deleted from S3");
+            return Ok(());
+        }
+
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+        let mut deleted = 0;
+
+        loop {
+            let page = self
+                .client
+                .list_object_versions()
+                .bucket(&self.bucket)
+                .prefix(s3_key)
+                .set_key_marker(key_marker.take())
+                .set_version_id_marker(version_id_marker.take())
+                .send()
+                .await
+                .context("Failed to list frame versions in S3")?;
+
+            let versions = key_versions(s3_key, &page)?;
+            if !versions.is_empty() {
+                deleted += versions.len();
+                self.delete_versions(versions).await?;
+            }
+
+            if !page.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = page.next_key_marker().map(String::from);
+            version_id_marker = page.next_version_id_marker().map(String::from);
+        }
+
+        debug!(s3_key = %s3_key, versions = deleted, "Frame deleted from S3");
+        Ok(())
+    }
+
+    /// Delete specific object versions in one request
+    async fn delete_versions(&self, versions: Vec<ObjectIdentifier>) -> Result<()> {
+        let delete = Delete::builder()
+            .set_objects(Some(versions))
+            .quiet(true)
+            .build()
+            .context("Failed to build frame version delete")?;
+
+        let output = self
+            .client
+            .delete_objects()
             .bucket(&self.bucket)
-            .key(s3_key)
+            .delete(delete)
             .send()
             .await
-            .context("Failed to delete frame from S3")?;
+            .context("Failed to delete frame versions from S3")?;
 
-        debug!(s3_key = %s3_key, "Frame deleted from S3");
+        if let Some(failure) = output.errors().first() {
+            bail!(
+                "Failed to delete version {} of {}: {}",
+                failure.version_id().unwrap_or("null"),
+                failure.key().unwrap_or_default(),
+                failure.message().unwrap_or("unknown error")
+            );
+        }
         Ok(())
     }
 
+    /// Download a frame's object
+    #[instrument(skip(self), fields(s3_key = %s3_key))]
+    pub async fn get_frame(&self, s3_key: &str) -> Result<Vec<u8>> {
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(s3_key)
+            .send()
+            .await
+            .context("Failed to get frame from S3")?;
+
+        let body = response
+            .body
+            .collect()
+            .await
+            .context("Failed to read frame from S3")?;
+        Ok(body.into_bytes().to_vec())
+    }
+
     /// Check if a frame exists in S3
     pub async fn frame_exists(&self, s3_key: &str) -> Result<bool> {
+        Ok(self.frame_size(s3_key).await?.is_some())
+    }
+
+    /// Size in bytes of the object under `s3_key`, or `None` if there is none
+    pub async fn frame_size(&self, s3_key: &str) -> Result<Option<u64>> {
         match self
             .client
             .head_object()
@@ -237,13 +522,13 @@ impl S3Uploader {
             .send()
             .await
         {
-            Ok(_) => Ok(true),
+            Ok(head) => Ok(Some(head.content_length().unwrap_or(0).max(0) as u64)),
             Err(e) => {
                 if e.as_service_error()
                     .map(|e| e.is_not_found())
                     .unwrap_or(false)
                 {
-                    Ok(false)
+                    Ok(None)
                 } else {
                     Err(e).context("Failed to check frame existence")
                 }
@@ -252,22 +537,23 @@ impl S3Uploader {
     }
 
     /// List frames for a specific date and device
+    ///
+    /// With shifts configured, `date` is in the partition timezone and devices
+    /// are partitioned under each shift, so narrowing to a device requires the
+    /// shift too.
     #[instrument(skip(self))]
     pub async fn list_frames(
         &self,
         date: &str,
+        shift: Option<&str>,
         device_id: Option<&str>,
         event_type: Option<&str>,
         max_keys: i32,
     ) -> Result<Vec<String>> {
-        let mut prefix = format!("frames/{}", date);
-
-        if let Some(device) = device_id {
-            prefix = format!("{}/{}", prefix, sanitize_path_component(device));
-            if let Some(etype) = event_type {
-                prefix = format!("{}/{}", prefix, etype);
-            }
+        if self.shifts.is_some() && shift.is_none() && device_id.is_some() {
+            bail!("Listing by device requires a shift when shift partitioning is enabled");
         }
+        let prefix = list_prefix(&self.config.key_prefix, date, shift, device_id, event_type);
 
         let response = self
             .client
@@ -299,6 +585,428 @@ impl S3Uploader {
     }
 }
 
+/// Object-level operations on stored frames
+///
+/// Implemented by [`S3Uploader`]; maintenance tasks depend on this trait so
+/// they can run against an in-memory store in tests.
+#[async_trait]
+pub trait FrameObjectStore: Send + Sync {
+    /// Upload a frame, returning its object key and whether it was written
+    async fn put_frame(&self, event: &StorageTriggerEvent) -> Result<PutResult>;
+
+    /// Upload a frame, returning its object key
+    async fn upload_frame(&self, event: &StorageTriggerEvent) -> Result<String> {
+        Ok(self.put_frame(event).await?.s3_key)
+    }
+
+    /// Delete the object stored under `s3_key`, with all of its versions
+    async fn delete_frame(&self, s3_key: &str) -> Result<()>;
+
+    /// Read the object stored under `s3_key`
+    async fn get_frame(&self, s3_key: &str) -> Result<Vec<u8>>;
+
+    /// Check whether an object exists under `s3_key`
+    async fn frame_exists(&self, s3_key: &str) -> Result<bool>;
+
+    /// Size in bytes of the object under `s3_key`, or `None` if there is none
+    async fn frame_size(&self, s3_key: &str) -> Result<Option<u64>>;
+}
+
+#[async_trait]
+impl FrameObjectStore for S3Uploader {
+    async fn put_frame(&self, event: &StorageTriggerEvent) -> Result<PutResult> {
+        S3Uploader::put_frame(self, event).await
+    }
+
+    async fn delete_frame(&self, s3_key: &str) -> Result<()> {
+        S3Uploader::delete_frame(self, s3_key).await
+    }
+
+    async fn get_frame(&self, s3_key: &str) -> Result<Vec<u8>> {
+        S3Uploader::get_frame(self, s3_key).await
+    }
+
+    async fn frame_exists(&self, s3_key: &str) -> Result<bool> {
+        S3Uploader::frame_exists(self, s3_key).await
+    }
+
+    async fn frame_size(&self, s3_key: &str) -> Result<Option<u64>> {
+        S3Uploader::frame_size(self, s3_key).await
+    }
+}
+
+/// Versions and delete markers stored under exactly `s3_key`
+///
+/// Listing by prefix also returns longer keys that start with `s3_key`;
+/// those are left alone.
+fn key_versions(s3_key: &str, page: &ListObjectVersionsOutput) -> Result<Vec<ObjectIdentifier>> {
+    let versions = page
+        .versions()
+        .iter()
+        .map(|v| (v.key(), v.version_id()));
+    let markers = page
+        .delete_markers()
+        .iter()
+        .map(|m| (m.key(), m.version_id()));
+
+    versions
+        .chain(markers)
+        .filter(|(key, _)| *key == Some(s3_key))
+        .map(|(_, version_id)| {
+            ObjectIdentifier::builder()
+                .key(s3_key)
+                .set_version_id(version_id.map(String::from))
+                .build()
+                .context("Failed to build frame version identifier")
+        })
+        .collect()
+}
+
+/// Idle connections to keep pooled per S3 host
+///
+/// Defaults to `upload_concurrency` so every concurrent upload can reuse a
+/// warm connection instead of opening a new one.
+fn connection_pool_size(config: &S3Config) -> usize {
+    config
+        .max_idle_connections
+        .unwrap_or(config.upload_concurrency)
+        .max(1)
+}
+
+/// hyper client settings keeping up to `pool_size` idle connections per host
+fn pooled_hyper_builder(pool_size: usize) -> hyper::client::Builder {
+    let mut hyper_builder = hyper::Client::builder();
+    hyper_builder.pool_max_idle_per_host(pool_size);
+    hyper_builder
+}
+
+/// HTTPS client for the S3 SDK with a sized idle connection pool
+fn pooled_http_client(pool_size: usize) -> SharedHttpClient {
+    HyperClientBuilder::new()
+        .hyper_builder(pooled_hyper_builder(pool_size))
+        .build_https()
+}
+
+/// Result of a put when conditional puts may skip existing objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PutOutcome {
+    /// The object was written
+    Created,
+    /// An object already existed under the key and was left unchanged
+    AlreadyExists,
+}
+
+/// Where a put left a frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PutResult {
+    pub s3_key: String,
+    pub outcome: PutOutcome,
+    /// Version written by this put, on versioned buckets. `None` when the
+    /// bucket is unversioned or the object already existed.
+    pub version_id: Option<String>,
+    /// Size of the stored object
+    pub size_bytes: u64,
+}
+
+/// Whether an S3 error status is a failed `If-None-Match` precondition
+fn is_precondition_failed(status: Option<u16>) -> bool {
+    status == Some(412)
+}
+
+/// Whether a failed upload is worth retrying: a timeout, a request that
+/// never reached S3, a 5xx or a throttling response
+///
+/// Errors such as access denied or a missing bucket fail the same way on
+/// every attempt, so they are returned at once.
+fn is_transient_upload_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        is_transient::<PutObjectError>(cause)
+            || is_transient::<CreateMultipartUploadError>(cause)
+            || is_transient::<UploadPartError>(cause)
+            || is_transient::<CompleteMultipartUploadError>(cause)
+            || is_transient::<HeadObjectError>(cause)
+    })
+}
+
+/// Whether `cause` is a transient failure of an S3 operation with error `E`
+fn is_transient<E>(cause: &(dyn std::error::Error + 'static)) -> bool
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    match cause.downcast_ref::<SdkError<E, HttpResponse>>() {
+        Some(SdkError::TimeoutError(_) | SdkError::DispatchFailure(_)) => true,
+        Some(SdkError::ServiceError(service)) => {
+            is_transient_status(service.raw().status().as_u16())
+                || matches!(
+                    service.err().code(),
+                    Some("SlowDown" | "RequestTimeout" | "Throttling" | "ThrottlingException")
+                )
+        }
+        _ => false,
+    }
+}
+
+/// 5xx, or 429 Too Many Requests
+fn is_transient_status(status: u16) -> bool {
+    status >= 500 || status == 429
+}
+
+/// Progress of a multipart upload, reported after each completed part
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartProgress {
+    /// Part that just completed; parts may complete out of order
+    pub part_number: i32,
+    pub total_parts: usize,
+    pub bytes_uploaded: usize,
+    pub total_bytes: usize,
+}
+
+/// Upload `data` in `part_size` chunks through `upload_part`, with up to
+/// `concurrency` parts in flight, emitting `storage.upload.part_completed` and
+/// calling `on_progress` after each part
+///
+/// Completed parts are returned in part number order. The first failed part
+/// fails the whole upload and cancels the parts still in flight.
+async fn upload_parts<F, Fut, P>(
+    data: &[u8],
+    part_size: usize,
+    concurrency: usize,
+    mut upload_part: F,
+    mut on_progress: P,
+) -> Result<Vec<CompletedPart>>
+where
+    F: FnMut(i32, Vec<u8>) -> Fut,
+    Fut: Future<Output = Result<CompletedPart>>,
+    P: FnMut(&PartProgress),
+{
+    use futures::stream::{self, StreamExt, TryStreamExt};
+
+    let total_parts = data.len().div_ceil(part_size);
+    let mut completed_parts = Vec::with_capacity(total_parts);
+    let mut bytes_uploaded = 0;
+
+    let mut uploads = stream::iter(data.chunks(part_size).enumerate())
+        .map(|(index, chunk)| {
+            let part_number = index as i32 + 1;
+            let len = chunk.len();
+            let upload = upload_part(part_number, chunk.to_vec());
+            async move { upload.await.map(|part| (part_number, len, part)) }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    while let Some((part_number, len, part)) = uploads.try_next().await? {
+        completed_parts.push(part);
+        bytes_uploaded += len;
+
+        metrics::counter!("storage.upload.part_completed").increment(1);
+        on_progress(&PartProgress {
+            part_number,
+            total_parts,
+            bytes_uploaded,
+            total_bytes: data.len(),
+        });
+    }
+
+    // S3 rejects completion unless parts are listed in ascending order
+    completed_parts.sort_by_key(|part| part.part_number());
+    Ok(completed_parts)
+}
+
+/// Await a step of a multipart upload, calling `abort` if it fails so the
+/// parts already uploaded don't linger and incur storage charges
+///
+/// The step's error is returned; a failed abort is only logged.
+async fn abort_on_failure<T, Fut, A, AbortFut>(s3_key: &str, step: Fut, abort: A) -> Result<T>
+where
+    Fut: Future<Output = Result<T>>,
+    A: FnOnce() -> AbortFut,
+    AbortFut: Future<Output = Result<()>>,
+{
+    let result = step.await;
+    if result.is_err() {
+        if let Err(abort_err) = abort().await {
+            warn!(s3_key = %s3_key, error = ?abort_err, "Failed to clean up multipart upload");
+        }
+    }
+    result
+}
+
+/// Speed and quality of AVIF re-encoding; the encoder's default speed is
+/// too slow to keep up with frame uploads
+const AVIF_SPEED: u8 = 8;
+const AVIF_QUALITY: u8 = 75;
+
+/// Shrink an event's frame so its longest side is at most `max_dimension`
+/// and re-encode it as `output_format`
+///
+/// The frame is decoded, resized and encoded at most once each, preserving
+/// aspect ratio. Updates the event's width, height, frame data and format, so
+/// the object key extension and content type follow the new encoding.
+/// Returns false, leaving the event untouched, if the frame already fits and
+/// is in the target encoding, or if its encoding isn't recognized (such as
+/// raw `rgb24` frames).
+pub fn reencode_for_storage(
+    event: &mut StorageTriggerEvent,
+    max_dimension: Option<u32>,
+    output_format: OutputFormat,
+) -> Result<bool> {
+    let Ok(source) = image::guess_format(&event.frame_data) else {
+        return Ok(false);
+    };
+    let max_dimension = max_dimension.filter(|&max| event.width.max(event.height) > max);
+    let target = match output_format {
+        OutputFormat::Passthrough => None,
+        OutputFormat::Webp => Some((ImageFormat::WebP, "webp")),
+        OutputFormat::Avif => Some((ImageFormat::Avif, "avif")),
+        OutputFormat::Jpeg => Some((ImageFormat::Jpeg, "jpeg")),
+    }
+    .filter(|&(format, _)| format != source);
+    if max_dimension.is_none() && target.is_none() {
+        return Ok(false);
+    }
+
+    let frame = image::load_from_memory_with_format(&event.frame_data, source)
+        .context("Failed to decode frame")?;
+    let resized = max_dimension
+        .filter(|&max| frame.width().max(frame.height()) > max)
+        .map(|max| frame.resize(max, max, FilterType::Triangle));
+    if resized.is_none() && target.is_none() {
+        return Ok(false);
+    }
+
+    let stored = resized.as_ref().unwrap_or(&frame);
+    let encoded = encode_frame(stored, target.map_or(source, |(format, _)| format))?;
+
+    debug!(
+        event_id = %event.event_id,
+        original_width = frame.width(),
+        original_height = frame.height(),
+        width = stored.width(),
+        height = stored.height(),
+        from = %event.format,
+        to = target.map_or(event.format.as_str(), |(_, extension)| extension),
+        original_bytes = event.frame_data.len(),
+        bytes = encoded.len(),
+        "Re-encoded frame for storage"
+    );
+    if resized.is_some() {
+        metrics::counter!("storage.frames.downscaled").increment(1);
+    }
+    if target.is_some() {
+        metrics::counter!("storage.frames.transcoded").increment(1);
+    }
+
+    event.width = stored.width();
+    event.height = stored.height();
+    event.frame_data = encoded;
+    if let Some((_, extension)) = target {
+        event.format = extension.to_string();
+    }
+    Ok(true)
+}
+
+/// Encode a frame as `format`
+fn encode_frame(frame: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut encoded = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Avif => frame.write_with_encoder(AvifEncoder::new_with_speed_quality(
+            &mut encoded,
+            AVIF_SPEED,
+            AVIF_QUALITY,
+        )),
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => {
+            DynamicImage::ImageRgb8(frame.to_rgb8()).write_to(&mut encoded, format)
+        }
+        _ => frame.write_to(&mut encoded, format),
+    }
+    .context("Failed to encode frame")?;
+    Ok(encoded.into_inner())
+}
+
+/// Object key for an event under the optional `key_prefix` namespace
+///
+/// Filenames start with the capture time and zero-padded frame number, so a
+/// lexical listing of a device's frames follows capture order even within
+/// one timestamp tick; the event ID keeps keys unique.
+fn frame_key(
+    key_prefix: &str,
+    shift_partition: Option<(NaiveDate, &str)>,
+    precision: KeyTimestampPrecision,
+    event: &StorageTriggerEvent,
+) -> String {
+    // Shift partitions use the local date the shift is evaluated in
+    let date = match shift_partition {
+        Some((local_date, shift)) => format!(
+            "{}/{}",
+            local_date.format("%Y-%m-%d"),
+            sanitize_path_component(shift)
+        ),
+        None => event.timestamp.format("%Y-%m-%d").to_string(),
+    };
+    let event_type = match event.trigger_type {
+        TriggerType::Detection => "detections",
+        TriggerType::Sample => "samples",
+        TriggerType::Debug => "debug",
+        TriggerType::Manual => "manual",
+        TriggerType::Alert => "alerts",
+    };
+
+    // Timestamp in sortable format for filename
+    let timestamp_str = event.timestamp.format(precision.time_format()).to_string();
+    let filename = format!(
+        "{timestamp}_{frame_number:010}_{event_id}.{format}",
+        timestamp = timestamp_str,
+        frame_number = event.frame_number,
+        event_id = event.event_id,
+        format = event.format.to_lowercase()
+    );
+
+    namespaced(
+        key_prefix,
+        format!(
+            "frames/{date}/{device_id}/{event_type}/{filename}",
+            date = date,
+            device_id = sanitize_path_component(&event.device_id),
+            event_type = event_type,
+            filename = filename
+        ),
+    )
+}
+
+/// Listing prefix for a date, optionally narrowed to a shift, device and event type
+fn list_prefix(
+    key_prefix: &str,
+    date: &str,
+    shift: Option<&str>,
+    device_id: Option<&str>,
+    event_type: Option<&str>,
+) -> String {
+    let mut prefix = format!("frames/{}", date);
+    if let Some(shift) = shift {
+        prefix = format!("{}/{}", prefix, sanitize_path_component(shift));
+    }
+
+    if let Some(device) = device_id {
+        prefix = format!("{}/{}", prefix, sanitize_path_component(device));
+        if let Some(etype) = event_type {
+            prefix = format!("{}/{}", prefix, etype);
+        }
+    }
+
+    namespaced(key_prefix, prefix)
+}
+
+/// Prepend the configured key prefix, ignoring surrounding slashes
+fn namespaced(key_prefix: &str, path: String) -> String {
+    let key_prefix = key_prefix.trim_matches('/');
+    if key_prefix.is_empty() {
+        path
+    } else {
+        format!("{}/{}", key_prefix, path)
+    }
+}
+
 /// Sanitize a path component to prevent path traversal
 fn sanitize_path_component(component: &str) -> String {
     component
@@ -310,26 +1018,96 @@ fn sanitize_path_component(component: &str) -> String {
         .collect()
 }
 
+/// Maximum number of tags S3 allows on an object
+const MAX_OBJECT_TAGS: usize = 10;
+/// Maximum S3 tag key length
+const MAX_TAG_KEY_LEN: usize = 128;
+/// Maximum S3 tag value length
+const MAX_TAG_VALUE_LEN: usize = 256;
+
+/// Pick configured keys from event metadata as S3 object tags
+///
+/// Only scalar values are used. Characters S3 does not allow in tags are
+/// replaced with `_`, values are truncated to the tag length limits, and at
+/// most 10 tags are returned.
+fn select_object_tags(metadata: &serde_json::Value, keys: &[String]) -> Vec<(String, String)> {
+    keys.iter()
+        .filter(|key| key.len() <= MAX_TAG_KEY_LEN)
+        .filter_map(|key| {
+            let value = match metadata.get(key)? {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            let value: String = sanitize_tag(&value).chars().take(MAX_TAG_VALUE_LEN).collect();
+            Some((sanitize_tag(key), value))
+        })
+        .take(MAX_OBJECT_TAGS)
+        .collect()
+}
+
+/// Replace characters not allowed in S3 tags
+fn sanitize_tag(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c,
+            ' ' | '+' | '-' | '=' | '.' | '_' | ':' | '/' | '@' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Encode tags as the URL query string expected by the `x-amz-tagging` header
+fn encode_tagging(tags: &[(String, String)]) -> String {
+    fn encode(s: &str) -> String {
+        s.bytes()
+            .map(|b| match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    tags.iter()
+        .map(|(k, v)| format!("{}={}", encode(k), encode(v)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
 /// Get content type for frame format
 fn get_content_type(format: &str) -> String {
     match format.to_lowercase().as_str() {
         "jpeg" | "jpg" => "image/jpeg".to_string(),
         "png" => "image/png".to_string(),
         "webp" => "image/webp".to_string(),
+        "avif" => "image/avif".to_string(),
         "bmp" => "image/bmp".to_string(),
         "gif" => "image/gif".to_string(),
         _ => "application/octet-stream".to_string(),
     }
 }
 
+/// Outcome of uploading one event in a batch
+#[derive(Debug)]
+pub struct BatchUploadResult {
+    /// Event the upload belongs to
+    pub event_id: Uuid,
+    /// S3 key on success
+    pub result: Result<String>,
+}
+
 /// Batch uploader for efficient bulk operations
 pub struct BatchUploader {
-    uploader: Arc<S3Uploader>,
+    uploader: Arc<dyn FrameObjectStore>,
     concurrency: usize,
 }
 
 impl BatchUploader {
-    pub fn new(uploader: Arc<S3Uploader>, concurrency: usize) -> Self {
+    pub fn new(uploader: Arc<dyn FrameObjectStore>, concurrency: usize) -> Self {
         Self {
             uploader,
             concurrency,
@@ -337,28 +1115,140 @@ impl BatchUploader {
     }
 
     /// Upload multiple frames concurrently
+    ///
+    /// Results are returned in the same order as `events`.
     #[instrument(skip(self, events))]
-    pub async fn upload_batch(&self, events: Vec<StorageTriggerEvent>) -> Vec<Result<String>> {
+    pub async fn upload_batch(&self, events: Vec<StorageTriggerEvent>) -> Vec<BatchUploadResult> {
         use futures::stream::{self, StreamExt};
 
         let uploader = self.uploader.clone();
 
-        stream::iter(events)
-            .map(move |event| {
+        let mut results: Vec<(usize, BatchUploadResult)> = stream::iter(events)
+            .enumerate()
+            .map(move |(index, event)| {
                 let uploader = uploader.clone();
-                async move { uploader.upload_frame(&event).await }
+                async move {
+                    let result = uploader.upload_frame(&event).await;
+                    let outcome = BatchUploadResult {
+                        event_id: event.event_id,
+                        result,
+                    };
+                    (index, outcome)
+                }
             })
             .buffer_unordered(self.concurrency)
             .collect()
-            .await
+            .await;
+
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+/// In-memory object store for tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{FrameObjectStore, PutOutcome, PutResult};
+    use crate::kafka_consumer::StorageTriggerEvent;
+    use anyhow::{bail, Result};
+    use async_trait::async_trait;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    pub struct InMemoryObjectStore {
+        objects: Mutex<HashMap<String, Vec<u8>>>,
+        failing_events: Mutex<HashSet<Uuid>>,
+        failing_deletes: Mutex<HashSet<String>>,
+        conditional_put: bool,
+    }
+
+    impl InMemoryObjectStore {
+        /// Store that never overwrites, like `S3Config::conditional_put`
+        pub fn conditional() -> Self {
+            Self {
+                conditional_put: true,
+                ..Self::default()
+            }
+        }
+
+        pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+            self.objects.lock().unwrap().get(key).cloned()
+        }
+
+        /// Make uploads for `event_id` fail
+        pub fn fail_uploads_for(&self, event_id: Uuid) {
+            self.failing_events.lock().unwrap().insert(event_id);
+        }
+
+        /// Make deletes of `key` fail
+        pub fn fail_deletes_for(&self, key: &str) {
+            self.failing_deletes.lock().unwrap().insert(key.to_string());
+        }
+
+        pub fn insert(&self, key: &str, data: Vec<u8>) {
+            self.objects.lock().unwrap().insert(key.to_string(), data);
+        }
+
+        pub fn contains(&self, key: &str) -> bool {
+            self.objects.lock().unwrap().contains_key(key)
+        }
+    }
+
+    #[async_trait]
+    impl FrameObjectStore for InMemoryObjectStore {
+        async fn put_frame(&self, event: &StorageTriggerEvent) -> Result<PutResult> {
+            if self.failing_events.lock().unwrap().contains(&event.event_id) {
+                bail!("Injected upload failure for {}", event.event_id);
+            }
+            let s3_key = format!("frames/{}.{}", event.event_id, event.format);
+            let mut objects = self.objects.lock().unwrap();
+            let outcome = if self.conditional_put && objects.contains_key(&s3_key) {
+                PutOutcome::AlreadyExists
+            } else {
+                objects.insert(s3_key.clone(), event.frame_data.clone());
+                PutOutcome::Created
+            };
+            Ok(PutResult {
+                s3_key,
+                outcome,
+                version_id: None,
+                size_bytes: event.frame_data.len() as u64,
+            })
+        }
+
+        async fn delete_frame(&self, s3_key: &str) -> Result<()> {
+            if self.failing_deletes.lock().unwrap().contains(s3_key) {
+                bail!("Injected delete failure for {}", s3_key);
+            }
+            self.objects.lock().unwrap().remove(s3_key);
+            Ok(())
+        }
+
+        async fn get_frame(&self, s3_key: &str) -> Result<Vec<u8>> {
+            match self.get(s3_key) {
+                Some(data) => Ok(data),
+                None => bail!("No object under {}", s3_key),
+            }
+        }
+
+        async fn frame_exists(&self, s3_key: &str) -> Result<bool> {
+            Ok(self.contains(s3_key))
+        }
+
+        async fn frame_size(&self, s3_key: &str) -> Result<Option<u64>> {
+            Ok(self.get(s3_key).map(|data| data.len() as u64))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::testing::InMemoryObjectStore;
     use super::*;
+    use crate::kafka_consumer::FrameLocation;
     use chrono::TimeZone;
-    use uuid::Uuid;
 
     fn create_test_event() -> StorageTriggerEvent {
         StorageTriggerEvent {
@@ -366,6 +1256,7 @@ mod tests {
             device_id: "glasses-001".to_string(),
             timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 45).unwrap(),
             frame_number: 12345,
+            session_id: None,
             frame_data: vec![0u8; 100],
             width: 1920,
             height: 1080,
@@ -373,32 +1264,50 @@ mod tests {
             detections: vec![],
             trigger_type: TriggerType::Detection,
             metadata: serde_json::Value::Null,
+            frame_location: FrameLocation::Inline,
         }
     }
 
-    #[test]
-    fn test_generate_s3_key_detection() {
-        let config = S3Config {
+    fn test_s3_config() -> S3Config {
+        S3Config {
             bucket: "test-bucket".to_string(),
             region: "us-east-1".to_string(),
             endpoint_url: None,
             force_path_style: false,
             presigned_url_expiry_secs: 3600,
             upload_concurrency: 10,
+            max_idle_connections: None,
             multipart_threshold_bytes: 5 * 1024 * 1024,
             part_size_bytes: 5 * 1024 * 1024,
-        };
+            multipart_concurrency: 4,
+            tag_metadata_keys: vec![],
+            key_prefix: String::new(),
+            storage_max_dimension: None,
+            conditional_put: false,
+            versioned_bucket: false,
+            partition_timezone: "UTC".to_string(),
+            shifts: vec![],
+            upload_max_attempts: 3,
+            upload_retry_base_delay_ms: 0,
+            key_timestamp_precision: KeyTimestampPrecision::default(),
+            output_format: OutputFormat::default(),
+        }
+    }
+
+    #[test]
+    fn test_generate_s3_key_detection() {
+        let config = test_s3_config();
 
         // Create a mock uploader (we only need the key generation logic)
         let event = create_test_event();
 
         // Manually test the key format
         let date = event.timestamp.format("%Y-%m-%d").to_string();
-        let timestamp_str = event.timestamp.format("%H%M%S%3f").to_string();
+        let timestamp_str = event.timestamp.format("%H%M%S%6f").to_string();
 
         let expected_key = format!(
-            "frames/{}/{}/detections/{}_{}.jpeg",
-            date, "glasses_001", timestamp_str, event.event_id
+            "frames/{}/{}/detections/{}_{:010}_{}.jpeg",
+            date, "glasses_001", timestamp_str, event.frame_number, event.event_id
         );
 
         assert!(expected_key.contains("2024-01-15"));
@@ -406,6 +1315,129 @@ mod tests {
         assert!(expected_key.contains("detections"));
     }
 
+    fn offline_uploader(config: &S3Config) -> S3Uploader {
+        let s3_config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(aws_sdk_s3::config::Region::new(config.region.clone()))
+            .build();
+        S3Uploader::from_client(S3Client::from_conf(s3_config), config).unwrap()
+    }
+
+    #[test]
+    fn test_preview_keys_match_upload_keys() {
+        let config = S3Config {
+            key_prefix: "tenant-a".to_string(),
+            ..test_s3_config()
+        };
+        let uploader = offline_uploader(&config);
+
+        let events: Vec<_> = [TriggerType::Detection, TriggerType::Sample, TriggerType::Alert]
+            .into_iter()
+            .map(|trigger_type| StorageTriggerEvent {
+                event_id: Uuid::new_v4(),
+                trigger_type,
+                ..create_test_event()
+            })
+            .collect();
+
+        let preview = uploader.preview_keys(&events);
+
+        assert_eq!(preview.len(), 3);
+        for (event, key) in events.iter().zip(&preview) {
+            // upload_frame stores each event under generate_s3_key
+            assert_eq!(key, &uploader.generate_s3_key(event));
+            assert!(key.starts_with("tenant-a/frames/2024-01-15/"));
+        }
+        assert!(preview[1].contains("/samples/"));
+        assert!(preview[2].contains("/alerts/"));
+    }
+
+    #[test]
+    fn test_key_prefix_applies_to_keys_and_listing() {
+        let event = create_test_event();
+
+        let key = frame_key("tenant-a/", None, KeyTimestampPrecision::Micros, &event);
+        assert!(key.starts_with("tenant-a/frames/2024-01-15/glasses-001/detections/"));
+        assert!(key.ends_with(&format!("_{}.jpeg", event.event_id)));
+        assert!(frame_key("", None, KeyTimestampPrecision::Micros, &event)
+            .starts_with("frames/2024-01-15/"));
+
+        let prefix = list_prefix(
+            "tenant-a",
+            "2024-01-15",
+            None,
+            Some("glasses-001"),
+            Some("detections"),
+        );
+        assert_eq!(prefix, "tenant-a/frames/2024-01-15/glasses-001/detections");
+        assert!(key.starts_with(&prefix));
+        assert_eq!(list_prefix("", "2024-01-15", None, None, None), "frames/2024-01-15");
+
+        // Shift partitions sit between the date and the device
+        let partition = Some((event.timestamp.date_naive(), "night"));
+        let key = frame_key("", partition, KeyTimestampPrecision::Micros, &event);
+        assert!(key.starts_with("frames/2024-01-15/night/glasses-001/detections/"));
+        let prefix = list_prefix("", "2024-01-15", Some("night"), Some("glasses-001"), None);
+        assert!(key.starts_with(&prefix));
+    }
+
+    #[test]
+    fn test_shift_keys_use_partition_timezone_date() {
+        let config = S3Config {
+            partition_timezone: "America/Chicago".to_string(),
+            shifts: vec![
+                crate::config::ShiftConfig {
+                    name: "day".to_string(),
+                    start: "06:00".to_string(),
+                },
+                crate::config::ShiftConfig {
+                    name: "swing".to_string(),
+                    start: "14:00".to_string(),
+                },
+            ],
+            ..test_s3_config()
+        };
+        // 21:00 on the 15th in Chicago, already the 16th in UTC
+        let event = StorageTriggerEvent {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 16, 3, 0, 0).unwrap(),
+            ..create_test_event()
+        };
+
+        let key = offline_uploader(&config).generate_s3_key(&event);
+        assert!(key.starts_with("frames/2024-01-15/swing/glasses-001/"));
+    }
+
+    #[test]
+    fn test_sub_millisecond_frames_get_distinct_ordered_keys() {
+        let base = create_test_event();
+        let first = StorageTriggerEvent {
+            event_id: Uuid::parse_str("ffffffff-e29b-41d4-a716-446655440000").unwrap(),
+            timestamp: base.timestamp + chrono::Duration::microseconds(250),
+            frame_number: 999,
+            ..create_test_event()
+        };
+        let second = StorageTriggerEvent {
+            event_id: Uuid::parse_str("00000000-e29b-41d4-a716-446655440000").unwrap(),
+            timestamp: base.timestamp + chrono::Duration::microseconds(750),
+            frame_number: 1000,
+            ..create_test_event()
+        };
+
+        let first_key = frame_key("", None, KeyTimestampPrecision::Micros, &first);
+        let second_key = frame_key("", None, KeyTimestampPrecision::Micros, &second);
+        assert_ne!(first_key, second_key);
+        assert!(first_key < second_key);
+        assert!(first_key.ends_with(
+            "/detections/103045000250_0000000999_ffffffff-e29b-41d4-a716-446655440000.jpeg"
+        ));
+
+        // Within one millisecond tick the zero-padded frame number keeps order
+        let first_key = frame_key("", None, KeyTimestampPrecision::Millis, &first);
+        let second_key = frame_key("", None, KeyTimestampPrecision::Millis, &second);
+        assert!(first_key.contains("/103045000_0000000999_"));
+        assert!(first_key < second_key);
+    }
+
     #[test]
     fn test_sanitize_path_component() {
         assert_eq!(sanitize_path_component("glasses-001"), "glasses-001");
@@ -414,6 +1446,40 @@ mod tests {
         assert_eq!(sanitize_path_component("hello world"), "hello_world");
     }
 
+    #[test]
+    fn test_select_object_tags() {
+        let metadata = serde_json::json!({
+            "model-version": "yolo-v8.2",
+            "shift": 2,
+            "compliance-hold": true,
+            "operator": "jane",
+            "nested": {"a": 1},
+        });
+        let keys = vec![
+            "model-version".to_string(),
+            "shift".to_string(),
+            "compliance-hold".to_string(),
+            "nested".to_string(),
+            "missing".to_string(),
+        ];
+
+        let tags = select_object_tags(&metadata, &keys);
+
+        assert_eq!(
+            tags,
+            vec![
+                ("model-version".to_string(), "yolo-v8.2".to_string()),
+                ("shift".to_string(), "2".to_string()),
+                ("compliance-hold".to_string(), "true".to_string()),
+            ]
+        );
+        assert!(!tags.iter().any(|(k, _)| k == "operator"));
+        assert_eq!(
+            encode_tagging(&tags),
+            "model-version=yolo-v8.2&shift=2&compliance-hold=true"
+        );
+    }
+
     #[test]
     fn test_get_content_type() {
         assert_eq!(get_content_type("jpeg"), "image/jpeg");
@@ -422,4 +1488,451 @@ mod tests {
         assert_eq!(get_content_type("png"), "image/png");
         assert_eq!(get_content_type("unknown"), "application/octet-stream");
     }
+
+    #[tokio::test]
+    async fn test_upload_batch_preserves_order_and_reports_failures() {
+        let store = Arc::new(InMemoryObjectStore::default());
+        let events: Vec<_> = (0..4)
+            .map(|_| StorageTriggerEvent {
+                event_id: Uuid::new_v4(),
+                ..create_test_event()
+            })
+            .collect();
+        store.fail_uploads_for(events[1].event_id);
+
+        let batch = BatchUploader::new(store.clone(), 4);
+        let results = batch.upload_batch(events.clone()).await;
+
+        assert_eq!(results.len(), 4);
+        for (event, result) in events.iter().zip(&results) {
+            assert_eq!(result.event_id, event.event_id);
+        }
+        assert!(results[0].result.is_ok());
+        assert!(results[1].result.is_err());
+        assert!(results[2].result.is_ok());
+        assert!(results[3].result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_upload_parts_reports_each_part() {
+        // 2.5 parts worth of data
+        let data = vec![7u8; 250];
+        let mut events = Vec::new();
+
+        let parts = upload_parts(
+            &data,
+            100,
+            1,
+            |part_number, chunk| async move {
+                assert!(chunk.len() <= 100);
+                Ok(CompletedPart::builder()
+                    .part_number(part_number)
+                    .e_tag(format!("etag-{}", part_number))
+                    .build())
+            },
+            |progress| events.push(*progress),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events.iter().map(|p| p.part_number).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert!(events.iter().all(|p| p.total_parts == 3 && p.total_bytes == 250));
+        assert_eq!(events.last().unwrap().bytes_uploaded, 250);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_upload_parts_reassemble_in_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let stored = std::sync::Mutex::new(std::collections::HashMap::new());
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        let parts = upload_parts(
+            &data,
+            100,
+            4,
+            |part_number, chunk| {
+                let (stored, in_flight, max_in_flight) = (&stored, &in_flight, &max_in_flight);
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(now, Ordering::SeqCst);
+                    // Earlier parts take longer, so parts complete out of order
+                    let delay = Duration::from_millis(5 * (10 - part_number as u64));
+                    tokio::time::sleep(delay).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    stored.lock().unwrap().insert(part_number, chunk);
+                    Ok(CompletedPart::builder()
+                        .part_number(part_number)
+                        .e_tag(format!("etag-{}", part_number))
+                        .build())
+                }
+            },
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            parts.iter().map(|p| p.part_number()).collect::<Vec<_>>(),
+            (1..=10).map(Some).collect::<Vec<_>>()
+        );
+        assert!(max_in_flight.load(Ordering::SeqCst) > 1);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 4);
+
+        // Reassemble the object the way S3 does on completion
+        let stored = stored.into_inner().unwrap();
+        let reassembled: Vec<u8> = parts
+            .iter()
+            .flat_map(|p| stored[&p.part_number().unwrap()].clone())
+            .collect();
+        assert_eq!(reassembled, data);
+    }
+
+    #[tokio::test]
+    async fn test_upload_parts_fails_on_part_error() {
+        let data = vec![0u8; 500];
+
+        let result = upload_parts(
+            &data,
+            100,
+            4,
+            |part_number, _chunk| async move {
+                if part_number == 3 {
+                    bail!("part {} rejected", part_number);
+                }
+                Ok(CompletedPart::builder().part_number(part_number).build())
+            },
+            |_| {},
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_part_aborts_multipart_upload() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let data = vec![0u8; 500];
+        let aborts = AtomicUsize::new(0);
+        let abort = || async {
+            aborts.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+
+        let upload = upload_parts(
+            &data,
+            100,
+            4,
+            |part_number, _chunk| async move {
+                if part_number == 3 {
+                    bail!("part {} rejected", part_number);
+                }
+                Ok(CompletedPart::builder().part_number(part_number).build())
+            },
+            |_| {},
+        );
+        let err = abort_on_failure("frames/large.jpeg", upload, abort)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "part 3 rejected");
+        assert_eq!(aborts.load(Ordering::SeqCst), 1);
+
+        // A failed abort doesn't hide why the upload failed
+        let failed_completion = async { Err::<(), _>(anyhow::anyhow!("completion rejected")) };
+        let failed_abort = || async { Err(anyhow::anyhow!("abort rejected")) };
+        let err = abort_on_failure("frames/large.jpeg", failed_completion, failed_abort)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "completion rejected");
+
+        // Successful steps leave the upload alone
+        let parts = abort_on_failure("frames/large.jpeg", async { Ok(2) }, abort)
+            .await
+            .unwrap();
+        assert_eq!(parts, 2);
+        assert_eq!(aborts.load(Ordering::SeqCst), 1);
+    }
+
+    fn encoded_png(width: u32, height: u32) -> Vec<u8> {
+        let frame = image::DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        let mut encoded = Cursor::new(Vec::new());
+        frame.write_to(&mut encoded, image::ImageFormat::Png).unwrap();
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn test_reencode_for_storage_downscale_preserves_aspect_ratio() {
+        let mut event = StorageTriggerEvent {
+            frame_data: encoded_png(400, 200),
+            width: 400,
+            height: 200,
+            format: "png".to_string(),
+            ..create_test_event()
+        };
+
+        assert!(reencode_for_storage(&mut event, Some(100), OutputFormat::Passthrough).unwrap());
+        assert_eq!((event.width, event.height), (100, 50));
+
+        let stored = image::load_from_memory(&event.frame_data).unwrap();
+        assert_eq!((stored.width(), stored.height()), (100, 50));
+        assert_eq!(
+            image::guess_format(&event.frame_data).unwrap(),
+            image::ImageFormat::Png
+        );
+
+        // Already within bounds: left untouched
+        let original = event.frame_data.clone();
+        assert!(!reencode_for_storage(&mut event, Some(100), OutputFormat::Passthrough).unwrap());
+        assert_eq!(event.frame_data, original);
+    }
+
+    #[test]
+    fn test_reencode_for_storage_downscales_and_transcodes_in_one_pass() {
+        let mut event = StorageTriggerEvent {
+            frame_data: encoded_png(400, 200),
+            width: 400,
+            height: 200,
+            format: "png".to_string(),
+            ..create_test_event()
+        };
+
+        assert!(reencode_for_storage(&mut event, Some(100), OutputFormat::Jpeg).unwrap());
+        assert_eq!((event.width, event.height), (100, 50));
+        assert_eq!(event.format, "jpeg");
+        assert_eq!(image::guess_format(&event.frame_data).unwrap(), ImageFormat::Jpeg);
+        let stored = image::load_from_memory(&event.frame_data).unwrap();
+        assert_eq!((stored.width(), stored.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_reencode_for_storage_keeps_raw_frames() {
+        let raw = vec![0u8; 400 * 200 * 3];
+        let mut event = StorageTriggerEvent {
+            frame_data: raw.clone(),
+            width: 400,
+            height: 200,
+            format: "rgb24".to_string(),
+            ..create_test_event()
+        };
+
+        assert!(!reencode_for_storage(&mut event, Some(100), OutputFormat::Webp).unwrap());
+        assert_eq!(event.frame_data, raw);
+        assert_eq!((event.width, event.height), (400, 200));
+        assert_eq!(event.format, "rgb24");
+    }
+
+    #[tokio::test]
+    async fn test_prepare_for_storage_downscales_or_keeps_original() {
+        let config = S3Config {
+            storage_max_dimension: Some(100),
+            ..test_s3_config()
+        };
+        let uploader = offline_uploader(&config);
+
+        let oversized = StorageTriggerEvent {
+            frame_data: encoded_png(400, 200),
+            width: 400,
+            height: 200,
+            format: "png".to_string(),
+            ..create_test_event()
+        };
+        let prepared = uploader.prepare_for_storage(oversized).await.unwrap();
+        assert_eq!((prepared.width, prepared.height), (100, 50));
+        let stored = image::load_from_memory(&prepared.frame_data).unwrap();
+        assert_eq!((stored.width(), stored.height()), (100, 50));
+
+        // Undecodable frame: stored as received rather than dropped
+        let corrupt = StorageTriggerEvent {
+            frame_data: vec![0u8; 64],
+            width: 400,
+            height: 200,
+            ..create_test_event()
+        };
+        let prepared = uploader.prepare_for_storage(corrupt).await.unwrap();
+        assert_eq!((prepared.width, prepared.height), (400, 200));
+        assert_eq!(prepared.frame_data, vec![0u8; 64]);
+    }
+
+    fn encoded_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let frame = image::DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        let mut encoded = Cursor::new(Vec::new());
+        frame.write_to(&mut encoded, ImageFormat::Jpeg).unwrap();
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn test_transcode_jpeg_to_webp_updates_key_and_content_type() {
+        let mut config = S3Config {
+            output_format: OutputFormat::Webp,
+            ..test_s3_config()
+        };
+        let mut event = StorageTriggerEvent {
+            frame_data: encoded_jpeg(64, 48),
+            width: 64,
+            height: 48,
+            ..create_test_event()
+        };
+
+        assert!(reencode_for_storage(&mut event, None, config.output_format).unwrap());
+        assert_eq!(event.format, "webp");
+        assert_eq!(
+            image::guess_format(&event.frame_data).unwrap(),
+            ImageFormat::WebP
+        );
+        assert_eq!(get_content_type(&event.format), "image/webp");
+        assert!(offline_uploader(&config)
+            .generate_s3_key(&event)
+            .ends_with(".webp"));
+
+        // Already in the target encoding: left untouched
+        let webp = event.frame_data.clone();
+        assert!(!reencode_for_storage(&mut event, None, OutputFormat::Webp).unwrap());
+        assert_eq!(event.frame_data, webp);
+
+        // Passthrough never re-encodes
+        config.output_format = OutputFormat::Passthrough;
+        let mut jpeg = StorageTriggerEvent {
+            frame_data: encoded_jpeg(64, 48),
+            ..create_test_event()
+        };
+        assert!(!reencode_for_storage(&mut jpeg, None, config.output_format).unwrap());
+        assert_eq!(jpeg.format, "jpeg");
+    }
+
+    #[tokio::test]
+    async fn test_conditional_put_reports_existing_object() {
+        let store = InMemoryObjectStore::conditional();
+        let event = create_test_event();
+
+        let put = store.put_frame(&event).await.unwrap();
+        assert_eq!(put.outcome, PutOutcome::Created);
+
+        // Reprocessing the same event must not overwrite the stored object
+        let reprocessed = StorageTriggerEvent {
+            frame_data: vec![1u8; 100],
+            ..create_test_event()
+        };
+        let second = store.put_frame(&reprocessed).await.unwrap();
+        assert_eq!(second.s3_key, put.s3_key);
+        assert_eq!(second.outcome, PutOutcome::AlreadyExists);
+        assert_eq!(store.get(&put.s3_key).unwrap(), event.frame_data);
+
+        assert!(is_precondition_failed(Some(412)));
+        assert!(!is_precondition_failed(Some(403)));
+        assert!(!is_precondition_failed(None));
+    }
+
+    #[test]
+    fn test_versioned_delete_targets_every_version_of_the_key() {
+        use aws_sdk_s3::types::{DeleteMarkerEntry, ObjectVersion};
+
+        let key = "frames/2024-01-15/glasses_001/detections/103045_0000012345_evt.jpeg";
+        let version = |key: &str, id: &str| {
+            ObjectVersion::builder().key(key).version_id(id).build()
+        };
+        let page = ListObjectVersionsOutput::builder()
+            .versions(version(key, "v2"))
+            .versions(version(key, "v1"))
+            // Shares the prefix but is a different object
+            .versions(version(&format!("{}.bak", key), "v9"))
+            .delete_markers(
+                DeleteMarkerEntry::builder()
+                    .key(key)
+                    .version_id("m1")
+                    .build(),
+            )
+            .build();
+
+        let targets = key_versions(key, &page).unwrap();
+        let ids: Vec<_> = targets
+            .iter()
+            .map(|target| {
+                assert_eq!(target.key(), key);
+                target.version_id().unwrap()
+            })
+            .collect();
+        assert_eq!(ids, ["v2", "v1", "m1"]);
+
+        // Unversioned buckets list their single object with the "null" version
+        let page = ListObjectVersionsOutput::builder()
+            .versions(version(key, "null"))
+            .build();
+        let targets = key_versions(key, &page).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].version_id(), Some("null"));
+
+        let empty = ListObjectVersionsOutput::builder().build();
+        assert!(key_versions(key, &empty).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_delete_is_a_plain_delete_object() {
+        use aws_smithy_runtime::client::http::test_util::capture_request;
+
+        let (http_client, request) = capture_request(None);
+        let s3_config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(aws_sdk_s3::config::Region::new("us-east-1"))
+            .credentials_provider(aws_sdk_s3::config::Credentials::new(
+                "test", "test", None, None, "test",
+            ))
+            .http_client(http_client)
+            .build();
+        let uploader =
+            S3Uploader::from_client(S3Client::from_conf(s3_config), &test_s3_config()).unwrap();
+
+        let key = "frames/2024-01-15/glasses_001/detections/103045_0000012345_evt.jpeg";
+        uploader.delete_frame(key).await.unwrap();
+
+        // No version listing, which buckets granting only s3:DeleteObject deny
+        let request = request.expect_request();
+        assert_eq!(request.method(), "DELETE");
+        assert!(request.uri().contains(key));
+        assert!(!request.uri().contains("versions"));
+        assert!(!request.uri().contains("versionId"));
+    }
+
+    #[test]
+    fn test_only_transient_upload_errors_are_retried() {
+        let timeout: SdkError<PutObjectError, HttpResponse> = SdkError::timeout_error("timed out");
+        let err = anyhow::Error::new(timeout).context("Failed to upload frame to S3");
+        assert!(is_transient_upload_error(&err));
+
+        let construction: SdkError<UploadPartError, HttpResponse> =
+            SdkError::construction_failure("missing bucket");
+        let err = anyhow::Error::new(construction).context("Failed to upload part");
+        assert!(!is_transient_upload_error(&err));
+
+        assert!(!is_transient_upload_error(&anyhow::anyhow!("No upload ID in response")));
+
+        assert!(is_transient_status(503));
+        assert!(is_transient_status(429));
+        assert!(!is_transient_status(403));
+        assert!(!is_transient_status(404));
+    }
+
+    #[test]
+    fn test_connection_pool_size_applied_to_hyper_client() {
+        let mut config = test_s3_config();
+        assert_eq!(connection_pool_size(&config), 10);
+
+        config.max_idle_connections = Some(64);
+        assert_eq!(connection_pool_size(&config), 64);
+
+        config.max_idle_connections = Some(0);
+        assert_eq!(connection_pool_size(&config), 1);
+
+        // hyper exposes its pool settings only through Debug
+        config.max_idle_connections = Some(64);
+        let hyper_builder = pooled_hyper_builder(connection_pool_size(&config));
+        assert!(format!("{:?}", hyper_builder).contains("max_idle_per_host: 64"));
+    }
 }
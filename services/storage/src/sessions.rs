@@ -0,0 +1,153 @@
+use crate::kafka_consumer::StorageTriggerEvent;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::info;
+use uuid::Uuid;
+
+/// Frame-number drop that marks a stream restart rather than reordering
+pub const DEFAULT_SESSION_RESET_THRESHOLD: u64 = 100;
+
+/// Assigns stream-session IDs so `frame_number` is unique per device session
+///
+/// Ingest resets a device's frame numbers to 0 when its stream reconnects.
+/// Events that carry a `session_id` keep it; for the rest, a frame number
+/// dropping by more than the threshold starts a new session.
+///
+/// The inferred sessions are best effort: they are held in memory, so a
+/// restart of this service starts a new session for every device, and a
+/// reconnect that resumes within the threshold of the last frame number is
+/// not detected. Producers that need exact sessions should set `session_id`.
+pub struct SessionTracker {
+    reset_threshold: u64,
+    devices: Mutex<HashMap<String, DeviceSession>>,
+}
+
+struct DeviceSession {
+    session_id: String,
+    last_frame_number: u64,
+}
+
+impl Default for SessionTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_RESET_THRESHOLD)
+    }
+}
+
+impl SessionTracker {
+    pub fn new(reset_threshold: u64) -> Self {
+        Self {
+            reset_threshold,
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fill in the event's session, starting a new one on a sequence reset
+    pub fn assign(&self, event: &mut StorageTriggerEvent) {
+        let mut devices = self.devices.lock().unwrap();
+
+        let session_id = match (event.session_id.take(), devices.get(&event.device_id)) {
+            (Some(session_id), _) => session_id,
+            (None, Some(current))
+                if event.frame_number.saturating_add(self.reset_threshold)
+                    >= current.last_frame_number =>
+            {
+                current.session_id.clone()
+            }
+            (None, current) => {
+                let session_id = Uuid::new_v4().to_string();
+                if let Some(previous) = current {
+                    info!(
+                        device_id = %event.device_id,
+                        previous_frame = previous.last_frame_number,
+                        frame_number = event.frame_number,
+                        session_id = %session_id,
+                        "Frame sequence reset, starting new stream session"
+                    );
+                    metrics::counter!("storage.sessions.reset").increment(1);
+                }
+                session_id
+            }
+        };
+
+        devices.insert(
+            event.device_id.clone(),
+            DeviceSession {
+                session_id: session_id.clone(),
+                last_frame_number: event.frame_number,
+            },
+        );
+        event.session_id = Some(session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kafka_consumer::TriggerType;
+
+    fn event(device_id: &str, frame_number: u64) -> StorageTriggerEvent {
+        StorageTriggerEvent::builder()
+            .device_id(device_id)
+            .frame_number(frame_number)
+            .frame_data(vec![0u8; 4])
+            .dimensions(640, 480)
+            .trigger_type(TriggerType::Sample)
+            .build()
+            .unwrap()
+    }
+
+    fn assigned(tracker: &SessionTracker, mut event: StorageTriggerEvent) -> String {
+        tracker.assign(&mut event);
+        event.session_id.unwrap()
+    }
+
+    #[test]
+    fn test_sequence_reset_starts_new_session() {
+        let tracker = SessionTracker::new(100);
+
+        let first = assigned(&tracker, event("glasses-001", 5000));
+        assert_eq!(assigned(&tracker, event("glasses-001", 5001)), first);
+        // Small reordering stays in the session
+        assert_eq!(assigned(&tracker, event("glasses-001", 4950)), first);
+
+        // Reconnect resets the sequence to 0
+        let second = assigned(&tracker, event("glasses-001", 0));
+        assert_ne!(second, first);
+        assert_eq!(assigned(&tracker, event("glasses-001", 1)), second);
+
+        // Other devices are tracked independently
+        assert_ne!(assigned(&tracker, event("glasses-002", 0)), second);
+    }
+
+    #[test]
+    fn test_ingest_session_id_is_kept() {
+        let tracker = SessionTracker::default();
+        let mut from_ingest = event("glasses-001", 10);
+        from_ingest.session_id = Some("ingest-session".to_string());
+
+        assert_eq!(assigned(&tracker, from_ingest), "ingest-session");
+        // Later events without one continue the ingest session
+        assert_eq!(assigned(&tracker, event("glasses-001", 11)), "ingest-session");
+    }
+
+    #[test]
+    fn test_reset_threshold_is_configurable() {
+        let tracker = SessionTracker::new(10);
+
+        let first = assigned(&tracker, event("glasses-001", 5000));
+        assert_eq!(assigned(&tracker, event("glasses-001", 4995)), first);
+        // A drop the default threshold would treat as reordering
+        assert_ne!(assigned(&tracker, event("glasses-001", 4950)), first);
+    }
+
+    #[test]
+    fn test_frame_numbers_near_u64_max() {
+        let tracker = SessionTracker::default();
+
+        let first = assigned(&tracker, event("glasses-001", u64::MAX - 10));
+        assert_eq!(assigned(&tracker, event("glasses-001", u64::MAX)), first);
+        // Adding the threshold must not overflow
+        assert_eq!(assigned(&tracker, event("glasses-001", u64::MAX - 20)), first);
+        assert_ne!(assigned(&tracker, event("glasses-001", 0)), first);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config::ShiftConfig;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+/// Maps frame timestamps to factory shifts
+///
+/// Each shift runs from its start time until the next shift starts, in the
+/// partition timezone. The latest-starting shift wraps past midnight, so a
+/// night shift starting at 22:00 covers the early hours of the next day.
+#[derive(Debug, Clone)]
+pub struct ShiftSchedule {
+    timezone: Tz,
+    /// (start, name), sorted by start
+    shifts: Vec<(NaiveTime, String)>,
+}
+
+impl ShiftSchedule {
+    /// Build a schedule, or `None` if no shifts are configured
+    pub fn new(timezone: &str, shifts: &[ShiftConfig]) -> Result<Option<Self>> {
+        if shifts.is_empty() {
+            return Ok(None);
+        }
+
+        let timezone: Tz = timezone
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid partition_timezone {}: {}", timezone, e))?;
+
+        let mut parsed = Vec::with_capacity(shifts.len());
+        for shift in shifts {
+            let start = NaiveTime::parse_from_str(&shift.start, "%H:%M").with_context(|| {
+                format!("Invalid start {:?} for shift {}", shift.start, shift.name)
+            })?;
+            parsed.push((start, shift.name.clone()));
+        }
+        parsed.sort();
+
+        if let Some(pair) = parsed.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            bail!(
+                "Shifts {} and {} start at the same time",
+                pair[0].1,
+                pair[1].1
+            );
+        }
+
+        Ok(Some(Self {
+            timezone,
+            shifts: parsed,
+        }))
+    }
+
+    /// Calendar date of `timestamp` in the partition timezone
+    pub fn local_date(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        timestamp.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Name of the shift `timestamp` falls in
+    pub fn shift_for(&self, timestamp: DateTime<Utc>) -> &str {
+        let local = timestamp.with_timezone(&self.timezone).time();
+        self.shifts
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= local)
+            .or_else(|| self.shifts.last())
+            .map(|(_, name)| name.as_str())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn shift(name: &str, start: &str) -> ShiftConfig {
+        ShiftConfig {
+            name: name.to_string(),
+            start: start.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_timestamps_map_to_their_shift() {
+        let schedule = ShiftSchedule::new(
+            "America/Chicago",
+            &[
+                shift("night", "22:00"),
+                shift("day", "06:00"),
+                shift("swing", "14:00"),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+
+        // Chicago is UTC-6 in January
+        let at = |hour, minute| Utc.with_ymd_and_hms(2024, 1, 15, hour, minute, 0).unwrap();
+        assert_eq!(schedule.shift_for(at(12, 0)), "day"); // 06:00 local
+        assert_eq!(schedule.shift_for(at(19, 59)), "day"); // 13:59
+        assert_eq!(schedule.shift_for(at(20, 0)), "swing"); // 14:00
+        assert_eq!(schedule.shift_for(at(4, 30)), "night"); // 22:30 the day before
+        assert_eq!(schedule.shift_for(at(11, 59)), "night"); // 05:59
+
+        // Dates follow the same timezone as the shifts
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        assert_eq!(schedule.local_date(at(4, 30)), day(14));
+        assert_eq!(schedule.local_date(at(12, 0)), day(15));
+    }
+
+    #[test]
+    fn test_invalid_schedules_are_rejected() {
+        assert!(ShiftSchedule::new("UTC", &[]).unwrap().is_none());
+        assert!(ShiftSchedule::new("Mars/Olympus", &[shift("day", "06:00")]).is_err());
+        assert!(ShiftSchedule::new("UTC", &[shift("day", "6am")]).is_err());
+        assert!(
+            ShiftSchedule::new("UTC", &[shift("day", "06:00"), shift("early", "06:00")]).is_err()
+        );
+    }
+}
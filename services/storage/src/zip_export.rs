@@ -0,0 +1,386 @@
+//! Streaming ZIP archives of stored frames.
+//!
+//! Frames are already compressed (JPEG/PNG), so entries are written with the
+//! `stored` method: each entry's CRC and sizes are known once its object has
+//! been fetched, and the archive can be emitted one frame at a time without
+//! buffering it whole. The central directory follows the last entry.
+//!
+//! Every archive ends with a `MANIFEST.json` entry counting the frames
+//! included and listing any that could not be, with the reason, so an export
+//! missing frames is never mistaken for a complete one.
+
+use crate::metadata_store::FrameMetadata;
+use crate::s3_uploader::FrameObjectStore;
+use anyhow::{bail, Result};
+use axum::body::Bytes;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use futures::{stream, Stream};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// Version 2.0: the minimum for stored entries in directories
+const ZIP_VERSION: u16 = 20;
+/// General purpose flag bit 11: file names are UTF-8
+const UTF8_NAMES_FLAG: u16 = 1 << 11;
+const STORED_METHOD: u16 = 0;
+/// Archive path of the export manifest; frame entries all sit in device
+/// directories, so it cannot collide with one
+pub const MANIFEST_NAME: &str = "MANIFEST.json";
+
+/// Incrementally writes a ZIP archive of uncompressed entries
+///
+/// Archives are limited to what fits without ZIP64 extensions: 65535
+/// entries and 4 GiB of data.
+#[derive(Debug, Default)]
+pub struct ZipWriter {
+    central_directory: Vec<u8>,
+    entries: u16,
+    offset: u64,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode one entry (local header followed by its data)
+    pub fn add_entry(&mut self, name: &str, data: &[u8], modified: DateTime<Utc>) -> Result<Bytes> {
+        let entry_len = 30 + name.len() as u64 + data.len() as u64;
+        if self.entries == u16::MAX
+            || name.len() > u16::MAX as usize
+            || self.offset + entry_len > u32::MAX as u64
+        {
+            bail!("ZIP archive too large for entry {}", name);
+        }
+
+        let crc = crc32fast::hash(data);
+        let size = data.len() as u32;
+        let (time, date) = dos_date_time(modified);
+
+        let mut local = Vec::with_capacity(entry_len as usize);
+        put_u32(&mut local, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut local, ZIP_VERSION);
+        put_u16(&mut local, UTF8_NAMES_FLAG);
+        put_u16(&mut local, STORED_METHOD);
+        put_u16(&mut local, time);
+        put_u16(&mut local, date);
+        put_u32(&mut local, crc);
+        put_u32(&mut local, size);
+        put_u32(&mut local, size);
+        put_u16(&mut local, name.len() as u16);
+        put_u16(&mut local, 0);
+        local.extend_from_slice(name.as_bytes());
+        local.extend_from_slice(data);
+
+        let central = &mut self.central_directory;
+        put_u32(central, CENTRAL_HEADER_SIGNATURE);
+        put_u16(central, ZIP_VERSION);
+        put_u16(central, ZIP_VERSION);
+        put_u16(central, UTF8_NAMES_FLAG);
+        put_u16(central, STORED_METHOD);
+        put_u16(central, time);
+        put_u16(central, date);
+        put_u32(central, crc);
+        put_u32(central, size);
+        put_u32(central, size);
+        put_u16(central, name.len() as u16);
+        put_u16(central, 0); // extra field length
+        put_u16(central, 0); // comment length
+        put_u16(central, 0); // disk number
+        put_u16(central, 0); // internal attributes
+        put_u32(central, 0); // external attributes
+        put_u32(central, self.offset as u32);
+        central.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+        self.offset += entry_len;
+        Ok(Bytes::from(local))
+    }
+
+    /// Encode the central directory and end-of-archive record
+    pub fn finish(self) -> Result<Bytes> {
+        let directory_len = self.central_directory.len() as u64;
+        if self.offset + directory_len > u32::MAX as u64 {
+            bail!("ZIP central directory exceeds 4 GiB");
+        }
+
+        let mut tail = self.central_directory;
+        put_u32(&mut tail, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut tail, 0); // this disk
+        put_u16(&mut tail, 0); // disk with the central directory
+        put_u16(&mut tail, self.entries);
+        put_u16(&mut tail, self.entries);
+        put_u32(&mut tail, directory_len as u32);
+        put_u32(&mut tail, self.offset as u32);
+        put_u16(&mut tail, 0); // comment length
+        Ok(Bytes::from(tail))
+    }
+}
+
+/// Archive path for a frame: `{device}/{timestamp}_f{frame_number}_{id}.{format}`
+///
+/// The timestamp sorts entries chronologically in archive listings; the
+/// short frame ID keeps names unique across stream sessions.
+pub fn entry_name(frame: &FrameMetadata) -> String {
+    let id = frame.id.simple().to_string();
+    format!(
+        "{}/{}_f{}_{}.{}",
+        sanitize_path_segment(&frame.device_id),
+        frame.timestamp.format("%Y%m%dT%H%M%S%.3fZ"),
+        frame.frame_number,
+        &id[..8],
+        sanitize_path_segment(&frame.format),
+    )
+}
+
+/// Contents of an export's `MANIFEST.json`
+#[derive(Debug, Default, Serialize)]
+pub struct ExportManifest {
+    /// Frames written to the archive
+    pub frames: usize,
+    /// Frames that matched the export but are not in the archive
+    pub skipped: Vec<SkippedFrame>,
+}
+
+/// A frame left out of an export
+#[derive(Debug, Serialize)]
+pub struct SkippedFrame {
+    pub frame_id: Uuid,
+    pub device_id: String,
+    pub frame_number: i64,
+    pub timestamp: DateTime<Utc>,
+    pub reason: String,
+}
+
+impl ExportManifest {
+    fn skip(&mut self, frame: &FrameMetadata, reason: impl Into<String>) {
+        metrics::counter!("storage.export.zip_frames_skipped").increment(1);
+        self.skipped.push(SkippedFrame {
+            frame_id: frame.id,
+            device_id: frame.device_id.clone(),
+            frame_number: frame.frame_number,
+            timestamp: frame.timestamp,
+            reason: reason.into(),
+        });
+    }
+}
+
+/// Stream a ZIP archive of `frames`, fetching each object as it is written
+///
+/// Archived frames (whose objects were expired) and objects that cannot be
+/// fetched do not fail the whole download; they are listed in the closing
+/// `MANIFEST.json` entry instead.
+pub fn zip_frames(
+    objects: Arc<dyn FrameObjectStore>,
+    frames: Vec<FrameMetadata>,
+) -> impl Stream<Item = Result<Bytes>> {
+    let state = Some((ZipWriter::new(), frames.into_iter(), ExportManifest::default()));
+    stream::unfold(state, move |state| {
+        let objects = objects.clone();
+        async move {
+            let (mut zip, mut frames, mut manifest) = state?;
+            for frame in frames.by_ref() {
+                if frame.archived {
+                    manifest.skip(&frame, "object expired by retention");
+                    continue;
+                }
+                let Some(s3_key) = frame.s3_key.as_deref() else {
+                    manifest.skip(&frame, "no stored object");
+                    continue;
+                };
+                let data = match objects.get_frame(s3_key).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        warn!(error = %e, s3_key = %s3_key, "Skipping frame in ZIP export");
+                        manifest.skip(&frame, format!("fetch failed: {:#}", e));
+                        continue;
+                    }
+                };
+                return match zip.add_entry(&entry_name(&frame), &data, frame.timestamp) {
+                    Ok(chunk) => {
+                        manifest.frames += 1;
+                        Some((Ok(chunk), Some((zip, frames, manifest))))
+                    }
+                    Err(e) => Some((Err(e), None)),
+                };
+            }
+            Some((finish_with_manifest(zip, &manifest), None))
+        }
+    })
+}
+
+/// Encode the manifest entry followed by the end of the archive
+fn finish_with_manifest(mut zip: ZipWriter, manifest: &ExportManifest) -> Result<Bytes> {
+    let json = serde_json::to_vec_pretty(manifest)?;
+    let entry = zip.add_entry(MANIFEST_NAME, &json, Utc::now())?;
+    let tail = zip.finish()?;
+    Ok(Bytes::from([entry.as_ref(), tail.as_ref()].concat()))
+}
+
+/// Replace characters that would create extra path levels or odd names
+pub(crate) fn sanitize_path_segment(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match sanitized.trim_matches('.') {
+        "" => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// MS-DOS time and date fields (2 second resolution, years from 1980)
+fn dos_date_time(at: DateTime<Utc>) -> (u16, u16) {
+    let year = at.year().clamp(1980, 2107) as u16;
+    let time = (at.hour() as u16) << 11 | (at.minute() as u16) << 5 | (at.second() as u16) / 2;
+    let date = (year - 1980) << 9 | (at.month() as u16) << 5 | at.day() as u16;
+    (time, date)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_store::testing::stored_frame;
+    use crate::s3_uploader::testing::InMemoryObjectStore;
+    use chrono::TimeZone;
+    use futures::StreamExt;
+    use uuid::Uuid;
+
+    fn frame(device_id: &str, frame_number: i64, s3_key: &str) -> FrameMetadata {
+        FrameMetadata {
+            device_id: device_id.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap()
+                + chrono::Duration::milliseconds(frame_number * 100),
+            frame_number,
+            ..stored_frame(Uuid::new_v4(), s3_key)
+        }
+    }
+
+    fn u16_at(buf: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([buf[at], buf[at + 1]])
+    }
+
+    fn u32_at(buf: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    /// Read (name, data) pairs by walking the central directory
+    fn read_entries(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let eocd = archive.len() - 22;
+        assert_eq!(u32_at(archive, eocd), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        let count = u16_at(archive, eocd + 10) as usize;
+        let mut at = u32_at(archive, eocd + 16) as usize;
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(archive, at), CENTRAL_HEADER_SIGNATURE);
+            let crc = u32_at(archive, at + 16);
+            let size = u32_at(archive, at + 20) as usize;
+            let name_len = u16_at(archive, at + 28) as usize;
+            let local = u32_at(archive, at + 42) as usize;
+            let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+            assert_eq!(u32_at(archive, local), LOCAL_HEADER_SIGNATURE);
+            let data_at = local + 30 + u16_at(archive, local + 26) as usize;
+            let data = archive[data_at..data_at + size].to_vec();
+            assert_eq!(crc32fast::hash(&data), crc);
+
+            entries.push((name, data));
+            at += 46 + name_len;
+        }
+        entries
+    }
+
+    #[tokio::test]
+    async fn test_zip_export_contains_expected_entries() {
+        let objects = Arc::new(InMemoryObjectStore::default());
+        let mut frames = Vec::new();
+        for n in 1..=3 {
+            let key = format!("frames/glasses-001/{}.jpeg", n);
+            objects.insert(&key, vec![n as u8; 10 * n as usize]);
+            frames.push(frame("glasses-001", n, &key));
+        }
+
+        let mut expected_names: Vec<String> = frames.iter().map(entry_name).collect();
+        expected_names.push(MANIFEST_NAME.to_string());
+        let chunks: Vec<Bytes> = zip_frames(objects, frames)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let archive = chunks.concat();
+
+        let entries = read_entries(&archive);
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, expected_names);
+        assert!(names[0].starts_with("glasses-001/20240115T103000.100Z_f1_"));
+        assert!(names[0].ends_with(".jpeg"));
+        for (n, (_, data)) in entries[..3].iter().enumerate() {
+            assert_eq!(data, &vec![n as u8 + 1; 10 * (n + 1)]);
+        }
+
+        let manifest: serde_json::Value = serde_json::from_slice(&entries[3].1).unwrap();
+        assert_eq!(manifest["frames"], 3);
+        assert_eq!(manifest["skipped"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_zip_export_lists_skipped_frames_in_manifest() {
+        let objects = Arc::new(InMemoryObjectStore::default());
+        objects.insert("frames/glasses-001/1.jpeg", vec![1; 10]);
+        let stored = frame("glasses-001", 1, "frames/glasses-001/1.jpeg");
+        let mut archived = frame("glasses-001", 2, "frames/glasses-001/2.jpeg");
+        archived.archived = true;
+        let missing = frame("glasses-001", 3, "frames/glasses-001/missing.jpeg");
+        let skipped_ids = [archived.id, missing.id];
+
+        let chunks: Vec<Bytes> = zip_frames(objects, vec![stored.clone(), archived, missing])
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let entries = read_entries(&chunks.concat());
+
+        // Only the stored frame is archived, followed by the manifest
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, [entry_name(&stored).as_str(), MANIFEST_NAME]);
+
+        let manifest: serde_json::Value = serde_json::from_slice(&entries[1].1).unwrap();
+        assert_eq!(manifest["frames"], 1);
+        let skipped = manifest["skipped"].as_array().unwrap();
+        assert_eq!(skipped.len(), 2);
+        for (entry, id) in skipped.iter().zip(skipped_ids) {
+            assert_eq!(entry["frame_id"], id.to_string());
+        }
+        assert_eq!(skipped[0]["reason"], "object expired by retention");
+        assert!(skipped[1]["reason"]
+            .as_str()
+            .unwrap()
+            .starts_with("fetch failed"));
+    }
+
+    #[test]
+    fn test_entry_name_cannot_escape_device_directory() {
+        let frame = frame("../../etc/cam 1", 7, "frames/x.jpeg");
+        let name = entry_name(&frame);
+        assert!(name.starts_with("_.._etc_cam_1/"));
+        assert_eq!(name.matches('/').count(), 1);
+    }
+}